@@ -0,0 +1,71 @@
+//! Benchmarks of the paths that slow down first on large stores, run on a
+//! store from `sniprrr bench --generate`'s generator:
+//!
+//!     cargo bench
+
+use std::collections::{BTreeSet, HashMap};
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use sniprrr::bench::{generate, SEED};
+use sniprrr::file_utils::{parse_store, store_contents, write_store};
+use sniprrr::search;
+use sniprrr::table::TableView;
+use sniprrr::theme::Theme;
+use sniprrr::time_utils;
+
+const SNIPPETS: usize = 50_000;
+
+fn store(c: &mut Criterion) {
+    let snippets = generate(SNIPPETS, SEED);
+    let contents = store_contents(&snippets).expect("the generated store serializes");
+
+    c.bench_function("load", |b| {
+        b.iter(|| parse_store(black_box(&contents)).expect("the generated store parses"))
+    });
+
+    c.bench_function("search", |b| {
+        b.iter(|| {
+            snippets
+                .iter()
+                .filter_map(|snippet| search::score(snippet, black_box("deploy prod")))
+                .count()
+        })
+    });
+
+    let marked = BTreeSet::new();
+    let collapsed = HashMap::new();
+    let theme = Theme::default();
+    let visible: Vec<usize> = (0..snippets.len()).collect();
+    let now = time_utils::now();
+    c.bench_function("render rows", |b| {
+        let view = TableView {
+            snippets: &snippets,
+            marked: &marked,
+            filter: "deploy",
+            tag_filter: None,
+            single_line: false,
+            description_offset: 0,
+            collapsed: &collapsed,
+            uses: false,
+            template: None,
+            theme: &theme,
+        };
+        b.iter(|| view.rows(black_box(&visible), now).len())
+    });
+
+    let dir = std::env::temp_dir().join(format!("sniprrr-bench-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("the temporary directory is writable");
+    let path = dir.join("messages.json");
+    c.bench_function("save", |b| {
+        b.iter(|| write_store(&path, black_box(&snippets)).expect("the store is written"))
+    });
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(10);
+    targets = store
+}
+criterion_main!(benches);
@@ -0,0 +1,79 @@
+//! Optional zstd compression of the store file, for libraries that grew large
+//! with binary snippets or long logs. Configured with `[compression]`; reading
+//! recognizes compressed files by their first bytes, so every store file loads
+//! whichever way it was written.
+
+use std::io::{self, Read};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde::Deserialize;
+
+/// First bytes of every zstd frame
+const MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+/// zstd's default, fast enough to run on every save
+const LEVEL: i32 = 3;
+
+/// The `[compression]` section of the config file
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CompressionConfig {
+    pub enabled: bool,
+    /// Stores smaller than this many bytes are written as plain JSON
+    pub above: u64,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> CompressionConfig {
+        CompressionConfig {
+            enabled: false,
+            above: 1024 * 1024,
+        }
+    }
+}
+
+/// Size from which stores are compressed, `u64::MAX` while compression is off.
+/// Set once the config is read since stores are written from many places.
+static THRESHOLD: AtomicU64 = AtomicU64::new(u64::MAX);
+
+pub fn configure(config: &CompressionConfig) {
+    let threshold = if config.enabled {
+        config.above
+    } else {
+        u64::MAX
+    };
+    THRESHOLD.store(threshold, Ordering::Relaxed);
+}
+
+pub fn is_compressed(bytes: &[u8]) -> bool {
+    bytes.starts_with(&MAGIC)
+}
+
+/// The bytes to write for a store holding `contents`, compressed when it is
+/// above the configured size
+pub fn encode(contents: String) -> io::Result<Vec<u8>> {
+    if (contents.len() as u64) < THRESHOLD.load(Ordering::Relaxed) {
+        return Ok(contents.into_bytes());
+    }
+    zstd::encode_all(contents.as_bytes(), LEVEL)
+}
+
+/// The text of a store file, decompressing it first if needed
+pub fn decode(bytes: &[u8]) -> io::Result<String> {
+    let bytes = match is_compressed(bytes) {
+        true => zstd::decode_all(bytes)?,
+        false => bytes.to_vec(),
+    };
+    String::from_utf8(bytes).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+}
+
+/// As much text as can be decompressed from the start of a damaged file
+pub fn decode_partial(bytes: &[u8]) -> String {
+    let mut text = vec![];
+    if let Ok(mut decoder) = zstd::stream::read::Decoder::new(bytes) {
+        let mut buffer = [0; 8192];
+        while let Ok(read @ 1..) = decoder.read(&mut buffer) {
+            text.extend_from_slice(&buffer[..read]);
+        }
+    }
+    String::from_utf8_lossy(&text).into_owned()
+}
@@ -0,0 +1,160 @@
+//! Optional encryption of the store file at rest, so snippets can hold API
+//! tokens and other secrets. Configured with `[encryption]`; the passphrase is
+//! asked for at start-up, turned into a key with Argon2id and the file sealed
+//! with ChaCha20-Poly1305. Reading recognizes encrypted files by their first
+//! bytes, so turning encryption off writes the store in the clear on the next
+//! save. Exports are never encrypted.
+
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+
+use argon2::Argon2;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use serde::Deserialize;
+
+/// First bytes of an encrypted store, followed by the salt, the nonce and
+/// the sealed contents
+const MAGIC: &[u8; 8] = b"sniprrr\x01";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// The `[encryption]` section of the config file
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct EncryptionConfig {
+    pub enabled: bool,
+}
+
+/// The passphrase given at start-up and the key derived from it for `salt`.
+/// New files are written with the same salt, so the key is derived once.
+struct Session {
+    passphrase: String,
+    salt: [u8; SALT_LEN],
+    key: Key,
+}
+
+/// Set once the config is read since stores are written from many places
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static SESSION: RwLock<Option<Session>> = RwLock::new(None);
+
+pub fn configure(config: &EncryptionConfig) {
+    ENABLED.store(config.enabled, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+pub fn is_encrypted(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> io::Result<Key> {
+    let mut key = Key::default();
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|error| invalid(error.to_string()))?;
+    Ok(key)
+}
+
+/// Splits an encrypted file into its salt, nonce and sealed contents
+fn parts(bytes: &[u8]) -> io::Result<(&[u8], &[u8], &[u8])> {
+    let rest = bytes
+        .strip_prefix(MAGIC.as_slice())
+        .filter(|rest| rest.len() >= SALT_LEN + NONCE_LEN)
+        .ok_or_else(|| invalid("the encrypted file is cut short"))?;
+    let (salt, rest) = rest.split_at(SALT_LEN);
+    let (nonce, sealed) = rest.split_at(NONCE_LEN);
+    Ok((salt, nonce, sealed))
+}
+
+fn open(key: &Key, nonce: &[u8], sealed: &[u8]) -> io::Result<Vec<u8>> {
+    ChaCha20Poly1305::new(key)
+        .decrypt(Nonce::from_slice(nonce), sealed)
+        .map_err(|_| invalid("wrong passphrase, or the encrypted file is damaged"))
+}
+
+/// Takes `passphrase` for the rest of the process. With the bytes of an
+/// encrypted store it must open that store, whose salt is kept; otherwise a
+/// new salt is picked.
+pub fn unlock(passphrase: &str, existing: Option<&[u8]>) -> io::Result<()> {
+    if let Some(bytes) = existing.filter(|bytes| is_encrypted(bytes)) {
+        let (salt, nonce, sealed) = parts(bytes)?;
+        let key = derive_key(passphrase, salt)?;
+        open(&key, nonce, sealed)?;
+        let mut kept = [0; SALT_LEN];
+        kept.copy_from_slice(salt);
+        return set_session(passphrase, kept, key);
+    }
+    let mut salt = [0; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    set_session(passphrase, salt, derive_key(passphrase, &salt)?)
+}
+
+fn set_session(passphrase: &str, salt: [u8; SALT_LEN], key: Key) -> io::Result<()> {
+    let mut session = SESSION
+        .write()
+        .map_err(|_| io::Error::other("the passphrase lock is poisoned"))?;
+    *session = Some(Session {
+        passphrase: passphrase.to_string(),
+        salt,
+        key,
+    });
+    Ok(())
+}
+
+/// The bytes to write for a store holding `contents`, sealed while
+/// encryption is on
+pub fn encrypt(contents: Vec<u8>) -> io::Result<Vec<u8>> {
+    if !enabled() {
+        return Ok(contents);
+    }
+    let session = SESSION
+        .read()
+        .map_err(|_| io::Error::other("the passphrase lock is poisoned"))?;
+    let session = session.as_ref().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "encryption is on but no passphrase was given",
+        )
+    })?;
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let sealed = ChaCha20Poly1305::new(&session.key)
+        .encrypt(&nonce, contents.as_slice())
+        .map_err(|_| io::Error::other("could not encrypt the store"))?;
+    let mut bytes = Vec::with_capacity(MAGIC.len() + SALT_LEN + NONCE_LEN + sealed.len());
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&session.salt);
+    bytes.extend_from_slice(&nonce);
+    bytes.extend_from_slice(&sealed);
+    Ok(bytes)
+}
+
+/// The contents of a store file, opening it first if it is encrypted
+pub fn decrypt(bytes: &[u8]) -> io::Result<Vec<u8>> {
+    if !is_encrypted(bytes) {
+        return Ok(bytes.to_vec());
+    }
+    let (salt, nonce, sealed) = parts(bytes)?;
+    let session = SESSION
+        .read()
+        .map_err(|_| io::Error::other("the passphrase lock is poisoned"))?;
+    let session = session.as_ref().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "the store is encrypted and no passphrase was given",
+        )
+    })?;
+    // Backups and other stores may have been written with another salt
+    match salt == session.salt {
+        true => open(&session.key, nonce, sealed),
+        false => open(&derive_key(&session.passphrase, salt)?, nonce, sealed),
+    }
+}
@@ -0,0 +1,288 @@
+use crate::compression;
+use crate::history;
+use crate::models::Snippet;
+use crate::storage::{self, Backend};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::{DirBuilder, File};
+use std::io::ErrorKind::NotFound;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::{fs, io};
+
+/// Version of the `messages.json` layout written by this build.
+/// Files from before versioning are a bare array of snippets.
+pub const STORE_VERSION: u64 = 1;
+
+/// How many previous versions of the store are kept next to it
+pub const BACKUP_COUNT: usize = 5;
+
+#[derive(Serialize)]
+struct StoreFileRef<'a> {
+    version: u64,
+    snippets: &'a [Snippet],
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoreFile {
+    Versioned {
+        version: u64,
+        snippets: Vec<Snippet>,
+    },
+    Legacy(Vec<Snippet>),
+}
+
+/// Why the store could not be loaded
+#[derive(Debug)]
+pub enum StoreError {
+    /// Written by a newer sniprrr
+    UnsupportedVersion(u64),
+    /// The file is not valid JSON, e.g. truncated by an interrupted write.
+    /// `salvaged` holds the entries that could still be read from the start of the file.
+    Corrupt {
+        reason: String,
+        salvaged: Vec<Snippet>,
+    },
+    Io(io::Error),
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreError::UnsupportedVersion(version) => write!(
+                f,
+                "The store was written by a newer version of sniprrr (format version {}, this build supports {})",
+                version, STORE_VERSION
+            ),
+            StoreError::Corrupt { reason, .. } => write!(f, "The store is damaged: {}", reason),
+            StoreError::Io(error) => write!(f, "Could not read the store: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for StoreError {}
+
+/// Store file used instead of `messages.json` in the config directory.
+/// Set once the config is read since the store is opened from many places.
+static STORE_PATH: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+/// Keeps the store at `path` from now on
+pub fn set_store_path(path: PathBuf) {
+    if let Ok(mut store_path) = STORE_PATH.write() {
+        *store_path = Some(path);
+    }
+}
+
+pub fn messages_file_path() -> Option<PathBuf> {
+    if let Some(path) = STORE_PATH.read().ok().and_then(|path| path.clone()) {
+        return Some(path);
+    }
+    default_store_path(storage::configured())
+}
+
+/// Where a store written with `backend` is kept when `store` isn't set
+pub fn default_store_path(backend: Backend) -> Option<PathBuf> {
+    Some(
+        dirs::config_dir()?
+            .join("sniprrr")
+            .join(backend.file_name()),
+    )
+}
+
+pub fn backup_file_path(store_path: &Path, number: usize) -> PathBuf {
+    let mut file_name = store_path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(format!(".bak.{}", number));
+    store_path.with_file_name(file_name)
+}
+
+pub fn write_messages_to_file(snippets: &[Snippet]) -> io::Result<()> {
+    let app_config_path = messages_file_path();
+
+    if app_config_path.is_none() {
+        return Err(io::Error::new(NotFound, "No app config dir"));
+    }
+
+    // Safe to unwrap, just checked.
+    let app_config_path = app_config_path.unwrap();
+
+    save_store(&app_config_path, snippets)
+}
+
+/// Replaces the store at `path` with `snippets` in the format it is kept in,
+/// keeping the one it replaces as a backup, unless only usage changed, and in
+/// the daily history. A store
+/// that can't be read anymore is left alone, since replacing it takes the
+/// user's choice of what to recover (see `integrity` in the app), and the
+/// snippets go to `<name>.unsaved` instead.
+pub fn save_store(path: &Path, snippets: &[Snippet]) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        DirBuilder::new().recursive(true).create(parent)?;
+    }
+
+    let stored = match path.exists() {
+        true => match load_store(path) {
+            Err(error @ (StoreError::Corrupt { .. } | StoreError::UnsupportedVersion(_))) => {
+                let unsaved = unsaved_file_path(path);
+                Backend::Json.storage().save(&unsaved, snippets)?;
+                return Err(io::Error::other(format!(
+                    "{}. It was left as it is and the snippets were written to {}, restart sniprrr to repair the store",
+                    error,
+                    unsaved.display()
+                )));
+            }
+            stored => stored.ok(),
+        },
+        false => None,
+    };
+
+    history::record_snapshot(path)?;
+    // Every copy saves its count, which would soon push out the backups
+    // worth recovering from
+    if stored.is_none_or(|stored| content_changed(&stored, snippets)) {
+        rotate_backups(path)?;
+    }
+    storage::detect(path).storage().save(path, snippets)
+}
+
+/// Whether `after` differs from `before` in more than what copying a snippet
+/// changes: its count, when it was last used and the values filled in
+fn content_changed(before: &[Snippet], after: &[Snippet]) -> bool {
+    let content = |snippets: &[Snippet]| -> Vec<Snippet> {
+        snippets
+            .iter()
+            .map(|snippet| {
+                let mut snippet = snippet.clone();
+                snippet.copy_count = 0;
+                snippet.last_used_at = None;
+                snippet.parameter_history.clear();
+                snippet
+            })
+            .collect()
+    };
+    serde_json::to_value(content(before)).ok() != serde_json::to_value(content(after)).ok()
+}
+
+/// Writes `snippets` to `path` as a JSON store, compressed when configured to
+/// and large enough. Used for exports, which are never encrypted.
+pub fn write_store(path: &Path, snippets: &[Snippet]) -> io::Result<()> {
+    write_atomically(path, &compression::encode(store_contents(snippets)?)?)
+}
+
+/// Writes `contents` to a temporary file next to `path` and renames it over
+/// `path`, so a crash midway leaves the previous file whole
+pub fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut temporary_name = path.file_name().unwrap_or_default().to_os_string();
+    temporary_name.push(".tmp");
+    let temporary = path.with_file_name(temporary_name);
+
+    let mut file = File::create(&temporary)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    fs::rename(&temporary, path)
+}
+
+/// Where `save_store` puts the snippets when the store can't be read
+fn unsaved_file_path(store_path: &Path) -> PathBuf {
+    let mut file_name = store_path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".unsaved");
+    store_path.with_file_name(file_name)
+}
+
+/// What the store file holds for `snippets` once written
+pub fn store_contents(snippets: &[Snippet]) -> serde_json::Result<String> {
+    serde_json::to_string(&StoreFileRef {
+        version: STORE_VERSION,
+        snippets,
+    })
+}
+
+/// Shifts `messages.json.bak.1..N` up by one and copies the current store into `.bak.1`
+pub fn rotate_backups(store_path: &Path) -> io::Result<()> {
+    if !store_path.exists() {
+        return Ok(());
+    }
+
+    for number in (1..BACKUP_COUNT).rev() {
+        let from = backup_file_path(store_path, number);
+        if from.exists() {
+            fs::rename(&from, backup_file_path(store_path, number + 1))?;
+        }
+    }
+
+    fs::copy(store_path, backup_file_path(store_path, 1))?;
+    Ok(())
+}
+
+/// Loads the store, returning an empty list when it doesn't exist yet
+pub fn load_messages_from_file() -> Result<Vec<Snippet>, StoreError> {
+    let app_config_path = match messages_file_path() {
+        Some(app_config_path) => app_config_path,
+        None => return Ok(vec![]),
+    };
+
+    if !app_config_path.exists() {
+        return Ok(vec![]);
+    }
+
+    load_store(&app_config_path)
+}
+
+/// Reads and validates the store file at `path`, whichever backend wrote it
+pub fn load_store(path: &Path) -> Result<Vec<Snippet>, StoreError> {
+    storage::detect(path).storage().load(path)
+}
+
+pub fn parse_store(file_contents: &str) -> Result<Vec<Snippet>, StoreError> {
+    match serde_json::from_str::<StoreFile>(file_contents) {
+        Ok(StoreFile::Legacy(snippets)) => Ok(snippets),
+        Ok(StoreFile::Versioned { version, snippets }) => {
+            if version > STORE_VERSION {
+                Err(StoreError::UnsupportedVersion(version))
+            } else {
+                Ok(snippets)
+            }
+        }
+        Err(_) => {
+            // The untagged enum hides the real error, so parse again for a useful message
+            let reason = match serde_json::from_str::<serde_json::Value>(file_contents) {
+                Err(error) if error.is_eof() => format!("the file ends early ({})", error),
+                Err(error) => error.to_string(),
+                Ok(_) => "the contents don't match the snippet format".to_string(),
+            };
+            Err(StoreError::Corrupt {
+                reason,
+                salvaged: salvage_entries(file_contents),
+            })
+        }
+    }
+}
+
+/// Reads as many complete snippets as possible from the start of the snippet array
+pub(crate) fn salvage_entries(file_contents: &str) -> Vec<Snippet> {
+    let array_start = match file_contents.find("\"snippets\"") {
+        Some(key) => file_contents[key..].find('[').map(|offset| key + offset),
+        None => file_contents.find('['),
+    };
+
+    let mut salvaged = Vec::new();
+    let mut rest = match array_start {
+        Some(start) => &file_contents[start + 1..],
+        None => return salvaged,
+    };
+
+    loop {
+        let mut stream = serde_json::Deserializer::from_str(rest).into_iter::<Snippet>();
+        match stream.next() {
+            Some(Ok(snippet)) => salvaged.push(snippet),
+            _ => return salvaged,
+        }
+
+        rest = rest[stream.byte_offset()..].trim_start();
+        match rest.strip_prefix(',') {
+            Some(after_comma) => rest = after_comma,
+            None => return salvaged,
+        }
+    }
+}
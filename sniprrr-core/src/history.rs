@@ -0,0 +1,136 @@
+//! Earlier states of the store: the rotating backups plus one snapshot per
+//! day in `history/`, which reach back far enough to recover a snippet that
+//! was deleted weeks ago.
+
+use std::cmp::Reverse;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::file_utils::{backup_file_path, load_store, BACKUP_COUNT};
+use crate::models::Snippet;
+use crate::time_utils::{self, SECONDS_PER_DAY};
+
+/// How many daily snapshots are kept
+pub const HISTORY_LIMIT: usize = 90;
+
+/// A readable earlier version of the store
+pub struct StoreState {
+    pub path: PathBuf,
+    /// Unix timestamp of when this state was replaced by a newer one
+    pub until: u64,
+    pub snippets: Vec<Snippet>,
+}
+
+fn history_dir(store_path: &Path) -> PathBuf {
+    store_path.with_file_name("history")
+}
+
+/// Daily snapshots as `(timestamp, path)`, oldest first
+pub fn snapshots(store_path: &Path) -> Vec<(u64, PathBuf)> {
+    let prefix = format!(
+        "{}.",
+        store_path.file_name().unwrap_or_default().to_string_lossy()
+    );
+    let mut snapshots: Vec<(u64, PathBuf)> = fs::read_dir(history_dir(store_path))
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let timestamp = path
+                .file_name()?
+                .to_str()?
+                .strip_prefix(&prefix)?
+                .parse::<u64>()
+                .ok()?;
+            Some((timestamp, path))
+        })
+        .collect();
+    snapshots.sort();
+    snapshots
+}
+
+/// Copies the store into `history/` before the first save of each day and
+/// drops the snapshots beyond `HISTORY_LIMIT`
+pub fn record_snapshot(store_path: &Path) -> io::Result<()> {
+    if !store_path.exists() {
+        return Ok(());
+    }
+
+    let now = time_utils::now();
+    let mut snapshots = snapshots(store_path);
+    if snapshots
+        .last()
+        .is_some_and(|(timestamp, _)| timestamp / SECONDS_PER_DAY == now / SECONDS_PER_DAY)
+    {
+        return Ok(());
+    }
+
+    let dir = history_dir(store_path);
+    fs::create_dir_all(&dir)?;
+    let mut file_name = store_path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(format!(".{}", now));
+    let path = dir.join(file_name);
+    fs::copy(store_path, &path)?;
+    snapshots.push((now, path));
+
+    let excess = snapshots.len().saturating_sub(HISTORY_LIMIT);
+    for (_, path) in snapshots.drain(..excess) {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+/// Every readable earlier state, newest first
+pub fn states(store_path: &Path) -> Vec<StoreState> {
+    // A backup is copied into place when the store is replaced, so its
+    // modification time is when it stopped being current
+    let backups = (1..=BACKUP_COUNT).filter_map(|number| {
+        let path = backup_file_path(store_path, number);
+        let until = fs::metadata(&path)
+            .and_then(|metadata| metadata.modified())
+            .ok()?
+            .duration_since(UNIX_EPOCH)
+            .ok()?
+            .as_secs();
+        Some((until, path))
+    });
+
+    let mut states: Vec<StoreState> = backups
+        .chain(snapshots(store_path))
+        .filter_map(|(until, path)| {
+            let snippets = load_store(&path).ok()?;
+            Some(StoreState {
+                path,
+                until,
+                snippets,
+            })
+        })
+        .collect();
+    states.sort_by_key(|state| Reverse(state.until));
+    states
+}
+
+/// The state that was current at `time`, `None` when that is the current store
+pub fn state_at(states: &[StoreState], time: u64) -> Option<&StoreState> {
+    states
+        .iter()
+        .filter(|state| state.until > time)
+        .min_by_key(|state| state.until)
+}
+
+/// Puts `snippet` back, replacing the snippet with the same title if there is one.
+/// Returns whether an existing snippet was replaced.
+pub fn restore_snippet(snippets: &mut Vec<Snippet>, snippet: Snippet) -> bool {
+    match snippets.iter_mut().find(|s| s.title == snippet.title) {
+        Some(existing) => {
+            *existing = snippet;
+            true
+        }
+        None => {
+            snippets.push(snippet);
+            false
+        }
+    }
+}
@@ -0,0 +1,20 @@
+//! The sniprrr snippet store without the TUI, for editor plugins, scripts and
+//! other tools. `SnippetStore` covers the usual reads and edits; the modules
+//! below it give access to the file format, storage backends, backups and
+//! history.
+
+pub mod compression;
+pub mod encryption;
+pub mod file_utils;
+pub mod history;
+pub mod models;
+pub mod namespace;
+pub mod schedule;
+pub mod search;
+pub mod storage;
+pub mod store;
+pub mod time_utils;
+
+pub use file_utils::StoreError;
+pub use models::Snippet;
+pub use store::SnippetStore;
@@ -0,0 +1,184 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{schedule, time_utils};
+
+/// Snippet
+/// Snippets have a title and a description
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Snippet {
+    pub title: String,
+    pub description: String,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+
+    /// Language of the body, e.g. `sql` or `bash`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+
+    /// Color shown in the label column of the table
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<LabelColor>,
+
+    /// Single character or emoji shown in the label column of the table
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+
+    /// Unix timestamp of when the snippet was added
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<u64>,
+
+    /// Unix timestamp of the last change to the title or description
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<u64>,
+
+    /// Unix timestamp of the last time the snippet was copied
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_used_at: Option<u64>,
+
+    /// How many times the snippet has been copied
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub copy_count: u32,
+
+    /// Time windows when the snippet is suggested, e.g. `19:00-20:00` (see `schedule`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schedule: Option<String>,
+
+    /// Title of the snippet whose run produced this one as its output
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_of: Option<String>,
+
+    /// Named alternatives to the description, e.g. for another shell, picked
+    /// from when copying (see `variants`)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub variants: BTreeMap<String, String>,
+
+    /// The description holds base64-encoded binary data (keys, certificates, small files)
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub binary: bool,
+
+    /// Value a template placeholder starts with before it has been filled in once
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub field_defaults: BTreeMap<String, String>,
+
+    /// Imported and not accepted yet, so it is never run before being looked at
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub needs_review: bool,
+
+    /// Fingerprint of the `{{cmd:...}}` commands allowed to run without asking
+    /// (see `template::commands_fingerprint`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trusted_commands: Option<String>,
+
+    /// Previously entered values for each template placeholder, most recent first
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub parameter_history: BTreeMap<String, Vec<String>>,
+
+    /// Environment variables a run of the snippet needs, with the value used
+    /// when the environment doesn't set one. An empty value has to be entered.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+
+    /// Directory a run of the snippet starts in, `~/` for the home directory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub working_dir: Option<String>,
+
+    /// Lives only for the current session of the TUI, as a scratchpad, and is
+    /// never written to the store
+    #[serde(skip)]
+    pub temporary: bool,
+
+    /// Name of the read-only source the snippet is layered in from (see
+    /// `sources` in the config), never written to the store
+    #[serde(skip)]
+    pub source: Option<String>,
+}
+
+/// Colors a snippet can be labelled with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LabelColor {
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    Gray,
+}
+
+impl LabelColor {
+    pub const ALL: [LabelColor; 7] = [
+        LabelColor::Red,
+        LabelColor::Green,
+        LabelColor::Yellow,
+        LabelColor::Blue,
+        LabelColor::Magenta,
+        LabelColor::Cyan,
+        LabelColor::Gray,
+    ];
+}
+
+/// How many previous values are kept per placeholder
+const PARAMETER_HISTORY_LIMIT: usize = 10;
+
+fn is_zero(value: &u32) -> bool {
+    *value == 0
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+impl Snippet {
+    pub fn new(title: String, description: String) -> Snippet {
+        let now = time_utils::now();
+        Snippet {
+            title,
+            description,
+            created_at: Some(now),
+            updated_at: Some(now),
+            ..Default::default()
+        }
+    }
+
+    /// Records a copy of this snippet for the usage statistics
+    pub fn mark_used(&mut self) {
+        self.copy_count += 1;
+        self.last_used_at = Some(time_utils::now());
+    }
+
+    /// Whether the snippet's schedule covers the current time
+    pub fn is_scheduled_now(&self, now: u64) -> bool {
+        self.schedule
+            .as_deref()
+            .is_some_and(|schedule| schedule::is_active(schedule, now))
+    }
+
+    /// Stores the values used to fill in the placeholders so the next copy can pre-fill them
+    pub fn remember_parameters(&mut self, values: &HashMap<String, String>) {
+        for (name, value) in values {
+            let history = self.parameter_history.entry(name.clone()).or_default();
+            history.retain(|previous| previous != value);
+            history.insert(0, value.clone());
+            history.truncate(PARAMETER_HISTORY_LIMIT);
+        }
+    }
+}
+
+/// Splits comma separated tags, dropping empty and repeated ones
+pub fn parse_tags(value: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in value
+        .split(',')
+        .map(str::trim)
+        .filter(|tag| !tag.is_empty())
+    {
+        if !tags.iter().any(|existing| existing == tag) {
+            tags.push(tag.to_string());
+        }
+    }
+    tags
+}
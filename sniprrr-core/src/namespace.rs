@@ -0,0 +1,22 @@
+//! `/` in titles as lightweight namespaces, e.g. `docker/prune/volumes`.
+//! A search ending in `/` lists only that namespace.
+
+/// The namespace of `title` and the name in it, e.g. `(Some("docker/prune"), "volumes")`
+pub fn split(title: &str) -> (Option<&str>, &str) {
+    match title.rsplit_once('/') {
+        Some((namespace, name)) if !namespace.is_empty() => (Some(namespace), name),
+        _ => (None, title),
+    }
+}
+
+/// Whether `title` is in `namespace` or one of the namespaces below it
+pub fn contains(namespace: &str, title: &str) -> bool {
+    title
+        .strip_prefix(namespace)
+        .is_some_and(|rest| rest.len() > 1 && rest.starts_with('/'))
+}
+
+/// Same as `contains`, ignoring case, for searches
+pub fn contains_ignore_case(namespace: &str, title: &str) -> bool {
+    contains(&namespace.to_lowercase(), &title.to_lowercase())
+}
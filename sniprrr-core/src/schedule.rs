@@ -0,0 +1,59 @@
+//! Time windows during which a snippet is especially relevant, written as
+//! `19:00-20:00`. Several windows can be separated by commas and a window may
+//! wrap around midnight (`23:30-01:00`).
+
+use crate::time_utils;
+
+struct Window {
+    start: u32,
+    end: u32,
+}
+
+impl Window {
+    fn contains(&self, minute: u32) -> bool {
+        if self.start <= self.end {
+            (self.start..self.end).contains(&minute)
+        } else {
+            minute >= self.start || minute < self.end
+        }
+    }
+}
+
+fn parse_time(input: &str) -> Result<u32, String> {
+    let (hours, minutes) = input.trim().split_once(':').unwrap_or((input.trim(), "0"));
+    let hours = hours.parse::<u32>().ok().filter(|h| *h <= 24);
+    let minutes = minutes.parse::<u32>().ok().filter(|m| *m < 60);
+    match (hours, minutes) {
+        (Some(hours), Some(minutes)) => Ok((hours * 60 + minutes) % (24 * 60)),
+        _ => Err(format!("'{}' is not a time like 19:30", input.trim())),
+    }
+}
+
+fn parse_windows(schedule: &str) -> Result<Vec<Window>, String> {
+    schedule
+        .split(',')
+        .filter(|window| !window.trim().is_empty())
+        .map(|window| {
+            let (start, end) = window
+                .split_once('-')
+                .ok_or_else(|| format!("'{}' is not a range like 19:00-20:00", window.trim()))?;
+            Ok(Window {
+                start: parse_time(start)?,
+                end: parse_time(end)?,
+            })
+        })
+        .collect()
+}
+
+/// Checks that `schedule` is well formed
+pub fn validate(schedule: &str) -> Result<(), String> {
+    parse_windows(schedule).map(|_| ())
+}
+
+/// Whether the current local time falls into one of the windows of `schedule`
+pub fn is_active(schedule: &str, now: u64) -> bool {
+    let minute = time_utils::local_minute_of_day(now);
+    parse_windows(schedule)
+        .map(|windows| windows.iter().any(|window| window.contains(minute)))
+        .unwrap_or(false)
+}
@@ -0,0 +1,127 @@
+//! Finding snippets: a fuzzy match as typed after `/` in the table, and the
+//! case-insensitive substring match `sniprrr apply` filters with
+
+use crate::models::Snippet;
+use crate::namespace;
+
+/// Byte ranges of every non-overlapping case-insensitive occurrence of `query` in `text`
+pub fn match_ranges(text: &str, query: &str) -> Vec<(usize, usize)> {
+    let needle: Vec<char> = query.chars().flat_map(char::to_lowercase).collect();
+    if needle.is_empty() {
+        return vec![];
+    }
+
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let mut ranges = vec![];
+    let mut start = 0;
+    while start < chars.len() {
+        match match_length(&chars[start..], &needle) {
+            Some(length) => {
+                let end = chars
+                    .get(start + length)
+                    .map_or(text.len(), |(offset, _)| *offset);
+                ranges.push((chars[start].0, end));
+                start += length;
+            }
+            None => start += 1,
+        }
+    }
+    ranges
+}
+
+/// How many chars of `haystack` match `needle` from its start, if they all do
+fn match_length(haystack: &[(usize, char)], needle: &[char]) -> Option<usize> {
+    let mut needle = needle.iter();
+    for (consumed, (_, c)) in haystack.iter().enumerate() {
+        for lower in c.to_lowercase() {
+            if needle.next() != Some(&lower) {
+                return None;
+            }
+        }
+        if needle.len() == 0 {
+            return Some(consumed + 1);
+        }
+    }
+    None
+}
+
+/// Bonus for each matched char that follows the previous one directly
+const CONSECUTIVE_BONUS: i64 = 8;
+/// Bonus for a matched char that starts a word, e.g. the `p` of `docker prune`
+const WORD_START_BONUS: i64 = 6;
+/// Matches in the title rank above the same match in the description
+const TITLE_BONUS: i64 = 20;
+
+/// How well `query` matches `text` when its chars appear in `text` in the
+/// same order, but not necessarily next to each other: `dkrprn` matches
+/// `docker prune`. Byte ranges of the matched chars come along for highlighting.
+pub fn fuzzy_match(text: &str, query: &str) -> Option<(i64, Vec<(usize, usize)>)> {
+    let needle: Vec<char> = query.chars().flat_map(char::to_lowercase).collect();
+    if needle.is_empty() {
+        return Some((0, vec![]));
+    }
+    // A plain occurrence beats any scattered match of the same chars
+    if let Some(&range) = match_ranges(text, query).first() {
+        let score = (CONSECUTIVE_BONUS + 1) * needle.len() as i64 + WORD_START_BONUS;
+        return Some((score, vec![range]));
+    }
+
+    let mut ranges: Vec<(usize, usize)> = vec![];
+    let mut score = 0;
+    let mut needle = needle.iter().peekable();
+    let mut previous: Option<char> = None;
+    let mut last_matched = false;
+    for (offset, c) in text.char_indices() {
+        let lower = c.to_lowercase().next().unwrap_or(c);
+        if needle.peek() == Some(&&lower) {
+            needle.next();
+            score += 1;
+            if last_matched {
+                score += CONSECUTIVE_BONUS;
+            }
+            if previous.is_none_or(|previous| !previous.is_alphanumeric()) {
+                score += WORD_START_BONUS;
+            }
+            match ranges.last_mut() {
+                Some((_, end)) if *end == offset => *end += c.len_utf8(),
+                _ => ranges.push((offset, offset + c.len_utf8())),
+            }
+            last_matched = true;
+            if needle.peek().is_none() {
+                return Some((score, ranges));
+            }
+        } else {
+            last_matched = false;
+        }
+        previous = Some(c);
+    }
+    None
+}
+
+/// Rank of `snippet` for the `/` filter, `None` when it isn't listed. Every
+/// word of the query has to match the title or the description fuzzily. A
+/// query ending in `/` such as `docker/` lists the snippets in that namespace.
+pub fn score(snippet: &Snippet, query: &str) -> Option<i64> {
+    if let Some(prefix) = query.strip_suffix('/').filter(|prefix| !prefix.is_empty()) {
+        return namespace::contains_ignore_case(prefix, &snippet.title).then_some(0);
+    }
+    query.split_whitespace().try_fold(0, |total, term| {
+        let title = fuzzy_match(&snippet.title, term).map(|(score, _)| score + TITLE_BONUS);
+        let description = match snippet.binary {
+            true => None,
+            false => fuzzy_match(&snippet.description, term).map(|(score, _)| score),
+        };
+        Some(total + title.max(description)?)
+    })
+}
+
+/// Whether `snippet` contains `query` in its title or description. A query
+/// ending in `/` such as `docker/` matches the snippets in that namespace.
+pub fn matches(snippet: &Snippet, query: &str) -> bool {
+    if let Some(prefix) = query.strip_suffix('/').filter(|prefix| !prefix.is_empty()) {
+        return namespace::contains_ignore_case(prefix, &snippet.title);
+    }
+    query.is_empty()
+        || !match_ranges(&snippet.title, query).is_empty()
+        || !match_ranges(&snippet.description, query).is_empty()
+}
@@ -0,0 +1,40 @@
+//! The JSON store: the snippets as one versioned document, zstd-compressed
+//! when `[compression]` says so and encrypted when `[encryption]` does
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use super::Storage;
+use crate::file_utils::{
+    parse_store, salvage_entries, store_contents, write_atomically, StoreError,
+};
+use crate::models::Snippet;
+use crate::{compression, encryption};
+
+pub struct Json;
+
+impl Storage for Json {
+    fn load(&self, path: &Path) -> Result<Vec<Snippet>, StoreError> {
+        // A store that can't be opened is not offered for repair like a damaged one
+        let bytes = fs::read(path)
+            .and_then(|bytes| encryption::decrypt(&bytes))
+            .map_err(StoreError::Io)?;
+        let file_contents = match compression::decode(&bytes) {
+            Ok(file_contents) => file_contents,
+            Err(error) if compression::is_compressed(&bytes) => {
+                return Err(StoreError::Corrupt {
+                    reason: format!("the compressed file is damaged ({})", error),
+                    salvaged: salvage_entries(&compression::decode_partial(&bytes)),
+                })
+            }
+            Err(error) => return Err(StoreError::Io(error)),
+        };
+        parse_store(&file_contents)
+    }
+
+    fn save(&self, path: &Path, snippets: &[Snippet]) -> io::Result<()> {
+        let contents = compression::encode(store_contents(snippets)?)?;
+        write_atomically(path, &encryption::encrypt(contents)?)
+    }
+}
@@ -0,0 +1,170 @@
+//! How a store keeps its snippets on disk. `Storage` is implemented by the
+//! JSON file sniprrr has always written and by a SQLite database, which only
+//! writes the snippets that changed on each save. `[storage] backend` picks
+//! the one new stores are written with; existing stores are read and written
+//! in whatever format they are in, recognized by their first bytes.
+
+mod json;
+mod sqlite;
+
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use serde::Deserialize;
+
+use crate::file_utils::{default_store_path, messages_file_path, rotate_backups, StoreError};
+use crate::models::Snippet;
+
+pub use json::Json;
+pub use sqlite::Sqlite;
+
+/// Reads and writes a whole store at a path
+pub trait Storage {
+    /// The snippets kept at `path`, which exists
+    fn load(&self, path: &Path) -> Result<Vec<Snippet>, StoreError>;
+    /// Makes `path` hold exactly `snippets`, creating it when needed
+    fn save(&self, path: &Path, snippets: &[Snippet]) -> io::Result<()>;
+}
+
+/// The formats a store can be kept in
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// One JSON file, rewritten on every save and optionally compressed
+    #[default]
+    Json,
+    /// A SQLite database with a row per snippet
+    Sqlite,
+}
+
+impl Backend {
+    pub fn storage(self) -> &'static dyn Storage {
+        match self {
+            Backend::Json => &Json,
+            Backend::Sqlite => &Sqlite,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Backend::Json => "JSON",
+            Backend::Sqlite => "SQLite",
+        }
+    }
+
+    /// Name of the store in the config directory when `store` isn't set
+    pub fn file_name(self) -> &'static str {
+        match self {
+            Backend::Json => "messages.json",
+            Backend::Sqlite => "messages.db",
+        }
+    }
+
+    fn other(self) -> Backend {
+        match self {
+            Backend::Json => Backend::Sqlite,
+            Backend::Sqlite => Backend::Json,
+        }
+    }
+}
+
+/// The `[storage]` section of the config file
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct StorageConfig {
+    pub backend: Backend,
+}
+
+/// The backend stores are written with. Set once the config is read since
+/// the store is opened from many places.
+static BACKEND: RwLock<Backend> = RwLock::new(Backend::Json);
+
+pub fn configure(config: &StorageConfig) {
+    if let Ok(mut backend) = BACKEND.write() {
+        *backend = config.backend;
+    }
+}
+
+/// The backend set with `[storage] backend`
+pub fn configured() -> Backend {
+    BACKEND.read().map_or(Backend::Json, |backend| *backend)
+}
+
+/// The backend of the store at `path`, or the configured one while there is
+/// nothing there yet
+pub fn detect(path: &Path) -> Backend {
+    let mut header = [0; sqlite::HEADER.len()];
+    match File::open(path).and_then(|mut file| file.read_exact(&mut header)) {
+        Ok(()) if header == *sqlite::HEADER => Backend::Sqlite,
+        Ok(()) => Backend::Json,
+        Err(_) if path.exists() => Backend::Json,
+        Err(_) => configured(),
+    }
+}
+
+/// Writes `snippets` to `path` with the configured backend, replacing
+/// whatever is there in one step so a failed write leaves it untouched
+pub fn replace(path: &Path, snippets: &[Snippet]) -> io::Result<()> {
+    let mut temporary_name = path.file_name().unwrap_or_default().to_os_string();
+    temporary_name.push(".tmp");
+    let temporary = path.with_file_name(temporary_name);
+    if temporary.exists() {
+        fs::remove_file(&temporary)?;
+    }
+    configured().storage().save(&temporary, snippets)?;
+    fs::rename(&temporary, path)
+}
+
+/// Brings the store over to the configured backend after `backend` was
+/// changed: a store in the other format is converted in place, keeping the
+/// old file as the newest backup. With the default location the store of the
+/// other backend is read instead, e.g. `messages.json` into `messages.db`,
+/// and renamed to `<name>.migrated`. Returns what was done, if anything.
+pub fn migrate() -> Result<Option<String>, StoreError> {
+    let Some(path) = messages_file_path() else {
+        return Ok(None);
+    };
+    let backend = configured();
+
+    if path.exists() {
+        let current = detect(&path);
+        if current == backend {
+            return Ok(None);
+        }
+        let snippets = current.storage().load(&path)?;
+        rotate_backups(&path).map_err(StoreError::Io)?;
+        replace(&path, &snippets).map_err(StoreError::Io)?;
+        return Ok(Some(format!(
+            "Converted the store {} from {} to {} ({} snippets), the {} file is its newest backup",
+            path.display(),
+            current.name(),
+            backend.name(),
+            snippets.len(),
+            current.name()
+        )));
+    }
+
+    let old = match default_store_path(backend.other()) {
+        Some(old) if Some(&path) == default_store_path(backend).as_ref() && old.exists() => old,
+        _ => return Ok(None),
+    };
+    let snippets = detect(&old).storage().load(&old)?;
+    replace(&path, &snippets).map_err(StoreError::Io)?;
+    let migrated = migrated_path(&old);
+    fs::rename(&old, &migrated).map_err(StoreError::Io)?;
+    Ok(Some(format!(
+        "Moved {} snippets from {} into {}, the old file was kept as {}",
+        snippets.len(),
+        old.display(),
+        path.display(),
+        migrated.display()
+    )))
+}
+
+fn migrated_path(path: &Path) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".migrated");
+    path.with_file_name(file_name)
+}
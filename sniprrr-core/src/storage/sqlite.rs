@@ -0,0 +1,101 @@
+//! The SQLite store: a row per snippet at its position in the list. Each row
+//! holds the snippet as JSON, like the JSON store does, so new snippet fields
+//! need no schema change. Saving compares the rows with the snippets and
+//! only writes the ones that changed. The format version is kept in
+//! `PRAGMA user_version`.
+
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+
+use rusqlite::{params, Connection, OpenFlags};
+
+use super::Storage;
+use crate::file_utils::{StoreError, STORE_VERSION};
+use crate::models::Snippet;
+
+/// First bytes of every SQLite database
+pub(super) const HEADER: &[u8; 16] = b"SQLite format 3\0";
+
+const SCHEMA: &str =
+    "CREATE TABLE IF NOT EXISTS snippets (position INTEGER PRIMARY KEY, snippet TEXT NOT NULL)";
+
+pub struct Sqlite;
+
+impl Storage for Sqlite {
+    fn load(&self, path: &Path) -> Result<Vec<Snippet>, StoreError> {
+        let damaged = |error: rusqlite::Error| StoreError::Corrupt {
+            reason: error.to_string(),
+            salvaged: vec![],
+        };
+        let connection =
+            Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY).map_err(damaged)?;
+        let version: u64 = connection
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .map_err(damaged)?;
+        if version > STORE_VERSION {
+            return Err(StoreError::UnsupportedVersion(version));
+        }
+
+        let rows = connection
+            .prepare("SELECT snippet FROM snippets ORDER BY position")
+            .and_then(|mut statement| {
+                statement
+                    .query_map([], |row| row.get::<_, String>(0))?
+                    .collect::<rusqlite::Result<Vec<String>>>()
+            })
+            .map_err(damaged)?;
+
+        // A row that can't be read doesn't cost the others
+        let mut snippets = vec![];
+        let mut unreadable = None;
+        for row in rows {
+            match serde_json::from_str(&row) {
+                Ok(snippet) => snippets.push(snippet),
+                Err(error) => unreadable = unreadable.or(Some(error)),
+            }
+        }
+        match unreadable {
+            None => Ok(snippets),
+            Some(error) => Err(StoreError::Corrupt {
+                reason: format!("a snippet can't be read ({})", error),
+                salvaged: snippets,
+            }),
+        }
+    }
+
+    fn save(&self, path: &Path, snippets: &[Snippet]) -> io::Result<()> {
+        let rows = snippets
+            .iter()
+            .map(serde_json::to_string)
+            .collect::<serde_json::Result<Vec<String>>>()?;
+        write_rows(path, &rows).map_err(io::Error::other)
+    }
+}
+
+/// Makes the `snippets` table hold `rows` in order, in one transaction
+fn write_rows(path: &Path, rows: &[String]) -> rusqlite::Result<()> {
+    let mut connection = Connection::open(path)?;
+    let transaction = connection.transaction()?;
+    transaction.execute_batch(SCHEMA)?;
+    transaction.pragma_update(None, "user_version", STORE_VERSION)?;
+
+    let stored: HashMap<i64, String> = transaction
+        .prepare("SELECT position, snippet FROM snippets")?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+    {
+        let mut upsert = transaction
+            .prepare("INSERT OR REPLACE INTO snippets (position, snippet) VALUES (?1, ?2)")?;
+        for (position, row) in (0..).zip(rows) {
+            if stored.get(&position) != Some(row) {
+                upsert.execute(params![position, row])?;
+            }
+        }
+    }
+    transaction.execute(
+        "DELETE FROM snippets WHERE position >= ?1",
+        [rows.len() as i64],
+    )?;
+    transaction.commit()
+}
@@ -0,0 +1,114 @@
+//! `SnippetStore`: the snippets of one store file, with the edits other tools
+//! make to them. Titles are unique within a store, the way the app keeps them.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::file_utils::{load_store, messages_file_path, save_store, StoreError};
+use crate::models::Snippet;
+use crate::{search, time_utils};
+
+/// The snippets of a store file, read once and written back with `save`
+pub struct SnippetStore {
+    path: PathBuf,
+    snippets: Vec<Snippet>,
+}
+
+impl SnippetStore {
+    /// Reads the store the sniprrr app uses, in the user's config directory
+    pub fn load() -> Result<SnippetStore, StoreError> {
+        let path = messages_file_path().ok_or_else(|| {
+            StoreError::Io(io::Error::new(
+                io::ErrorKind::NotFound,
+                "There is no config directory to keep the store in",
+            ))
+        })?;
+        SnippetStore::load_from(&path)
+    }
+
+    /// Reads the store at `path`, empty when the file doesn't exist yet
+    pub fn load_from(path: &Path) -> Result<SnippetStore, StoreError> {
+        let snippets = match path.exists() {
+            true => load_store(path)?,
+            false => vec![],
+        };
+        Ok(SnippetStore {
+            path: path.to_path_buf(),
+            snippets,
+        })
+    }
+
+    /// Writes the snippets back, keeping the previous file as a backup
+    pub fn save(&self) -> io::Result<()> {
+        save_store(&self.path, &self.snippets)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn snippets(&self) -> &[Snippet] {
+        &self.snippets
+    }
+
+    pub fn get(&self, title: &str) -> Option<&Snippet> {
+        self.snippets.iter().find(|snippet| snippet.title == title)
+    }
+
+    /// Adds `snippet` at the end, unless its title is taken
+    pub fn add(&mut self, snippet: Snippet) -> Result<(), String> {
+        if self.get(&snippet.title).is_some() {
+            return Err(format!(
+                "There already is a snippet titled '{}'",
+                snippet.title
+            ));
+        }
+        self.snippets.push(snippet);
+        Ok(())
+    }
+
+    /// Removes the snippet titled `title` and returns it
+    pub fn remove(&mut self, title: &str) -> Option<Snippet> {
+        let index = self.position(title)?;
+        Some(self.snippets.remove(index))
+    }
+
+    /// Replaces the snippet titled `title` with `snippet`, which may be
+    /// renamed to a title that isn't taken. Marks it updated when its title
+    /// or description changed.
+    pub fn update(&mut self, title: &str, mut snippet: Snippet) -> Result<(), String> {
+        let index = self
+            .position(title)
+            .ok_or_else(|| format!("No snippet titled '{}'", title))?;
+        if snippet.title != title && self.get(&snippet.title).is_some() {
+            return Err(format!(
+                "There already is a snippet titled '{}'",
+                snippet.title
+            ));
+        }
+        let existing = &self.snippets[index];
+        if snippet.title != existing.title || snippet.description != existing.description {
+            snippet.updated_at = Some(time_utils::now());
+        }
+        self.snippets[index] = snippet;
+        Ok(())
+    }
+
+    /// The snippets matching `query` the way the `/` filter of the app does,
+    /// best match first. An empty query lists them all in store order.
+    pub fn search(&self, query: &str) -> Vec<&Snippet> {
+        let mut found: Vec<(i64, &Snippet)> = self
+            .snippets
+            .iter()
+            .filter_map(|snippet| Some((search::score(snippet, query)?, snippet)))
+            .collect();
+        found.sort_by_key(|&(score, _)| std::cmp::Reverse(score));
+        found.into_iter().map(|(_, snippet)| snippet).collect()
+    }
+
+    fn position(&self, title: &str) -> Option<usize> {
+        self.snippets
+            .iter()
+            .position(|snippet| snippet.title == title)
+    }
+}
@@ -0,0 +1,99 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const SECONDS_PER_DAY: u64 = 86_400;
+
+/// Current time as seconds since the Unix epoch
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+/// Converts a civil date to days since 1970-01-01 (proleptic Gregorian calendar)
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Converts days since 1970-01-01 to a (year, month, day) civil date
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = if days >= 0 { days } else { days - 146_096 } / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// Formats a Unix timestamp as `YYYY-MM-DD` (UTC)
+pub fn format_date(timestamp: u64) -> String {
+    let (year, month, day) = civil_from_days((timestamp / SECONDS_PER_DAY) as i64);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Parses a `YYYY-MM-DD` date into the Unix timestamp of its midnight (UTC)
+pub fn parse_date(input: &str) -> Option<u64> {
+    let mut parts = input.trim().splitn(3, '-');
+    let year = parts.next()?.parse::<i64>().ok()?;
+    let month = parts.next()?.parse::<u32>().ok()?;
+    let day = parts.next()?.parse::<u32>().ok()?;
+
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    let days = days_from_civil(year, month, day);
+    if days < 0 {
+        return None;
+    }
+    Some(days as u64 * SECONDS_PER_DAY)
+}
+
+/// Formats a Unix timestamp as `YYYY-MM-DD HH:MM` (UTC)
+pub fn format_datetime(timestamp: u64) -> String {
+    let seconds_of_day = timestamp % SECONDS_PER_DAY;
+    format!(
+        "{} {:02}:{:02}",
+        format_date(timestamp),
+        seconds_of_day / 3600,
+        (seconds_of_day % 3600) / 60
+    )
+}
+
+/// Offset of the local timezone from UTC in seconds at `timestamp`
+#[cfg(unix)]
+pub fn local_offset(timestamp: u64) -> i64 {
+    let time = timestamp as libc::time_t;
+    let mut tm = std::mem::MaybeUninit::<libc::tm>::zeroed();
+    // Safe: localtime_r only writes into the provided struct
+    let tm = unsafe {
+        if libc::localtime_r(&time, tm.as_mut_ptr()).is_null() {
+            return 0;
+        }
+        tm.assume_init()
+    };
+    tm.tm_gmtoff as i64
+}
+
+#[cfg(not(unix))]
+pub fn local_offset(_timestamp: u64) -> i64 {
+    0
+}
+
+/// Minutes since local midnight at `timestamp`
+pub fn local_minute_of_day(timestamp: u64) -> u32 {
+    let local = timestamp as i64 + local_offset(timestamp);
+    (local.rem_euclid(SECONDS_PER_DAY as i64) / 60) as u32
+}
@@ -0,0 +1,122 @@
+//! Exports shell snippets as functions for a shell profile, so the library
+//! doubles as the source of aliases: `aliases.sh` to source from `.bashrc` or
+//! `.zshrc`, or a PowerShell fragment (`.ps1`) for `$PROFILE`.
+//!
+//! Only snippets that are shell commands make it: those with a shell language,
+//! or tagged `shell` or `alias`. Templates are left out since their
+//! placeholders are filled in when copying. Each function is named after the
+//! title, e.g. `docker/prune volumes` becomes `docker_prune_volumes`.
+
+use std::fs;
+use std::path::Path;
+
+use crate::models::Snippet;
+use crate::{run, template};
+
+/// Tags that mark a snippet as a command for the export even without a language
+const TAGS: [&str; 2] = ["shell", "alias"];
+/// Languages of the snippets the PowerShell export takes in addition
+const POWERSHELL_LANGUAGES: [&str; 3] = ["powershell", "pwsh", "ps1"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shell {
+    /// sh, bash and zsh
+    Posix,
+    PowerShell,
+}
+
+impl Shell {
+    /// The shell a script at `path` is for, by its extension
+    pub fn for_path(path: &str) -> Option<Shell> {
+        let extension = Path::new(path)
+            .extension()?
+            .to_string_lossy()
+            .to_lowercase();
+        match extension.as_str() {
+            "sh" | "bash" | "zsh" => Some(Shell::Posix),
+            "ps1" => Some(Shell::PowerShell),
+            _ => None,
+        }
+    }
+}
+
+/// Whether `snippet` goes into the export for `shell`
+fn exported(snippet: &Snippet, shell: Shell) -> bool {
+    let tagged = snippet
+        .tags
+        .iter()
+        .any(|tag| TAGS.contains(&tag.to_lowercase().as_str()));
+    let language = snippet.language.as_deref().map(str::to_lowercase);
+    let shell_language = match language {
+        Some(language) if shell == Shell::PowerShell => {
+            POWERSHELL_LANGUAGES.contains(&language.as_str()) || run::is_runnable(snippet)
+        }
+        Some(_) => run::is_runnable(snippet),
+        None => false,
+    };
+    (shell_language || tagged && !snippet.binary)
+        && !snippet.temporary
+        && !snippet.description.trim().is_empty()
+        && template::placeholders(&snippet.description).is_empty()
+        && !template::has_expansions(&snippet.description)
+}
+
+/// `title` as a function name: lower case letters, digits and underscores
+fn function_name(title: &str) -> String {
+    let mut name = String::new();
+    for c in title.chars().flat_map(char::to_lowercase) {
+        if c.is_ascii_alphanumeric() {
+            name.push(c);
+        } else if !name.is_empty() && !name.ends_with('_') {
+            name.push('_');
+        }
+    }
+    let name = name.trim_end_matches('_');
+    match name.chars().next() {
+        None => "snippet".to_string(),
+        Some(first) if first.is_ascii_digit() => format!("_{}", name),
+        Some(_) => name.to_string(),
+    }
+}
+
+/// The script defining a function for each exported snippet, and how many there are
+pub fn render(snippets: &[Snippet], shell: Shell) -> (String, usize) {
+    let mut names: Vec<String> = vec![];
+    let mut functions = String::new();
+    for snippet in snippets.iter().filter(|snippet| exported(snippet, shell)) {
+        let base = function_name(&snippet.title);
+        let mut name = base.clone();
+        let mut number = 2;
+        while names.contains(&name) {
+            name = format!("{}_{}", base, number);
+            number += 1;
+        }
+
+        // Not indented, which would change heredocs and multi-line strings
+        let body = format!("{}\n", snippet.description.trim_end());
+        let title = snippet.title.replace('\n', " ");
+        match shell {
+            Shell::Posix => {
+                functions.push_str(&format!("\n# {}\n{}() {{\n{}}}\n", title, name, body))
+            }
+            Shell::PowerShell => functions.push_str(&format!(
+                "\n# {}\nfunction {} {{\n{}}}\n",
+                title, name, body
+            )),
+        }
+        names.push(name);
+    }
+
+    let header = format!(
+        "# Generated by sniprrr from {} snippets. Edit the snippets rather than\n# this file, it is replaced on the next export.\n",
+        names.len()
+    );
+    (header + &functions, names.len())
+}
+
+/// Writes the functions for `shell` to `path`, returning how many there are
+pub fn write(path: &Path, snippets: &[Snippet], shell: Shell) -> Result<usize, String> {
+    let (script, count) = render(snippets, shell);
+    fs::write(path, script).map_err(|error| format!("{}: {}", path.display(), error))?;
+    Ok(count)
+}
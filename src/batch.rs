@@ -0,0 +1,264 @@
+//! `sniprrr batch`: runs a list of operations from a YAML file, e.g. to
+//! provision the same snippets on every machine from dotfiles. The operations
+//! run on a copy of the store and nothing is written unless all of them succeed.
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::bulk::Edit;
+use crate::file_utils::write_store;
+use crate::import;
+use crate::models::Snippet;
+use crate::time_utils;
+
+/// One entry of the script, written as e.g. `- delete: Old title`
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub enum Operation {
+    /// Adds a snippet, or updates the one with the same title so scripts can be rerun
+    Add(NewSnippet),
+    /// Deletes the snippet with this exact title if there is one
+    Delete(String),
+    /// Renames a tag on every snippet, or removes it when `to` is empty
+    Retag {
+        from: String,
+        #[serde(default)]
+        to: String,
+    },
+    /// Adds the snippets of a sniprrr store file, backup or export
+    Import(String),
+    /// Writes the snippets as they are at this point of the script to a file
+    Export(String),
+}
+
+impl Operation {
+    fn name(&self) -> &'static str {
+        match self {
+            Operation::Add(_) => "add",
+            Operation::Delete(_) => "delete",
+            Operation::Retag { .. } => "retag",
+            Operation::Import(_) => "import",
+            Operation::Export(_) => "export",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NewSnippet {
+    title: String,
+    description: String,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    language: Option<String>,
+}
+
+/// What a run changed, printed once it is done
+#[derive(Default)]
+pub struct Summary {
+    pub added: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+    pub deleted: usize,
+    /// Titles given to `delete` that weren't there
+    pub missing: Vec<String>,
+    pub retagged: usize,
+    pub imported: usize,
+    /// Files to write once every operation succeeded, with the snippets for each
+    exports: Vec<(PathBuf, Vec<Snippet>)>,
+}
+
+impl Summary {
+    /// Whether the store needs to be written
+    pub fn changed(&self) -> bool {
+        self.added + self.updated + self.deleted + self.retagged + self.imported > 0
+    }
+
+    /// Writes the files of the `export` operations next to where they go,
+    /// to be moved in place with `commit` once the store is saved
+    pub fn stage_exports(&self) -> Result<StagedExports, String> {
+        let mut staged = StagedExports(Vec::new());
+        for (path, snippets) in &self.exports {
+            let mut name = path.file_name().unwrap_or_default().to_os_string();
+            name.push(".pending");
+            let pending = path.with_file_name(name);
+            write_store(&pending, snippets)
+                .map_err(|error| format!("{}: {}", path.display(), error))?;
+            // A later export to the same file replaced the pending one
+            if !staged.0.iter().any(|(_, staged)| staged == path) {
+                staged.0.push((pending, path.clone()));
+            }
+        }
+        Ok(staged)
+    }
+}
+
+/// Export files written as `<name>.pending`, removed again unless committed
+pub struct StagedExports(Vec<(PathBuf, PathBuf)>);
+
+impl StagedExports {
+    /// Moves the files to their names
+    pub fn commit(mut self) -> Result<(), String> {
+        while !self.0.is_empty() {
+            let (pending, path) = self.0.remove(0);
+            fs::rename(&pending, &path).map_err(|error| {
+                let _ = fs::remove_file(&pending);
+                format!("{}: {}", path.display(), error)
+            })?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for StagedExports {
+    fn drop(&mut self) {
+        for (pending, _) in &self.0 {
+            let _ = fs::remove_file(pending);
+        }
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "added {}, updated {}, unchanged {}",
+            self.added, self.updated, self.unchanged
+        )?;
+        writeln!(f, "deleted {}", self.deleted)?;
+        for title in &self.missing {
+            writeln!(f, "  '{}' was already gone", title)?;
+        }
+        writeln!(f, "retagged {}", self.retagged)?;
+        writeln!(f, "imported {}", self.imported)?;
+        for (path, snippets) in &self.exports {
+            writeln!(f, "exported {} to {}", snippets.len(), path.display())?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads the list of operations from the YAML file at `path`
+pub fn load(path: &Path) -> Result<Vec<Operation>, String> {
+    let contents =
+        fs::read_to_string(path).map_err(|error| format!("{}: {}", path.display(), error))?;
+    // serde_yaml only reads enums written as `!tags`, so go through plain values
+    // to allow the usual `- add: ...` maps
+    let entries: Vec<serde_json::Value> = serde_yaml::from_str(&contents)
+        .map_err(|error| format!("{}: {}", path.display(), error))?;
+    entries
+        .into_iter()
+        .enumerate()
+        .map(|(number, entry)| {
+            serde_json::from_value(entry)
+                .map_err(|error| format!("{}: operation {}: {}", path.display(), number + 1, error))
+        })
+        .collect()
+}
+
+/// Runs `operations` in order on `snippets`. Relative paths are taken from
+/// `base`, the directory of the script. Stops at the first failing operation.
+pub fn run(
+    operations: &[Operation],
+    snippets: &mut Vec<Snippet>,
+    base: &Path,
+) -> Result<Summary, String> {
+    let mut summary = Summary::default();
+    for (number, operation) in operations.iter().enumerate() {
+        run_one(operation, snippets, base, &mut summary).map_err(|error| {
+            format!("Operation {} ({}): {}", number + 1, operation.name(), error)
+        })?;
+    }
+    Ok(summary)
+}
+
+fn run_one(
+    operation: &Operation,
+    snippets: &mut Vec<Snippet>,
+    base: &Path,
+    summary: &mut Summary,
+) -> Result<(), String> {
+    match operation {
+        Operation::Add(new) => {
+            if new.title.trim().is_empty() {
+                return Err("the title is empty".to_string());
+            }
+            match snippets.iter_mut().find(|s| s.title == new.title) {
+                Some(existing) => {
+                    let changed = existing.description != new.description
+                        || existing.tags != new.tags
+                        || existing.language != new.language;
+                    if changed {
+                        existing.description = new.description.clone();
+                        existing.tags = new.tags.clone();
+                        existing.language = new.language.clone();
+                        existing.updated_at = Some(time_utils::now());
+                        summary.updated += 1;
+                    } else {
+                        summary.unchanged += 1;
+                    }
+                }
+                None => {
+                    let mut snippet = Snippet::new(new.title.clone(), new.description.clone());
+                    snippet.tags = new.tags.clone();
+                    snippet.language = new.language.clone();
+                    snippets.push(snippet);
+                    summary.added += 1;
+                }
+            }
+        }
+        Operation::Delete(title) => {
+            let count = snippets.len();
+            snippets.retain(|s| &s.title != title);
+            if snippets.len() == count {
+                summary.missing.push(title.clone());
+            } else {
+                summary.deleted += 1;
+            }
+        }
+        Operation::Retag { from, to } => {
+            if from.is_empty() {
+                return Err("there is no tag to rename in 'from'".to_string());
+            }
+            let edit = Edit::Retag {
+                from: from.clone(),
+                to: to.clone(),
+            };
+            for snippet in snippets.iter_mut() {
+                let tags = snippet.tags.clone();
+                edit.apply(snippet);
+                if snippet.tags != tags {
+                    summary.retagged += 1;
+                }
+            }
+        }
+        Operation::Import(path) => {
+            let incoming = import::read_store_file(&resolve(base, path).to_string_lossy())?;
+            let (added, replaced) = import::merge(snippets, incoming);
+            summary.imported += added + replaced;
+        }
+        Operation::Export(path) => {
+            let path = resolve(base, path);
+            match path.parent() {
+                Some(dir) if !dir.as_os_str().is_empty() && !dir.is_dir() => {
+                    return Err(format!("{} is not a directory", dir.display()))
+                }
+                _ => {}
+            }
+            summary.exports.push((path, snippets.clone()));
+        }
+    }
+    Ok(())
+}
+
+/// `path` relative to `base` unless it is absolute or starts at the home directory (`~/`)
+fn resolve(base: &Path, path: &str) -> PathBuf {
+    match path.strip_prefix("~/") {
+        Some(rest) => dirs::home_dir().unwrap_or_default().join(rest),
+        None => base.join(path),
+    }
+}
@@ -0,0 +1,86 @@
+//! `sniprrr bench --generate`: writes a large store of made-up snippets to
+//! try out, and benchmark, sniprrr at sizes nobody has by hand
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use crate::models::{LabelColor, Snippet};
+
+/// Seed of the generator, so two runs with the same count give the same store
+pub const SEED: u64 = 0x5eed;
+
+const NAMESPACES: [&str; 8] = [
+    "git", "docker", "k8s", "sql", "support", "aws", "nginx", "bash",
+];
+const WORDS: [&str; 24] = [
+    "list",
+    "show",
+    "restart",
+    "deploy",
+    "rollback",
+    "reset",
+    "dump",
+    "restore",
+    "tail",
+    "grep",
+    "config",
+    "logs",
+    "users",
+    "branches",
+    "pods",
+    "images",
+    "tables",
+    "volumes",
+    "secrets",
+    "staging",
+    "production",
+    "local",
+    "reply",
+    "refund",
+];
+const TAGS: [&str; 10] = [
+    "ops", "daily", "danger", "template", "shell", "email", "oncall", "db", "ci", "misc",
+];
+
+/// `count` snippets with titles, bodies, tags and labels of the usual shapes
+pub fn generate(count: usize, seed: u64) -> Vec<Snippet> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    (0..count)
+        .map(|number| {
+            let mut words = || *WORDS.choose(&mut rng).unwrap_or(&"");
+            let title = format!("{} {} {}", words(), words(), number);
+            let title = match rng.gen_ratio(1, 2) {
+                true => format!("{}/{}", NAMESPACES.choose(&mut rng).unwrap_or(&""), title),
+                false => title,
+            };
+
+            let lines = rng.gen_range(1..=12);
+            let description = (0..lines)
+                .map(|_| {
+                    let length = rng.gen_range(2..=14);
+                    (0..length)
+                        .map(|_| *WORDS.choose(&mut rng).unwrap_or(&""))
+                        .collect::<Vec<&str>>()
+                        .join(" ")
+                })
+                .collect::<Vec<String>>()
+                .join("\n");
+
+            let mut snippet = Snippet::new(title, description);
+            let tag_count = rng.gen_range(0..=3);
+            snippet.tags = TAGS
+                .choose_multiple(&mut rng, tag_count)
+                .map(|tag| tag.to_string())
+                .collect();
+            if rng.gen_ratio(1, 5) {
+                snippet.color = LabelColor::ALL.choose(&mut rng).copied();
+            }
+            if rng.gen_ratio(1, 10) {
+                snippet.description.push_str(" {{name}}");
+            }
+            snippet.copy_count = rng.gen_range(0..50);
+            snippet
+        })
+        .collect()
+}
@@ -0,0 +1,214 @@
+//! Bulk edits for `sniprrr apply`
+
+use regex::Regex;
+
+use crate::diff::diff_lines;
+use crate::models::Snippet;
+use crate::search;
+use crate::time_utils;
+
+/// Selects the snippets an `apply` run touches. All filters have to match.
+#[derive(Clone)]
+pub enum Filter {
+    Tag(String),
+    Language(String),
+    Title(String),
+    /// Same as the `/` filter in the TUI
+    Text(String),
+}
+
+impl Filter {
+    /// Parses `tag:<TAG>`, `lang:<LANGUAGE>`, `title:<TEXT>` or plain text
+    pub fn parse(value: &str) -> Filter {
+        match value.split_once(':') {
+            Some(("tag", tag)) => Filter::Tag(tag.to_string()),
+            Some(("lang", language)) => Filter::Language(language.to_string()),
+            Some(("title", title)) => Filter::Title(title.to_string()),
+            _ => Filter::Text(value.to_string()),
+        }
+    }
+
+    pub fn matches(&self, snippet: &Snippet) -> bool {
+        match self {
+            Filter::Tag(tag) => snippet.tags.iter().any(|t| t == tag),
+            Filter::Language(language) => snippet
+                .language
+                .as_deref()
+                .is_some_and(|l| l.eq_ignore_ascii_case(language)),
+            Filter::Title(title) => !search::match_ranges(&snippet.title, title).is_empty(),
+            Filter::Text(text) => search::matches(snippet, text),
+        }
+    }
+}
+
+/// One change made to every selected snippet
+#[derive(Clone)]
+pub enum Edit {
+    Replace {
+        from: String,
+        to: String,
+    },
+    RegexReplace {
+        pattern: Regex,
+        replacement: String,
+    },
+    Prefix(String),
+    Suffix(String),
+    AddTag(String),
+    RemoveTag(String),
+    Retag {
+        from: String,
+        to: String,
+    },
+    /// Sets the language, or clears it when empty
+    Language(String),
+}
+
+impl Edit {
+    /// Parses the `--transform` syntax, e.g. `replace:staging->prod` or `tag:sql`
+    pub fn parse(value: &str) -> Result<Edit, String> {
+        let (kind, argument) = value
+            .split_once(':')
+            .ok_or_else(|| format!("Invalid transform '{}', expected <KIND>:<VALUE>", value))?;
+        let pair = || {
+            argument
+                .split_once("->")
+                .map(|(from, to)| (from.to_string(), to.to_string()))
+                .ok_or_else(|| {
+                    format!(
+                        "Invalid transform '{}', expected {}:<FROM>-><TO>",
+                        value, kind
+                    )
+                })
+        };
+
+        match kind {
+            "replace" => {
+                let (from, to) = pair()?;
+                if from.is_empty() {
+                    return Err("Nothing to replace in 'replace:->...'".to_string());
+                }
+                Ok(Edit::Replace { from, to })
+            }
+            "regex" => {
+                let (pattern, replacement) = pair()?;
+                let pattern = Regex::new(&pattern)
+                    .map_err(|error| format!("Invalid regex '{}': {}", pattern, error))?;
+                Ok(Edit::RegexReplace {
+                    pattern,
+                    replacement,
+                })
+            }
+            "prefix" => Ok(Edit::Prefix(argument.to_string())),
+            "suffix" => Ok(Edit::Suffix(argument.to_string())),
+            "tag" if !argument.is_empty() => Ok(Edit::AddTag(argument.to_string())),
+            "untag" if !argument.is_empty() => Ok(Edit::RemoveTag(argument.to_string())),
+            "retag" => {
+                let (from, to) = pair()?;
+                Ok(Edit::Retag { from, to })
+            }
+            "lang" => Ok(Edit::Language(argument.to_string())),
+            _ => Err(format!("Unknown transform '{}'", value)),
+        }
+    }
+
+    pub fn apply(&self, snippet: &mut Snippet) {
+        match self {
+            Edit::Replace { from, to } => {
+                snippet.description = snippet.description.replace(from.as_str(), to)
+            }
+            Edit::RegexReplace {
+                pattern,
+                replacement,
+            } => {
+                snippet.description = pattern
+                    .replace_all(&snippet.description, replacement.as_str())
+                    .to_string()
+            }
+            Edit::Prefix(prefix) => snippet.description.insert_str(0, prefix),
+            Edit::Suffix(suffix) => snippet.description.push_str(suffix),
+            Edit::AddTag(tag) => {
+                if !snippet.tags.contains(tag) {
+                    snippet.tags.push(tag.clone());
+                }
+            }
+            Edit::RemoveTag(tag) => snippet.tags.retain(|t| t != tag),
+            Edit::Retag { from, to } => {
+                if let Some(position) = snippet.tags.iter().position(|t| t == from) {
+                    snippet.tags.remove(position);
+                    if !to.is_empty() && !snippet.tags.contains(to) {
+                        snippet.tags.insert(position, to.clone());
+                    }
+                }
+            }
+            Edit::Language(language) => {
+                snippet.language = (!language.is_empty()).then(|| language.clone())
+            }
+        }
+    }
+}
+
+/// Applies `edits` in order to every snippet matching all `filters` and
+/// prints what changed. Returns how many snippets matched and changed.
+pub fn apply(snippets: &mut [Snippet], filters: &[Filter], edits: &[Edit]) -> (usize, usize) {
+    let mut matched = 0;
+    let mut changed = 0;
+    for snippet in snippets
+        .iter_mut()
+        .filter(|snippet| filters.iter().all(|filter| filter.matches(snippet)))
+    {
+        matched += 1;
+        let mut edited = snippet.clone();
+        for edit in edits {
+            edit.apply(&mut edited);
+        }
+        if let Some(preview) = preview(snippet, &edited) {
+            println!("~ {}", snippet.title);
+            print!("{}", preview);
+            edited.updated_at = Some(time_utils::now());
+            *snippet = edited;
+            changed += 1;
+        }
+    }
+    (matched, changed)
+}
+
+/// Lists the changed lines, tags and language, or `None` when nothing changed
+fn preview(before: &Snippet, after: &Snippet) -> Option<String> {
+    let mut out = String::new();
+    if before.description != after.description {
+        // Removed lines first within each run of changes, like a unified diff
+        let mut added = String::new();
+        for row in diff_lines(&before.description, &after.description) {
+            if !row.is_change() {
+                out.push_str(&std::mem::take(&mut added));
+                continue;
+            }
+            if let Some(line) = row.left {
+                out.push_str(&format!("  - {}\n", line));
+            }
+            if let Some(line) = row.right {
+                added.push_str(&format!("  + {}\n", line));
+            }
+        }
+        out.push_str(&added);
+        if out.is_empty() {
+            out.push_str("  (line endings changed)\n");
+        }
+    }
+    if before.tags != after.tags {
+        out.push_str(&format!(
+            "  tags: [{}] -> [{}]\n",
+            before.tags.join(", "),
+            after.tags.join(", ")
+        ));
+    }
+    if before.language != after.language {
+        out.push_str(&format!(
+            "  language: {} -> {}\n",
+            before.language.as_deref().unwrap_or("none"),
+            after.language.as_deref().unwrap_or("none")
+        ));
+    }
+    (!out.is_empty()).then_some(out)
+}
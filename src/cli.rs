@@ -0,0 +1,426 @@
+use std::path::PathBuf;
+
+use clap::error::ErrorKind;
+use clap::{ArgGroup, Args, CommandFactory, Parser, Subcommand, ValueEnum};
+
+use crate::aliases::Shell;
+use crate::bulk::{Edit, Filter};
+use crate::formats::Format;
+use crate::lookup::Selector;
+use crate::models::parse_tags;
+use crate::run;
+use crate::time_utils::{self, SECONDS_PER_DAY};
+
+/// Snippets in the terminal. Running `sniprrr` without a command starts the
+/// interactive UI.
+#[derive(Parser)]
+#[command(
+    name = "sniprrr",
+    version,
+    after_help = "Exit codes: 0 when done, 1 when the snippet asked for isn't there, 2 on errors"
+)]
+pub struct Cli {
+    /// Stop on errors that would otherwise be worked around, also available
+    /// as `strict` in config.toml
+    #[arg(long, global = true)]
+    pub strict: bool,
+    /// Only print what was asked for and errors, no confirmations. Also the
+    /// case whenever stdout isn't a terminal.
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
+    /// Never start the UI, fail instead, for scripts
+    #[arg(long, global = true)]
+    pub no_tui: bool,
+    /// Print the snippet picked to stdout and close, instead of copying it,
+    /// as in `eval "$(sniprrr --print)"`. The UI is drawn on the terminal itself.
+    #[arg(long, global = true)]
+    pub print: bool,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Subcommands available from the command line.
+/// Running `sniprrr` without one starts the TUI.
+#[derive(Subcommand)]
+pub enum Command {
+    /// Print a Markdown summary of recent snippet changes
+    Digest(DigestOptions),
+    /// Check the store for problems and offer to fix them
+    Doctor(DoctorOptions),
+    /// Copy the snippet whose title matches, asking which one when several
+    /// do (through the running sniprrr when there is one). The
+    /// {{placeholders}} of templates are asked for first.
+    Copy(CopyOptions),
+    /// Add a snippet without opening the UI
+    Add(AddOptions),
+    /// Print the id, title and tags of every snippet, separated by tabs
+    #[command(visible_alias = "ls")]
+    List(ListOptions),
+    /// Delete the snippet with exactly this title
+    #[command(name = "rm", visible_alias = "delete")]
+    Remove(RemoveOptions),
+    /// Edit every matching snippet at once, printing the changes
+    Apply(ApplyOptions),
+    /// List earlier states of the store, or go back to one
+    Restore(RestoreOptions),
+    /// Run the add, delete, retag, import and export operations listed in a
+    /// YAML file, saving nothing unless all of them succeed
+    Batch(BatchOptions),
+    /// Keep the store in memory and serve it to other invocations, with
+    /// Prometheus metrics on /metrics on `[metrics] listen`, and the snippets
+    /// on /snippets?q=&tag=&limit=&offset= and their changes on /events to
+    /// clients with a token on `[server] listen`, where editors can change
+    /// them too
+    Daemon,
+    /// Collect crash reports, the redacted config, store statistics and
+    /// environment details into a tarball for bug reports
+    DebugBundle {
+        /// Where to write it (default: a file in the current directory)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Write a store file of made-up snippets to import or point the
+    /// benchmarks at
+    Bench(BenchOptions),
+    /// Print the shell snippets as functions to source from a shell profile,
+    /// named after their titles
+    Aliases(AliasesOptions),
+    /// Open the UI with the snippets of the files listed to pick which ones
+    /// to keep, leaving out those already in the store
+    Import(ImportOptions),
+    /// Learn to add, tag, search, copy and delete snippets step by step, on
+    /// a sandbox collection that leaves your store alone
+    Tutorial,
+    /// Print every snippet in another format, to import elsewhere
+    Export(ExportOptions),
+    /// Merge the store with what other machines pushed to the git repository
+    /// of `[sync] remote`, then push it
+    Sync,
+    /// Show the config directory in the file manager, or edit the store in
+    /// $VISUAL or $EDITOR
+    Open {
+        #[arg(value_enum)]
+        target: OpenTarget,
+    },
+}
+
+/// What `open` opens
+#[derive(Clone, Copy, ValueEnum)]
+pub enum OpenTarget {
+    /// The config directory, with config.toml and the store, in the file manager
+    Config,
+    /// The store file, in `$EDITOR`, checking that it still reads afterwards
+    Store,
+}
+
+#[derive(Args)]
+pub struct DigestOptions {
+    /// Report on the last N days
+    #[arg(long, value_name = "N", default_value_t = 7, conflicts_with = "since")]
+    pub days: u64,
+    /// Report on everything since the given date
+    #[arg(long, value_name = "YYYY-MM-DD", value_parser = parse_date)]
+    pub since: Option<u64>,
+    /// Number of most used snippets to list
+    #[arg(long, value_name = "N", default_value_t = 5)]
+    pub top: usize,
+}
+
+impl DigestOptions {
+    /// Unix timestamp of the start of the reported period
+    pub fn start(&self) -> u64 {
+        self.since
+            .unwrap_or_else(|| time_utils::now().saturating_sub(self.days * SECONDS_PER_DAY))
+    }
+}
+
+#[derive(Args)]
+#[group(required = true, multiple = false)]
+pub struct CopyOptions {
+    /// Part of the title
+    #[arg(value_name = "TITLE")]
+    query: Vec<String>,
+    /// Only copy the snippet with exactly this title
+    #[arg(long, value_name = "TITLE")]
+    exact: Option<String>,
+    /// Copy the N-th snippet of the store, as listed when asking
+    #[arg(long, value_name = "N")]
+    id: Option<usize>,
+}
+
+impl CopyOptions {
+    pub fn selector(self) -> Selector {
+        match (self.exact, self.id) {
+            (Some(title), _) => Selector::Exact(title),
+            (None, Some(id)) => Selector::Id(id),
+            (None, None) => Selector::Query(self.query.join(" ")),
+        }
+    }
+}
+
+#[derive(Args)]
+#[command(group(ArgGroup::new("body_source").required(true)))]
+pub struct AddOptions {
+    /// Title of the new snippet
+    #[arg(long)]
+    pub title: String,
+    /// The body
+    #[arg(long, value_name = "TEXT", group = "body_source")]
+    body: Option<String>,
+    /// Read the body from a file instead, or from stdin with `-`
+    #[arg(long, value_name = "FILE", group = "body_source")]
+    template_from: Option<String>,
+    /// Tags separated by commas
+    #[arg(long, value_parser = parse_tag_list, default_value = "")]
+    pub tags: Tags,
+    /// Default value for one of the body's {{placeholders}}
+    #[arg(long = "field", value_name = "NAME=DEFAULT", value_parser = parse_field)]
+    pub fields: Vec<(String, String)>,
+    /// Language of the body (default: the current project's)
+    #[arg(long = "lang", value_name = "LANGUAGE")]
+    pub language: Option<String>,
+    /// Environment variable a run needs, asked for when it isn't set
+    #[arg(long, value_name = "NAME[=DEFAULT]", value_parser = parse_variable)]
+    pub env: Vec<(String, String)>,
+    /// Directory a run starts in
+    #[arg(long = "cwd", value_name = "DIR")]
+    pub working_dir: Option<String>,
+}
+
+/// The tags of `--tags`, parsed as a whole rather than one per occurrence
+pub type Tags = Vec<String>;
+
+impl AddOptions {
+    /// Where the body comes from, `--body` or `--template-from`
+    pub fn body_source(&self) -> BodySource {
+        match (&self.body, &self.template_from) {
+            (Some(text), _) => BodySource::Text(text.clone()),
+            (None, Some(path)) => BodySource::File(path.clone()),
+            (None, None) => unreachable!("add without a body"),
+        }
+    }
+}
+
+/// Where `add` takes the body from
+pub enum BodySource {
+    /// Given on the command line with `--body`
+    Text(String),
+    /// A file named with `--template-from`, `-` for stdin
+    File(String),
+}
+
+#[derive(Args)]
+pub struct ListOptions {
+    /// Print the whole snippets as JSON instead, in the store's format
+    #[arg(long)]
+    pub json: bool,
+    /// Only list the snippets with this tag
+    #[arg(long)]
+    pub tag: Option<String>,
+}
+
+#[derive(Args)]
+#[group(required = true, multiple = false)]
+pub struct RemoveOptions {
+    /// Exact title of the snippet
+    title: Option<String>,
+    /// Delete the N-th snippet of the store instead, as listed
+    #[arg(long, value_name = "N")]
+    id: Option<usize>,
+}
+
+impl RemoveOptions {
+    pub fn selector(self) -> Selector {
+        match (self.title, self.id) {
+            (Some(title), _) => Selector::Exact(title),
+            (None, Some(id)) => Selector::Id(id),
+            (None, None) => unreachable!("rm without a title or id"),
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct ApplyOptions {
+    /// tag:<TAG>, lang:<LANGUAGE>, title:<TEXT> or text to search for, repeat
+    /// to narrow down further (default: all snippets)
+    #[arg(long = "filter", value_name = "FILTER", value_parser = parse_filter)]
+    pub filters: Vec<Filter>,
+    /// replace:<FROM>-><TO>, regex:<PATTERN>-><REPLACEMENT>, prefix:<TEXT>,
+    /// suffix:<TEXT>, tag:<TAG>, untag:<TAG>, retag:<FROM>-><TO> or
+    /// lang:<LANGUAGE>, applied in order
+    #[arg(long = "transform", value_name = "EDIT", value_parser = Edit::parse, required = true)]
+    pub edits: Vec<Edit>,
+    /// Show the changes without saving them
+    #[arg(short = 'n', long)]
+    pub dry_run: bool,
+}
+
+#[derive(Args)]
+pub struct RestoreOptions {
+    /// Restore the store as it was at the end of that day. Without it the
+    /// available states are listed.
+    #[arg(long, value_name = "YYYY-MM-DD", value_parser = parse_date)]
+    pub at: Option<u64>,
+    /// Only bring back this snippet, keeping everything else
+    #[arg(long, value_name = "TITLE", requires = "at")]
+    pub snippet: Option<String>,
+}
+
+#[derive(Args)]
+pub struct BatchOptions {
+    /// YAML file listing the operations
+    #[arg(value_name = "FILE")]
+    pub script: PathBuf,
+    /// Show the summary without saving anything
+    #[arg(short = 'n', long)]
+    pub dry_run: bool,
+}
+
+#[derive(Args)]
+pub struct BenchOptions {
+    /// Number of snippets to generate
+    #[arg(long, value_name = "N")]
+    pub generate: usize,
+    /// Store file to write, never the real store unless asked for
+    #[arg(short, long, value_name = "PATH", default_value = "sniprrr-bench.json")]
+    pub output: PathBuf,
+}
+
+#[derive(Args)]
+pub struct AliasesOptions {
+    /// For a PowerShell profile instead of sh, bash and zsh
+    #[arg(long)]
+    powershell: bool,
+    /// Write them to a file, for PowerShell when it ends in .ps1 (default: stdout)
+    #[arg(short, long, value_name = "PATH")]
+    pub output: Option<PathBuf>,
+}
+
+impl AliasesOptions {
+    pub fn shell(&self) -> Shell {
+        match self.powershell {
+            true => Shell::PowerShell,
+            false => self
+                .output
+                .as_deref()
+                .and_then(|path| Shell::for_path(&path.to_string_lossy()))
+                .unwrap_or(Shell::Posix),
+        }
+    }
+}
+
+/// What `import` reads its files as, when not each by its extension as a
+/// sniprrr store, backup or export
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    /// Every file in this format
+    File(Format),
+    /// bash, zsh or fish history, the shell's own files when none are given
+    ShellHistory,
+}
+
+#[derive(Args)]
+pub struct ImportOptions {
+    /// json (the store format), yaml, toml, csv, or shell-history, which
+    /// offers the most run commands of bash, zsh and fish history files
+    /// (default: the ones in the home directory). Without it each file is
+    /// read by its extension.
+    #[arg(long, value_parser = parse_import_format)]
+    pub format: Option<ImportFormat>,
+    #[arg(value_name = "FILE", required_unless_present = "format")]
+    pub paths: Vec<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct ExportOptions {
+    /// json, yaml, toml or csv, which only has the title, body and tags
+    /// (default: by the extension of --output, else json)
+    #[arg(long = "format", value_parser = Format::parse)]
+    format_name: Option<Format>,
+    /// Write them to a file instead of stdout
+    #[arg(short, long, value_name = "PATH")]
+    pub output: Option<PathBuf>,
+    /// Only export the snippets with this tag
+    #[arg(long)]
+    pub tag: Option<String>,
+}
+
+impl ExportOptions {
+    pub fn format(&self) -> Format {
+        self.format_name
+            .or_else(|| self.output.as_deref().and_then(Format::for_path))
+            .unwrap_or(Format::Json)
+    }
+}
+
+#[derive(Args)]
+pub struct DoctorOptions {
+    /// Apply all available fixes without asking
+    #[arg(short = 'y', long = "yes")]
+    pub assume_yes: bool,
+    /// Show what the terminal was found to support instead of checking the store
+    #[arg(long)]
+    pub terminal: bool,
+}
+
+/// Parses the command line, printing the usage and exiting with 2 when it
+/// is wrong
+pub fn parse() -> Cli {
+    let cli = Cli::parse();
+    if let Some(Command::Import(options)) = &cli.command {
+        if options.format != Some(ImportFormat::ShellHistory) && options.paths.is_empty() {
+            Cli::command()
+                .error(
+                    ErrorKind::MissingRequiredArgument,
+                    "import needs files to read, or --format shell-history",
+                )
+                .exit();
+        }
+    }
+    cli
+}
+
+/// The help of `sniprrr --help`
+pub fn usage() -> String {
+    Cli::command().render_help().to_string()
+}
+
+fn parse_date(value: &str) -> Result<u64, String> {
+    time_utils::parse_date(value)
+        .ok_or_else(|| format!("Invalid date '{}', expected YYYY-MM-DD", value))
+}
+
+fn parse_tag_list(value: &str) -> Result<Tags, String> {
+    Ok(parse_tags(value))
+}
+
+fn parse_field(value: &str) -> Result<(String, String), String> {
+    let (name, default) = value
+        .split_once('=')
+        .ok_or_else(|| format!("Invalid field '{}', expected NAME=DEFAULT", value))?;
+    Ok((name.trim().to_string(), default.to_string()))
+}
+
+fn parse_variable(value: &str) -> Result<(String, String), String> {
+    let (name, default) = value.split_once('=').unwrap_or((value, ""));
+    if !run::is_variable_name(name) {
+        return Err(format!("Invalid variable name '{}'", name));
+    }
+    Ok((name.to_string(), default.to_string()))
+}
+
+fn parse_filter(value: &str) -> Result<Filter, String> {
+    Ok(Filter::parse(value))
+}
+
+fn parse_import_format(value: &str) -> Result<ImportFormat, String> {
+    match value {
+        "shell-history" => Ok(ImportFormat::ShellHistory),
+        name => Format::parse(name).map(ImportFormat::File).map_err(|_| {
+            format!(
+                "Unknown import format '{}', use json, yaml, toml, csv or shell-history",
+                name
+            )
+        }),
+    }
+}
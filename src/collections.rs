@@ -0,0 +1,102 @@
+//! Other stores listed under `[collections]` in the config, which snippets
+//! can be moved or copied to from the app with `m` and `Y`. New snippets are
+//! checked against them so one command isn't kept in several places.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use sniprrr_core::SnippetStore;
+
+use crate::dedupe::same_body;
+use crate::import::expand_home;
+use crate::models::Snippet;
+
+/// A snippet already holding a body that is about to be added
+#[derive(Debug, Clone)]
+pub enum Existing {
+    /// At this index of the collection open in the app
+    Here { index: usize, title: String },
+    /// In another collection of `[collections]`
+    Elsewhere { collection: String, title: String },
+}
+
+impl Existing {
+    /// e.g. "'Disk usage' in work"
+    pub fn describe(&self) -> String {
+        match self {
+            Existing::Here { title, .. } => format!("'{}'", title),
+            Existing::Elsewhere { collection, title } => format!("'{}' in {}", title, collection),
+        }
+    }
+}
+
+/// Name and store path of each collection in `[collections]`, leaving out
+/// the store at `current`
+pub fn others(config: &BTreeMap<String, String>, current: Option<&Path>) -> Vec<(String, PathBuf)> {
+    config
+        .iter()
+        .map(|(name, path)| (name.clone(), expand_home(path)))
+        .filter(|(_, path)| Some(path.as_path()) != current)
+        .collect()
+}
+
+/// The first snippet with the same body as `body`, see `same_body`, looking
+/// through `current` before the `others`. Collections that can't be read are
+/// left out.
+pub fn find_body(
+    body: &str,
+    current: &[Snippet],
+    others: &[(String, PathBuf)],
+) -> Option<Existing> {
+    if let Some(index) = current.iter().position(|s| same_body(&s.description, body)) {
+        return Some(Existing::Here {
+            index,
+            title: current[index].title.clone(),
+        });
+    }
+    others.iter().find_map(|(name, path)| {
+        let store = SnippetStore::load_from(path).ok()?;
+        let snippet = store
+            .snippets()
+            .iter()
+            .find(|s| same_body(&s.description, body))?;
+        Some(Existing::Elsewhere {
+            collection: name.clone(),
+            title: snippet.title.clone(),
+        })
+    })
+}
+
+/// Adds `snippets` to the store at `destination` and saves it. A snippet
+/// whose title is taken there is added as "<title> (2)", "<title> (3)" and so
+/// on; the titles it got are returned with the ones they had.
+pub fn transfer(
+    snippets: Vec<Snippet>,
+    destination: &Path,
+) -> Result<Vec<(String, String)>, String> {
+    let mut store = SnippetStore::load_from(destination).map_err(|error| error.to_string())?;
+    let mut renamed = vec![];
+    for mut snippet in snippets {
+        let title = free_title(&store, &snippet.title);
+        if title != snippet.title {
+            renamed.push((snippet.title.clone(), title.clone()));
+            snippet.title = title;
+        }
+        // Kept for good once it's in a store of its own
+        snippet.temporary = false;
+        snippet.source = None;
+        store.add(snippet)?;
+    }
+    store.save().map_err(|error| error.to_string())?;
+    Ok(renamed)
+}
+
+fn free_title(store: &SnippetStore, title: &str) -> String {
+    (1..)
+        .map(|number| match number {
+            1 => title.to_string(),
+            number => format!("{} ({})", title, number),
+        })
+        .find(|candidate| store.get(candidate).is_none())
+        .expect("a free title")
+}
@@ -0,0 +1,171 @@
+use crate::compression::CompressionConfig;
+use crate::copy_target::{CopyField, CopyTarget};
+use crate::encryption::EncryptionConfig;
+use crate::generate::GenerateConfig;
+use crate::import::expand_home;
+use crate::keymap::Action;
+use crate::metrics::MetricsConfig;
+use crate::notify::NotificationConfig;
+use crate::paste::PasteConfig;
+use crate::row_template::RowTemplate;
+#[cfg(feature = "semantic")]
+use crate::semantic::SemanticConfig;
+use crate::server::ServerConfig;
+use crate::share::ShareConfig;
+use crate::sort::SortOrder;
+use crate::sources::Source;
+use crate::storage::StorageConfig;
+use crate::sync::SyncConfig;
+use crate::terminal::TerminalConfig;
+use crate::theme::ThemeConfig;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Config
+/// User settings loaded from `config.toml` in the sniprrr config directory
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Where the snippets are kept, instead of `messages.json` (or
+    /// `messages.db`) next to this file. Read at start-up only.
+    pub store: Option<String>,
+    /// The format of the store, JSON or SQLite
+    pub storage: StorageConfig,
+    pub theme: ThemeConfig,
+    /// Key sequence -> action, on top of the default bindings, e.g. `"y" = "copy"`
+    /// or `"<C-d>" = "delete"`
+    pub keys: BTreeMap<String, Action>,
+    /// Lays each row out on one line from this template instead of in the
+    /// table's columns, e.g. `{title} [{tags}] — {description:40}`. `V`
+    /// switches between the two. See `row_template` for the fields.
+    pub row_template: Option<RowTemplate>,
+    /// Other stores, name -> path, that `m` moves and `Y` copies snippets to
+    pub collections: BTreeMap<String, String>,
+    /// Stores listed along with the personal one, read-only, from the lowest
+    /// precedence to the highest: a later source wins a title. An entry
+    /// without a `path` places the personal store, last otherwise.
+    pub sources: Vec<Source>,
+    /// A git repository the store is merged with when the UI opens and
+    /// pushed to when it closes
+    pub sync: SyncConfig,
+    /// Order of the table when nothing is searched for, until `s` switches it
+    pub sort: SortOrder,
+    /// Destinations for copied snippets, cycled with `t`. Defaults to the clipboard.
+    pub copy_targets: Vec<CopyTarget>,
+    /// Which fields `c`, `Q` and `sniprrr copy` take from a snippet: `body`,
+    /// `title` or `both`. `C` always copies the title and `y` both.
+    pub copy_field: CopyField,
+    /// Tag -> copy target name, so e.g. `chat` snippets always go to the chat target
+    pub copy_routes: BTreeMap<String, String>,
+    /// Paste service used by the share action
+    pub share: ShareConfig,
+    /// Language model endpoint drafting snippet bodies, off unless set
+    pub generate: GenerateConfig,
+    /// Which background events the daemon shows desktop notifications for
+    pub notifications: NotificationConfig,
+    /// Prometheus metrics served over HTTP by the daemon
+    pub metrics: MetricsConfig,
+    /// The snippets and their changes served over HTTP by the daemon to
+    /// clients with a token, and changed by those with an editor token
+    pub server: ServerConfig,
+    /// Compressing the store file once it grows large
+    pub compression: CompressionConfig,
+    /// Encrypting the store file with a passphrase asked for at start-up.
+    /// Read at start-up only.
+    pub encryption: EncryptionConfig,
+    /// Clean-up of text pasted into the form, such as dropping shell prompts
+    pub paste: PasteConfig,
+    /// What the terminal supports, guessed for each setting left out. Read at
+    /// start-up only, see `sniprrr doctor --terminal`.
+    pub terminal: TerminalConfig,
+    /// Embedding endpoint for searches starting with `~`
+    #[cfg(feature = "semantic")]
+    pub semantic: SemanticConfig,
+    /// Quitting while the form holds a draft takes `q` (or Ctrl+C) twice
+    pub confirm_quit: bool,
+    /// Deleting a snippet asks first
+    pub confirm_delete: bool,
+    /// The sidebar listing the namespaces of the titles only shows while Tab
+    /// moved there or one of them is picked
+    pub hide_sidebar: bool,
+    /// Same as passing `--strict`: failures are shown as errors instead of being worked around
+    pub strict: bool,
+}
+
+impl Config {
+    /// The store file set with `store`, with `~/` expanded
+    pub fn store_path(&self) -> Option<PathBuf> {
+        self.store.as_deref().map(expand_home)
+    }
+}
+
+pub fn config_file_path() -> Option<PathBuf> {
+    let app_config_path = dirs::config_dir()?;
+    Some(app_config_path.join("sniprrr").join("config.toml"))
+}
+
+pub fn parse_config(contents: &str) -> Result<Config, String> {
+    toml::from_str::<Config>(contents).map_err(|error| error.to_string())
+}
+
+/// Loads the config file, using the defaults when there is none
+pub fn load_config() -> Result<Config, String> {
+    let config_path = match config_file_path() {
+        Some(config_path) if config_path.exists() => config_path,
+        _ => return Ok(Config::default()),
+    };
+
+    fs::read_to_string(&config_path)
+        .map_err(|error| error.to_string())
+        .and_then(|contents| parse_config(&contents))
+        .map_err(|error| format!("Invalid {}: {}", config_path.display(), error))
+}
+
+/// Notices changes to the config file so the running app can pick them up
+pub struct ConfigWatcher {
+    path: Option<PathBuf>,
+    modified: Option<SystemTime>,
+}
+
+impl Default for ConfigWatcher {
+    fn default() -> Self {
+        ConfigWatcher::new()
+    }
+}
+
+impl ConfigWatcher {
+    pub fn new() -> ConfigWatcher {
+        let path = config_file_path();
+        let modified = path.as_deref().and_then(modified_time);
+        ConfigWatcher { path, modified }
+    }
+
+    /// The new config when the file changed since the last call. Removing the
+    /// file goes back to the defaults.
+    pub fn poll(&mut self) -> Option<Result<Config, String>> {
+        let path = self.path.as_ref()?;
+        let modified = modified_time(path);
+        if modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+
+        match modified {
+            None => Some(Ok(Config::default())),
+            Some(_) => Some(
+                fs::read_to_string(path)
+                    .map_err(|error| error.to_string())
+                    .and_then(|contents| parse_config(&contents)),
+            ),
+        }
+    }
+}
+
+pub fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
@@ -0,0 +1,270 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use arboard::Clipboard;
+use serde::Deserialize;
+
+use crate::config::Config;
+use crate::models::Snippet;
+use crate::terminal;
+use crate::transform::{self, Transform};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TargetKind {
+    #[default]
+    Clipboard,
+    /// The tmux paste buffer
+    Tmux,
+    /// Any program reading the text from stdin, e.g. a chat bot CLI
+    Command,
+}
+
+/// Which fields of a snippet a copy takes
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CopyField {
+    #[default]
+    Body,
+    Title,
+    /// The title on a line of its own, a blank line and the body
+    Both,
+}
+
+impl CopyField {
+    /// The text copied from a snippet titled `title` with `body`
+    pub fn text(self, title: &str, body: &str) -> String {
+        match self {
+            CopyField::Body => body.to_string(),
+            CopyField::Title => title.to_string(),
+            CopyField::Both => format!("{}\n\n{}", title, body),
+        }
+    }
+}
+
+/// Form of a single copy, picked from the menu behind `F`. Applied after the
+/// target's own transformations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flavor {
+    Plain,
+    /// A fenced Markdown code block tagged with the snippet's language
+    Markdown,
+    /// An HTML `<pre>` block, with the plain text as fallback on the clipboard
+    Html,
+    /// Everything on one line, for chats where Enter sends the message
+    Chat,
+}
+
+impl Flavor {
+    pub const ALL: [Flavor; 4] = [Flavor::Plain, Flavor::Markdown, Flavor::Html, Flavor::Chat];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Flavor::Plain => "plain text",
+            Flavor::Markdown => "Markdown code fence",
+            Flavor::Html => "HTML",
+            Flavor::Chat => "single line for chat",
+        }
+    }
+
+    /// Key choosing this flavor in the menu
+    pub fn key(self) -> char {
+        match self {
+            Flavor::Plain => 'p',
+            Flavor::Markdown => 'm',
+            Flavor::Html => 'h',
+            Flavor::Chat => 's',
+        }
+    }
+
+    fn apply(self, text: &str, language: Option<&str>) -> String {
+        match self {
+            Flavor::Plain => text.to_string(),
+            Flavor::Markdown => {
+                // A longer fence than any run of backticks inside keeps the block intact
+                let longest = text
+                    .split(|c| c != '`')
+                    .map(str::len)
+                    .max()
+                    .unwrap_or_default();
+                let fence = "`".repeat(longest.max(2) + 1);
+                format!(
+                    "{}{}\n{}\n{}",
+                    fence,
+                    language.unwrap_or_default(),
+                    text.trim_end_matches('\n'),
+                    fence
+                )
+            }
+            Flavor::Html => format!("<pre><code>{}</code></pre>", escape_html(text)),
+            Flavor::Chat => Transform::SingleLine.apply(text),
+        }
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Where copied snippets go, configured with `[[copy_targets]]`
+#[derive(Debug, Clone, Deserialize)]
+pub struct CopyTarget {
+    pub name: String,
+    #[serde(default)]
+    pub kind: TargetKind,
+    /// Shell command used by the `command` kind
+    #[serde(default)]
+    pub command: Option<String>,
+    /// Transformations applied to the text before it is sent
+    #[serde(default)]
+    pub transforms: Vec<Transform>,
+}
+
+impl Default for CopyTarget {
+    fn default() -> CopyTarget {
+        CopyTarget {
+            name: "clipboard".to_string(),
+            kind: TargetKind::Clipboard,
+            command: None,
+            transforms: vec![],
+        }
+    }
+}
+
+impl CopyTarget {
+    /// Transforms `text` and sends it to the target
+    pub fn send(&self, text: &str) -> Result<(), String> {
+        self.send_as(text, Flavor::Plain, None)
+    }
+
+    /// `text` transformed and put into the form of `flavor`, as it is sent
+    pub fn render(&self, text: &str, flavor: Flavor, language: Option<&str>) -> String {
+        flavor.apply(&transform::apply_all(&self.transforms, text), language)
+    }
+
+    /// Transforms `text`, puts it into the form of `flavor` and sends it to the target
+    pub fn send_as(
+        &self,
+        text: &str,
+        flavor: Flavor,
+        language: Option<&str>,
+    ) -> Result<(), String> {
+        let plain = transform::apply_all(&self.transforms, text);
+        let text = flavor.apply(&plain, language);
+
+        match self.kind {
+            // Over SSH and such the terminal can still reach a clipboard
+            TargetKind::Clipboard if terminal::copies_through_terminal() => {
+                terminal::copy_osc52(&text)
+            }
+            // Rich text clipboards keep the plain text around for apps without HTML support
+            TargetKind::Clipboard if flavor == Flavor::Html => {
+                let mut clipboard = Clipboard::new().map_err(|error| error.to_string())?;
+                clipboard
+                    .set_html(text, Some(plain))
+                    .map_err(|error| error.to_string())
+            }
+            TargetKind::Clipboard => {
+                let mut clipboard = Clipboard::new().map_err(|error| error.to_string())?;
+                clipboard.set_text(text).map_err(|error| error.to_string())
+            }
+            TargetKind::Tmux => pipe_to(Command::new("tmux").args(["load-buffer", "-"]), &text),
+            TargetKind::Command => {
+                let command = self
+                    .command
+                    .as_deref()
+                    .ok_or_else(|| format!("Copy target '{}' has no command", self.name))?;
+                let mut shell = if cfg!(windows) {
+                    let mut shell = Command::new("cmd");
+                    shell.args(["/C", command]);
+                    shell
+                } else {
+                    let mut shell = Command::new("sh");
+                    shell.args(["-c", command]);
+                    shell
+                };
+                pipe_to(&mut shell, &text)
+            }
+        }
+    }
+}
+
+fn pipe_to(command: &mut Command, text: &str) -> Result<(), String> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|error| error.to_string())?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(text.as_bytes())
+            .map_err(|error| error.to_string())?;
+    }
+
+    let status = child.wait().map_err(|error| error.to_string())?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("command exited with {}", status))
+    }
+}
+
+/// Picks the copy target for a snippet: the first of its tags with a route
+/// in `[copy_routes]` wins, everything else goes to the active target
+pub struct CopyRouter {
+    targets: Vec<CopyTarget>,
+    /// Tag -> target name
+    routes: BTreeMap<String, String>,
+    /// Index into `targets` used for snippets without a routed tag, cycled with `t`
+    active: usize,
+}
+
+impl CopyRouter {
+    pub fn new(targets: Vec<CopyTarget>, routes: BTreeMap<String, String>) -> CopyRouter {
+        let targets = if targets.is_empty() {
+            vec![CopyTarget::default()]
+        } else {
+            targets
+        };
+        CopyRouter {
+            targets,
+            routes,
+            active: 0,
+        }
+    }
+
+    pub fn from_config(config: &Config) -> CopyRouter {
+        CopyRouter::new(config.copy_targets.clone(), config.copy_routes.clone())
+    }
+
+    pub fn active(&self) -> &CopyTarget {
+        &self.targets[self.active]
+    }
+
+    /// Switches the default target to the next configured one
+    pub fn cycle(&mut self) -> &CopyTarget {
+        self.active = (self.active + 1) % self.targets.len();
+        self.active()
+    }
+
+    pub fn target_for(&self, snippet: &Snippet) -> &CopyTarget {
+        snippet
+            .tags
+            .iter()
+            .filter_map(|tag| self.routes.get(tag))
+            .find_map(|name| self.targets.iter().find(|target| &target.name == name))
+            .unwrap_or_else(|| self.active())
+    }
+}
+
+impl Default for CopyRouter {
+    fn default() -> CopyRouter {
+        CopyRouter::new(vec![], BTreeMap::new())
+    }
+}
@@ -0,0 +1,118 @@
+//! CSV import and export, so spreadsheets of canned responses can round-trip
+//! into sniprrr. Which columns hold the title, body and tags is picked in
+//! `ColumnsPopup`, guessed from the header row to start with.
+//!
+//! Only the title, body and tags make the trip: labels, variants, usage and
+//! the rest are left out on export. Titles are trimmed, rows without one are
+//! skipped, and tags are split on commas as typed in the form.
+
+use std::io;
+use std::path::Path;
+
+use crate::models::{parse_tags, Snippet};
+
+/// The header row written on export, which the import guesses right away
+const EXPORT_HEADERS: [&str; 3] = ["title", "description", "tags"];
+
+/// A CSV file with its header row split off
+pub struct Table {
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+/// The column each snippet field is read from
+#[derive(Clone, Copy)]
+pub struct ColumnMapping {
+    pub title: usize,
+    pub description: usize,
+    pub tags: Option<usize>,
+}
+
+/// Reads the CSV file at `path`, whose first row names the columns
+pub fn read_table(path: &Path) -> Result<Table, String> {
+    let error = |error: csv::Error| format!("{}: {}", path.display(), error);
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_path(path)
+        .map_err(error)?;
+    let headers = reader
+        .headers()
+        .map_err(error)?
+        .iter()
+        .map(str::to_string)
+        .collect();
+    let rows = reader
+        .records()
+        .map(|record| Ok(record?.iter().map(str::to_string).collect()))
+        .collect::<Result<Vec<Vec<String>>, csv::Error>>()
+        .map_err(error)?;
+    Ok(Table { headers, rows })
+}
+
+impl Table {
+    /// Picks columns by their header, falling back to the first two for the title and body
+    pub fn guess_mapping(&self) -> ColumnMapping {
+        let find = |names: &[&str]| {
+            self.headers.iter().position(|header| {
+                let header = header.trim().to_lowercase();
+                names.iter().any(|name| header.contains(name))
+            })
+        };
+        let title = find(&["title", "name", "subject"]).unwrap_or(0);
+        let description = find(&["description", "body", "text", "content", "response"])
+            .filter(|&column| column != title)
+            .unwrap_or(if title == 0 { 1 } else { 0 })
+            .min(self.headers.len().saturating_sub(1));
+        ColumnMapping {
+            title,
+            description,
+            tags: find(&["tag"]),
+        }
+    }
+
+    /// One snippet per row with a title
+    pub fn to_snippets(&self, mapping: ColumnMapping) -> Vec<Snippet> {
+        let cell = |row: &[String], column: usize| row.get(column).cloned().unwrap_or_default();
+        self.rows
+            .iter()
+            .filter(|row| !cell(row, mapping.title).trim().is_empty())
+            .map(|row| {
+                let mut snippet = Snippet::new(
+                    cell(row, mapping.title).trim().to_string(),
+                    cell(row, mapping.description),
+                );
+                if let Some(tags) = mapping.tags {
+                    snippet.tags = parse_tags(&cell(row, tags));
+                }
+                snippet
+            })
+            .collect()
+    }
+}
+
+/// Writes the title, body and tags of `snippets` to a CSV file at `path`
+pub fn write(path: &Path, snippets: &[Snippet]) -> Result<(), String> {
+    let writer =
+        csv::Writer::from_path(path).map_err(|error| format!("{}: {}", path.display(), error))?;
+    write_records(writer, snippets).map_err(|error| format!("{}: {}", path.display(), error))
+}
+
+/// The CSV file `write` would write for `snippets`
+pub fn to_string(snippets: &[Snippet]) -> Result<String, String> {
+    let mut out = vec![];
+    write_records(csv::Writer::from_writer(&mut out), snippets)
+        .map_err(|error| error.to_string())?;
+    String::from_utf8(out).map_err(|error| error.to_string())
+}
+
+fn write_records<W: io::Write>(mut writer: csv::Writer<W>, snippets: &[Snippet]) -> io::Result<()> {
+    writer.write_record(EXPORT_HEADERS)?;
+    for snippet in snippets {
+        writer.write_record([
+            snippet.title.as_str(),
+            snippet.description.as_str(),
+            snippet.tags.join(", ").as_str(),
+        ])?;
+    }
+    writer.flush()
+}
@@ -0,0 +1,325 @@
+use std::collections::{HashSet, VecDeque};
+use std::error::Error;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use crate::config::{modified_time, Config};
+use crate::copy_target::CopyRouter;
+use crate::events::{self, Change, Feed};
+use crate::file_utils::{messages_file_path, write_messages_to_file};
+use crate::integrity;
+use crate::ipc::{self, Request, Response, DAEMON_PING};
+use crate::listing::Listing;
+use crate::metrics::{self, Metrics};
+use crate::models::Snippet;
+use crate::notify::{Event, NotificationConfig};
+use crate::server;
+use crate::sync;
+use crate::template;
+use crate::time_utils;
+
+/// How often schedules and the store file are checked between requests
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+/// Saves a client can be behind and still have its own merged in
+const KEPT_REVISIONS: usize = 32;
+
+fn is_template(text: &str) -> bool {
+    !template::placeholders(text).is_empty() || template::has_expansions(text)
+}
+
+fn due_titles(snippets: &[Snippet]) -> HashSet<String> {
+    let now = time_utils::now();
+    snippets
+        .iter()
+        .filter(|s| s.is_scheduled_now(now))
+        .map(|s| s.title.clone())
+        .collect()
+}
+
+/// The snippets after each of the last saves. Clients save everything they
+/// hold, so a save made from an older revision is merged with the ones since,
+/// snippet by snippet, rather than undoing what other clients saved.
+struct Revisions {
+    current: u64,
+    kept: VecDeque<(u64, Vec<Snippet>)>,
+}
+
+impl Revisions {
+    fn new(snippets: &[Snippet]) -> Revisions {
+        Revisions {
+            current: 0,
+            kept: VecDeque::from([(0, snippets.to_vec())]),
+        }
+    }
+
+    /// Counts a save of `snippets`, returning its revision
+    fn saved(&mut self, snippets: &[Snippet]) -> u64 {
+        self.current += 1;
+        self.kept.push_back((self.current, snippets.to_vec()));
+        if self.kept.len() > KEPT_REVISIONS {
+            self.kept.pop_front();
+        }
+        self.current
+    }
+
+    fn at(&self, revision: u64) -> Option<&[Snippet]> {
+        self.kept
+            .iter()
+            .find(|(kept, _)| *kept == revision)
+            .map(|(_, snippets)| snippets.as_slice())
+    }
+}
+
+/// What a client saving from revision `base` means the store to hold now:
+/// `saved` as it is when nothing was saved since, else merged with the
+/// snippets saved since. `None` when `base` is too old to merge with.
+fn rebase(
+    revisions: &Revisions,
+    current: &[Snippet],
+    saved: Vec<Snippet>,
+    base: Option<u64>,
+) -> Option<sync::Merged> {
+    match base {
+        Some(base) if base != revisions.current => Some(sync::merge(
+            revisions.at(base)?,
+            saved,
+            current,
+            "other client",
+        )),
+        // Clients without a base are of an older sniprrr, which replaced the
+        // store like this
+        _ => Some(sync::Merged {
+            snippets: saved,
+            ..Default::default()
+        }),
+    }
+}
+
+/// What a client whose save was merged is told
+fn merge_summary(conflicts: &[String]) -> String {
+    match conflicts.is_empty() {
+        true => "Saved along with the changes of another client".to_string(),
+        false => format!(
+            "Saved along with the changes of another client, changed in both and kept twice: {}",
+            conflicts.join(", ")
+        ),
+    }
+}
+
+/// Tells about what changed since the last check: snippets that became due
+/// and edits to the store file made by something else
+struct Watcher {
+    notifications: NotificationConfig,
+    due: HashSet<String>,
+    /// Modification time of the store file as last written or noticed
+    store_modified: Option<SystemTime>,
+    metrics: Arc<Metrics>,
+    listing: Arc<Listing>,
+}
+
+impl Watcher {
+    fn new(
+        notifications: NotificationConfig,
+        snippets: &[Snippet],
+        metrics: Arc<Metrics>,
+        listing: Arc<Listing>,
+    ) -> Watcher {
+        Watcher {
+            notifications,
+            due: due_titles(snippets),
+            store_modified: messages_file_path().as_deref().and_then(modified_time),
+            metrics,
+            listing,
+        }
+    }
+
+    fn notify(&self, event: Event) {
+        if let Err(error) = self.notifications.notify(event) {
+            eprintln!("{}", error);
+        }
+    }
+
+    /// Remembers the store file as written by the daemon itself, when `ok`
+    fn saved(&mut self, ok: bool, snippets: &[Snippet]) {
+        self.metrics.saved(ok, snippets.len());
+        if ok {
+            self.store_modified = messages_file_path().as_deref().and_then(modified_time);
+            self.listing.update(snippets);
+        }
+    }
+
+    fn check(&mut self, snippets: &[Snippet]) {
+        let due = due_titles(snippets);
+        for title in due.difference(&self.due) {
+            self.notify(Event::Due(title));
+        }
+        self.due = due;
+
+        let store_modified = messages_file_path().as_deref().and_then(modified_time);
+        if store_modified != self.store_modified {
+            self.store_modified = store_modified;
+            self.metrics.out_of_sync();
+            self.notify(Event::Conflict);
+        }
+    }
+}
+
+/// Runs `sniprrr daemon`: keeps the store in memory and serves it over the
+/// socket, so the TUI and CLI invocations become thin clients that neither
+/// reload nor race over the store file.
+pub fn run(config: &Config, strict: bool) -> Result<(), Box<dyn Error>> {
+    if ipc::is_running() {
+        return Err("sniprrr is already running, stop it before starting the daemon".into());
+    }
+
+    let mut snippets = integrity::load_or_repair()?;
+    let mut revisions = Revisions::new(&snippets);
+    let (sender, requests) = mpsc::channel();
+    let _instance_guard = ipc::listen_on(sender.clone())?;
+    let copy_router = CopyRouter::from_config(config);
+    let metrics = Arc::new(Metrics::new(snippets.len()));
+    let listing = Arc::new(Listing::new(&snippets));
+    let feed = Arc::new(Feed::default());
+    if let Some(address) = &config.metrics.listen {
+        metrics::serve(address, metrics.clone())
+            .map_err(|error| format!("Could not serve HTTP on {}: {}", address, error))?;
+    }
+    let server_address = config.server.address();
+    if let Some(address) = &server_address {
+        server::serve(
+            address,
+            config.server.clone(),
+            listing.clone(),
+            feed.clone(),
+            sender,
+        )
+        .map_err(|error| format!("Could not serve the snippets on {}: {}", address, error))?;
+    }
+
+    println!(
+        "sniprrr daemon serving {} snippets from {}",
+        snippets.len(),
+        messages_file_path().unwrap_or_default().display()
+    );
+    if let Some(address) = &config.metrics.listen {
+        println!("Metrics on http://{}/metrics", address);
+    }
+    if let Some(address) = &server_address {
+        println!(
+            "Snippets on http://{0}/snippets and their changes on http://{0}/events",
+            address
+        );
+    }
+
+    let mut watcher = Watcher::new(
+        config.notifications.clone(),
+        &snippets,
+        metrics.clone(),
+        listing,
+    );
+    loop {
+        let incoming = match requests.recv_timeout(CHECK_INTERVAL) {
+            Ok(incoming) => incoming,
+            Err(RecvTimeoutError::Timeout) => {
+                watcher.check(&snippets);
+                continue;
+            }
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+        metrics.request(incoming.request.name());
+        let response = match incoming.request {
+            Request::Ping | Request::Focus => Response::ok(DAEMON_PING),
+            Request::List => {
+                Response::with_snippets(snippets.clone()).at_revision(revisions.current)
+            }
+            Request::Save {
+                snippets: saved,
+                base,
+            } => match rebase(&revisions, &snippets, saved, base) {
+                // Sent the store as it is, to start over from
+                None => Response {
+                    ok: false,
+                    message: "The store changed too often in other clients since it was loaded, \
+                              nothing was saved"
+                        .to_string(),
+                    ..Response::with_snippets(snippets.clone()).at_revision(revisions.current)
+                },
+                Some(merged) => match write_messages_to_file(&merged.snippets) {
+                    Ok(_) => {
+                        let new_snippets = merged.snippets;
+                        watcher.saved(true, &new_snippets);
+                        for added in new_snippets
+                            .iter()
+                            .filter(|new| snippets.iter().all(|s| s.title != new.title))
+                        {
+                            watcher.notify(Event::Saved(&added.title));
+                        }
+                        for change in events::changes(&snippets, &new_snippets) {
+                            feed.publish(&change);
+                        }
+                        snippets = new_snippets;
+                        let revision = revisions.saved(&snippets);
+                        match base.is_some_and(|base| base + 1 != revision) {
+                            // The client is sent what it didn't have yet
+                            true => Response {
+                                message: merge_summary(&merged.conflicts),
+                                ..Response::with_snippets(snippets.clone()).at_revision(revision)
+                            },
+                            false => Response::ok(format!("Saved {} snippets", snippets.len()))
+                                .at_revision(revision),
+                        }
+                    }
+                    Err(error) => {
+                        watcher.saved(false, &snippets);
+                        Response::error(format!("Could not save: {}", error))
+                    }
+                },
+            },
+            Request::Copy {
+                title,
+                text,
+                parameters,
+            } => match snippets.iter().position(|s| s.title == title) {
+                Some(index) if text.is_none() && is_template(&snippets[index].description) => {
+                    Response::error(format!("'{}' is a template, copy it from the TUI", title))
+                }
+                Some(index) => {
+                    let copy_target = copy_router.target_for(&snippets[index]);
+                    let text = text.unwrap_or_else(|| snippets[index].description.clone());
+                    let sent = copy_target.send(&text);
+                    metrics.copied(sent.is_ok());
+                    match sent {
+                        Ok(_) => {
+                            snippets[index].remember_parameters(&parameters);
+                            snippets[index].mark_used();
+                            let saved = write_messages_to_file(&snippets);
+                            watcher.saved(saved.is_ok(), &snippets);
+                            revisions.saved(&snippets);
+                            feed.publish(&Change::Copy(&snippets[index]));
+                            match saved {
+                                Err(error) if strict => Response::error(format!(
+                                    "Copied '{}' to {} but could not save: {}",
+                                    title, copy_target.name, error
+                                )),
+                                _ => Response::ok(format!(
+                                    "Copied '{}' to {}",
+                                    title, copy_target.name
+                                )),
+                            }
+                        }
+                        Err(error) => Response::error(format!(
+                            "Could not copy to {}: {}",
+                            copy_target.name, error
+                        )),
+                    }
+                }
+                None => Response::error(format!("No snippet titled '{}'", title)),
+            },
+        };
+        let _ = incoming.reply.send(response);
+        watcher.check(&snippets);
+    }
+
+    Ok(())
+}
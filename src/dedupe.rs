@@ -0,0 +1,199 @@
+use crate::diff::{combine_lines, similarity};
+use crate::models::Snippet;
+use crate::time_utils;
+
+/// Pairs scoring at least this similarity are reported as near-duplicates
+pub const DUPLICATE_THRESHOLD: f64 = 0.8;
+
+pub struct DuplicatePair {
+    pub left: usize,
+    pub right: usize,
+    pub score: f64,
+}
+
+/// Which side of a pair to take a field from when merging
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Pick {
+    Left,
+    Right,
+    Both,
+}
+
+/// Whether the store holds `snippet`, unlike the read-only snippets of
+/// sources and temporary ones, so merging may replace it
+fn owned(snippet: &Snippet) -> bool {
+    snippet.source.is_none() && !snippet.temporary
+}
+
+/// Finds all pairs of snippets of the store whose descriptions are similar,
+/// most similar first
+pub fn find_near_duplicates(snippets: &[Snippet]) -> Vec<DuplicatePair> {
+    let mut pairs = Vec::new();
+    for left in (0..snippets.len()).filter(|&i| owned(&snippets[i])) {
+        for right in (left + 1..snippets.len()).filter(|&i| owned(&snippets[i])) {
+            let score = similarity(&snippets[left].description, &snippets[right].description);
+            if score >= DUPLICATE_THRESHOLD {
+                pairs.push(DuplicatePair { left, right, score });
+            }
+        }
+    }
+
+    pairs.sort_by(|a, b| b.score.total_cmp(&a.score));
+    pairs
+}
+
+/// Whether two bodies are the same apart from whitespace, the way one
+/// command saved twice tends to differ
+pub fn same_body(a: &str, b: &str) -> bool {
+    !a.trim().is_empty() && a.split_whitespace().eq(b.split_whitespace())
+}
+
+/// Builds the snippet replacing both sides of a pair from the side whose
+/// body is kept, the left one when both are, with the tags, variants, field
+/// defaults and parameter history of both and the usage history of both
+pub fn merge_snippets(left: &Snippet, right: &Snippet, title: Pick, body: Pick) -> Snippet {
+    let (kept, other) = match body {
+        Pick::Right => (right, left),
+        _ => (left, right),
+    };
+    let mut merged = kept.clone();
+    merged.title = match title {
+        Pick::Right => right.title.clone(),
+        _ => left.title.clone(),
+    };
+    merged.description = match body {
+        Pick::Left => left.description.clone(),
+        Pick::Right => right.description.clone(),
+        Pick::Both => combine_lines(&left.description, &right.description),
+    };
+    // The other body is in there too, so it is only trusted when both were
+    if body == Pick::Both {
+        merged.needs_review |= other.needs_review;
+        merged.trusted_commands = None;
+    }
+
+    for tag in &other.tags {
+        if !merged.tags.contains(tag) {
+            merged.tags.push(tag.clone());
+        }
+    }
+    for (name, text) in &other.variants {
+        merged
+            .variants
+            .entry(name.clone())
+            .or_insert_with(|| text.clone());
+    }
+    for (name, value) in &other.field_defaults {
+        merged
+            .field_defaults
+            .entry(name.clone())
+            .or_insert_with(|| value.clone());
+    }
+    for (name, values) in &other.parameter_history {
+        let history = merged.parameter_history.entry(name.clone()).or_default();
+        for value in values {
+            if !history.contains(value) {
+                history.push(value.clone());
+            }
+        }
+    }
+
+    merged.created_at = match (left.created_at, right.created_at) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    };
+    merged.updated_at = Some(time_utils::now());
+    merged.last_used_at = left.last_used_at.max(right.last_used_at);
+    merged.copy_count = left.copy_count + right.copy_count;
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snippet(title: &str, description: &str, tags: &[&str]) -> Snippet {
+        let mut snippet = Snippet::new(title.to_string(), description.to_string());
+        snippet.tags = tags.iter().map(|tag| tag.to_string()).collect();
+        snippet
+    }
+
+    #[test]
+    fn pairs_only_similar_bodies() {
+        let snippets = [
+            snippet("a", "git log --oneline --graph", &[]),
+            snippet("b", "git log --oneline --graph --all", &[]),
+            snippet("c", "docker compose up -d", &[]),
+        ];
+        let pairs = find_near_duplicates(&snippets);
+        assert_eq!(pairs.len(), 1);
+        assert_eq!((pairs[0].left, pairs[0].right), (0, 1));
+        assert!(pairs[0].score >= DUPLICATE_THRESHOLD);
+    }
+
+    #[test]
+    fn skips_snippets_the_store_does_not_hold() {
+        let mut from_source = snippet("b", "git log --oneline", &[]);
+        from_source.source = Some("team".to_string());
+        let mut temporary = snippet("c", "git log --oneline", &[]);
+        temporary.temporary = true;
+        let snippets = [
+            snippet("a", "git log --oneline", &[]),
+            from_source,
+            temporary,
+        ];
+        assert!(find_near_duplicates(&snippets).is_empty());
+    }
+
+    #[test]
+    fn merge_keeps_the_tags_of_both() {
+        let left = snippet("a", "ls -la", &["shell", "files"]);
+        let right = snippet("b", "ls -al", &["files", "unix"]);
+        let merged = merge_snippets(&left, &right, Pick::Left, Pick::Right);
+        assert_eq!(merged.tags, ["files", "unix", "shell"]);
+    }
+
+    #[test]
+    fn merge_keeps_the_fields_of_the_side_whose_body_is_kept() {
+        let mut left = snippet("a", "ls -la", &[]);
+        left.language = Some("bash".to_string());
+        left.working_dir = Some("~/left".to_string());
+        left.variants
+            .insert("fish".to_string(), "ls -la".to_string());
+        left.field_defaults
+            .insert("dir".to_string(), ".".to_string());
+        left.copy_count = 2;
+        let mut right = snippet("b", "ls -al", &[]);
+        right.language = Some("sh".to_string());
+        right.working_dir = Some("~/right".to_string());
+        right
+            .variants
+            .insert("fish".to_string(), "ls -al".to_string());
+        right.variants.insert("nu".to_string(), "ls -a".to_string());
+        right.schedule = Some("09:00-10:00".to_string());
+        right.trusted_commands = Some("fingerprint".to_string());
+        right.copy_count = 3;
+
+        let merged = merge_snippets(&left, &right, Pick::Left, Pick::Right);
+        assert_eq!(merged.title, "a");
+        assert_eq!(merged.description, "ls -al");
+        assert_eq!(merged.language.as_deref(), Some("sh"));
+        assert_eq!(merged.working_dir.as_deref(), Some("~/right"));
+        assert_eq!(merged.schedule.as_deref(), Some("09:00-10:00"));
+        assert_eq!(merged.trusted_commands.as_deref(), Some("fingerprint"));
+        assert_eq!(merged.variants["fish"], "ls -al");
+        assert_eq!(merged.variants["nu"], "ls -a");
+        assert_eq!(merged.field_defaults["dir"], ".");
+        assert_eq!(merged.copy_count, 5);
+    }
+
+    #[test]
+    fn merging_both_bodies_needs_review_if_either_did() {
+        let left = snippet("a", "ls -la", &[]);
+        let mut right = snippet("b", "ls -al", &[]);
+        right.needs_review = true;
+        let merged = merge_snippets(&left, &right, Pick::Left, Pick::Both);
+        assert!(merged.needs_review);
+        assert!(merged.trusted_commands.is_none());
+    }
+}
@@ -0,0 +1,110 @@
+/// One row of a side-by-side diff. A side is `None` when the line only exists
+/// on the other side.
+pub struct DiffRow<'a> {
+    pub left: Option<&'a str>,
+    pub right: Option<&'a str>,
+}
+
+impl DiffRow<'_> {
+    pub fn is_change(&self) -> bool {
+        self.left != self.right
+    }
+}
+
+/// Line based diff using the longest common subsequence of both texts
+pub fn diff_lines<'a>(left: &'a str, right: &'a str) -> Vec<DiffRow<'a>> {
+    let left: Vec<&str> = left.lines().collect();
+    let right: Vec<&str> = right.lines().collect();
+
+    // lcs[i][j] is the LCS length of left[i..] and right[j..]
+    let mut lcs = vec![vec![0usize; right.len() + 1]; left.len() + 1];
+    for i in (0..left.len()).rev() {
+        for j in (0..right.len()).rev() {
+            lcs[i][j] = if left[i] == right[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut rows = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < left.len() || j < right.len() {
+        if i < left.len() && j < right.len() && left[i] == right[j] {
+            rows.push(DiffRow {
+                left: Some(left[i]),
+                right: Some(right[j]),
+            });
+            i += 1;
+            j += 1;
+        } else if j < right.len() && (i == left.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            rows.push(DiffRow {
+                left: None,
+                right: Some(right[j]),
+            });
+            j += 1;
+        } else {
+            rows.push(DiffRow {
+                left: Some(left[i]),
+                right: None,
+            });
+            i += 1;
+        }
+    }
+
+    rows
+}
+
+/// Merges two texts line by line, keeping shared lines once and the lines
+/// unique to either side in order
+pub fn combine_lines(left: &str, right: &str) -> String {
+    diff_lines(left, right)
+        .iter()
+        .flat_map(|row| {
+            if row.is_change() {
+                vec![row.left, row.right]
+            } else {
+                vec![row.left]
+            }
+        })
+        .flatten()
+        .collect::<Vec<&str>>()
+        .join("\n")
+}
+
+/// Similarity of two texts between 0.0 and 1.0 (Dice coefficient over character bigrams)
+pub fn similarity(left: &str, right: &str) -> f64 {
+    let bigrams = |text: &str| -> Vec<(char, char)> {
+        let chars: Vec<char> = text
+            .split_whitespace()
+            .flat_map(|word| word.chars().chain(std::iter::once(' ')))
+            .flat_map(char::to_lowercase)
+            .collect();
+        let mut pairs: Vec<(char, char)> = chars.windows(2).map(|w| (w[0], w[1])).collect();
+        pairs.sort_unstable();
+        pairs
+    };
+
+    let left = bigrams(left);
+    let right = bigrams(right);
+    if left.is_empty() && right.is_empty() {
+        return 1.0;
+    }
+
+    // Both lists are sorted, so the shared bigrams can be counted in one pass
+    let (mut i, mut j, mut shared) = (0, 0, 0);
+    while i < left.len() && j < right.len() {
+        match left[i].cmp(&right[j]) {
+            std::cmp::Ordering::Equal => {
+                shared += 1;
+                i += 1;
+                j += 1;
+            }
+            std::cmp::Ordering::Less => i += 1,
+            std::cmp::Ordering::Greater => j += 1,
+        }
+    }
+
+    2.0 * shared as f64 / (left.len() + right.len()) as f64
+}
@@ -0,0 +1,73 @@
+use std::fmt::Write;
+
+use crate::cli::DigestOptions;
+use crate::models::Snippet;
+use crate::time_utils;
+
+/// Builds a Markdown summary of the snippets added, edited and used since `options.start()`
+pub fn build_digest(snippets: &[Snippet], options: &DigestOptions) -> String {
+    let since = options.start();
+    let in_period = |timestamp: Option<u64>| timestamp.is_some_and(|t| t >= since);
+
+    let added: Vec<&Snippet> = snippets
+        .iter()
+        .filter(|s| in_period(s.created_at))
+        .collect();
+    let edited: Vec<&Snippet> = snippets
+        .iter()
+        .filter(|s| in_period(s.updated_at) && !in_period(s.created_at))
+        .collect();
+    let mut used: Vec<&Snippet> = snippets
+        .iter()
+        .filter(|s| in_period(s.last_used_at) && s.copy_count > 0)
+        .collect();
+    used.sort_by_key(|s| std::cmp::Reverse(s.copy_count));
+    used.truncate(options.top);
+
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "# Snippet digest: {} to {}\n",
+        time_utils::format_date(since),
+        time_utils::format_date(time_utils::now())
+    );
+
+    let _ = writeln!(out, "## Added ({})\n", added.len());
+    write_list(&mut out, &added);
+
+    let _ = writeln!(out, "## Edited ({})\n", edited.len());
+    write_list(&mut out, &edited);
+
+    let _ = writeln!(out, "## Most used\n");
+    if used.is_empty() {
+        let _ = writeln!(out, "_Nothing copied in this period._\n");
+    }
+    for (rank, snippet) in used.iter().enumerate() {
+        let _ = writeln!(
+            out,
+            "{}. **{}** ({} copies, last used {})",
+            rank + 1,
+            snippet.title,
+            snippet.copy_count,
+            snippet
+                .last_used_at
+                .map(time_utils::format_date)
+                .unwrap_or_default()
+        );
+    }
+
+    out
+}
+
+fn write_list(out: &mut String, snippets: &[&Snippet]) {
+    if snippets.is_empty() {
+        let _ = writeln!(out, "_None._\n");
+        return;
+    }
+
+    for snippet in snippets {
+        let first_line = snippet.description.lines().next().unwrap_or_default();
+        let _ = writeln!(out, "- **{}**: `{}`", snippet.title, first_line);
+    }
+    let _ = writeln!(out);
+}
@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+use std::io::{self, BufRead, IsTerminal, Write};
+
+use crate::models::Snippet;
+use crate::template;
+
+/// A problem found in the store
+pub enum Problem {
+    DuplicateTitle { index: usize, first: usize },
+    EmptyTitle { index: usize },
+    EmptyBody { index: usize },
+    InvalidTag { index: usize, tag: String },
+}
+
+impl Problem {
+    fn describe(&self, snippets: &[Snippet]) -> String {
+        match self {
+            Problem::DuplicateTitle { index, first } => format!(
+                "#{} '{}' has the same title as #{}",
+                index + 1,
+                snippets[*index].title,
+                first + 1
+            ),
+            Problem::EmptyTitle { index } => format!("#{} has no title", index + 1),
+            Problem::EmptyBody { index } => {
+                format!(
+                    "#{} '{}' has an empty body",
+                    index + 1,
+                    snippets[*index].title
+                )
+            }
+            Problem::InvalidTag { index, tag } => format!(
+                "#{} '{}' contains an invalid placeholder {}",
+                index + 1,
+                snippets[*index].title,
+                tag
+            ),
+        }
+    }
+
+    /// Describes the automatic fix, if there is one
+    fn fix_description(&self) -> Option<&'static str> {
+        match self {
+            Problem::DuplicateTitle { .. } => Some("add a number to the title"),
+            Problem::EmptyTitle { .. } => Some("use the first line of the body as the title"),
+            Problem::EmptyBody { .. } => Some("delete the snippet"),
+            Problem::InvalidTag { .. } => None,
+        }
+    }
+}
+
+/// Checks every snippet for problems
+pub fn diagnose(snippets: &[Snippet]) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    let mut titles: HashMap<&str, usize> = HashMap::new();
+
+    for (index, snippet) in snippets.iter().enumerate() {
+        let title = snippet.title.trim();
+        if title.is_empty() {
+            problems.push(Problem::EmptyTitle { index });
+        } else if let Some(first) = titles.get(title) {
+            problems.push(Problem::DuplicateTitle {
+                index,
+                first: *first,
+            });
+        } else {
+            titles.insert(title, index);
+        }
+
+        if snippet.description.trim().is_empty() {
+            problems.push(Problem::EmptyBody { index });
+        }
+
+        for tag in template::invalid_tags(&snippet.description) {
+            problems.push(Problem::InvalidTag { index, tag });
+        }
+    }
+
+    problems
+}
+
+/// Applies the chosen fixes. Returns whether anything changed.
+fn apply_fixes(snippets: &mut Vec<Snippet>, fixes: &[&Problem]) -> bool {
+    let mut removed = Vec::new();
+    for problem in fixes {
+        match problem {
+            Problem::DuplicateTitle { index, .. } => {
+                let base = snippets[*index].title.trim().to_string();
+                let mut number = 2;
+                while snippets
+                    .iter()
+                    .any(|s| s.title == format!("{} ({})", base, number))
+                {
+                    number += 1;
+                }
+                snippets[*index].title = format!("{} ({})", base, number);
+            }
+            Problem::EmptyTitle { index } => {
+                let first_line = snippets[*index]
+                    .description
+                    .lines()
+                    .next()
+                    .unwrap_or_default();
+                snippets[*index].title = first_line.trim().to_string();
+            }
+            Problem::EmptyBody { index } => removed.push(*index),
+            Problem::InvalidTag { .. } => {}
+        }
+    }
+
+    // Delete from the back so the remaining indices stay valid
+    removed.sort_unstable();
+    removed.dedup();
+    for index in removed.iter().rev() {
+        snippets.remove(*index);
+    }
+
+    !fixes.is_empty()
+}
+
+/// Runs the checks, prints a report and walks through the fixes.
+/// With `assume_yes` every available fix is applied without asking.
+/// Returns whether the store changed and how many problems were left unfixed.
+pub fn run(snippets: &mut Vec<Snippet>, assume_yes: bool) -> (bool, usize) {
+    let problems = diagnose(snippets);
+    if problems.is_empty() {
+        println!("No problems found in {} snippets.", snippets.len());
+        return (false, 0);
+    }
+
+    println!("Found {} problem(s):", problems.len());
+    let interactive = !assume_yes && io::stdin().is_terminal();
+    let mut fixes = Vec::new();
+    let mut stdin = io::stdin().lock();
+
+    for problem in &problems {
+        println!("  - {}", problem.describe(snippets));
+        let fix = match problem.fix_description() {
+            Some(fix) => fix,
+            None => continue,
+        };
+
+        if assume_yes {
+            fixes.push(problem);
+        } else if interactive {
+            print!("    Fix: {}? [y/N] ", fix);
+            let _ = io::stdout().flush();
+            let mut answer = String::new();
+            if stdin.read_line(&mut answer).is_ok() && answer.trim().eq_ignore_ascii_case("y") {
+                fixes.push(problem);
+            }
+        } else {
+            println!("    Fix available: {} (run with --yes to apply)", fix);
+        }
+    }
+
+    let unfixed = problems.len() - fixes.len();
+    let changed = apply_fixes(snippets, &fixes);
+    if changed {
+        println!("Fixed {} problem(s).", fixes.len());
+    }
+    (changed, unfixed)
+}
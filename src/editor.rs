@@ -0,0 +1,216 @@
+//! Text fields with a cursor for the form, the description's running over
+//! several lines
+
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// Text being typed, with the cursor as a byte offset into it
+#[derive(Default)]
+pub struct TextArea {
+    text: String,
+    cursor: usize,
+}
+
+impl TextArea {
+    /// Replaces the text, with the cursor at its end
+    pub fn set_text(&mut self, text: String) {
+        self.cursor = text.len();
+        self.text = text;
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// The text, leaving the field empty
+    pub fn take(&mut self) -> String {
+        self.cursor = 0;
+        std::mem::take(&mut self.text)
+    }
+
+    pub fn insert(&mut self, c: char) {
+        self.text.insert(self.cursor, c);
+        self.cursor += c.len_utf8();
+    }
+
+    pub fn insert_str(&mut self, text: &str) {
+        self.text.insert_str(self.cursor, text);
+        self.cursor += text.len();
+    }
+
+    /// Deletes the character before the cursor
+    pub fn backspace(&mut self) {
+        if let Some(c) = self.text[..self.cursor].chars().next_back() {
+            self.cursor -= c.len_utf8();
+            self.text.remove(self.cursor);
+        }
+    }
+
+    /// Deletes the character under the cursor
+    pub fn delete(&mut self) {
+        if self.cursor < self.text.len() {
+            self.text.remove(self.cursor);
+        }
+    }
+
+    pub fn left(&mut self) {
+        if let Some(c) = self.text[..self.cursor].chars().next_back() {
+            self.cursor -= c.len_utf8();
+        }
+    }
+
+    pub fn right(&mut self) {
+        if let Some(c) = self.text[self.cursor..].chars().next() {
+            self.cursor += c.len_utf8();
+        }
+    }
+
+    /// Moves to the start of the word before the cursor
+    pub fn word_left(&mut self) {
+        let before = self.text[..self.cursor].trim_end_matches(|c| !is_word(c));
+        self.cursor = before
+            .char_indices()
+            .rev()
+            .find(|(_, c)| !is_word(*c))
+            .map_or(0, |(offset, c)| offset + c.len_utf8());
+    }
+
+    /// Moves to the end of the word after the cursor
+    pub fn word_right(&mut self) {
+        let after = &self.text[self.cursor..];
+        let rest = after.trim_start_matches(|c| !is_word(c));
+        let word = rest.find(|c| !is_word(c)).unwrap_or(rest.len());
+        self.cursor += after.len() - rest.len() + word;
+    }
+
+    pub fn home(&mut self) {
+        self.cursor = self.line_start(self.cursor);
+    }
+
+    pub fn end(&mut self) {
+        self.cursor = self.line_end(self.cursor);
+    }
+
+    /// Moves to the same column of the previous line, or its end when it is shorter
+    pub fn up(&mut self) {
+        let start = self.line_start(self.cursor);
+        if start == 0 {
+            self.cursor = 0;
+            return;
+        }
+        let column = self.text[start..self.cursor].chars().count();
+        self.cursor = self.at_column(self.line_start(start - 1), column);
+    }
+
+    /// Moves to the same column of the next line, or its end when it is shorter
+    pub fn down(&mut self) {
+        let end = self.line_end(self.cursor);
+        if end == self.text.len() {
+            self.cursor = end;
+            return;
+        }
+        let column = self.text[self.line_start(self.cursor)..self.cursor]
+            .chars()
+            .count();
+        self.cursor = self.at_column(end + 1, column);
+    }
+
+    fn line_start(&self, position: usize) -> usize {
+        self.text[..position]
+            .rfind('\n')
+            .map_or(0, |newline| newline + 1)
+    }
+
+    fn line_end(&self, position: usize) -> usize {
+        self.text[position..]
+            .find('\n')
+            .map_or(self.text.len(), |newline| position + newline)
+    }
+
+    /// Offset of the `column`-th character of the line starting at `start`
+    fn at_column(&self, start: usize, column: usize) -> usize {
+        let end = self.line_end(start);
+        self.text[start..end]
+            .char_indices()
+            .nth(column)
+            .map_or(end, |(offset, _)| start + offset)
+    }
+
+    /// The text on a row `width` columns wide, scrolled sideways so the cursor
+    /// is in view, and the cursor's column in it. For fields without newlines.
+    pub fn line_view(&self, width: usize) -> (String, u16) {
+        let width = width.max(1);
+        let before = self.text[..self.cursor].width();
+        // The cursor at the end takes a column of its own
+        let offset = before.saturating_sub(width - 1);
+        let mut skipped = 0;
+        let mut shown = String::new();
+        let mut used = 0;
+        for c in self.text.chars() {
+            let char_width = c.width().unwrap_or(0);
+            if skipped < offset {
+                skipped += char_width;
+                continue;
+            }
+            if used + char_width > width {
+                break;
+            }
+            shown.push(c);
+            used += char_width;
+        }
+        (shown, (before - skipped) as u16)
+    }
+
+    /// The rows shown in a box `width` columns wide and `height` rows high,
+    /// with long lines wrapped and scrolled so the cursor is in view, and the
+    /// cursor's column and row within the box
+    pub fn view(&self, width: usize, height: usize) -> (Vec<String>, u16, u16) {
+        let width = width.max(1);
+        let mut rows = vec![String::new()];
+        let mut column = 0;
+        let mut cursor = None;
+        for (offset, c) in self.text.char_indices() {
+            if c == '\n' {
+                if offset == self.cursor {
+                    cursor = Some((column, rows.len() - 1));
+                }
+                rows.push(String::new());
+                column = 0;
+                continue;
+            }
+            let char_width = c.width().unwrap_or(0);
+            if column + char_width > width {
+                rows.push(String::new());
+                column = 0;
+            }
+            if offset == self.cursor {
+                cursor = Some((column, rows.len() - 1));
+            }
+            if let Some(row) = rows.last_mut() {
+                row.push(c);
+            }
+            column += char_width;
+        }
+        let (column, row) = match cursor {
+            Some(cursor) => cursor,
+            // At the end, past a full row the cursor starts the next one
+            None if column >= width => {
+                rows.push(String::new());
+                (0, rows.len() - 1)
+            }
+            None => (column, rows.len() - 1),
+        };
+
+        let first = (row + 1).saturating_sub(height.max(1));
+        let rows = rows.into_iter().skip(first).take(height.max(1)).collect();
+        (rows, column as u16, (row - first) as u16)
+    }
+}
+
+/// Letters, digits and `_` make up words for moving by word
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
@@ -0,0 +1,138 @@
+//! Changes to the store as Server-Sent Events on `/events` of `[server]
+//! listen`, so web pages and bots stay in sync without polling `/snippets`. Each event is named `add`, `edit`, `delete` or `copy` and
+//! carries the snippet as JSON, just its title for `delete`. Snippets are
+//! told apart by title, so a rename arrives as a `delete` and an `add`.
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::net::TcpStream;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde_json::json;
+
+use crate::models::Snippet;
+
+/// A comment is sent this often when nothing happens, which keeps proxies
+/// from closing the stream and notices clients that went away
+const KEEPALIVE: Duration = Duration::from_secs(15);
+
+pub enum Change<'a> {
+    Add(&'a Snippet),
+    Edit(&'a Snippet),
+    Delete(&'a str),
+    Copy(&'a Snippet),
+}
+
+impl Change<'_> {
+    fn name(&self) -> &'static str {
+        match self {
+            Change::Add(_) => "add",
+            Change::Edit(_) => "edit",
+            Change::Delete(_) => "delete",
+            Change::Copy(_) => "copy",
+        }
+    }
+
+    /// The change in the event stream format
+    fn event(&self) -> String {
+        let data = match self {
+            Change::Add(snippet) | Change::Edit(snippet) | Change::Copy(snippet) => {
+                json!({ "title": snippet.title, "snippet": snippet })
+            }
+            Change::Delete(title) => json!({ "title": title }),
+        };
+        format!("event: {}\ndata: {}\n\n", self.name(), data)
+    }
+}
+
+/// The snippet without what copying it changes
+fn content(snippet: &Snippet) -> Snippet {
+    let mut snippet = snippet.clone();
+    snippet.copy_count = 0;
+    snippet.last_used_at = None;
+    snippet.parameter_history.clear();
+    snippet
+}
+
+/// What changed from the snippets `before` to the snippets `after`. A save
+/// that only counts a copy, as the app makes when copying through the
+/// daemon, is a `copy`.
+pub fn changes<'a>(before: &'a [Snippet], after: &'a [Snippet]) -> Vec<Change<'a>> {
+    let mut old: HashMap<&str, &Snippet> = before.iter().map(|s| (s.title.as_str(), s)).collect();
+    let mut changes = vec![];
+    for snippet in after {
+        match old.remove(snippet.title.as_str()) {
+            None => changes.push(Change::Add(snippet)),
+            Some(previous) => {
+                let same = serde_json::to_value(content(previous)).ok()
+                    == serde_json::to_value(content(snippet)).ok();
+                if !same {
+                    changes.push(Change::Edit(snippet));
+                } else if snippet.copy_count > previous.copy_count {
+                    changes.push(Change::Copy(snippet));
+                }
+            }
+        }
+    }
+    // In store order, not the map's
+    changes.extend(
+        before
+            .iter()
+            .filter(|s| old.contains_key(s.title.as_str()))
+            .map(|s| Change::Delete(&s.title)),
+    );
+    changes
+}
+
+/// The clients listening on `/events`
+#[derive(Default)]
+pub struct Feed {
+    subscribers: Mutex<Vec<Sender<String>>>,
+}
+
+impl Feed {
+    pub fn subscribe(&self) -> Receiver<String> {
+        let (sender, receiver) = mpsc::channel();
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.push(sender);
+        }
+        receiver
+    }
+
+    /// Sends `change` to every client, forgetting the ones that went away
+    pub fn publish(&self, change: &Change) {
+        let event = change.event();
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.retain(|subscriber| subscriber.send(event.clone()).is_ok());
+        }
+    }
+}
+
+/// Answers a request for `/events`, sending the events that arrive on
+/// `events` until the client closes the connection
+pub fn stream(mut stream: TcpStream, events: Receiver<String>) -> io::Result<()> {
+    // A client that stops reading is let go rather than blocking the thread
+    stream.set_write_timeout(Some(KEEPALIVE))?;
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: keep-alive\r\n\r\n: connected\n\n"
+    )?;
+    stream.flush()?;
+    loop {
+        let text = match events.recv_timeout(KEEPALIVE) {
+            Ok(event) => event,
+            Err(RecvTimeoutError::Timeout) => ": keepalive\n\n".to_string(),
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        };
+        // The client going away ends the stream, it isn't an error
+        if stream
+            .write_all(text.as_bytes())
+            .and_then(|_| stream.flush())
+            .is_err()
+        {
+            return Ok(());
+        }
+    }
+}
@@ -0,0 +1,110 @@
+//! The formats `sniprrr export` writes and `sniprrr import` reads: the store
+//! file itself as JSON, YAML and TOML documents laid out like it with every
+//! field, and CSV with only the title, body and tags. A file's format is told
+//! from its extension unless one is named.
+
+use std::fs;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::file_utils::{parse_store, write_store, STORE_VERSION};
+use crate::models::Snippet;
+use crate::{csv_io, import};
+
+/// The formats listed in messages
+pub const NAMES: &str = "json, yaml, toml or csv";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// The store format, which imports everything back
+    Json,
+    Yaml,
+    Toml,
+    /// Title, body and tags only, for spreadsheets
+    Csv,
+}
+
+/// The layout of the YAML and TOML documents, the same as the store file's
+#[derive(Serialize)]
+struct Document<'a> {
+    version: u64,
+    snippets: &'a [Snippet],
+}
+
+impl Format {
+    /// The format called `name`, `store` being another name for JSON
+    pub fn parse(name: &str) -> Result<Format, String> {
+        match name.to_lowercase().as_str() {
+            "json" | "store" => Ok(Format::Json),
+            "yaml" | "yml" => Ok(Format::Yaml),
+            "toml" => Ok(Format::Toml),
+            "csv" => Ok(Format::Csv),
+            other => Err(format!("Unknown format '{}', use {}", other, NAMES)),
+        }
+    }
+
+    /// The format of a file by its extension, `None` for other extensions
+    pub fn for_path(path: &Path) -> Option<Format> {
+        Format::parse(path.extension()?.to_str()?).ok()
+    }
+}
+
+/// `snippets` written out in `format`
+pub fn render(snippets: &[Snippet], format: Format) -> Result<String, String> {
+    let document = Document {
+        version: STORE_VERSION,
+        snippets,
+    };
+    match format {
+        Format::Json => serde_json::to_string_pretty(&document).map_err(|error| error.to_string()),
+        Format::Yaml => serde_yaml::to_string(&document).map_err(|error| error.to_string()),
+        Format::Toml => toml::to_string_pretty(&document).map_err(|error| error.to_string()),
+        Format::Csv => csv_io::to_string(snippets),
+    }
+}
+
+/// Writes `snippets` to a file at `path` in `format`. JSON files are written
+/// like the store, compressed when it would be.
+pub fn write(path: &Path, snippets: &[Snippet], format: Format) -> Result<(), String> {
+    let error = |error: String| format!("{}: {}", path.display(), error);
+    match format {
+        Format::Json => write_store(path, snippets).map_err(|e| error(e.to_string())),
+        Format::Csv => csv_io::write(path, snippets),
+        format => fs::write(path, render(snippets, format)?).map_err(|e| error(e.to_string())),
+    }
+}
+
+/// The snippets of the JSON, YAML or TOML file at `path`. CSV files are read
+/// with `csv_io` since their columns are picked first.
+pub fn read(path: &Path, format: Format) -> Result<Vec<Snippet>, String> {
+    let error = |error: String| format!("{}: {}", path.display(), error);
+    let value: serde_json::Value = match format {
+        Format::Json => return import::read_store_file(&path.to_string_lossy()),
+        Format::Csv => {
+            return Err(error(
+                "CSV files are imported with their columns picked first".to_string(),
+            ))
+        }
+        Format::Yaml => {
+            let contents = fs::read_to_string(path).map_err(|e| error(e.to_string()))?;
+            serde_yaml::from_str(&contents).map_err(|e| error(e.to_string()))?
+        }
+        Format::Toml => {
+            let contents = fs::read_to_string(path).map_err(|e| error(e.to_string()))?;
+            toml::from_str(&contents).map_err(|e| error(e.to_string()))?
+        }
+    };
+    parse_document(value).map_err(error)
+}
+
+/// Reads a document in the store's layout. The version may be left out of
+/// hand-written ones, and a bare list of snippets is read too.
+fn parse_document(mut value: serde_json::Value) -> Result<Vec<Snippet>, String> {
+    if let Some(document) = value.as_object_mut() {
+        document
+            .entry("version")
+            .or_insert_with(|| STORE_VERSION.into());
+    }
+    parse_store(&value.to_string()).map_err(|error| error.to_string())
+}
@@ -0,0 +1,35 @@
+//! Frecency: how often and how recently a snippet was copied, in one score.
+//! The snippet scoring highest is selected when sniprrr opens and after each
+//! copy, so the likeliest next copy takes just `c`.
+
+use crate::models::Snippet;
+use crate::time_utils::SECONDS_PER_DAY;
+
+/// What a copy counts for while the last one is younger than so many days
+const WEIGHTS: [(u64, u64); 4] = [(4, 100), (14, 70), (31, 50), (90, 30)];
+/// What a copy counts for once the last one is older than all of the above
+const OLD_WEIGHT: u64 = 10;
+
+/// The frecency of `snippet` at `now`, 0 for snippets never copied
+pub fn score(snippet: &Snippet, now: u64) -> u64 {
+    let Some(last_used) = snippet.last_used_at else {
+        return 0;
+    };
+    let days = now.saturating_sub(last_used) / SECONDS_PER_DAY;
+    let weight = WEIGHTS
+        .iter()
+        .find(|(younger_than, _)| days < *younger_than)
+        .map_or(OLD_WEIGHT, |(_, weight)| *weight);
+    snippet.copy_count as u64 * weight
+}
+
+/// Of `indices` into `snippets`, the one with the highest frecency, the first
+/// of them on ties. `None` when none of them was ever copied.
+pub fn best(snippets: &[Snippet], indices: &[usize], now: u64) -> Option<usize> {
+    indices
+        .iter()
+        .map(|&i| (score(&snippets[i], now), i))
+        .filter(|(score, _)| *score > 0)
+        .min_by_key(|(score, _)| std::cmp::Reverse(*score))
+        .map(|(_, i)| i)
+}
@@ -0,0 +1,110 @@
+//! Drafts snippet bodies with a language model behind an OpenAI-compatible
+//! `/chat/completions` endpoint set with `[generate]`, such as a local
+//! Ollama. The answer only goes into the form, nothing is saved until the
+//! form is submitted.
+
+use serde::Deserialize;
+
+use crate::http;
+use crate::models::Snippet;
+
+/// The `[generate]` section of the config file
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct GenerateConfig {
+    /// e.g. `http://localhost:11434/v1/chat/completions`. Off when not set.
+    pub endpoint: Option<String>,
+    pub model: String,
+    /// Environment variable holding an API key, sent as a bearer token
+    pub api_key_env: Option<String>,
+    /// Instructions sent ahead of every prompt
+    pub system_prompt: String,
+}
+
+impl Default for GenerateConfig {
+    fn default() -> GenerateConfig {
+        GenerateConfig {
+            endpoint: None,
+            model: "llama3.2".to_string(),
+            api_key_env: None,
+            system_prompt: "You write snippets for a snippet manager, mostly shell commands and \
+                short texts. Answer with the snippet only, without explanations or Markdown."
+                .to_string(),
+        }
+    }
+}
+
+impl GenerateConfig {
+    pub fn enabled(&self) -> bool {
+        self.endpoint.is_some()
+    }
+}
+
+#[derive(Deserialize)]
+struct CompletionResponse {
+    choices: Vec<Choice>,
+}
+
+#[derive(Deserialize)]
+struct Choice {
+    message: Message,
+}
+
+#[derive(Deserialize)]
+struct Message {
+    content: String,
+}
+
+/// A snippet body written for `prompt`, which is about `snippet` when given
+pub fn generate(
+    config: &GenerateConfig,
+    prompt: &str,
+    snippet: Option<&Snippet>,
+) -> Result<String, String> {
+    let endpoint = config
+        .endpoint
+        .as_deref()
+        .ok_or("Generating needs an endpoint set with `[generate] endpoint`")?;
+    let prompt = match snippet {
+        Some(snippet) => format!(
+            "{}\n\nThe snippet '{}':\n{}",
+            prompt, snippet.title, snippet.description
+        ),
+        None => prompt.to_string(),
+    };
+    let body = serde_json::json!({
+        "model": config.model,
+        "messages": [
+            { "role": "system", "content": config.system_prompt },
+            { "role": "user", "content": prompt },
+        ],
+    });
+    let answer = http::post_json(endpoint, config.api_key_env.as_deref(), &body)
+        .map_err(|error| format!("The generate endpoint failed: {}", error))?;
+
+    let response: CompletionResponse = serde_json::from_slice(&answer)
+        .map_err(|error| format!("Unexpected answer from the generate endpoint: {}", error))?;
+    let content = response
+        .choices
+        .into_iter()
+        .next()
+        .map(|choice| choice.message.content)
+        .ok_or("The generate endpoint sent no answer")?;
+    Ok(strip_fence(&content))
+}
+
+/// The answer without the Markdown code fence models tend to add anyway
+fn strip_fence(answer: &str) -> String {
+    let answer = answer.trim();
+    let Some(inner) = answer
+        .strip_prefix("```")
+        .and_then(|rest| rest.strip_suffix("```"))
+    else {
+        return answer.to_string();
+    };
+    // The rest of the opening line names the language
+    match inner.split_once('\n') {
+        Some((_, body)) => body.trim_end().to_string(),
+        None => inner.trim().to_string(),
+    }
+}
@@ -0,0 +1,53 @@
+//! JSON requests to OpenAI-compatible endpoints, made with curl like the
+//! uploads of `share`
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Posts `body` to `endpoint` and returns the answer. The API key is read
+/// from the environment variable `api_key_env` and sent as a bearer token.
+/// The request goes to curl as a config on stdin, which keeps the key out of
+/// the process list.
+pub fn post_json(
+    endpoint: &str,
+    api_key_env: Option<&str>,
+    body: &serde_json::Value,
+) -> Result<Vec<u8>, String> {
+    let mut curl_config = format!(
+        "url = \"{}\"\nheader = \"Content-Type: application/json\"\n",
+        quote(endpoint)
+    );
+    if let Some(name) = api_key_env {
+        let key = std::env::var(name).map_err(|_| format!("{} is not set", name))?;
+        curl_config.push_str(&format!(
+            "header = \"Authorization: Bearer {}\"\n",
+            quote(&key)
+        ));
+    }
+    curl_config.push_str(&format!("data-binary = \"{}\"\n", quote(&body.to_string())));
+
+    let mut child = Command::new("curl")
+        .args(["--silent", "--show-error", "--fail", "--config", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|error| format!("Could not run curl: {}", error))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(curl_config.as_bytes())
+            .map_err(|error| error.to_string())?;
+    }
+    let output = child
+        .wait_with_output()
+        .map_err(|error| error.to_string())?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    Ok(output.stdout)
+}
+
+/// `text` inside double quotes of a curl config
+fn quote(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
@@ -0,0 +1,39 @@
+//! Importers turn snippet collections from elsewhere into snippets. They all
+//! end in `ImportPopup`, where the incoming snippets are reviewed before
+//! anything is written.
+
+use std::path::PathBuf;
+
+use crate::file_utils::load_store;
+use crate::history;
+use crate::models::Snippet;
+
+/// `path` with a leading `~/` pointing into the home directory
+pub fn expand_home(path: &str) -> PathBuf {
+    match path.strip_prefix("~/") {
+        Some(rest) => dirs::home_dir().unwrap_or_default().join(rest),
+        None => PathBuf::from(path),
+    }
+}
+
+/// Reads the snippets of a sniprrr store file, backup or export at `path`
+pub fn read_store_file(path: &str) -> Result<Vec<Snippet>, String> {
+    let path = expand_home(path);
+    load_store(&path).map_err(|error| format!("{}: {}", path.display(), error))
+}
+
+/// Adds `incoming` to `snippets`, replacing snippets with the same title.
+/// They wait for review and lose any command allowlisting they came with.
+/// Returns how many were added and how many replaced.
+pub fn merge(snippets: &mut Vec<Snippet>, incoming: Vec<Snippet>) -> (usize, usize) {
+    let mut replaced = 0;
+    let count = incoming.len();
+    for mut snippet in incoming {
+        snippet.needs_review = true;
+        snippet.trusted_commands = None;
+        if history::restore_snippet(snippets, snippet) {
+            replaced += 1;
+        }
+    }
+    (count - replaced, replaced)
+}
@@ -0,0 +1,123 @@
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+use crate::file_utils::{
+    backup_file_path, load_messages_from_file, load_store, messages_file_path, StoreError,
+    BACKUP_COUNT,
+};
+use crate::models::Snippet;
+use crate::{storage, time_utils};
+
+/// A backup that could be read successfully
+struct UsableBackup {
+    number: usize,
+    modified: Option<u64>,
+    snippets: Vec<Snippet>,
+}
+
+/// Loads the store. When it is damaged, reports what could be recovered and
+/// asks whether to keep the salvaged entries or restore a backup instead of
+/// silently starting with an empty list.
+pub fn load_or_repair() -> Result<Vec<Snippet>, String> {
+    let (reason, salvaged) = match load_messages_from_file() {
+        Ok(snippets) => return Ok(snippets),
+        Err(StoreError::Corrupt { reason, salvaged }) => (reason, salvaged),
+        Err(error) => return Err(error.to_string()),
+    };
+
+    // Only reachable when the file exists, so the path is known
+    let store_path = messages_file_path().unwrap();
+    let backups = usable_backups(&store_path);
+
+    eprintln!(
+        "The snippet store {} is damaged: {}",
+        store_path.display(),
+        reason
+    );
+    eprintln!(
+        "{} complete entries could be recovered from it:",
+        salvaged.len()
+    );
+    for snippet in &salvaged {
+        eprintln!("  - {}", snippet.title);
+    }
+
+    if !io::stdin().is_terminal() {
+        return Err("Run sniprrr in a terminal to repair the store".to_string());
+    }
+
+    eprintln!();
+    eprintln!("  [s] keep the {} recovered entries", salvaged.len());
+    for backup in &backups {
+        eprintln!(
+            "  [{}] restore backup {} ({} snippets, saved {})",
+            backup.number,
+            backup_file_path(&store_path, backup.number).display(),
+            backup.snippets.len(),
+            backup
+                .modified
+                .map(time_utils::format_datetime)
+                .unwrap_or_else(|| "at an unknown time".to_string())
+        );
+    }
+    eprintln!("  [q] quit without changing anything");
+
+    let mut stdin = io::stdin().lock();
+    loop {
+        eprint!("> ");
+        let _ = io::stderr().flush();
+
+        let mut answer = String::new();
+        if stdin.read_line(&mut answer).map_err(|e| e.to_string())? == 0 {
+            return Err("No choice made, the store was left untouched".to_string());
+        }
+
+        let chosen = match answer.trim() {
+            "q" | "Q" => return Err("The store was left untouched".to_string()),
+            "s" | "S" => salvaged.clone(),
+            other => match other
+                .parse::<usize>()
+                .ok()
+                .and_then(|number| backups.iter().find(|b| b.number == number))
+            {
+                Some(backup) => backup.snippets.clone(),
+                None => continue,
+            },
+        };
+
+        // Keep the damaged file around in case something else can be recovered by hand
+        let mut damaged_name = store_path.file_name().unwrap_or_default().to_os_string();
+        damaged_name.push(format!(".damaged-{}", time_utils::now()));
+        let damaged_path = store_path.with_file_name(damaged_name);
+        fs::copy(&store_path, &damaged_path).map_err(|e| e.to_string())?;
+        storage::replace(&store_path, &chosen).map_err(|e| e.to_string())?;
+
+        eprintln!(
+            "Restored {} snippets. The damaged file was kept as {}",
+            chosen.len(),
+            damaged_path.display()
+        );
+        return Ok(chosen);
+    }
+}
+
+fn usable_backups(store_path: &Path) -> Vec<UsableBackup> {
+    (1..=BACKUP_COUNT)
+        .filter_map(|number| {
+            let path = backup_file_path(store_path, number);
+            let snippets = load_store(&path).ok()?;
+            let modified = fs::metadata(&path)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+                .map(|duration| duration.as_secs());
+            Some(UsableBackup {
+                number,
+                modified,
+                snippets,
+            })
+        })
+        .collect()
+}
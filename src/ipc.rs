@@ -0,0 +1,249 @@
+//! Local socket used to talk to an already running sniprrr.
+//!
+//! The first interactive instance listens on `sniprrr.sock` in the config
+//! directory. Later invocations send it a JSON request per connection and
+//! read back a JSON response, so they don't race over the store file.
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::Snippet;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "kebab-case")]
+pub enum Request {
+    /// Checks whether an instance is running without bothering it
+    Ping,
+    /// Another instance was started; let the user know where sniprrr is already open
+    Focus,
+    Copy {
+        title: String,
+        /// The text to copy instead of the body, for a template filled in by the sender
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        text: Option<String>,
+        /// Placeholder values `text` was filled in with, remembered with the snippet
+        #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+        parameters: HashMap<String, String>,
+    },
+    /// Fetch every snippet
+    List,
+    /// Replace the stored snippets. `base` is the revision they were loaded
+    /// at, so the daemon merges in what other clients saved since instead
+    /// of writing over it.
+    Save {
+        snippets: Vec<Snippet>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        base: Option<u64>,
+    },
+}
+
+impl Request {
+    /// The command as it is sent, e.g. `copy`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Request::Ping => "ping",
+            Request::Focus => "focus",
+            Request::Copy { .. } => "copy",
+            Request::List => "list",
+            Request::Save { .. } => "save",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Response {
+    pub ok: bool,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snippets: Option<Vec<Snippet>>,
+    /// Revision of the daemon's store the snippets are at, counting its saves
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revision: Option<u64>,
+}
+
+/// `Response::message` of a ping answered by `sniprrr daemon`
+pub const DAEMON_PING: &str = "daemon";
+
+impl Response {
+    pub fn ok(message: impl Into<String>) -> Response {
+        Response {
+            ok: true,
+            message: message.into(),
+            snippets: None,
+            revision: None,
+        }
+    }
+
+    pub fn error(message: impl Into<String>) -> Response {
+        Response {
+            ok: false,
+            message: message.into(),
+            snippets: None,
+            revision: None,
+        }
+    }
+
+    pub fn with_snippets(snippets: Vec<Snippet>) -> Response {
+        Response {
+            ok: true,
+            message: String::new(),
+            snippets: Some(snippets),
+            revision: None,
+        }
+    }
+
+    pub fn at_revision(mut self, revision: u64) -> Response {
+        self.revision = Some(revision);
+        self
+    }
+}
+
+/// A request received by the running instance, with the channel its response goes back on
+pub struct Incoming {
+    pub request: Request,
+    pub reply: Sender<Response>,
+}
+
+pub fn socket_path() -> Option<PathBuf> {
+    Some(dirs::config_dir()?.join("sniprrr").join("sniprrr.sock"))
+}
+
+/// Sends `request` to the running instance. Returns `None` when no instance is running.
+#[cfg(unix)]
+pub fn send(request: &Request) -> Option<io::Result<Response>> {
+    use std::os::unix::net::UnixStream;
+
+    let stream = UnixStream::connect(socket_path()?).ok()?;
+    Some(exchange(stream, request))
+}
+
+#[cfg(not(unix))]
+pub fn send(_request: &Request) -> Option<io::Result<Response>> {
+    None
+}
+
+#[cfg(unix)]
+fn exchange(mut stream: std::os::unix::net::UnixStream, request: &Request) -> io::Result<Response> {
+    stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+    let mut line = serde_json::to_string(request)?;
+    line.push('\n');
+    stream.write_all(line.as_bytes())?;
+
+    let mut response = String::new();
+    BufReader::new(stream).read_line(&mut response)?;
+    serde_json::from_str(&response)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+}
+
+/// Whether another instance is listening on the socket
+pub fn is_running() -> bool {
+    send(&Request::Ping).is_some()
+}
+
+/// Whether the instance listening on the socket is `sniprrr daemon`
+pub fn daemon_is_running() -> bool {
+    matches!(send(&Request::Ping), Some(Ok(response)) if response.message == DAEMON_PING)
+}
+
+/// Sends a request that must succeed, turning connection problems and
+/// error responses into `io::Error`s
+pub fn request(request: &Request) -> io::Result<Response> {
+    let response = send(request).ok_or_else(|| {
+        io::Error::new(io::ErrorKind::NotConnected, "sniprrr daemon is not running")
+    })??;
+    if response.ok {
+        Ok(response)
+    } else {
+        Err(io::Error::other(response.message))
+    }
+}
+
+/// Hands `request` to the main loop listening on `sender` and waits for its
+/// response
+pub fn ask(sender: &Sender<Incoming>, request: Request) -> Response {
+    let (reply, response) = mpsc::channel();
+    let _ = sender.send(Incoming { request, reply });
+    response
+        .recv_timeout(Duration::from_secs(30))
+        .unwrap_or_else(|_| Response::error("sniprrr did not answer"))
+}
+
+/// Owns the socket of the running instance and removes it on drop
+pub struct InstanceGuard {
+    path: PathBuf,
+}
+
+impl Drop for InstanceGuard {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Starts listening for requests from other invocations. The returned
+/// receiver yields them for the main loop to answer.
+pub fn listen() -> io::Result<(InstanceGuard, Receiver<Incoming>)> {
+    let (sender, receiver) = mpsc::channel();
+    Ok((listen_on(sender)?, receiver))
+}
+
+/// Starts listening for requests from other invocations, sending them on
+/// `sender`, which others in the process may send their requests on too
+#[cfg(unix)]
+pub fn listen_on(sender: Sender<Incoming>) -> io::Result<InstanceGuard> {
+    use std::os::unix::net::UnixListener;
+
+    let path = socket_path()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No app config dir"))?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    // Nobody answered on the socket, so a leftover file is from a crashed instance
+    if path.exists() {
+        std::fs::remove_file(&path)?;
+    }
+
+    let listener = UnixListener::bind(&path)?;
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let sender = sender.clone();
+            std::thread::spawn(move || {
+                let _ = serve_connection(stream, &sender);
+            });
+        }
+    });
+
+    Ok(InstanceGuard { path })
+}
+
+#[cfg(not(unix))]
+pub fn listen_on(_sender: Sender<Incoming>) -> io::Result<InstanceGuard> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "single instance handoff is only available on Unix",
+    ))
+}
+
+#[cfg(unix)]
+fn serve_connection(
+    stream: std::os::unix::net::UnixStream,
+    sender: &Sender<Incoming>,
+) -> io::Result<()> {
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line)?;
+
+    let response = match serde_json::from_str::<Request>(&line) {
+        Ok(request) => ask(sender, request),
+        Err(error) => Response::error(format!("Invalid request: {}", error)),
+    };
+
+    let mut line = serde_json::to_string(&response)?;
+    line.push('\n');
+    (&stream).write_all(line.as_bytes())
+}
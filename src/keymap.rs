@@ -0,0 +1,523 @@
+//! Normal mode key bindings, including vim-like sequences such as `dd`,
+//! counts such as `5j` and keys held with Ctrl or Alt such as `<C-r>`
+
+use std::collections::BTreeMap;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+
+/// Something the user can do from Normal mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Action {
+    Edit,
+    Delete,
+    Copy,
+    /// Copy, then close sniprrr once the copy is made
+    CopyAndQuit,
+    /// Copy the title instead of the body
+    CopyTitle,
+    /// Copy the title and the body below it
+    CopyBoth,
+    CopyDecoded,
+    /// Pick the form of this one copy from a menu
+    CopyAs,
+    /// Try chains of copy transformations on the snippet, before and after side by side
+    PreviewTransforms,
+    /// Upload to a paste service and copy the link
+    Share,
+    /// Run the snippet as a shell command and show its output
+    Run,
+    /// Find and replace in the bodies of all snippets, match by match
+    FindReplace,
+    /// Run the snippet through `$SHELL` on the terminal, with the app
+    /// suspended until it ends
+    Execute,
+    /// Draft a body with the language model of `[generate]`, into the form
+    Generate,
+    ToggleBase64,
+    Schedule,
+    EditTags,
+    /// Edit the working directory and variables a run of the snippet needs
+    EditRunEnvironment,
+    Label,
+    NextScheduled,
+    Search,
+    /// Move the marked snippets, or the selected one, to a collection picked
+    /// from `[collections]`
+    MoveToCollection,
+    /// Copy them there instead, keeping them here too
+    CopyToCollection,
+    /// List only the snippets with a tag picked from a menu
+    FilterTag,
+    /// Move to the sidebar to list only the snippets of a namespace
+    FocusSidebar,
+    ClearFilter,
+    CycleTarget,
+    Mark,
+    Diff,
+    Dedupe,
+    /// Browse earlier states of the store to restore snippets from
+    History,
+    /// Import snippets from a file, reviewing them first
+    Import,
+    /// Write the snippets to a file in one of the export formats
+    Export,
+    /// Overview of the store on disk and its backups
+    Storage,
+    /// Show the config directory in the file manager
+    OpenConfigDir,
+    /// Edit the store file in `$EDITOR`, with the app suspended, and reload it
+    OpenStore,
+    /// Switch to the next order of the table, see `sort` in the config
+    CycleSort,
+    /// List the most copied snippets first with their counts, or go back to the order before
+    ToggleMostUsed,
+    /// Go through the imported snippets waiting to be accepted
+    Review,
+    Down,
+    Up,
+    /// Move the selected snippet down the store order
+    MoveDown,
+    /// Move the selected snippet up the store order
+    MoveUp,
+    ScrollLeft,
+    ScrollRight,
+    /// Scrolls the description back to its first column
+    ScrollHome,
+    /// Move the split between the title and description columns, kept per collection
+    NarrowTitle,
+    WidenTitle,
+    ToggleSingleLine,
+    /// Lay the rows out from `row_template` in one column, or in the table's columns again
+    ToggleList,
+    /// Show the selected snippet in full next to the table, or hide it again
+    TogglePreview,
+    /// Collapse the namespace of the selected snippet into one row, or expand it again
+    ToggleNamespace,
+    /// Keep the snippet for this session only, or in the library again
+    ToggleTemporary,
+    Top,
+    Bottom,
+    /// Take back the last change to the snippets
+    Undo,
+    /// Make the last undone change again
+    Redo,
+    /// List all the keys and what they do
+    Help,
+    Quit,
+}
+
+impl Action {
+    /// Whether the action changes the selected snippet or the marked ones,
+    /// which the read-only snippets of other sources refuse
+    pub fn changes_snippets(self) -> bool {
+        matches!(
+            self,
+            Action::Delete
+                | Action::ToggleBase64
+                | Action::Schedule
+                | Action::EditTags
+                | Action::EditRunEnvironment
+                | Action::Label
+                | Action::MoveToCollection
+                | Action::Review
+                | Action::MoveDown
+                | Action::MoveUp
+                | Action::ToggleTemporary
+                | Action::Dedupe
+        )
+    }
+
+    /// What the action does, for the help screen
+    pub fn describe(self) -> &'static str {
+        match self {
+            Action::Edit => "Add a snippet in the form",
+            Action::Delete => "Delete the selected snippet",
+            Action::Copy => "Copy the snippet",
+            Action::CopyAndQuit => "Copy the snippet and close sniprrr",
+            Action::CopyTitle => "Copy the title",
+            Action::CopyBoth => "Copy the title and the body",
+            Action::CopyDecoded => "Copy base64 data decoded",
+            Action::CopyAs => "Copy in a form picked from a menu",
+            Action::PreviewTransforms => "Preview copy transformations before and after",
+            Action::Share => "Upload to the paste service and copy the link",
+            Action::Run => "Run the snippet and show its output",
+            Action::Execute => "Run the snippet in the terminal, filling in placeholders",
+            Action::FindReplace => "Find and replace in every snippet, like :%s/old/new/",
+            Action::Generate => "Draft a body with the language model",
+            Action::ToggleBase64 => "Encode the body as base64, or decode it",
+            Action::Schedule => "Set when the snippet is suggested",
+            Action::EditTags => "Edit the tags",
+            Action::EditRunEnvironment => "Edit the directory and variables of a run",
+            Action::Label => "Set the color or icon",
+            Action::NextScheduled => "Select the next snippet scheduled now",
+            Action::Search => "Search titles and bodies",
+            Action::MoveToCollection => "Move to another collection",
+            Action::CopyToCollection => "Copy to another collection",
+            Action::FilterTag => "List only the snippets with a tag",
+            Action::FocusSidebar => "Pick a namespace in the sidebar",
+            Action::ClearFilter => "Clear the search, tag and namespace",
+            Action::CycleTarget => "Switch where copies go",
+            Action::Mark => "Mark the row for multi-row actions",
+            Action::Diff => "Compare the two marked snippets",
+            Action::Dedupe => "Find near duplicates",
+            Action::History => "Browse earlier states of the store",
+            Action::Import => "Import snippets from a file",
+            Action::Export => "Export the snippets to a file",
+            Action::Storage => "Show the store and its backups",
+            Action::OpenConfigDir => "Open the config directory in the file manager",
+            Action::OpenStore => "Edit the store file in $EDITOR",
+            Action::CycleSort => "Switch the order of the table",
+            Action::ToggleMostUsed => "List the most copied snippets first",
+            Action::Review => "Review imported snippets",
+            Action::Down => "Select the next row",
+            Action::Up => "Select the previous row",
+            Action::MoveDown => "Move the snippet down",
+            Action::MoveUp => "Move the snippet up",
+            Action::ScrollLeft => "Scroll the descriptions left",
+            Action::ScrollRight => "Scroll the descriptions right",
+            Action::ScrollHome => "Scroll the descriptions back",
+            Action::NarrowTitle => "Narrow the title column",
+            Action::WidenTitle => "Widen the title column",
+            Action::ToggleSingleLine => "Show descriptions on one line, or in full",
+            Action::ToggleList => "Switch between the list and the table",
+            Action::TogglePreview => "Show or hide the preview pane",
+            Action::ToggleNamespace => "Collapse or expand the namespace",
+            Action::ToggleTemporary => "Make the snippet temporary, or keep it",
+            Action::Top => "Select the first row",
+            Action::Bottom => "Select the last row, or row N",
+            Action::Undo => "Undo the last change",
+            Action::Redo => "Redo the last undone change",
+            Action::Help => "Show this help",
+            Action::Quit => "Close sniprrr",
+        }
+    }
+}
+
+/// A key and the Ctrl, Alt and Shift held with it. Shift is part of the
+/// character for character keys, so `J` is a `J` however it was typed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Key {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl Key {
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Key {
+        let mut modifiers =
+            modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT);
+        if let KeyCode::Char(_) = code {
+            modifiers.remove(KeyModifiers::SHIFT);
+        }
+        Key { code, modifiers }
+    }
+
+    fn plain(code: KeyCode) -> Key {
+        Key::new(code, KeyModifiers::NONE)
+    }
+}
+
+impl From<KeyEvent> for Key {
+    fn from(event: KeyEvent) -> Key {
+        Key::new(event.code, event.modifiers)
+    }
+}
+
+/// Key sequences and the action each one runs
+pub struct Keymap {
+    bindings: Vec<(Vec<Key>, Action)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Keymap {
+        let defaults = [
+            ("e", Action::Edit),
+            ("dd", Action::Delete),
+            ("<Del>", Action::Delete),
+            ("<BS>", Action::Delete),
+            ("c", Action::Copy),
+            ("Q", Action::CopyAndQuit),
+            ("C", Action::CopyTitle),
+            ("y", Action::CopyBoth),
+            ("B", Action::CopyDecoded),
+            ("F", Action::CopyAs),
+            ("P", Action::PreviewTransforms),
+            ("S", Action::Share),
+            ("x", Action::Execute),
+            ("r", Action::Run),
+            ("%", Action::FindReplace),
+            ("A", Action::Generate),
+            ("b", Action::ToggleBase64),
+            ("w", Action::Schedule),
+            ("#", Action::EditTags),
+            ("X", Action::EditRunEnvironment),
+            ("L", Action::Label),
+            ("n", Action::NextScheduled),
+            ("/", Action::Search),
+            ("T", Action::FilterTag),
+            ("<Tab>", Action::FocusSidebar),
+            ("m", Action::MoveToCollection),
+            ("Y", Action::CopyToCollection),
+            ("<Esc>", Action::ClearFilter),
+            ("t", Action::CycleTarget),
+            ("<Space>", Action::Mark),
+            ("=", Action::Diff),
+            ("D", Action::Dedupe),
+            ("H", Action::History),
+            ("I", Action::Import),
+            ("E", Action::Export),
+            ("s", Action::CycleSort),
+            ("M", Action::ToggleMostUsed),
+            ("O", Action::Storage),
+            ("gc", Action::OpenConfigDir),
+            ("gs", Action::OpenStore),
+            ("R", Action::Review),
+            ("j", Action::Down),
+            ("<Down>", Action::Down),
+            ("k", Action::Up),
+            ("<Up>", Action::Up),
+            ("J", Action::MoveDown),
+            ("<C-Down>", Action::MoveDown),
+            ("K", Action::MoveUp),
+            ("<C-Up>", Action::MoveUp),
+            ("h", Action::ScrollLeft),
+            ("<Left>", Action::ScrollLeft),
+            ("l", Action::ScrollRight),
+            ("<Right>", Action::ScrollRight),
+            ("0", Action::ScrollHome),
+            ("<lt>", Action::NarrowTitle),
+            (">", Action::WidenTitle),
+            ("z", Action::ToggleSingleLine),
+            ("V", Action::ToggleList),
+            ("p", Action::TogglePreview),
+            ("-", Action::ToggleNamespace),
+            ("~", Action::ToggleTemporary),
+            ("gg", Action::Top),
+            ("G", Action::Bottom),
+            ("u", Action::Undo),
+            ("<C-r>", Action::Redo),
+            ("?", Action::Help),
+            ("q", Action::Quit),
+            ("<C-c>", Action::Quit),
+        ];
+        let bindings = defaults
+            .iter()
+            .map(|(keys, action)| {
+                (
+                    parse_sequence(keys).expect("valid default binding"),
+                    *action,
+                )
+            })
+            .collect();
+        Keymap { bindings }
+    }
+}
+
+impl Keymap {
+    /// The default bindings with those of `[keys]` in the config file on top,
+    /// e.g. `"y" = "copy"` or `"<C-d>" = "delete"`. A sequence bound there
+    /// loses its default action.
+    pub fn with_bindings(bindings: &BTreeMap<String, Action>) -> Result<Keymap, String> {
+        let mut keymap = Keymap::default();
+        for (sequence, action) in bindings {
+            let keys = parse_sequence(sequence)
+                .map_err(|error| format!("'{}' in [keys]: {}", sequence, error))?;
+            keymap.bindings.retain(|(bound, _)| *bound != keys);
+            keymap.bindings.push((keys, *action));
+        }
+        Ok(keymap)
+    }
+}
+
+/// Keys typed so far towards a binding, with the count typed before them
+#[derive(Default)]
+pub struct Chord {
+    count: Option<usize>,
+    keys: Vec<Key>,
+}
+
+impl Chord {
+    pub fn is_empty(&self) -> bool {
+        self.count.is_none() && self.keys.is_empty()
+    }
+
+    /// What has been typed, e.g. `5d`, for showing while a sequence is pending
+    pub fn describe(&self) -> String {
+        let count = self
+            .count
+            .map(|count| count.to_string())
+            .unwrap_or_default();
+        let keys: String = self.keys.iter().map(|key| describe_key(*key)).collect();
+        format!("{}{}", count, keys)
+    }
+
+    fn clear(&mut self) {
+        self.count = None;
+        self.keys.clear();
+    }
+}
+
+/// Result of feeding a key to the keymap
+pub enum Dispatch {
+    /// The key started or continued a sequence
+    Pending,
+    /// A binding was completed. The count is 1 when none was typed.
+    Run(Action, usize),
+    /// The keys typed don't lead to any binding and were dropped
+    Unbound,
+}
+
+impl Keymap {
+    /// Each bound action with the sequences running it, e.g. `dd, <Del>`, in
+    /// the order they were bound
+    pub fn describe(&self) -> Vec<(String, Action)> {
+        let mut described: Vec<(String, Action)> = vec![];
+        for (keys, action) in &self.bindings {
+            let sequence: String = keys.iter().map(|key| describe_key(*key)).collect();
+            match described.iter_mut().find(|(_, other)| other == action) {
+                Some((sequences, _)) => {
+                    sequences.push_str(", ");
+                    sequences.push_str(&sequence);
+                }
+                None => described.push((sequence, *action)),
+            }
+        }
+        described
+    }
+
+    pub fn feed(&self, chord: &mut Chord, key: Key) -> Dispatch {
+        if let (KeyCode::Char(c @ '0'..='9'), KeyModifiers::NONE) = (key.code, key.modifiers) {
+            // A leading 0 is not a count, like in vim
+            if chord.keys.is_empty() && (c != '0' || chord.count.is_some()) {
+                let digit = c.to_digit(10).unwrap_or_default() as usize;
+                chord.count = Some(
+                    chord
+                        .count
+                        .unwrap_or_default()
+                        .saturating_mul(10)
+                        .saturating_add(digit),
+                );
+                return Dispatch::Pending;
+            }
+        }
+
+        chord.keys.push(key);
+        if let Some((_, action)) = self.bindings.iter().find(|(keys, _)| *keys == chord.keys) {
+            let count = chord.count.unwrap_or(1);
+            chord.clear();
+            return Dispatch::Run(*action, count);
+        }
+        if self
+            .bindings
+            .iter()
+            .any(|(keys, _)| keys.starts_with(&chord.keys))
+        {
+            return Dispatch::Pending;
+        }
+        chord.clear();
+        Dispatch::Unbound
+    }
+}
+
+/// Parses a key sequence such as `dd` or `gg`. Special keys are written in
+/// angle brackets like `<Down>`, `<Esc>`, `<Space>`, `<Del>` and `<BS>`, and
+/// so are keys held with Ctrl (`C-`), Alt (`A-`) or Shift (`S-`), like
+/// `<C-r>` or `<S-Down>`.
+pub fn parse_sequence(sequence: &str) -> Result<Vec<Key>, String> {
+    let mut keys = vec![];
+    let mut rest = sequence;
+    while let Some(c) = rest.chars().next() {
+        if c == '<' {
+            if let Some(end) = rest.find('>') {
+                let name = &rest[1..end];
+                if let Some(key) = bracketed_key(name) {
+                    keys.push(key);
+                    rest = &rest[end + 1..];
+                    continue;
+                }
+                if !name.is_empty() {
+                    return Err(format!("Unknown key <{}>", name));
+                }
+            }
+        }
+        keys.push(Key::plain(KeyCode::Char(c)));
+        rest = &rest[c.len_utf8()..];
+    }
+    if keys.is_empty() {
+        return Err("Empty key sequence".to_string());
+    }
+    Ok(keys)
+}
+
+const NAMED_KEYS: [(&str, KeyCode); 13] = [
+    ("Esc", KeyCode::Esc),
+    ("Space", KeyCode::Char(' ')),
+    ("Del", KeyCode::Delete),
+    ("BS", KeyCode::Backspace),
+    ("Enter", KeyCode::Enter),
+    ("Tab", KeyCode::Tab),
+    ("Up", KeyCode::Up),
+    ("Down", KeyCode::Down),
+    ("Left", KeyCode::Left),
+    ("Right", KeyCode::Right),
+    ("Home", KeyCode::Home),
+    ("End", KeyCode::End),
+    ("lt", KeyCode::Char('<')),
+];
+
+const MODIFIERS: [(&str, KeyModifiers); 3] = [
+    ("C-", KeyModifiers::CONTROL),
+    ("A-", KeyModifiers::ALT),
+    ("S-", KeyModifiers::SHIFT),
+];
+
+fn named_key(name: &str) -> Option<KeyCode> {
+    NAMED_KEYS
+        .iter()
+        .find(|(key_name, _)| key_name.eq_ignore_ascii_case(name))
+        .map(|(_, key)| *key)
+}
+
+/// The key written between angle brackets, such as `Down` or `C-r`
+fn bracketed_key(name: &str) -> Option<Key> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut rest = name;
+    while let Some((prefix, modifier)) = MODIFIERS.iter().find(|(prefix, _)| {
+        rest.len() > prefix.len()
+            && rest
+                .get(..prefix.len())
+                .is_some_and(|start| start.eq_ignore_ascii_case(prefix))
+    }) {
+        modifiers |= *modifier;
+        rest = &rest[prefix.len()..];
+    }
+    let mut chars = rest.chars();
+    let code = match (chars.next(), chars.next()) {
+        // A single character is taken as it is, <C-r> and <C-R> differ
+        (Some(c), None) if modifiers != KeyModifiers::NONE => KeyCode::Char(c),
+        _ => named_key(rest)?,
+    };
+    Some(Key::new(code, modifiers))
+}
+
+fn describe_key(key: Key) -> String {
+    let name = match key.code {
+        KeyCode::Char(' ') => "Space".to_string(),
+        KeyCode::Char('<') => "lt".to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        code => NAMED_KEYS
+            .iter()
+            .find(|(_, named)| *named == code)
+            .map_or_else(|| format!("{:?}", code), |(name, _)| name.to_string()),
+    };
+    let prefixes: String = MODIFIERS
+        .iter()
+        .filter(|(_, modifier)| key.modifiers.contains(*modifier))
+        .map(|(prefix, _)| *prefix)
+        .collect();
+    match (key.code, prefixes.is_empty()) {
+        (KeyCode::Char(c), true) if c != ' ' && c != '<' => name,
+        _ => format!("<{}{}>", prefixes, name),
+    }
+}
@@ -0,0 +1,68 @@
+//! The modules behind the `sniprrr` binary, also used by the benchmarks. The
+//! store and snippet model live in `sniprrr-core` and are re-exported here.
+
+pub use sniprrr_core::{
+    compression, encryption, file_utils, history, models, schedule, time_utils,
+};
+
+pub mod aliases;
+pub mod base64;
+pub mod batch;
+pub mod bench;
+pub mod binary;
+pub mod bulk;
+pub mod cli;
+pub mod collections;
+pub mod config;
+pub mod copy_target;
+pub mod csv_io;
+pub mod daemon;
+pub mod debug_bundle;
+pub mod dedupe;
+pub mod diff;
+pub mod digest;
+pub mod doctor;
+pub mod editor;
+pub mod events;
+pub mod formats;
+pub mod frecency;
+pub mod generate;
+pub mod http;
+pub mod import;
+pub mod integrity;
+pub mod ipc;
+pub mod keymap;
+pub mod listing;
+pub mod lookup;
+pub mod metrics;
+pub mod namespace;
+pub mod notify;
+pub mod open;
+pub mod paste;
+pub mod popups;
+pub mod replace;
+pub mod row_template;
+pub mod run;
+pub mod search;
+#[cfg(feature = "semantic")]
+pub mod semantic;
+pub mod server;
+pub mod share;
+pub mod shell_history;
+pub mod sort;
+pub mod sources;
+pub mod status;
+pub mod storage;
+pub mod sync;
+pub mod syntax;
+pub mod table;
+pub mod tag_suggest;
+pub mod template;
+pub mod terminal;
+pub mod theme;
+pub mod title;
+pub mod transform;
+pub mod tutorial;
+pub mod variants;
+pub mod view;
+pub mod workspace;
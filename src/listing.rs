@@ -0,0 +1,140 @@
+//! `GET /snippets` on `[server] listen` of the daemon, for overlays and chat
+//! bots that poll the library. `q` filters like `/` in the app and `tag`
+//! keeps the snippets with that tag, given more than once to require several.
+//! `limit` and `offset` page through what is left, in store order unless `q`
+//! ranks it, so polling clients get the same page for the same store.
+
+use std::sync::{RwLock, RwLockReadGuard};
+
+use serde_json::json;
+
+use crate::models::Snippet;
+use crate::search;
+
+/// Snippets sent when the request has no `limit`
+const DEFAULT_LIMIT: usize = 50;
+/// A larger `limit` is lowered to this rather than refused
+const MAX_LIMIT: usize = 500;
+
+/// What `GET /snippets` was asked for
+#[derive(Debug, Default)]
+pub struct Query {
+    q: Option<String>,
+    tags: Vec<String>,
+    limit: Option<usize>,
+    offset: usize,
+}
+
+impl Query {
+    /// Parses the part of the request target after `?`
+    pub fn parse(query: &str) -> Result<Query, String> {
+        let mut parsed = Query::default();
+        for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            // `+` is a space in query strings, a `+` itself comes as %2B
+            let value = decode(&value.replace('+', " "))?;
+            let number = || {
+                value
+                    .parse()
+                    .map_err(|_| format!("{} must be a whole number, not '{}'", name, value))
+            };
+            match name {
+                "q" => parsed.q = Some(value.clone()).filter(|q| !q.trim().is_empty()),
+                "tag" => parsed.tags.push(value.clone()),
+                "limit" => parsed.limit = Some(number()?),
+                "offset" => parsed.offset = number()?,
+                _ => return Err(format!("Unknown parameter '{}'", name)),
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+/// `value` with its `%XX` escapes decoded
+pub fn decode(value: &str) -> Result<String, String> {
+    let mut bytes = vec![];
+    let mut rest = value.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        rest = tail;
+        match byte {
+            b'%' => {
+                let escaped = rest
+                    .get(..2)
+                    .and_then(|hex| std::str::from_utf8(hex).ok())
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                    .ok_or_else(|| format!("'{}' has a broken % escape", value))?;
+                bytes.push(escaped);
+                rest = &rest[2..];
+            }
+            byte => bytes.push(byte),
+        }
+    }
+    String::from_utf8(bytes).map_err(|_| format!("'{}' is not UTF-8", value))
+}
+
+/// The snippets as the daemon last saved them, shared with the listener thread
+pub struct Listing {
+    snippets: RwLock<Vec<Snippet>>,
+}
+
+impl Listing {
+    pub fn new(snippets: &[Snippet]) -> Listing {
+        Listing {
+            snippets: RwLock::new(snippets.to_vec()),
+        }
+    }
+
+    fn snippets(&self) -> RwLockReadGuard<'_, Vec<Snippet>> {
+        match self.snippets.read() {
+            Ok(snippets) => snippets,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    pub fn update(&self, snippets: &[Snippet]) {
+        if let Ok(mut listed) = self.snippets.write() {
+            *listed = snippets.to_vec();
+        }
+    }
+
+    /// The page of snippets for `query` as JSON, with the number of matches
+    /// and the limit applied so clients know where to go on
+    pub fn page(&self, query: &Query) -> String {
+        let snippets = self.snippets();
+        let tagged = snippets
+            .iter()
+            .filter(|s| query.tags.iter().all(|tag| s.tags.contains(tag)));
+        let matches: Vec<&Snippet> = match &query.q {
+            Some(q) => {
+                let mut scored: Vec<(i64, &Snippet)> = tagged
+                    .filter_map(|s| Some((search::score(s, q)?, s)))
+                    .collect();
+                // Stable, so equal matches keep the store order
+                scored.sort_by_key(|&(score, _)| std::cmp::Reverse(score));
+                scored.into_iter().map(|(_, s)| s).collect()
+            }
+            None => tagged.collect(),
+        };
+        let limit = query.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+        let page: Vec<&Snippet> = matches
+            .iter()
+            .skip(query.offset)
+            .take(limit)
+            .copied()
+            .collect();
+        json!({
+            "total": matches.len(),
+            "offset": query.offset,
+            "limit": limit,
+            "snippets": page,
+        })
+        .to_string()
+    }
+
+    /// The snippet titled `title` as JSON, for `GET /snippets/<TITLE>`
+    pub fn find(&self, title: &str) -> Option<String> {
+        let snippets = self.snippets();
+        let snippet = snippets.iter().find(|s| s.title == title)?;
+        Some(json!(snippet).to_string())
+    }
+}
@@ -0,0 +1,177 @@
+//! Finds the snippet `sniprrr copy` was asked for: by exact title, by its
+//! position in the store, or fuzzily by part of the title, asking which one
+//! is meant when several match
+
+use std::error::Error;
+use std::fmt;
+use std::io::{self, BufRead, IsTerminal, Write};
+
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::models::Snippet;
+use crate::search;
+
+/// Matches listed when asking which one is meant
+const LIST_LIMIT: usize = 20;
+/// Columns of a title shown in the list before it is cut off
+const TITLE_WIDTH: usize = 60;
+
+/// How the snippet to copy is picked
+pub enum Selector {
+    /// Part of the title, fuzzily, the whole title winning outright
+    Query(String),
+    /// Only this exact title
+    Exact(String),
+    /// Position in the store counting from 1, as shown in the list of matches
+    Id(usize),
+}
+
+/// Why no snippet was picked
+#[derive(Debug)]
+pub enum LookupError {
+    /// Nothing matches, which `sniprrr` exits with 1 for
+    NotFound(String),
+    /// Several match and none was picked, or reading the pick failed
+    Unpicked(String),
+}
+
+impl fmt::Display for LookupError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LookupError::NotFound(message) | LookupError::Unpicked(message) => f.write_str(message),
+        }
+    }
+}
+
+impl Error for LookupError {}
+
+/// Index into `snippets` of the snippet `selector` picks. When a query
+/// matches several, the user picks one from a list if stdin is a terminal.
+pub fn find(snippets: &[Snippet], selector: &Selector) -> Result<usize, LookupError> {
+    let query = match selector {
+        Selector::Id(id) => {
+            return id
+                .checked_sub(1)
+                .filter(|&index| index < snippets.len())
+                .ok_or_else(|| {
+                    LookupError::NotFound(format!(
+                        "No snippet with id {}, there are {}",
+                        id,
+                        snippets.len()
+                    ))
+                })
+        }
+        Selector::Exact(title) => {
+            return snippets
+                .iter()
+                .position(|snippet| &snippet.title == title)
+                .ok_or_else(|| LookupError::NotFound(format!("No snippet titled '{}'", title)))
+        }
+        Selector::Query(query) => query,
+    };
+    if let Some(index) = snippets.iter().position(|snippet| &snippet.title == query) {
+        return Ok(index);
+    }
+
+    let mut matches: Vec<(i64, usize)> = snippets
+        .iter()
+        .enumerate()
+        .filter_map(|(index, snippet)| Some((title_score(&snippet.title, query)?, index)))
+        .collect();
+    matches.sort_by_key(|&(score, _)| std::cmp::Reverse(score));
+    let matches: Vec<usize> = matches.into_iter().map(|(_, index)| index).collect();
+
+    match matches.as_slice() {
+        [] => Err(LookupError::NotFound(format!(
+            "No snippet title matches '{}'",
+            query
+        ))),
+        [index] => Ok(*index),
+        _ if io::stdin().is_terminal() => ask(snippets, &matches).map_err(LookupError::Unpicked),
+        _ => Err(LookupError::Unpicked(format!(
+            "{} snippets match '{}', pick one with --exact or --id:\n{}",
+            matches.len(),
+            query,
+            list(snippets, &matches).trim_end()
+        ))),
+    }
+}
+
+/// Every word of `query` has to match the title fuzzily
+fn title_score(title: &str, query: &str) -> Option<i64> {
+    query.split_whitespace().try_fold(0, |total, term| {
+        Some(total + search::fuzzy_match(title, term)?.0)
+    })
+}
+
+/// Lists the best `matches` numbered from 1 and reads the number of the one to copy
+fn ask(snippets: &[Snippet], matches: &[usize]) -> Result<usize, String> {
+    let shown = matches.len().min(LIST_LIMIT);
+    let mut stderr = io::stderr();
+    let _ = write!(
+        stderr,
+        "{} snippets match:\n{}Copy which one? [1-{}, Enter to cancel] ",
+        matches.len(),
+        list(snippets, matches),
+        shown
+    );
+    let _ = stderr.flush();
+
+    let mut answer = String::new();
+    io::stdin()
+        .lock()
+        .read_line(&mut answer)
+        .map_err(|error| error.to_string())?;
+    let answer = answer.trim();
+    if answer.is_empty() {
+        return Err("Nothing copied".to_string());
+    }
+    answer
+        .parse::<usize>()
+        .ok()
+        .filter(|&number| (1..=shown).contains(&number))
+        .map(|number| matches[number - 1])
+        .ok_or_else(|| format!("'{}' isn't one of the listed numbers", answer))
+}
+
+/// One line per match with its number, title and id, the best `LIST_LIMIT` of them
+fn list(snippets: &[Snippet], matches: &[usize]) -> String {
+    let mut out = String::new();
+    for (number, &index) in matches.iter().take(LIST_LIMIT).enumerate() {
+        out.push_str(&format!(
+            "{:>3}) {}  (--id {})\n",
+            number + 1,
+            truncate(&snippets[index].title, TITLE_WIDTH),
+            index + 1
+        ));
+    }
+    if matches.len() > LIST_LIMIT {
+        out.push_str(&format!(
+            "     ... and {} more, narrow down the title\n",
+            matches.len() - LIST_LIMIT
+        ));
+    }
+    out
+}
+
+/// `text` on one line, cut to `width` columns with an ellipsis when longer
+fn truncate(text: &str, width: usize) -> String {
+    let text: String = text
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect();
+    if text.width() <= width {
+        return text;
+    }
+    let mut out = String::new();
+    let mut used = 0;
+    for c in text.chars() {
+        used += c.width().unwrap_or(0);
+        if used >= width {
+            break;
+        }
+        out.push(c);
+    }
+    out.push('…');
+    out
+}
@@ -1,52 +1,1020 @@
-use std::{error::Error, io};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::{IsTerminal, Write};
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::mpsc::Receiver;
+use std::time::{Duration, Instant};
+use std::{error::Error, fs, io};
 
-use crate::file_utils::{load_messages_from_file, write_messages_to_file};
-use arboard::Clipboard;
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind},
+    event::{
+        self, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+        Event, KeyCode, KeyEventKind, KeyModifiers,
+    },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use ratatui::widgets::{Cell, Row, Table, TableState};
+use ratatui::widgets::{Row, Table, TableState};
 use ratatui::{
     backend::{Backend, CrosstermBackend},
-    layout::{Constraint, Direction, Layout},
-    style::{Color, Modifier, Style},
+    layout::{Constraint, Direction, Layout, Rect},
     text::{Span, Spans, Text},
-    widgets::{Block, Borders, Paragraph},
+    widgets::{Block, Borders, Paragraph, Wrap},
     Frame, Terminal,
 };
+use sniprrr::file_utils::{
+    messages_file_path, rotate_backups, write_messages_to_file, write_store,
+};
 use unicode_width::UnicodeWidthStr;
 
-use crate::models::Snippet;
-
-mod file_utils;
-mod models;
+use sniprrr::cli::{BodySource, Command, ImportFormat, ImportOptions, OpenTarget};
+use sniprrr::collections::Existing;
+use sniprrr::config::{load_config, Config, ConfigWatcher};
+use sniprrr::copy_target::{CopyField, CopyRouter, CopyTarget, Flavor};
+use sniprrr::editor::TextArea;
+use sniprrr::formats::Format;
+use sniprrr::generate::{self, GenerateConfig};
+use sniprrr::ipc::{Request, Response};
+use sniprrr::keymap::{Action, Chord, Dispatch, Key, Keymap};
+use sniprrr::lookup::LookupError;
+use sniprrr::models::{parse_tags, Snippet};
+use sniprrr::paste::{PasteConfig, PasteMode};
+use sniprrr::popups::{
+    preview_popup, CollectionPopup, ColumnsPopup, DedupePopup, DeletePopup, DiffPopup,
+    DuplicatePopup, FillPopup, FlavorPopup, HelpPopup, HistoryPopup, ImportPopup, InputPopup,
+    InputPurpose, LabelPopup, MessagePopup, OutputPopup, Popup, PopupOutcome, ReplacePopup,
+    ReviewPopup, RunPopup, SplitPastePopup, StorageAction, StoragePopup, TagFilterPopup,
+    TagSuggestPopup, TransformPopup, VariantPopup,
+};
+use sniprrr::row_template::RowTemplate;
+#[cfg(feature = "semantic")]
+use sniprrr::semantic::{self, SemanticConfig};
+use sniprrr::share::ShareConfig;
+use sniprrr::sort::SortOrder;
+use sniprrr::status::{Notifications, Severity};
+use sniprrr::table::{description_view, preview_text, TableView};
+use sniprrr::theme::Theme;
+use sniprrr::transform::Transform;
+use sniprrr::tutorial::{self, Tutorial};
+use sniprrr::view::View;
+use sniprrr::{
+    aliases, base64, batch, bench, binary, bulk, cli, collections, compression, csv_io, daemon,
+    debug_bundle, digest, doctor, encryption, file_utils, formats, frecency, history, import,
+    integrity, ipc, lookup, namespace, open, paste, replace, run, schedule, search, share,
+    shell_history, sources, storage, sync, syntax, tag_suggest, template, terminal, time_utils,
+    title, variants, view, workspace,
+};
 
 enum InputMode {
     Normal,
     Editing,
+    /// Typing the filter query after `/`
+    Search,
 }
 
-const MAX_INPUT_COUNT: i8 = 2;
+const MAX_INPUT_COUNT: i8 = 4;
 const INPUT_TITLE_INDEX: i8 = 0;
-const INPUT_DESCRIPTION_INDEX: i8 = 1;
+const INPUT_TAGS_INDEX: i8 = 1;
+const INPUT_VARIANT_INDEX: i8 = 2;
+const INPUT_DESCRIPTION_INDEX: i8 = 3;
 
 /// App holds the state of the application
 struct AppState {
-    title_input: String,
-    description_input: String,
+    title_input: TextArea,
+    /// Tags of the new snippet, separated by commas
+    tags_input: TextArea,
+    /// Name of the variant to edit the description of, empty for the main body
+    variant_input: TextArea,
+    description_input: TextArea,
+    /// The variant whose body is in `description_input`
+    form_variant: String,
+    /// Bodies typed for the other variants of the form, the main one under ""
+    form_bodies: BTreeMap<String, String>,
+    /// The form adds a temporary snippet, toggled with Ctrl+T
+    form_temporary: bool,
     focused_input_index: i8,
     input_mode: InputMode,
+    paste_mode: PasteMode,
+    /// Clean-up of text pasted into the form
+    paste: PasteConfig,
     messages: Vec<Snippet>,
+    /// Selection within the rows that match `filter`, see `selected_index`
     table_state: TableState,
+    /// Rows show only the first line of the description, with the rest joined onto it
+    single_line: bool,
+    /// The selected snippet is shown in full in a pane next to the table
+    preview: bool,
+    /// Columns of the description scrolled out of view on the left
+    description_offset: usize,
+    /// Widths of the title and description columns for this collection
+    view: View,
+    /// Rows are laid out from `row_template` in one column, toggled with `V`
+    list: bool,
+    /// See `row_template` in the config, a default one when not set
+    row_template: RowTemplate,
+    /// Only snippets whose title or description fuzzily match this are listed
+    filter: String,
+    /// Indices into `messages` of the snippets matching `filter`, best match
+    /// first, see `refilter`
+    filtered: Vec<usize>,
+    #[cfg(feature = "semantic")]
+    semantic: SemanticConfig,
+    /// The filter of the last semantic search and the snippets it found, most
+    /// similar first. Dropped when the snippets change.
+    #[cfg(feature = "semantic")]
+    semantic_results: Option<(String, Vec<usize>)>,
+    /// Order of the snippets while nothing is searched for
+    sort: SortOrder,
+    /// The order `ToggleMostUsed` goes back to
+    sort_before_most_used: SortOrder,
+    /// Other stores snippets can be moved or copied to, see `collections` in the config
+    collections: BTreeMap<String, String>,
+    /// Only snippets with this tag are listed, picked with `FilterTag`
+    tag_filter: Option<String>,
+    /// Only snippets in this namespace are listed, picked in the sidebar
+    folder: Option<String>,
+    /// Keys move through the sidebar instead of the table
+    sidebar_focus: bool,
+    /// See `hide_sidebar` in the config
+    hide_sidebar: bool,
+    /// Rows marked with Space for multi-row actions
+    marked: BTreeSet<usize>,
+    /// Namespaces listed as a single row while no filter is typed
+    collapsed: BTreeSet<String>,
+    /// Personal snippets a source of higher precedence hides, with their
+    /// position in the store, see `sources::Layers`
+    shadowed: Vec<(usize, Snippet)>,
+    /// The `[[sources]]` of the config, to layer the store with again after
+    /// it was edited by hand
+    sources: Vec<sources::Source>,
+    popup: Option<Popup>,
+    /// Messages shown in place of the help line
+    notifications: Notifications,
+    /// The copy being made was started with `CopyAndQuit`, so sniprrr closes once it's done
+    quit_after_copy: bool,
+    /// Copies are printed to stdout once the UI is closed, for `--print`
+    print: bool,
+    /// What to print then
+    printed: Option<String>,
+    /// Fields taken by `c` and `Q`, see `copy_field` in the config
+    default_copy_field: CopyField,
+    /// Fields taken by the copy being made, picked by the action that started it
+    copy_field: CopyField,
+    /// Quitting with a draft in the form takes a second `q`, see `confirm_quit` in the config
+    confirm_quit: bool,
+    /// Progress through `sniprrr tutorial`, which runs on a sandbox
+    tutorial: Option<Tutorial>,
+    /// Deleting asks first, see `confirm_delete` in the config
+    confirm_delete: bool,
+    /// The last key was a `q` that was held back because of the draft
+    quit_pending: bool,
+    copy_router: CopyRouter,
+    share: ShareConfig,
+    generate: GenerateConfig,
+    keymap: Keymap,
+    /// What each change undone with `u` was, and the snippets from before it, latest last
+    undo: Vec<(String, Vec<Snippet>)>,
+    /// Changes undone, to be made again with `Redo`, latest last
+    redo: Vec<(String, Vec<Snippet>)>,
+    /// Normal mode keys typed towards a sequence such as `dd`
+    chord: Chord,
+    /// Failures interrupt with a popup instead of a status message, see `--strict`
+    strict: bool,
+    /// Snippets are loaded from and saved through `sniprrr daemon`
+    daemon_client: bool,
+    /// Revision of the daemon's store the snippets shown were loaded or last
+    /// saved at
+    daemon_revision: u64,
+    theme: Theme,
 }
 
 impl AppState {
+    /// Indices into `messages` of the rows listed in the table. The first
+    /// snippet of a collapsed namespace stands for all of them.
+    pub fn visible_rows(&self) -> Vec<usize> {
+        let mut listed_namespaces = BTreeSet::new();
+        self.filtered
+            .iter()
+            .copied()
+            .filter(
+                |&i| match self.collapsed_namespace(&self.messages[i].title) {
+                    Some(namespace) => listed_namespaces.insert(namespace),
+                    None => true,
+                },
+            )
+            .collect()
+    }
+
+    /// Matches the snippets against the filter again, after either changed
+    pub fn refilter(&mut self) {
+        // A namespace whose snippets are all gone lists everything again
+        if let Some(folder) = &self.folder {
+            if !self
+                .messages
+                .iter()
+                .any(|s| namespace::contains(folder, &s.title))
+            {
+                self.folder = None;
+            }
+        }
+        let tagged = |snippet: &Snippet| match &self.tag_filter {
+            Some(tag) => snippet.tags.contains(tag),
+            None => true,
+        };
+        let in_folder = |snippet: &Snippet| match &self.folder {
+            Some(folder) => namespace::contains(folder, &snippet.title),
+            None => true,
+        };
+        let mut listed: Vec<usize> = (0..self.messages.len())
+            .filter(|&i| tagged(&self.messages[i]) && in_folder(&self.messages[i]))
+            .collect();
+        self.sort.sort(&self.messages, &mut listed);
+        if self.filter.is_empty() {
+            self.filtered = listed;
+            return;
+        }
+        #[cfg(feature = "semantic")]
+        if semantic::query(&self.filter).is_some() {
+            self.filtered = match &self.semantic_results {
+                Some((filter, found)) if *filter == self.filter => found
+                    .iter()
+                    .copied()
+                    .filter(|i| listed.contains(i))
+                    .collect(),
+                // Everything stays listed until Enter runs the search
+                _ => listed,
+            };
+            return;
+        }
+        let mut scored: Vec<(i64, usize)> = listed
+            .into_iter()
+            .filter_map(|i| Some((search::score(&self.messages[i], &self.filter)?, i)))
+            .collect();
+        // Stable, so equal matches keep the sort order
+        scored.sort_by_key(|&(score, _)| std::cmp::Reverse(score));
+        self.filtered = scored.into_iter().map(|(_, i)| i).collect();
+    }
+
+    /// The outermost collapsed namespace `title` is in. Searching shows everything.
+    fn collapsed_namespace(&self, title: &str) -> Option<&str> {
+        if self.is_filtered() {
+            return None;
+        }
+        self.collapsed
+            .iter()
+            .filter(|namespace| namespace::contains(namespace, title))
+            .min_by_key(|namespace| namespace.len())
+            .map(String::as_str)
+    }
+
+    /// Whether a search, a tag or a namespace narrows down the listed snippets
+    fn is_filtered(&self) -> bool {
+        !self.filter.is_empty() || self.tag_filter.is_some() || self.folder.is_some()
+    }
+
+    /// Lists only the snippets tagged `tag`, or all of them again, staying on
+    /// the selected snippet when it is still listed
+    pub fn set_tag_filter(&mut self, tag: Option<String>) {
+        let selected = self.selected_index();
+        self.tag_filter = tag;
+        self.refilter();
+        match selected {
+            Some(selected) if self.filtered.contains(&selected) => self.select_index(selected),
+            _ => self.table_state.select(None),
+        }
+        self.clamp_selection();
+    }
+
+    /// Title and source of the first layered snippet among the marked ones,
+    /// or the selected one when none are marked
+    fn read_only_target(&self) -> Option<(String, String)> {
+        let targets: Vec<usize> = match self.marked.is_empty() {
+            true => self.selected_index().into_iter().collect(),
+            false => self.marked.iter().copied().collect(),
+        };
+        targets.into_iter().find_map(|index| {
+            let snippet = &self.messages[index];
+            Some((snippet.title.clone(), snippet.source.clone()?))
+        })
+    }
+
+    /// Lists only the snippets in `folder`, or all of them again, the same
+    /// way as `set_tag_filter`
+    pub fn set_folder(&mut self, folder: Option<String>) {
+        let selected = self.selected_index();
+        self.folder = folder;
+        self.refilter();
+        match selected {
+            Some(selected) if self.filtered.contains(&selected) => self.select_index(selected),
+            _ => self.table_state.select(None),
+        }
+        self.clamp_selection();
+    }
+
+    /// The namespaces listed in the sidebar with their counts, see `namespace::counts`
+    fn folders(&self) -> Vec<(String, usize)> {
+        namespace::counts(self.messages.iter().map(|s| s.title.as_str()))
+    }
+
+    /// Whether the sidebar takes up the left of the table
+    fn sidebar_shown(&self) -> bool {
+        self.sidebar_focus
+            || self.folder.is_some()
+            || (!self.hide_sidebar
+                && self
+                    .messages
+                    .iter()
+                    .any(|s| namespace::split(&s.title).0.is_some()))
+    }
+
+    /// Handles a key pressed while the sidebar has focus: moving picks the
+    /// namespace right away, Enter or Tab goes back to the table and Esc
+    /// lists everything again. Other keys are left to the keymap.
+    fn sidebar_key(&mut self, key: Key) -> bool {
+        if !key.modifiers.is_empty() {
+            return false;
+        }
+        let folders = self.folders();
+        // 0 is "All"
+        let position = self
+            .folder
+            .as_ref()
+            .and_then(|folder| folders.iter().position(|(name, _)| name == folder))
+            .map_or(0, |position| position + 1);
+        let position = match key.code {
+            KeyCode::Down | KeyCode::Char('j') => (position + 1).min(folders.len()),
+            KeyCode::Up | KeyCode::Char('k') => position.saturating_sub(1),
+            KeyCode::Home | KeyCode::Char('g') => 0,
+            KeyCode::End | KeyCode::Char('G') => folders.len(),
+            KeyCode::Enter | KeyCode::Tab | KeyCode::Right | KeyCode::Char('l') => {
+                self.sidebar_focus = false;
+                return true;
+            }
+            KeyCode::Esc => {
+                self.sidebar_focus = false;
+                0
+            }
+            _ => return false,
+        };
+        let folder = position
+            .checked_sub(1)
+            .map(|position| folders[position].0.clone());
+        if folder != self.folder {
+            self.set_folder(folder);
+        }
+        true
+    }
+
+    /// Collapses the namespace of the selected snippet, or expands the
+    /// collapsed namespace the selected row stands for
+    pub fn toggle_namespace(&mut self) {
+        let selected = match self.selected_index() {
+            Some(selected) => selected,
+            None => return,
+        };
+        let title = &self.messages[selected].title;
+        if let Some(namespace) = self.collapsed_namespace(title).map(str::to_string) {
+            self.collapsed.remove(&namespace);
+        } else if let (Some(namespace), _) = namespace::split(title) {
+            self.collapsed.insert(namespace.to_string());
+        } else {
+            self.notifications.info(format!(
+                "'{}' isn't in a namespace, titles like docker/prune put it in one",
+                title
+            ));
+            return;
+        }
+        // Stay on the snippet, or on the row that stands for its namespace now
+        let collapsed = self
+            .collapsed_namespace(&self.messages[selected].title)
+            .map(str::to_string);
+        let row = self.visible_rows().iter().position(|&i| {
+            i == selected
+                || collapsed.as_deref().is_some_and(|namespace| {
+                    namespace::contains(namespace, &self.messages[i].title)
+                })
+        });
+        self.table_state.select(row);
+        self.clamp_selection();
+    }
+
+    /// Index into `messages` of the selected row
+    pub fn selected_index(&self) -> Option<usize> {
+        let selected = self.table_state.selected()?;
+        self.visible_rows().get(selected).copied()
+    }
+
+    /// Selects the listed snippet with the highest frecency, leaving the
+    /// selection alone when none of them was copied yet
+    pub fn select_frecent(&mut self) {
+        let rows = self.visible_rows();
+        if let Some(index) = frecency::best(&self.messages, &rows, time_utils::now()) {
+            self.select_index(index);
+        }
+    }
+
+    /// Selects the row of `messages[index]` if it is listed
+    pub fn select_index(&mut self, index: usize) {
+        let row = self.visible_rows().iter().position(|&i| i == index);
+        self.table_state.select(row);
+    }
+
+    /// Lists the snippets in `sort` order, keeping the selection
+    pub fn set_sort(&mut self, sort: SortOrder) {
+        let selected = self.selected_index();
+        self.sort = sort;
+        self.refilter();
+        if let Some(selected) = selected {
+            self.select_index(selected);
+        }
+        self.notifications.info(format!("Listing {}", sort.label()));
+    }
+
+    /// Moves the selected snippet `rows` rows down the table, up for negative
+    /// `rows`, and saves the new order. Only the store order can be changed.
+    pub fn move_selected(&mut self, rows: isize) {
+        let Some(row) = self.table_state.selected() else {
+            return;
+        };
+        if !self.filter.is_empty() || self.sort != SortOrder::Store {
+            self.notifications
+                .info("Snippets can be moved in store order without a search, see s");
+            return;
+        }
+        let visible = self.visible_rows();
+        let target = row
+            .saturating_add_signed(rows)
+            .min(visible.len().saturating_sub(1));
+        let (low, high) = (row.min(target), row.max(target));
+        if visible[low..=high]
+            .iter()
+            .any(|&i| self.collapsed_namespace(&self.messages[i].title).is_some())
+        {
+            self.notifications
+                .info("Expand the namespace with - to move its snippets");
+            return;
+        }
+        if target == row {
+            return;
+        }
+
+        let title = self.messages[visible[row]].title.clone();
+        self.record_undo(format!("moving '{}'", title));
+        // Swapping with one listed neighbour after the other keeps the order
+        // of the rows in between, and of the snippets hidden by a tag filter
+        let mut current = row;
+        while current != target {
+            let next = if target > current {
+                current + 1
+            } else {
+                current - 1
+            };
+            let (a, b) = (visible[current], visible[next]);
+            self.messages.swap(a, b);
+            let (a_marked, b_marked) = (self.marked.remove(&a), self.marked.remove(&b));
+            if a_marked {
+                self.marked.insert(b);
+            }
+            if b_marked {
+                self.marked.insert(a);
+            }
+            current = next;
+        }
+        self.refilter();
+        self.select_index(visible[target]);
+        self.save();
+    }
+
+    /// Moves the split between the title and description columns by `steps`
+    /// and keeps it for the collection
+    pub fn widen_title(&mut self, steps: i32) {
+        self.view = self.view.widen_title(steps);
+        let saved = messages_file_path().map_or(Ok(()), |store| view::save(&store, self.view));
+        if let Err(error) = saved {
+            self.report_error(format!("Could not keep the column widths: {}", error));
+        }
+    }
+
+    /// Scrolls the description column sideways, stopping at the end of the longest listed line
+    pub fn scroll_description(&mut self, columns: isize) {
+        let longest = self
+            .visible_rows()
+            .into_iter()
+            .filter(|&i| !self.messages[i].binary)
+            .flat_map(|i| {
+                description_view(&self.messages[i].description, self.single_line)
+                    .lines()
+                    .map(|line| line.width())
+                    .collect::<Vec<usize>>()
+            })
+            .max()
+            .unwrap_or_default();
+        self.description_offset = self
+            .description_offset
+            .saturating_add_signed(columns)
+            .min(longest.saturating_sub(1));
+    }
+
+    /// Selects the listed row at `row`, or the last one when there are fewer rows
+    pub fn select_row(&mut self, row: usize) {
+        let count = self.visible_rows().len();
+        self.table_state
+            .select((count > 0).then(|| row.min(count - 1)));
+    }
+
+    /// Keeps the selection on a listed row after the filter or the snippets changed
+    fn clamp_selection(&mut self) {
+        self.refilter();
+        let count = self.visible_rows().len();
+        match self.table_state.selected() {
+            _ if count == 0 => self.table_state.select(None),
+            Some(selected) if selected >= count => self.table_state.select(Some(count - 1)),
+            None if self.is_filtered() => self.table_state.select(Some(0)),
+            _ => {}
+        }
+    }
+
+    /// Moves the selection to the next snippet whose schedule covers the current time
+    pub fn select_next_scheduled(&mut self) {
+        let now = time_utils::now();
+        let rows = self.visible_rows();
+        let start = self.table_state.selected().map_or(0, |i| i + 1);
+        let count = rows.len();
+        let next = (0..count)
+            .map(|offset| (start + offset) % count)
+            .find(|&row| self.messages[rows[row]].is_scheduled_now(now));
+        match next {
+            Some(next) => self.table_state.select(Some(next)),
+            None => self.notifications.info("No snippets are scheduled for now"),
+        }
+    }
+
+    /// Deletes the snippet at `index` and saves, keeping it for `u`
+    pub fn delete(&mut self, index: usize) {
+        let title = &self.messages[index].title;
+        self.record_undo(format!("deleting '{}'", title));
+        self.messages.remove(index);
+        self.marked.clear();
+        self.clamp_selection();
+        self.save();
+    }
+
+    /// Adds the snippets at `indices` to the collection `name` kept at `path`
+    /// and, when `moving`, deletes them here. The collection is saved first,
+    /// so they stay here when that fails. `u` brings moved snippets back here
+    /// without taking them out of the collection.
+    pub fn transfer(&mut self, mut indices: Vec<usize>, name: &str, path: &Path, moving: bool) {
+        let snippets: Vec<Snippet> = indices.iter().map(|&i| self.messages[i].clone()).collect();
+        let what = match snippets.as_slice() {
+            [snippet] => format!("'{}'", snippet.title),
+            _ => format!("{} snippets", snippets.len()),
+        };
+        let renamed = match collections::transfer(snippets, path) {
+            Ok(renamed) => renamed,
+            Err(error) => {
+                self.report_error(format!("Could not add {} to {}: {}", what, name, error));
+                return;
+            }
+        };
+
+        let mut message = match moving {
+            true => format!("Moved {} to {}", what, name),
+            false => format!("Copied {} to {}", what, name),
+        };
+        if !renamed.is_empty() {
+            let titles: Vec<String> = renamed
+                .iter()
+                .map(|(old, new)| format!("'{}' as '{}'", old, new))
+                .collect();
+            message.push_str(&format!(
+                ", titles taken there so added {}",
+                titles.join(", ")
+            ));
+        }
+        self.notifications.info(message);
+
+        if moving {
+            self.record_undo(format!("moving {} to {}", what, name));
+            indices.sort_unstable();
+            for index in indices.into_iter().rev() {
+                self.messages.remove(index);
+            }
+            self.marked.clear();
+            self.save();
+        }
+    }
+
+    /// Whether the form holds anything typed that hasn't been added yet.
+    /// Snippets are saved as each change is made, so the form is all there is to lose.
+    fn has_draft(&self) -> bool {
+        !self.title_input.is_empty()
+            || !self.tags_input.is_empty()
+            || !self.variant_input.is_empty()
+            || !self.description_input.is_empty()
+            || !self.form_bodies.is_empty()
+    }
+
+    /// Whether `Quit` should close sniprrr now, or first ask for another `q`.
+    /// `asked` tells whether the key before was the `q` that asked.
+    fn may_quit(&mut self, asked: bool) -> bool {
+        if !self.confirm_quit || asked || !self.has_draft() {
+            return true;
+        }
+        self.quit_pending = true;
+        self.notifications
+            .info("The form has a draft that isn't added yet, press q again to quit");
+        false
+    }
+
+    /// Remembers the snippets from before `change`, which is about to be made,
+    /// so `u` can bring them back
+    pub fn record_undo(&mut self, change: String) {
+        self.undo.push((change, self.messages.clone()));
+        if self.undo.len() > UNDO_LIMIT {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+    }
+
+    /// Takes in the store as `edited` by hand outside the app, keeping the
+    /// temporary snippets of this session
+    pub fn reload_store(&mut self, edited: Vec<Snippet>) {
+        self.record_undo("editing the store by hand".to_string());
+        self.take_in(edited);
+    }
+
+    /// Shows `stored` as the snippets of the store, layered with the sources
+    /// again, keeping the temporary snippets of this session
+    fn take_in(&mut self, stored: Vec<Snippet>) {
+        let temporary: Vec<Snippet> = self
+            .messages
+            .iter()
+            .filter(|snippet| snippet.temporary)
+            .cloned()
+            .collect();
+        let layers = sources::layer(&self.sources, stored);
+        self.messages = layers.snippets;
+        self.messages.extend(temporary);
+        self.shadowed = layers.shadowed;
+        for error in layers.errors {
+            self.notifications.error(error);
+        }
+        self.marked.clear();
+        self.clamp_selection();
+    }
+
+    /// Puts the snippets back the way they were before the last change and saves them
+    pub fn undo(&mut self) {
+        match self.undo.pop() {
+            Some((change, snippets)) => {
+                let current = std::mem::replace(&mut self.messages, snippets);
+                self.redo.push((change.clone(), current));
+                self.notifications.info(format!("Undid {}", change));
+                self.marked.clear();
+                self.save();
+            }
+            None => self.notifications.info("Nothing to undo"),
+        }
+    }
+
+    /// Makes the last undone change again and saves it
+    pub fn redo(&mut self) {
+        match self.redo.pop() {
+            Some((change, snippets)) => {
+                let current = std::mem::replace(&mut self.messages, snippets);
+                self.undo.push((change.clone(), current));
+                self.notifications.info(format!("Redid {}", change));
+                self.marked.clear();
+                self.save();
+            }
+            None => self.notifications.info("Nothing to redo"),
+        }
+    }
+
+    /// Shows a failure in the status line, or in strict mode in a popup that has to be dismissed
+    pub fn report_error(&mut self, message: String) {
+        if self.strict {
+            self.popup = Some(Popup::Message(MessagePopup::new("Error", &message)));
+        }
+        self.notifications.error(message);
+    }
+
+    /// Inserts pasted text into the field being typed in. The title and the
+    /// filter stay on one line.
+    pub fn insert_pasted(&mut self, text: &str) {
+        let text = text.replace("\r\n", "\n").replace('\r', "\n");
+        let single_line = || text.lines().collect::<Vec<&str>>().join(" ");
+        match self.input_mode {
+            InputMode::Editing => {
+                let text = self.paste.clean_up(&text);
+                let single_line = || text.lines().collect::<Vec<&str>>().join(" ");
+                match self.focused_input_index {
+                    // Offered only into an empty form, so nothing typed gets replaced
+                    INPUT_TITLE_INDEX
+                        if self.title_input.is_empty() && self.description_input.is_empty() =>
+                    {
+                        match SplitPastePopup::new(&text) {
+                            Some(split) => self.popup = Some(Popup::SplitPaste(split)),
+                            None => self.title_input.insert_str(&single_line()),
+                        }
+                    }
+                    INPUT_TITLE_INDEX => self.title_input.insert_str(&single_line()),
+                    INPUT_TAGS_INDEX => self.tags_input.insert_str(&single_line()),
+                    INPUT_VARIANT_INDEX => self.variant_input.insert_str(&single_line()),
+                    INPUT_DESCRIPTION_INDEX => self.description_input.insert_str(&text),
+                    _ => {}
+                }
+            }
+            InputMode::Search => {
+                self.filter.push_str(&single_line());
+                self.table_state.select(Some(0));
+                self.clamp_selection();
+            }
+            InputMode::Normal => {}
+        }
+    }
+
+    /// "sniprrr — <collection> (<N> snippets)", the collection being the store's file name
+    pub fn window_title(&self) -> String {
+        let collection = messages_file_path()
+            .and_then(|path| Some(path.file_stem()?.to_string_lossy().into_owned()))
+            .unwrap_or_else(|| "snippets".to_string());
+        format!(
+            "sniprrr — {} ({} snippets)",
+            collection,
+            self.messages.len()
+        )
+    }
+
+    /// Lists `incoming` for review before importing them
+    pub fn review_import(&mut self, source: &str, incoming: Vec<Snippet>) {
+        if incoming.is_empty() {
+            self.notifications
+                .info(format!("There are no snippets in {}", source));
+        } else {
+            self.popup = Some(Popup::Import(ImportPopup::new(
+                source,
+                incoming,
+                &self.messages,
+            )));
+        }
+    }
+
+    /// The form field being typed in
+    fn focused_input(&mut self) -> Option<&mut TextArea> {
+        match self.focused_input_index {
+            INPUT_TITLE_INDEX => Some(&mut self.title_input),
+            INPUT_TAGS_INDEX => Some(&mut self.tags_input),
+            INPUT_VARIANT_INDEX => Some(&mut self.variant_input),
+            INPUT_DESCRIPTION_INDEX => Some(&mut self.description_input),
+            _ => None,
+        }
+    }
+
+    /// Moves to the next field of the form, loading the body of the variant
+    /// named in the form into the description
+    fn focus_next_input(&mut self) {
+        let name = self.variant_input.text().trim().to_string();
+        if name != self.form_variant {
+            let body = self.description_input.take();
+            let previous = std::mem::replace(&mut self.form_variant, name);
+            self.form_bodies.insert(previous, body);
+            self.description_input.set_text(
+                self.form_bodies
+                    .remove(&self.form_variant)
+                    .unwrap_or_default(),
+            );
+        }
+        self.focused_input_index = (self.focused_input_index + 1) % MAX_INPUT_COUNT;
+    }
+
+    /// The snippet typed into the form, clearing it. Variants left empty are dropped.
+    fn take_form_snippet(&mut self) -> Snippet {
+        let mut bodies = std::mem::take(&mut self.form_bodies);
+        bodies.insert(
+            std::mem::take(&mut self.form_variant),
+            self.description_input.take(),
+        );
+        let description = bodies.remove("").unwrap_or_default();
+        let mut snippet = Snippet::new(self.title_input.take(), description);
+        snippet.tags = parse_tags(&self.tags_input.take());
+        snippet.temporary = std::mem::take(&mut self.form_temporary);
+        snippet.variants = bodies
+            .into_iter()
+            .filter(|(_, body)| !body.is_empty())
+            .collect();
+        self.variant_input.take();
+        snippet
+    }
+
+    /// Where the main body in the form is kept already, in this collection or
+    /// another one of `[collections]`
+    fn form_duplicate(&self) -> Option<Existing> {
+        let body = match self.form_variant.is_empty() {
+            true => self.description_input.text(),
+            false => self.form_bodies.get("")?.as_str(),
+        };
+        let others = collections::others(&self.collections, messages_file_path().as_deref());
+        collections::find_body(body, &self.messages, &others)
+    }
+
+    /// Adds the snippet in the form, after suggesting tags for it
+    fn add_form_snippet(&mut self) {
+        let snippet = self.take_form_snippet();
+        self.input_mode = InputMode::Normal;
+
+        // Temporary snippets don't stay long enough to need organizing
+        let suggestions = match snippet.temporary {
+            true => vec![],
+            false => tag_suggest::suggest(&snippet),
+        };
+        if suggestions.is_empty() {
+            self.record_undo(format!("adding '{}'", snippet.title));
+            self.messages.push(snippet);
+            self.save();
+        } else {
+            self.popup = Some(Popup::TagSuggest(TagSuggestPopup::new(
+                snippet,
+                suggestions,
+            )));
+        }
+    }
+
+    /// Opens the review of the snippet at `index`, or of the first one waiting
+    /// for review when that one isn't. Snippets of other sources stay flagged.
+    pub fn open_review(&mut self, index: Option<usize>) {
+        let waiting: Vec<usize> = (0..self.messages.len())
+            .filter(|&i| self.messages[i].needs_review && self.messages[i].source.is_none())
+            .collect();
+        let index = index
+            .filter(|index| waiting.contains(index))
+            .or_else(|| waiting.first().copied());
+        match index {
+            Some(index) => {
+                self.popup = Some(Popup::Review(ReviewPopup::new(
+                    index,
+                    &self.messages[index],
+                    waiting.len() - 1,
+                )))
+            }
+            None => self
+                .notifications
+                .info("No imported snippets are waiting for review"),
+        }
+    }
+
+    /// Asks where to export the snippets to
+    pub fn open_export(&mut self) {
+        self.popup = Some(Popup::Input(InputPopup::new(
+            "Export the store to",
+            "Path of the file to write: .json for a store file, .yaml or .toml with every field, .csv for a spreadsheet, or .sh or .ps1 for the shell snippets as functions. All but the shell functions can be imported again with I.",
+            InputPurpose::Export,
+            String::new(),
+        )));
+    }
+
+    /// Opens the storage overview for the store as it is now
+    pub fn open_storage(&mut self) {
+        match messages_file_path() {
+            Some(store_path) => {
+                let report = storage::report(&store_path, &self.messages, self.daemon_client);
+                self.popup = Some(Popup::Storage(StoragePopup::new(report)));
+            }
+            None => {
+                self.report_error("There is no config directory to keep the store in".to_string())
+            }
+        }
+    }
+
+    /// Runs the semantic search typed into the filter, if it is one
+    #[cfg(feature = "semantic")]
+    pub fn search_semantically(&mut self) {
+        let Some(query) = semantic::query(&self.filter) else {
+            return;
+        };
+        match semantic::rank(&self.semantic, query, &self.messages) {
+            Ok(found) => {
+                self.notifications.info(format!(
+                    "{} snippets close in meaning to '{}'",
+                    found.len(),
+                    query
+                ));
+                self.semantic_results = Some((self.filter.clone(), found));
+            }
+            Err(error) => self.report_error(format!("Could not search: {}", error)),
+        }
+        self.table_state.select(Some(0));
+        self.clamp_selection();
+    }
+
+    /// Applies the settings from `config.toml`
+    pub fn apply_config(&mut self, config: &Config) {
+        self.theme = Theme::from_config(&config.theme);
+        if config.theme.syntax != "none" && syntax::scheme(&config.theme.syntax).is_none() {
+            self.report_error(format!(
+                "Unknown syntax color scheme '{}', use one of {} or none",
+                config.theme.syntax,
+                syntax::scheme_names().join(", ")
+            ));
+        }
+        self.copy_router = CopyRouter::from_config(config);
+        self.share = config.share.clone();
+        self.generate = config.generate.clone();
+        self.default_copy_field = config.copy_field;
+        #[cfg(feature = "semantic")]
+        {
+            self.semantic = config.semantic.clone();
+        }
+        self.paste = config.paste.clone();
+        self.confirm_quit = config.confirm_quit;
+        self.confirm_delete = config.confirm_delete;
+        self.hide_sidebar = config.hide_sidebar;
+        self.sort = config.sort;
+        self.collections = config.collections.clone();
+        self.sources = config.sources.clone();
+        self.row_template = config.row_template.clone().unwrap_or_default();
+        self.list = config.row_template.is_some();
+        self.refilter();
+        match Keymap::with_bindings(&config.keys) {
+            Ok(keymap) => self.keymap = keymap,
+            Err(error) => self.report_error(format!("Invalid key binding: {}", error)),
+        }
+        compression::configure(&config.compression);
+    }
+
+    /// Runs the snippet at `index` and shows what it printed
+    pub fn run_snippet(&mut self, index: usize, environment: &run::Environment) {
+        let snippet = &self.messages[index];
+        match run::run(&snippet.description, environment) {
+            Ok(output) => {
+                self.popup = Some(Popup::Output(OutputPopup::new(&snippet.title, output)))
+            }
+            Err(error) => self.report_error(format!("Could not run: {}", error)),
+        }
+    }
+
+    /// Persists the snippets, through the daemon when connected to one. A
+    /// failure is reported on the status line; the snippets stay as they are
+    /// here and go out with the next save.
+    pub fn save(&mut self) {
+        #[cfg(feature = "semantic")]
+        {
+            self.semantic_results = None;
+        }
+        self.clamp_selection();
+        let mut snippets: Vec<Snippet> = self
+            .messages
+            .iter()
+            .filter(|snippet| !snippet.temporary && snippet.source.is_none())
+            .cloned()
+            .collect();
+        for (position, snippet) in &self.shadowed {
+            snippets.insert((*position).min(snippets.len()), snippet.clone());
+        }
+        let saved = if self.daemon_client {
+            self.save_to_daemon(snippets)
+        } else {
+            write_messages_to_file(&snippets)
+        };
+        if let Err(error) = saved {
+            self.report_error(format!("Could not save the snippets: {}", error));
+        }
+    }
+
+    /// Saves `snippets` through the daemon along with the revision they were
+    /// loaded at. What other clients saved meanwhile comes back merged in and
+    /// is taken in.
+    fn save_to_daemon(&mut self, snippets: Vec<Snippet>) -> io::Result<()> {
+        let request = Request::Save {
+            snippets,
+            base: Some(self.daemon_revision),
+        };
+        let response = ipc::send(&request).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotConnected, "sniprrr daemon is not running")
+        })??;
+        if let Some(revision) = response.revision {
+            self.daemon_revision = revision;
+        }
+        if let Some(current) = response.snippets {
+            self.take_in(current);
+            if response.ok {
+                self.notifications.info(response.message.clone());
+            }
+        }
+        match response.ok {
+            true => Ok(()),
+            false => Err(io::Error::other(response.message)),
+        }
+    }
+
     pub fn next(&mut self) {
+        let count = self.visible_rows().len();
+        if count == 0 {
+            return;
+        }
         let i = match self.table_state.selected() {
             Some(i) => {
-                if i >= self.messages.len() - 1 {
+                if i >= count - 1 {
                     0
                 } else {
                     i + 1
@@ -58,10 +1026,14 @@ impl AppState {
     }
 
     pub fn previous(&mut self) {
+        let count = self.visible_rows().len();
+        if count == 0 {
+            return;
+        }
         let i = match self.table_state.selected() {
             Some(i) => {
                 if i == 0 {
-                    self.messages.len() - 1
+                    count - 1
                 } else {
                     i - 1
                 }
@@ -75,149 +1047,2167 @@ impl AppState {
 impl Default for AppState {
     fn default() -> AppState {
         AppState {
-            title_input: String::new(),
-            description_input: String::new(),
+            title_input: TextArea::default(),
+            tags_input: TextArea::default(),
+            variant_input: TextArea::default(),
+            description_input: TextArea::default(),
+            form_variant: String::new(),
+            form_bodies: BTreeMap::new(),
+            form_temporary: false,
             focused_input_index: INPUT_TITLE_INDEX,
             input_mode: InputMode::Normal,
+            paste: PasteConfig::default(),
+            paste_mode: PasteMode::default(),
             table_state: TableState::default(),
+            filter: String::new(),
+            filtered: Vec::new(),
+            sort: SortOrder::default(),
+            sort_before_most_used: SortOrder::default(),
+            tag_filter: None,
+            folder: None,
+            sidebar_focus: false,
+            hide_sidebar: false,
+            collections: BTreeMap::new(),
+            single_line: false,
+            preview: false,
+            description_offset: 0,
+            view: View::default(),
+            list: false,
+            row_template: RowTemplate::default(),
             messages: Vec::new(),
+            marked: BTreeSet::new(),
+            collapsed: BTreeSet::new(),
+            shadowed: vec![],
+            sources: vec![],
+            popup: None,
+            notifications: Notifications::default(),
+            quit_after_copy: false,
+            print: false,
+            printed: None,
+            default_copy_field: CopyField::Body,
+            copy_field: CopyField::Body,
+            confirm_quit: false,
+            tutorial: None,
+            confirm_delete: false,
+            quit_pending: false,
+            copy_router: CopyRouter::default(),
+            share: ShareConfig::default(),
+            generate: GenerateConfig::default(),
+            #[cfg(feature = "semantic")]
+            semantic: SemanticConfig::default(),
+            #[cfg(feature = "semantic")]
+            semantic_results: None,
+            keymap: Keymap::default(),
+            undo: vec![],
+            redo: vec![],
+            chord: Chord::default(),
+            strict: false,
+            daemon_client: false,
+            daemon_revision: 0,
+            theme: Theme::default(),
         }
     }
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
+    debug_bundle::install_panic_hook();
+
+    let cli::Cli {
+        strict: strict_flag,
+        quiet,
+        no_tui,
+        print,
+        command: parsed,
+    } = cli::parse();
+    // Scripts and pipelines only get what they asked for
+    let quiet = quiet || !io::stdout().is_terminal();
+    let (config, strict) = match load_settings(strict_flag) {
+        Ok(settings) => settings,
+        Err(message) => {
+            eprintln!("{}", message);
+            std::process::exit(2);
+        }
+    };
+    compression::configure(&config.compression);
+    encryption::configure(&config.encryption);
+    storage::configure(&config.storage);
+    terminal::configure(terminal::detect(&config.terminal));
+    if let Some(store_path) = config.store_path() {
+        file_utils::set_store_path(store_path);
+    }
+    if !matches!(parsed, Some(Command::Tutorial)) {
+        if config.encryption.enabled && storage::configured() == storage::Backend::Sqlite && !quiet
+        {
+            eprintln!(
+                "[encryption] covers the JSON store only, the SQLite one is kept in the clear"
+            );
+        }
+        if let Err(message) = unlock_store() {
+            eprintln!("{}", message);
+            std::process::exit(2);
+        }
+    }
+    // A store left in the other format stays readable, so carry on when it
+    // can't be converted. The tutorial doesn't touch it at all.
+    if !matches!(parsed, Some(Command::Tutorial)) {
+        match storage::migrate() {
+            Ok(Some(note)) if !quiet => eprintln!("{}", note),
+            Ok(_) => {}
+            Err(error) => eprintln!(
+                "Could not move the store to {}: {}",
+                storage::configured().name(),
+                error
+            ),
+        }
+    }
+
+    // `import` opens the UI with what it read listed for review, `tutorial`
+    // with a sandbox in place of the store
+    let mut sandbox = None;
+    let import = match parsed {
+        Some(Command::Import(options)) => Some(options),
+        Some(Command::Tutorial) => {
+            match tutorial::Sandbox::create() {
+                Ok(created) => sandbox = Some(created),
+                Err(error) => {
+                    eprintln!("Could not set up the tutorial's sandbox: {}", error);
+                    std::process::exit(2);
+                }
+            }
+            None
+        }
+        Some(_) if print => {
+            eprintln!("--print is for the UI, the commands print on their own");
+            std::process::exit(2);
+        }
+        Some(command) => {
+            if let Err(error) = run_command(command, &config, strict, quiet) {
+                eprintln!("{}", error);
+                std::process::exit(exit_code(&*error));
+            }
+            return Ok(());
+        }
+        None => None,
+    };
+    // Printing leaves stdout to the snippet, the UI goes to the terminal itself
+    let tty = match print {
+        true => match open_tty() {
+            Ok(tty) => Some(tty),
+            Err(error) => {
+                eprintln!("--print needs a terminal to show the UI on: {}", error);
+                std::process::exit(2);
+            }
+        },
+        false => None,
+    };
+    if no_tui || tty.is_none() && !io::stdout().is_terminal() {
+        eprintln!(
+            "{}, give one of the commands\n\n{}",
+            match no_tui {
+                true => "--no-tui leaves nothing to do",
+                false => "The UI needs a terminal",
+            },
+            cli::usage()
+        );
+        std::process::exit(2);
+    }
+
+    // With a daemon running the TUI is just another client of it. The
+    // tutorial stays out of the way of other instances.
+    let daemon_client = sandbox.is_none() && ipc::daemon_is_running();
+    if !daemon_client && sandbox.is_none() && !print {
+        if let Some(response) = ipc::send(&Request::Focus) {
+            let message = response
+                .map(|response| response.message)
+                .unwrap_or_else(|error| error.to_string());
+            eprintln!("{}", message);
+            std::process::exit(1);
+        }
+    }
+
+    // Take in what other machines pushed before the store is read
+    let sync_store = messages_file_path()
+        .filter(|_| config.sync.enabled() && !daemon_client && sandbox.is_none());
+    let synced = sync_store
+        .as_ref()
+        .map(|store| sync::pull(&config.sync, store));
+
+    // Load before taking over the terminal, a damaged store may need repairing
+    let mut daemon_revision = 0;
+    let messages = if daemon_client {
+        ipc::request(&Request::List)
+            .map(|response| {
+                daemon_revision = response.revision.unwrap_or_default();
+                response.snippets.unwrap_or_default()
+            })
+            .map_err(|error| error.to_string())
+    } else {
+        integrity::load_or_repair()
+    };
+    let messages = match messages {
+        Ok(messages) => messages,
+        Err(message) => {
+            eprintln!("{}", message);
+            std::process::exit(2);
+        }
+    };
+    let import_popup = match import.map(|options| import_popup(options, &messages)) {
+        Some(Ok(popup)) => Some(popup),
+        Some(Err(message)) => {
+            eprintln!("{}", message);
+            std::process::exit(2);
+        }
+        None => None,
+    };
+
     enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
-    let backend = CrosstermBackend::new(stdout);
+    let mut screen: Box<dyn Write> = match tty {
+        Some(tty) => Box::new(tty),
+        None => Box::new(io::stdout()),
+    };
+    execute!(screen, EnterAlternateScreen, EnableBracketedPaste)?;
+    if terminal::mouse() {
+        execute!(screen, EnableMouseCapture)?;
+    }
+    if !print {
+        title::save()?;
+    }
+    let backend = CrosstermBackend::new(screen);
     let mut terminal = Terminal::new(backend)?;
 
     let mut app_state = AppState::default();
 
-    // Load from disk
-    let messages = load_messages_from_file();
-    app_state.messages = messages;
+    app_state.apply_config(&config);
+    match synced {
+        Some(Ok(summary)) => app_state.notifications.info(summary),
+        Some(Err(error)) => app_state
+            .notifications
+            .error(format!("Could not sync: {}", error)),
+        None => {}
+    }
+
+    // The tutorial's sandbox stands on its own
+    match sandbox {
+        Some(_) => app_state.messages = messages,
+        None => {
+            let layers = sources::layer(&config.sources, messages);
+            app_state.messages = layers.snippets;
+            app_state.shadowed = layers.shadowed;
+            for error in layers.errors {
+                app_state.notifications.error(error);
+            }
+        }
+    }
+    app_state.refilter();
+    if let Some(store) = messages_file_path() {
+        app_state.view = view::load(&store);
+    }
+    app_state.daemon_client = daemon_client;
+    app_state.daemon_revision = daemon_revision;
+    app_state.strict = strict;
+    app_state.print = print;
+    app_state.popup = import_popup;
+    if sandbox.is_some() {
+        app_state.tutorial = Some(Tutorial::new(&app_state.messages));
+    }
+
+    // Start on whatever is relevant at this time of day, or else on the
+    // likeliest snippet to be copied
+    let now = time_utils::now();
+    match app_state
+        .messages
+        .iter()
+        .position(|s| s.is_scheduled_now(now))
+    {
+        Some(scheduled) => app_state.select_index(scheduled),
+        None => app_state.select_frecent(),
+    }
 
-    let res = run_app(&mut terminal, app_state);
+    // Keep the guard alive until the app exits so the socket file gets cleaned up.
+    // With --print, stdout is the caller's pipe, so copies and the bell for
+    // other invocations would end up in it rather than the clipboard.
+    let (_instance_guard, requests) = match daemon_client || sandbox.is_some() || print {
+        true => (None, None),
+        false => match ipc::listen() {
+            Ok((guard, requests)) => (Some(guard), Some(requests)),
+            Err(error) => {
+                if strict {
+                    app_state.report_error(format!(
+                        "Not listening for other sniprrr invocations: {}",
+                        error
+                    ));
+                }
+                (None, None)
+            }
+        },
+    };
+
+    let res = run_app(&mut terminal, &mut app_state, requests);
 
     // restore terminal / tear down
     disable_raw_mode()?;
     execute!(
         terminal.backend_mut(),
         LeaveAlternateScreen,
-        DisableMouseCapture
+        DisableMouseCapture,
+        DisableBracketedPaste
     )?;
+    if !print {
+        title::restore()?;
+    }
     terminal.show_cursor()?;
 
+    drop(sandbox);
+    if let Some(store) = &sync_store {
+        if let Err(error) = sync::push(&config.sync, store) {
+            eprintln!("Could not sync: {}", error);
+        }
+    }
+
+    if let Some(text) = &app_state.printed {
+        let mut stdout = io::stdout().lock();
+        let newline = if text.ends_with('\n') { "" } else { "\n" };
+        match write!(stdout, "{}{}", text, newline) {
+            Err(error) if error.kind() == io::ErrorKind::BrokenPipe => {}
+            written => written?,
+        }
+    }
+
     if let Err(err) = res {
-        println!("{:?}", err)
+        eprintln!("{}", err);
+        if strict {
+            std::process::exit(1);
+        }
     }
 
     Ok(())
 }
 
-fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app_state: AppState) -> io::Result<()> {
-    loop {
-        terminal.draw(|f| ui(f, &mut app_state))?;
+/// The terminal sniprrr runs in, whatever stdin and stdout are redirected to
+fn open_tty() -> io::Result<std::fs::File> {
+    let path = if cfg!(windows) { "CONOUT$" } else { "/dev/tty" };
+    std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+}
+
+/// Where programs started from the app write: the terminal, which isn't
+/// stdout when that is kept for the snippet of `--print`
+fn child_output(print: bool) -> io::Result<Stdio> {
+    match print {
+        true => Ok(Stdio::from(open_tty()?)),
+        false => Ok(Stdio::inherit()),
+    }
+}
+
+/// Leaves the app's screen to run `command` on the terminal, waits for
+/// Enter once it ended and comes back. Returns how the command ended.
+fn execute_in_terminal<B: Backend + Write>(
+    terminal: &mut Terminal<B>,
+    command: &str,
+    environment: &run::Environment,
+    print: bool,
+) -> io::Result<Result<String, String>> {
+    suspended(terminal, |screen| {
+        for line in command.lines() {
+            writeln!(screen, "$ {}", line)?;
+        }
+        Write::flush(screen)?;
+        let result = run::execute(command, environment, child_output(print)?);
+        match &result {
+            Ok(status) => writeln!(screen, "\n[{}]", status)?,
+            Err(error) => writeln!(screen, "Could not run: {}", error)?,
+        }
+        write!(screen, "Press Enter to return to sniprrr")?;
+        Write::flush(screen)?;
+        io::stdin().read_line(&mut String::new())?;
+        Ok(result)
+    })
+}
+
+/// Edits the store in `$EDITOR` with the app suspended, returning the
+/// snippets it holds afterwards, or `None` when it wasn't changed
+fn edit_store<B: Backend + Write>(
+    terminal: &mut Terminal<B>,
+    store: &Path,
+    print: bool,
+) -> io::Result<Result<Option<Vec<Snippet>>, String>> {
+    if let Err(error) = open::check_editable(store) {
+        return Ok(Err(error));
+    }
+    let before = fs::read(store)?;
+    if let Err(error) = suspended(terminal, |_| Ok(open::edit(store, child_output(print)?)))? {
+        return Ok(Err(error));
+    }
+    if fs::read(store)? == before {
+        return Ok(Ok(None));
+    }
+    Ok(file_utils::load_store(store).map(Some).map_err(|error| {
+        format!(
+            "Kept the snippets shown, the edited store can't be read: {}. Fix it with gs, or run sniprrr doctor",
+            error
+        )
+    }))
+}
+
+/// Hands the terminal to `run`, outside the alternate screen and raw mode
+/// until it returns, along with the app's screen to write to
+fn suspended<B: Backend + Write, T>(
+    terminal: &mut Terminal<B>,
+    run: impl FnOnce(&mut B) -> io::Result<T>,
+) -> io::Result<T> {
+    disable_raw_mode()?;
+    execute!(
+        terminal.backend_mut(),
+        LeaveAlternateScreen,
+        DisableMouseCapture,
+        DisableBracketedPaste
+    )?;
+    terminal.show_cursor()?;
+    let result = run(terminal.backend_mut());
+
+    enable_raw_mode()?;
+    execute!(
+        terminal.backend_mut(),
+        EnterAlternateScreen,
+        EnableBracketedPaste
+    )?;
+    if terminal::mouse() {
+        execute!(terminal.backend_mut(), EnableMouseCapture)?;
+    }
+    terminal.clear()?;
+    result
+}
+
+/// The text to copy, if it isn't the body, and the placeholder values used
+type FilledIn = (Option<String>, HashMap<String, String>);
+
+/// The text of a template for `sniprrr copy`, with the values for its
+/// placeholders asked for on the terminal. `None` for other snippets, which
+/// are copied as they are.
+fn fill_in(snippet: &Snippet) -> Result<FilledIn, Box<dyn Error>> {
+    let body = &snippet.description;
+    if snippet.binary || template::placeholders(body).is_empty() && !template::has_expansions(body)
+    {
+        return Ok((None, HashMap::new()));
+    }
+    if template::commands_fingerprint(body)
+        .is_some_and(|commands| snippet.trusted_commands.as_deref() != Some(commands.as_str()))
+    {
+        return Err(format!(
+            "'{}' runs commands when expanded, copy it from sniprrr to allow them first",
+            snippet.title
+        )
+        .into());
+    }
+    let values = template::ask(snippet, &mut io::stdin().lock(), &mut io::stderr())?;
+    let text = template::render_segments(body, &values)
+        .into_iter()
+        .map(|segment| segment.text)
+        .collect();
+    Ok((Some(text), values))
+}
+
+/// What `sniprrr copy` sends for `snippet`: the fields of `field`, with the
+/// body filled in when it is a template, and the placeholder values used
+fn copy_text(
+    snippet: &Snippet,
+    field: CopyField,
+) -> Result<(String, HashMap<String, String>), Box<dyn Error>> {
+    let (text, values) = match field {
+        CopyField::Title => (None, HashMap::new()),
+        CopyField::Body | CopyField::Both => fill_in(snippet)?,
+    };
+    let body = text.as_deref().unwrap_or(&snippet.description);
+    Ok((field.text(&snippet.title, body), values))
+}
+
+/// The review popup for `sniprrr import`, listing the snippets read from its files
+fn import_popup(options: ImportOptions, current: &[Snippet]) -> Result<Popup, String> {
+    match options.format {
+        None | Some(ImportFormat::File(_)) => {
+            let format_of = |path: &Path| match options.format {
+                Some(ImportFormat::File(format)) => format,
+                _ => Format::for_path(path).unwrap_or(Format::Json),
+            };
+            // The columns of a CSV file are picked before its snippets are listed
+            if let [path] = options.paths.as_slice() {
+                if format_of(path) == Format::Csv {
+                    let table = csv_io::read_table(path)?;
+                    if table.headers.is_empty() {
+                        return Err(format!("There are no columns in {}", path.display()));
+                    }
+                    return Ok(Popup::Columns(ColumnsPopup::new(
+                        &path.display().to_string(),
+                        table,
+                    )));
+                }
+            }
+            let mut incoming = vec![];
+            for path in &options.paths {
+                if format_of(path) == Format::Csv {
+                    return Err(format!(
+                        "{}: import CSV files on their own, their columns are picked first",
+                        path.display()
+                    ));
+                }
+                incoming.extend(formats::read(path, format_of(path))?);
+            }
+            let source = options
+                .paths
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect::<Vec<String>>()
+                .join(", ");
+            Ok(Popup::Import(ImportPopup::new(&source, incoming, current)))
+        }
+        Some(ImportFormat::ShellHistory) => {
+            let paths = match options.paths.is_empty() {
+                true => shell_history::default_paths(),
+                false => options.paths,
+            };
+            if paths.is_empty() {
+                return Err(
+                    "There is no bash, zsh or fish history in the home directory, name the file to import"
+                        .to_string(),
+                );
+            }
+            let candidates = shell_history::read(&paths)?;
+            if candidates.is_empty() {
+                return Err(format!(
+                    "There are no commands worth keeping in {}",
+                    shell_history::describe(&paths)
+                ));
+            }
+            let notes = candidates
+                .iter()
+                .map(|candidate| match candidate.count {
+                    1 => "ran once".to_string(),
+                    count => format!("ran {} times", count),
+                })
+                .collect();
+            let incoming = candidates
+                .into_iter()
+                .map(|candidate| candidate.snippet)
+                .collect();
+            let popup = ImportPopup::new(&shell_history::describe(&paths), incoming, current)
+                .none_included()
+                .with_notes(notes);
+            Ok(Popup::Import(popup))
+        }
+    }
+}
+
+/// Environment variable holding the passphrase of the store, for scripts and
+/// services that can't be asked for it
+const PASSPHRASE_VARIABLE: &str = "SNIPRRR_PASSPHRASE";
+/// Wrong passphrases taken before giving up
+const PASSPHRASE_TRIES: usize = 3;
+
+/// Asks for the passphrase of an encrypted store, or for a new one when
+/// `[encryption]` is on for a store kept in the clear so far
+fn unlock_store() -> Result<(), String> {
+    let existing = messages_file_path().and_then(|path| fs::read(path).ok());
+    let encrypted = existing.as_deref().is_some_and(encryption::is_encrypted);
+    if !encrypted && !encryption::enabled() {
+        return Ok(());
+    }
+    let unlock = |passphrase: &str| {
+        encryption::unlock(passphrase, existing.as_deref())
+            .map_err(|error| format!("Could not open the store: {}", error))
+    };
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_VARIABLE) {
+        return unlock(&passphrase);
+    }
+    let prompt = |text: &str| {
+        rpassword::prompt_password(text).map_err(|error| {
+            format!(
+                "Could not ask for the passphrase ({}), set {} instead",
+                error, PASSPHRASE_VARIABLE
+            )
+        })
+    };
+
+    if !encrypted {
+        eprintln!(
+            "Encryption is on, choose a passphrase for the snippet store. Backups and history \
+             written before stay in the clear until they rotate out."
+        );
+        let passphrase = prompt("New passphrase: ")?;
+        if passphrase.is_empty() {
+            return Err("The passphrase can't be empty".to_string());
+        }
+        if prompt("Repeat it: ")? != passphrase {
+            return Err("The passphrases don't match".to_string());
+        }
+        return unlock(&passphrase);
+    }
+    let mut tries = 1;
+    loop {
+        match unlock(&prompt("Passphrase for the snippet store: ")?) {
+            Ok(()) => return Ok(()),
+            Err(error) if tries == PASSPHRASE_TRIES => return Err(error),
+            Err(error) => {
+                eprintln!("{}", error);
+                tries += 1;
+            }
+        }
+    }
+}
+
+/// The config and whether strict mode is on. Without `--strict` a broken
+/// config file falls back to the defaults, with it that is an error.
+fn load_settings(strict_flag: bool) -> Result<(Config, bool), String> {
+    match load_config() {
+        Ok(config) => {
+            let strict = strict_flag || config.strict;
+            Ok((config, strict))
+        }
+        Err(error) if strict_flag => Err(error),
+        Err(_) => Ok((Config::default(), false)),
+    }
+}
+
+/// What `sniprrr <command>` exits with after `error`: 1 when the snippet asked
+/// for isn't there, 2 for anything else
+fn exit_code(error: &(dyn Error + 'static)) -> i32 {
+    match error.downcast_ref::<LookupError>() {
+        Some(LookupError::NotFound(_)) => 1,
+        _ => 2,
+    }
+}
+
+/// Runs a subcommand. With `quiet` only what was asked for is printed, no
+/// confirmations of what was done.
+fn run_command(
+    command: Command,
+    config: &Config,
+    strict: bool,
+    quiet: bool,
+) -> Result<(), Box<dyn Error>> {
+    let confirm = |message: String| {
+        if !quiet {
+            println!("{}", message);
+        }
+    };
+    match command {
+        Command::Digest(options) => {
+            let messages = integrity::load_or_repair()?;
+            print!("{}", digest::build_digest(&messages, &options));
+        }
+        Command::Doctor(options) if options.terminal => {
+            print!("{}", terminal::report(&terminal::detect(&config.terminal)));
+        }
+        Command::Doctor(options) => {
+            if ipc::is_running() {
+                return Err(
+                    "sniprrr is running in another terminal, close it before running doctor".into(),
+                );
+            }
+
+            let mut messages = integrity::load_or_repair()?;
+            let (changed, unfixed) = doctor::run(&mut messages, options.assume_yes);
+            if changed {
+                write_messages_to_file(&messages)?;
+            }
+            if unfixed > 0 {
+                std::process::exit(1);
+            }
+        }
+        Command::Add(options) => {
+            let body = match options.body_source() {
+                BodySource::Text(body) => body,
+                BodySource::File(path) if path == "-" => io::read_to_string(io::stdin())?,
+                BodySource::File(path) => std::fs::read_to_string(&path)
+                    .map_err(|error| format!("{}: {}", path, error))?,
+            };
+
+            let fields = template::placeholders(&body);
+            let mut snippet = Snippet::new(options.title.clone(), body);
+            snippet.tags = options.tags;
+            snippet.language = options.language.or_else(|| {
+                std::env::current_dir()
+                    .ok()
+                    .and_then(|dir| workspace::detect_language(&dir))
+            });
+            for (name, default) in options.fields {
+                let field = fields
+                    .iter()
+                    .find(|field| field.name == name)
+                    .ok_or_else(|| format!("The body has no {{{{{}}}}} placeholder", name))?;
+                field
+                    .field_type
+                    .validate(&default)
+                    .map_err(|error| format!("Invalid default for {}: {}", name, error))?;
+                snippet.field_defaults.insert(name, default);
+            }
+            snippet.env = options.env.into_iter().collect();
+            snippet.working_dir = options.working_dir;
+
+            let (mut messages, daemon) = open_store("add the snippet")?;
+            if messages
+                .iter()
+                .any(|existing| existing.title == options.title)
+            {
+                return Err(
+                    format!("There already is a snippet titled '{}'", options.title).into(),
+                );
+            }
+            let others = collections::others(&config.collections, messages_file_path().as_deref());
+            let existing = collections::find_body(&snippet.description, &messages, &others);
+            messages.push(snippet);
+            close_store(messages, daemon)?;
+
+            let names: Vec<&str> = fields.iter().map(|field| field.name.as_str()).collect();
+            if names.is_empty() {
+                confirm(format!("Added '{}'", options.title));
+            } else {
+                confirm(format!(
+                    "Added '{}' with the fields {}",
+                    options.title,
+                    names.join(", ")
+                ));
+            }
+            if let Some(existing) = existing {
+                confirm(format!(
+                    "Note: {} already has this body",
+                    existing.describe()
+                ));
+            }
+        }
+        Command::Aliases(options) => {
+            let messages = match ipc::send(&Request::List) {
+                Some(response) => response?.snippets.unwrap_or_default(),
+                None => integrity::load_or_repair()?,
+            };
+            let shell = options.shell();
+            match options.output {
+                Some(path) => {
+                    let count = aliases::write(&path, &messages, shell)?;
+                    confirm(format!("Wrote {} functions to {}", count, path.display()));
+                }
+                None => print!("{}", aliases::render(&messages, shell).0),
+            }
+        }
+        Command::List(options) => {
+            let messages = match ipc::send(&Request::List) {
+                Some(response) => response?.snippets.unwrap_or_default(),
+                None => integrity::load_or_repair()?,
+            };
+            // Ids are positions in the whole store, so number before filtering
+            let listed: Vec<(usize, &Snippet)> = messages
+                .iter()
+                .enumerate()
+                .filter(|(_, snippet)| match &options.tag {
+                    Some(tag) => snippet.tags.contains(tag),
+                    None => true,
+                })
+                .collect();
+            let mut out = io::stdout().lock();
+            let written = if options.json {
+                let snippets: Vec<&Snippet> = listed.iter().map(|(_, snippet)| *snippet).collect();
+                writeln!(out, "{}", serde_json::to_string_pretty(&snippets)?)
+            } else {
+                listed.iter().try_for_each(|(index, snippet)| {
+                    writeln!(
+                        out,
+                        "{}\t{}\t{}",
+                        index + 1,
+                        snippet.title.replace(['\t', '\n'], " "),
+                        snippet.tags.join(",")
+                    )
+                })
+            };
+            // Piped into `head` and the like, which stop reading early
+            match written {
+                Err(error) if error.kind() == io::ErrorKind::BrokenPipe => {}
+                written => written?,
+            }
+        }
+        Command::Remove(options) => {
+            let selector = options.selector();
+            let (mut messages, daemon) = open_store("delete the snippet")?;
+            let index = lookup::find(&messages, &selector)?;
+            let removed = messages.remove(index);
+            close_store(messages, daemon)?;
+            confirm(format!("Deleted '{}'", removed.title));
+        }
+        Command::Apply(options) => {
+            if !options.dry_run && ipc::is_running() {
+                return Err(
+                    "sniprrr is running in another terminal, close it before running apply".into(),
+                );
+            }
+
+            let mut messages = integrity::load_or_repair()?;
+            let (matched, changed) = bulk::apply(&mut messages, &options.filters, &options.edits);
+            if options.dry_run {
+                println!(
+                    "{} of {} matching snippets would change (dry run, nothing was saved)",
+                    changed, matched
+                );
+            } else {
+                if changed > 0 {
+                    write_messages_to_file(&messages)?;
+                }
+                confirm(format!(
+                    "Changed {} of {} matching snippets",
+                    changed, matched
+                ));
+            }
+        }
+        Command::Batch(options) => {
+            if !options.dry_run && ipc::is_running() {
+                return Err(
+                    "sniprrr is running in another terminal, close it before running batch".into(),
+                );
+            }
+
+            let operations = batch::load(&options.script)?;
+            let base = options
+                .script
+                .parent()
+                .unwrap_or_else(|| Path::new(""))
+                .to_path_buf();
+            let mut messages = integrity::load_or_repair()?;
+            let summary = batch::run(&operations, &mut messages, &base)
+                .map_err(|error| format!("{}\nNothing was saved", error))?;
+            if options.dry_run {
+                print!("{}", summary);
+                println!("(dry run, nothing was saved)");
+            } else {
+                let exports = summary.stage_exports()?;
+                if summary.changed() {
+                    write_messages_to_file(&messages)?;
+                }
+                exports.commit()?;
+                print!("{}", summary);
+            }
+        }
+        Command::Restore(options) => {
+            let store_path = messages_file_path().ok_or("No config directory")?;
+            let states = history::states(&store_path);
+            let at = match options.at {
+                Some(at) => at,
+                None => {
+                    for state in &states {
+                        println!(
+                            "until {}  {:>4} snippets  {}",
+                            time_utils::format_datetime(state.until),
+                            state.snippets.len(),
+                            state.path.display()
+                        );
+                    }
+                    if states.is_empty() {
+                        println!("No earlier states yet, they are kept as snippets are saved");
+                    }
+                    return Ok(());
+                }
+            };
+
+            if ipc::is_running() {
+                return Err(
+                    "sniprrr is running in another terminal, close it before running restore"
+                        .into(),
+                );
+            }
+
+            let state =
+                history::state_at(&states, at + time_utils::SECONDS_PER_DAY).ok_or_else(|| {
+                    format!(
+                        "The store has not changed since {}",
+                        time_utils::format_date(at)
+                    )
+                })?;
+            match options.snippet {
+                None => {
+                    write_messages_to_file(&state.snippets)?;
+                    confirm(format!(
+                        "Restored {} snippets from {}. The replaced store is the newest backup.",
+                        state.snippets.len(),
+                        time_utils::format_datetime(state.until)
+                    ));
+                }
+                Some(title) => {
+                    let snippet = state
+                        .snippets
+                        .iter()
+                        .find(|snippet| snippet.title == title)
+                        .ok_or_else(|| {
+                            LookupError::NotFound(format!(
+                                "There was no snippet titled '{}' on {}",
+                                title,
+                                time_utils::format_date(at)
+                            ))
+                        })?;
+                    let mut messages = integrity::load_or_repair()?;
+                    history::restore_snippet(&mut messages, snippet.clone());
+                    write_messages_to_file(&messages)?;
+                    confirm(format!("Restored '{}'", title));
+                }
+            }
+        }
+        Command::Copy(options) => {
+            let selector = options.selector();
+            // Let the running instance do it so only one process writes the store
+            if let Some(response) = ipc::send(&Request::List) {
+                let snippets = response?.snippets.unwrap_or_default();
+                let index = lookup::find(&snippets, &selector)?;
+                let (text, parameters) = copy_text(&snippets[index], config.copy_field)?;
+                let response = ipc::request(&Request::Copy {
+                    title: snippets[index].title.clone(),
+                    text: Some(text),
+                    parameters,
+                })?;
+                if !response.ok {
+                    return Err(response.message.into());
+                }
+                confirm(response.message);
+                return Ok(());
+            }
+
+            let mut messages = integrity::load_or_repair()?;
+            let index = lookup::find(&messages, &selector)?;
+            let (text, parameters) = copy_text(&messages[index], config.copy_field)?;
+
+            let copy_router = CopyRouter::from_config(config);
+            let target = copy_router.target_for(&messages[index]);
+            target.send(&text)?;
+            messages[index].remember_parameters(&parameters);
+            messages[index].mark_used();
+            write_messages_to_file(&messages)?;
+            confirm(format!(
+                "Copied '{}' to {}",
+                messages[index].title, target.name
+            ));
+        }
+        Command::Daemon => daemon::run(config, strict)?,
+        Command::DebugBundle { output } => {
+            let path = debug_bundle::run(output)?;
+            confirm(format!(
+                "Wrote {}, check it before attaching it to a bug report",
+                path.display()
+            ));
+        }
+        Command::Import(_) | Command::Tutorial => unreachable!("opens the UI, see main"),
+        Command::Sync => {
+            if !config.sync.enabled() {
+                return Err("Set remote under [sync] in the config to sync".into());
+            }
+            if ipc::is_running() {
+                return Err("sniprrr is running in another terminal, it syncs once closed".into());
+            }
+            let store = messages_file_path().ok_or("No config directory for the store")?;
+            confirm(sync::pull(&config.sync, &store)?);
+            if sync::push(&config.sync, &store)? {
+                confirm("Pushed the store".to_string());
+            }
+        }
+        Command::Open {
+            target: OpenTarget::Config,
+        } => {
+            let dir = open::config_dir().ok_or("No config directory")?;
+            open::reveal(&dir)?;
+            confirm(format!("Opened {}", dir.display()));
+        }
+        Command::Open {
+            target: OpenTarget::Store,
+        } => {
+            // Either would write its own snippets over the edits
+            if ipc::daemon_is_running() {
+                return Err("The daemon keeps the store, stop it to edit the file by hand".into());
+            }
+            if ipc::is_running() {
+                return Err(
+                    "sniprrr is running in another terminal, press gs there or close it".into(),
+                );
+            }
+            let store = messages_file_path().ok_or("No config directory for the store")?;
+            open::check_editable(&store)?;
+            open::edit(&store, Stdio::inherit())?;
+            let snippets = file_utils::load_store(&store).map_err(|error| {
+                format!(
+                    "The edited store can't be read: {}. Fix it with `sniprrr open store` or run sniprrr doctor",
+                    error
+                )
+            })?;
+            confirm(format!("The store holds {} snippets", snippets.len()));
+        }
+        Command::Export(options) => {
+            let messages = match ipc::send(&Request::List) {
+                Some(response) => response?.snippets.unwrap_or_default(),
+                None => integrity::load_or_repair()?,
+            };
+            let snippets: Vec<Snippet> = messages
+                .into_iter()
+                .filter(|snippet| match &options.tag {
+                    Some(tag) => snippet.tags.contains(tag),
+                    None => true,
+                })
+                .collect();
+            match &options.output {
+                Some(path) => {
+                    formats::write(path, &snippets, options.format())?;
+                    confirm(format!(
+                        "Exported {} snippets to {}",
+                        snippets.len(),
+                        path.display()
+                    ));
+                }
+                None => {
+                    let text = formats::render(&snippets, options.format())?;
+                    // Piped into `head` and the like, which stop reading early
+                    match writeln!(io::stdout().lock(), "{}", text.trim_end()) {
+                        Err(error) if error.kind() == io::ErrorKind::BrokenPipe => {}
+                        written => written?,
+                    }
+                }
+            }
+        }
+        Command::Bench(options) => {
+            let snippets = bench::generate(options.generate, bench::SEED);
+            write_store(&options.output, &snippets)?;
+            confirm(format!(
+                "Wrote {} snippets to {}",
+                snippets.len(),
+                options.output.display()
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Loads the snippets for a command that changes them, from the daemon when
+/// there is one so it keeps the only copy of the store. Returns the revision
+/// of the daemon's store they are at when it was.
+fn open_store(action: &str) -> Result<(Vec<Snippet>, Option<u64>), Box<dyn Error>> {
+    let daemon = ipc::daemon_is_running();
+    if !daemon && ipc::is_running() {
+        return Err(format!(
+            "sniprrr is running in another terminal, {} there or close it",
+            action
+        )
+        .into());
+    }
+    if daemon {
+        let response = ipc::request(&Request::List)?;
+        let revision = response.revision.unwrap_or_default();
+        return Ok((response.snippets.unwrap_or_default(), Some(revision)));
+    }
+    Ok((integrity::load_or_repair()?, None))
+}
+
+/// Saves the snippets loaded with `open_store`
+fn close_store(messages: Vec<Snippet>, revision: Option<u64>) -> io::Result<()> {
+    match revision {
+        Some(base) => ipc::request(&Request::Save {
+            snippets: messages,
+            base: Some(base),
+        })
+        .map(|_| ()),
+        None => write_messages_to_file(&messages),
+    }
+}
+
+fn run_app<B: Backend + Write>(
+    terminal: &mut Terminal<B>,
+    app_state: &mut AppState,
+    requests: Option<Receiver<ipc::Incoming>>,
+) -> io::Result<()> {
+    let mut config_watcher = ConfigWatcher::new();
+    let mut window_title = String::new();
+    loop {
+        match config_watcher.poll() {
+            Some(Ok(config)) => {
+                app_state.apply_config(&config);
+                app_state.notifications.info("Reloaded config.toml");
+            }
+            Some(Err(error)) => app_state.report_error(format!(
+                "config.toml has errors, keeping the previous settings: {}",
+                error
+            )),
+            None => {}
+        }
+
+        if let Some(tutorial) = &mut app_state.tutorial {
+            let search = match app_state.input_mode {
+                InputMode::Normal => app_state.filter.as_str(),
+                _ => "",
+            };
+            tutorial.check(&app_state.messages, search);
+        }
+        terminal.draw(|f| ui(f, app_state))?;
+        if !app_state.print {
+            title::update(&mut window_title, app_state.window_title())?;
+        }
+
+        if let Some(requests) = &requests {
+            while let Ok(incoming) = requests.try_recv() {
+                let response = handle_request(app_state, incoming.request)?;
+                let _ = incoming.reply.send(response);
+            }
+        }
+
+        // Wake up regularly so requests from other instances get answered
+        if !event::poll(Duration::from_millis(200))? {
+            continue;
+        }
+
+        let event = event::read()?;
+        // Bracketed paste delivers the whole block at once instead of a key per character
+        if let Event::Paste(text) = &event {
+            if app_state.popup.is_none() {
+                app_state.insert_pasted(text);
+            }
+            continue;
+        }
+
+        if let Event::Key(key) = event {
+            app_state.notifications.key_pressed();
+
+            if let Some(popup) = app_state.popup.as_mut() {
+                if key.kind == KeyEventKind::Press {
+                    match popup.handle_key(key.code) {
+                        PopupOutcome::Keep => {}
+                        PopupOutcome::Close => app_state.popup = None,
+                        PopupOutcome::Open(next) => app_state.popup = Some(*next),
+                        PopupOutcome::Submit(InputPurpose::Schedule { index }, value) => {
+                            app_state.popup = None;
+                            let value = value.trim();
+                            match schedule::validate(value) {
+                                Ok(_) => {
+                                    let title = &app_state.messages[index].title;
+                                    app_state.record_undo(format!("the schedule of '{}'", title));
+                                    app_state.messages[index].schedule =
+                                        (!value.is_empty()).then(|| value.to_string());
+                                    app_state.save();
+                                }
+                                Err(error) => {
+                                    app_state.report_error(format!("Invalid schedule: {}", error))
+                                }
+                            }
+                        }
+                        PopupOutcome::Submit(InputPurpose::Generate { index }, value) => {
+                            app_state.popup = None;
+                            let snippet = index.map(|index| &app_state.messages[index]);
+                            match generate::generate(&app_state.generate, &value, snippet) {
+                                Ok(body) => {
+                                    // Only drafted, saving is up to the user
+                                    app_state.description_input.insert_str(&body);
+                                    app_state.focused_input_index = INPUT_DESCRIPTION_INDEX;
+                                    app_state.input_mode = InputMode::Editing;
+                                    app_state
+                                        .notifications
+                                        .info("Generated into the form, check it before saving");
+                                }
+                                Err(error) => app_state.report_error(error),
+                            }
+                        }
+                        PopupOutcome::Submit(InputPurpose::FindReplace, value) => {
+                            match replace::Substitution::parse(&value) {
+                                Ok(substitution) => {
+                                    let found = replace::find(&app_state.messages, &substitution);
+                                    app_state.popup = match found.is_empty() {
+                                        true => {
+                                            app_state
+                                                .notifications
+                                                .info(format!("Nothing matches {}", value.trim()));
+                                            None
+                                        }
+                                        false => Some(Popup::Replace(ReplacePopup::new(
+                                            found,
+                                            &app_state.messages,
+                                        ))),
+                                    };
+                                }
+                                Err(error) => app_state.report_error(error),
+                            }
+                        }
+                        PopupOutcome::Replace { edits, replaced } => {
+                            app_state.popup = None;
+                            let indices: BTreeSet<usize> =
+                                edits.iter().map(|(index, _, _)| *index).collect();
+                            app_state
+                                .record_undo(format!("replacing in {} snippets", indices.len()));
+                            let now = time_utils::now();
+                            for (index, variant, body) in edits {
+                                let snippet = &mut app_state.messages[index];
+                                match variant {
+                                    Some(name) => {
+                                        snippet.variants.insert(name, body);
+                                    }
+                                    None => snippet.description = body,
+                                }
+                                snippet.updated_at = Some(now);
+                            }
+                            app_state.save();
+                            app_state.notifications.info(format!(
+                                "Replaced {} matches in {} snippets",
+                                replaced,
+                                indices.len()
+                            ));
+                        }
+                        PopupOutcome::Submit(InputPurpose::Tags { index }, value) => {
+                            app_state.popup = None;
+                            let title = &app_state.messages[index].title;
+                            app_state.record_undo(format!("the tags of '{}'", title));
+                            app_state.messages[index].tags = parse_tags(&value);
+                            app_state.save();
+                        }
+                        PopupOutcome::Submit(InputPurpose::RunEnvironment { index }, value) => {
+                            match run::parse_declaration(&value) {
+                                Ok((working_dir, env)) => {
+                                    app_state.popup = None;
+                                    let title = &app_state.messages[index].title;
+                                    app_state
+                                        .record_undo(format!("the run environment of '{}'", title));
+                                    app_state.messages[index].working_dir = working_dir;
+                                    app_state.messages[index].env = env;
+                                    app_state.save();
+                                }
+                                Err(error) => app_state.report_error(error),
+                            }
+                        }
+                        PopupOutcome::Run { index, environment } => {
+                            app_state.popup = None;
+                            app_state.run_snippet(index, &environment);
+                        }
+                        PopupOutcome::Execute {
+                            index,
+                            command,
+                            environment,
+                            parameters,
+                        } => {
+                            app_state.popup = None;
+                            let title = app_state.messages[index].title.clone();
+                            match execute_in_terminal(
+                                terminal,
+                                &command,
+                                &environment,
+                                app_state.print,
+                            )? {
+                                Ok(status) => app_state
+                                    .notifications
+                                    .info(format!("Ran '{}': {}", title, status)),
+                                Err(error) => {
+                                    app_state.report_error(format!("Could not run: {}", error))
+                                }
+                            }
+                            if !parameters.is_empty() {
+                                app_state.messages[index].remember_parameters(&parameters);
+                                app_state.save();
+                            }
+                        }
+                        PopupOutcome::Label { index, color, icon } => {
+                            app_state.popup = None;
+                            let title = &app_state.messages[index].title;
+                            app_state.record_undo(format!("the label of '{}'", title));
+                            app_state.messages[index].color = color;
+                            app_state.messages[index].icon = icon;
+                            app_state.save();
+                        }
+                        PopupOutcome::Storage(StorageAction::Compact) => {
+                            app_state.save();
+                            app_state
+                                .notifications
+                                .info("Rewrote the store from the snippets shown");
+                            app_state.open_storage();
+                        }
+                        PopupOutcome::Storage(StorageAction::BackUp) => {
+                            match messages_file_path().map(|store_path| rotate_backups(&store_path))
+                            {
+                                Some(Ok(())) => {
+                                    app_state.notifications.info("Backed up the store");
+                                    app_state.open_storage();
+                                }
+                                Some(Err(error)) => app_state.report_error(format!(
+                                    "Could not back up the store: {}",
+                                    error
+                                )),
+                                None => app_state.report_error(
+                                    "There is no config directory to keep the store in".to_string(),
+                                ),
+                            }
+                        }
+                        PopupOutcome::Storage(StorageAction::Export) => app_state.open_export(),
+                        PopupOutcome::Submit(InputPurpose::Export, value) => {
+                            app_state.popup = None;
+                            let path = value.trim();
+                            let count = app_state.messages.len();
+                            let written = if let Some(shell) = aliases::Shell::for_path(path) {
+                                aliases::write(
+                                    &import::expand_home(path),
+                                    &app_state.messages,
+                                    shell,
+                                )
+                            } else {
+                                let path = import::expand_home(path);
+                                let format = Format::for_path(&path).unwrap_or(Format::Json);
+                                formats::write(&path, &app_state.messages, format).map(|_| count)
+                            };
+                            match written {
+                                Ok(count) => app_state
+                                    .notifications
+                                    .info(format!("Exported {} snippets to {}", count, path)),
+                                Err(error) => {
+                                    app_state.report_error(format!("Could not export: {}", error))
+                                }
+                            }
+                        }
+                        PopupOutcome::Submit(InputPurpose::Import, value) => {
+                            app_state.popup = None;
+                            let path = value.trim();
+                            let format = Format::for_path(&import::expand_home(path));
+                            if format == Some(Format::Csv) {
+                                match csv_io::read_table(&import::expand_home(path)) {
+                                    Ok(table) if table.headers.is_empty() => app_state
+                                        .notifications
+                                        .info(format!("There are no columns in {}", path)),
+                                    Ok(table) => {
+                                        app_state.popup =
+                                            Some(Popup::Columns(ColumnsPopup::new(path, table)))
+                                    }
+                                    Err(error) => app_state
+                                        .report_error(format!("Could not import: {}", error)),
+                                }
+                            } else {
+                                let format = format.unwrap_or(Format::Json);
+                                match formats::read(&import::expand_home(path), format) {
+                                    Ok(incoming) => app_state.review_import(path, incoming),
+                                    Err(error) => app_state
+                                        .report_error(format!("Could not import: {}", error)),
+                                }
+                            }
+                        }
+                        PopupOutcome::Review { source, incoming } => {
+                            app_state.popup = None;
+                            app_state.review_import(&source, incoming);
+                        }
+                        PopupOutcome::Delete(index) => {
+                            app_state.popup = None;
+                            app_state.delete(index);
+                        }
+                        PopupOutcome::AddDuplicate => {
+                            app_state.popup = None;
+                            app_state.add_form_snippet();
+                        }
+                        PopupOutcome::Jump(index) => {
+                            // The draft stays in the form for another e
+                            app_state.popup = None;
+                            app_state.input_mode = InputMode::Normal;
+                            app_state.filter.clear();
+                            app_state.tag_filter = None;
+                            app_state.folder = None;
+                            app_state.refilter();
+                            app_state.select_index(index);
+                        }
+                        PopupOutcome::Add(snippet) => {
+                            app_state.popup = None;
+                            app_state.record_undo(format!("adding '{}'", snippet.title));
+                            app_state
+                                .notifications
+                                .info(format!("Added '{}'", snippet.title));
+                            app_state.messages.push(snippet);
+                            app_state.refilter();
+                            app_state.select_index(app_state.messages.len() - 1);
+                            app_state.save();
+                        }
+                        PopupOutcome::Import(incoming) => {
+                            app_state.popup = None;
+                            if !incoming.is_empty() {
+                                app_state.record_undo("the import".to_string());
+                                let (added, replaced) =
+                                    import::merge(&mut app_state.messages, incoming);
+                                app_state.marked.clear();
+                                app_state.notifications.info(format!(
+                                    "Imported {} new snippets, replaced {}",
+                                    added, replaced
+                                ));
+                                app_state.save();
+                            }
+                        }
+                        PopupOutcome::Restore(snippet) => {
+                            app_state.popup = None;
+                            let title = snippet.title.clone();
+                            app_state.record_undo(format!("restoring '{}'", title));
+                            let replaced =
+                                history::restore_snippet(&mut app_state.messages, snippet);
+                            app_state.refilter();
+                            if let Some(index) =
+                                app_state.messages.iter().position(|s| s.title == title)
+                            {
+                                app_state.select_index(index);
+                            }
+                            app_state.notifications.info(if replaced {
+                                format!("Restored the earlier version of '{}'", title)
+                            } else {
+                                format!("Restored '{}'", title)
+                            });
+                            app_state.save();
+                        }
+                        PopupOutcome::Merge {
+                            keep,
+                            remove,
+                            merged,
+                        } => {
+                            app_state.record_undo(format!("merging into '{}'", merged.title));
+                            app_state.messages[keep] = merged;
+                            app_state.messages.remove(remove);
+                            app_state.refilter();
+                            app_state.marked.clear();
+                            app_state.select_index(keep);
+                            app_state.popup = None;
+
+                            app_state.save();
+                        }
+                        PopupOutcome::Trust {
+                            index,
+                            fingerprint,
+                            next,
+                        } => {
+                            app_state.popup = Some(*next);
+                            app_state.messages[index].trusted_commands = Some(fingerprint);
+                            app_state.save();
+                        }
+                        PopupOutcome::Reviewed { index, accepted } => {
+                            app_state.popup = None;
+                            if accepted {
+                                app_state.messages[index].needs_review = false;
+                            } else {
+                                let title = &app_state.messages[index].title;
+                                app_state.record_undo(format!("rejecting '{}'", title));
+                                app_state.messages.remove(index);
+                                app_state.marked.clear();
+                                app_state.clamp_selection();
+                            }
+                            app_state.save();
+                            if app_state
+                                .messages
+                                .iter()
+                                .any(|s| s.needs_review && s.source.is_none())
+                            {
+                                app_state.open_review(None);
+                            }
+                        }
+                        PopupOutcome::TagFilter(tag) => {
+                            app_state.popup = None;
+                            app_state.set_tag_filter(tag);
+                        }
+                        PopupOutcome::Transfer {
+                            indices,
+                            name,
+                            path,
+                            moving,
+                        } => {
+                            app_state.popup = None;
+                            app_state.transfer(indices, &name, &path, moving);
+                        }
+                        PopupOutcome::Paste { title, description } => {
+                            app_state.popup = None;
+                            app_state.title_input.insert_str(&title);
+                            if let Some(description) = description {
+                                app_state.description_input.set_text(description);
+                            }
+                        }
+                        PopupOutcome::Variant { index, variant } => {
+                            app_state.popup = None;
+                            if start_copy(app_state, index, variant.as_deref())
+                                && app_state.quit_after_copy
+                            {
+                                return Ok(());
+                            }
+                        }
+                        PopupOutcome::CopyAs { index, flavor } => {
+                            app_state.popup = None;
+                            let text = app_state.messages[index].description.clone();
+                            if copy_snippet_as(app_state, index, &text, flavor)
+                                && app_state.quit_after_copy
+                            {
+                                return Ok(());
+                            }
+                        }
+                        PopupOutcome::CopyTransformed { index, transforms } => {
+                            app_state.popup = None;
+                            let text = app_state.messages[index].description.clone();
+                            if copy_snippet_transformed(app_state, index, &text, transforms)
+                                && app_state.quit_after_copy
+                            {
+                                return Ok(());
+                            }
+                        }
+                        PopupOutcome::Copy {
+                            index,
+                            text,
+                            parameters,
+                        } => {
+                            app_state.popup = None;
+                            app_state.messages[index].remember_parameters(&parameters);
+                            if copy_snippet(app_state, index, &text) && app_state.quit_after_copy {
+                                return Ok(());
+                            }
+                        }
+                    }
+                }
+                continue;
+            }
 
-        if let Event::Key(key) = event::read()? {
             match app_state.input_mode {
-                InputMode::Normal => match key.code {
-                    KeyCode::Char('e') => {
-                        app_state.focused_input_index = INPUT_TITLE_INDEX;
-                        app_state.input_mode = InputMode::Editing;
+                InputMode::Normal if key.kind == KeyEventKind::Press => {
+                    if app_state.sidebar_focus && app_state.sidebar_key(Key::from(key)) {
+                        continue;
                     }
-                    KeyCode::Delete | KeyCode::Backspace => {
-                        let selected = app_state.table_state.selected();
-                        if let Some(selected) = selected {
-                            app_state.messages.remove(selected);
-
-                            let json_string =
-                                serde_json::to_string::<Vec<Snippet>>(&app_state.messages).unwrap();
-                            write_messages_to_file(&json_string)?
+                    let (action, count) =
+                        match app_state.keymap.feed(&mut app_state.chord, Key::from(key)) {
+                            Dispatch::Run(action, count) => (action, count),
+                            Dispatch::Pending | Dispatch::Unbound => continue,
+                        };
+                    let given_count = (count > 1).then_some(count);
+                    app_state.quit_after_copy = action == Action::CopyAndQuit || app_state.print;
+                    app_state.copy_field = match action {
+                        Action::Copy | Action::CopyAndQuit => app_state.default_copy_field,
+                        Action::CopyTitle => CopyField::Title,
+                        Action::CopyBoth => CopyField::Both,
+                        _ => CopyField::Body,
+                    };
+                    let quit_pending = std::mem::take(&mut app_state.quit_pending);
+                    if action.changes_snippets() {
+                        if let Some((title, source)) = app_state.read_only_target() {
+                            app_state.notifications.info(format!(
+                                "'{}' is read from the source {}, change it there",
+                                title, source
+                            ));
+                            continue;
                         }
                     }
-                    KeyCode::Char('c') => {
-                        match Clipboard::new() {
-                            Ok(mut clipboard) => {
-                                let selected_snippet = get_selected_snippet(&app_state);
-                                if selected_snippet.is_none() {
-                                    return Ok(());
+                    match action {
+                        Action::Edit => {
+                            app_state.focused_input_index = INPUT_TITLE_INDEX;
+                            app_state.input_mode = InputMode::Editing;
+                        }
+                        Action::Delete => {
+                            let selected = app_state.selected_index();
+                            if let Some(selected) = selected {
+                                if app_state.confirm_delete {
+                                    let title = &app_state.messages[selected].title;
+                                    app_state.popup =
+                                        Some(Popup::Delete(DeletePopup::new(selected, title)));
+                                } else {
+                                    app_state.delete(selected);
                                 }
+                            }
+                        }
+                        Action::Copy
+                        | Action::CopyAndQuit
+                        | Action::CopyTitle
+                        | Action::CopyBoth => {
+                            let selected = match app_state.selected_index() {
+                                Some(selected) => selected,
+                                None => continue,
+                            };
 
-                                let selected_snippet = selected_snippet.unwrap();
-
-                                match clipboard.set_text(&selected_snippet.description) {
-                                    Ok(_) => return Ok(()),
-                                    Err(_error) => {
-                                        // TODO: handle copy error? - output to console instead
-                                        // println!("{}", error)
+                            let snippet = &app_state.messages[selected];
+                            let copied = if snippet.variants.is_empty()
+                                || app_state.copy_field == CopyField::Title
+                            {
+                                start_copy(app_state, selected, None)
+                            } else if let Some(variant) =
+                                variants::for_this_system(snippet).map(str::to_string)
+                            {
+                                start_copy(app_state, selected, Some(&variant))
+                            } else {
+                                app_state.popup =
+                                    Some(Popup::Variant(VariantPopup::new(selected, snippet)));
+                                false
+                            };
+                            if copied && app_state.quit_after_copy {
+                                return Ok(());
+                            }
+                        }
+                        Action::CopyAs => {
+                            if let Some(selected) = app_state.selected_index() {
+                                let snippet = &app_state.messages[selected];
+                                if snippet.binary
+                                    || !template::placeholders(&snippet.description).is_empty()
+                                    || template::has_expansions(&snippet.description)
+                                {
+                                    app_state.notifications.info(
+                                        "Templates and base64 snippets can only be copied with c",
+                                    );
+                                } else {
+                                    app_state.popup = Some(Popup::Flavor(FlavorPopup::new(
+                                        selected,
+                                        &snippet.title,
+                                    )));
+                                }
+                            }
+                        }
+                        Action::PreviewTransforms => {
+                            if let Some(selected) = app_state.selected_index() {
+                                let snippet = &app_state.messages[selected];
+                                let target = app_state.copy_router.target_for(snippet);
+                                let copyable = !snippet.binary
+                                    && template::placeholders(&snippet.description).is_empty()
+                                    && !template::has_expansions(&snippet.description);
+                                app_state.popup = Some(Popup::Transforms(TransformPopup::new(
+                                    selected,
+                                    snippet,
+                                    &target.name,
+                                    &target.transforms,
+                                    copyable,
+                                )));
+                            }
+                        }
+                        Action::CopyDecoded => {
+                            if let Some(selected) = app_state.selected_index() {
+                                let snippet = &app_state.messages[selected];
+                                if !snippet.binary {
+                                    app_state
+                                        .notifications
+                                        .info("Only base64 snippets can be copied decoded");
+                                } else {
+                                    match binary::decode_to_text(&snippet.description) {
+                                        Ok(text) => {
+                                            copy_snippet(app_state, selected, &text);
+                                        }
+                                        Err(error) => app_state
+                                            .report_error(format!("Can't copy decoded: {}", error)),
                                     }
                                 }
                             }
-                            Err(error) => {
-                                // TODO: Output to console
-                                println!("{}", error)
+                        }
+                        Action::Generate => {
+                            if !app_state.generate.enabled() {
+                                app_state.notifications.info(
+                                    "Generating needs an endpoint set with `[generate] endpoint`",
+                                );
+                                continue;
                             }
-                        };
+                            let selected = app_state.selected_index();
+                            let hint = match selected {
+                                Some(selected) => format!(
+                                    "'{}' is sent along, the answer goes into the form",
+                                    app_state.messages[selected].title
+                                ),
+                                None => "The answer goes into the form".to_string(),
+                            };
+                            app_state.popup = Some(Popup::Input(InputPopup::new(
+                                "Generate",
+                                &hint,
+                                InputPurpose::Generate { index: selected },
+                                String::new(),
+                            )));
+                        }
+                        Action::Share => {
+                            if let Some(selected) = app_state.selected_index() {
+                                let snippet = &app_state.messages[selected];
+                                match share::upload(&app_state.share, &snippet.description) {
+                                    Ok(link) => {
+                                        let target = app_state.copy_router.target_for(snippet);
+                                        let message = match target.send(&link) {
+                                            Ok(_) => format!(
+                                                "Shared '{}', copied {} to {}",
+                                                snippet.title, link, target.name
+                                            ),
+                                            Err(error) => format!(
+                                                "Shared '{}' as {} but could not copy it: {}",
+                                                snippet.title, link, error
+                                            ),
+                                        };
+                                        app_state.notifications.info(message);
+                                    }
+                                    Err(error) => app_state
+                                        .report_error(format!("Could not share: {}", error)),
+                                }
+                            }
+                        }
+                        Action::Run => {
+                            if let Some(selected) = app_state.selected_index() {
+                                let snippet = &app_state.messages[selected];
+                                if let Some(source) = &snippet.source {
+                                    app_state.notifications.info(format!(
+                                        "'{}' is read from the source {}, snippets of sources don't run",
+                                        snippet.title, source
+                                    ));
+                                } else if snippet.needs_review {
+                                    app_state.notifications.info(
+                                        "Imported snippets run once accepted, review them with R",
+                                    );
+                                } else if !run::is_runnable(snippet) {
+                                    app_state
+                                        .notifications
+                                        .info("Only shell snippets can be run, see the language");
+                                } else if !template::placeholders(&snippet.description).is_empty() {
+                                    app_state.notifications.info(
+                                        "Templates can't be run, copy and fill them in instead",
+                                    );
+                                } else if run::has_environment(snippet) {
+                                    match run::working_dir(snippet) {
+                                        Ok(dir) => {
+                                            app_state.popup = Some(Popup::Run(RunPopup::new(
+                                                selected, snippet, dir,
+                                            )))
+                                        }
+                                        Err(error) => app_state.report_error(error),
+                                    }
+                                } else {
+                                    app_state.run_snippet(selected, &run::Environment::default());
+                                }
+                            }
+                        }
+                        Action::Execute => {
+                            if let Some(selected) = app_state.selected_index() {
+                                let snippet = &app_state.messages[selected];
+                                if let Some(source) = &snippet.source {
+                                    app_state.notifications.info(format!(
+                                        "'{}' is read from the source {}, snippets of sources don't run",
+                                        snippet.title, source
+                                    ));
+                                } else if snippet.needs_review {
+                                    app_state.notifications.info(
+                                        "Imported snippets run once accepted, review them with R",
+                                    );
+                                } else if !run::is_runnable(snippet) {
+                                    app_state
+                                        .notifications
+                                        .info("Only shell snippets can be run, see the language");
+                                } else if template::has_expansions(&snippet.description) {
+                                    app_state.notifications.info(
+                                        "Templates running commands can't be run, copy them instead",
+                                    );
+                                } else {
+                                    match run::working_dir(snippet) {
+                                        Ok(dir) => {
+                                            let run =
+                                                RunPopup::new(selected, snippet, dir).in_terminal();
+                                            let fields =
+                                                template::placeholders(&snippet.description);
+                                            let popup = match fields.is_empty() {
+                                                true => Popup::Run(run),
+                                                false => Popup::Fill(
+                                                    FillPopup::new(selected, snippet, fields)
+                                                        .then_run(run),
+                                                ),
+                                            };
+                                            app_state.popup = Some(popup);
+                                        }
+                                        Err(error) => app_state.report_error(error),
+                                    }
+                                }
+                            }
+                        }
+                        Action::ToggleBase64 => {
+                            if let Some(selected) = app_state.selected_index() {
+                                let snippet = &app_state.messages[selected];
+                                // Converted first, so a body that isn't valid
+                                // base64 leaves no undo step and no save behind
+                                let description = match snippet.binary {
+                                    true => match binary::decode_to_text(&snippet.description) {
+                                        Ok(text) => text,
+                                        Err(error) => {
+                                            app_state.report_error(format!(
+                                                "Can't store as text: {}",
+                                                error
+                                            ));
+                                            continue;
+                                        }
+                                    },
+                                    false => base64::encode(snippet.description.as_bytes()),
+                                };
+                                let title = snippet.title.clone();
+                                app_state.record_undo(format!("converting '{}'", title));
+                                let snippet = &mut app_state.messages[selected];
+                                snippet.description = description;
+                                snippet.binary = !snippet.binary;
+                                app_state.save();
+                            }
+                        }
+                        Action::Schedule => {
+                            if let Some(selected) = app_state.selected_index() {
+                                let current = app_state.messages[selected]
+                                    .schedule
+                                    .clone()
+                                    .unwrap_or_default();
+                                app_state.popup = Some(Popup::Input(InputPopup::new(
+                                "Suggest this snippet during",
+                                "Time windows like 19:00-20:00, separated by commas. Empty to clear.",
+                                InputPurpose::Schedule { index: selected },
+                                current,
+                            )));
+                            }
+                        }
+                        Action::EditTags => {
+                            if let Some(selected) = app_state.selected_index() {
+                                let current = app_state.messages[selected].tags.join(", ");
+                                app_state.popup = Some(Popup::Input(InputPopup::new(
+                                "Tags",
+                                "Separated by commas. Tags with a copy route send the snippet to that target.",
+                                InputPurpose::Tags { index: selected },
+                                current,
+                            )));
+                            }
+                        }
+                        Action::EditRunEnvironment => {
+                            if let Some(selected) = app_state.selected_index() {
+                                let current = run::declaration(&app_state.messages[selected]);
+                                app_state.popup = Some(Popup::Input(InputPopup::new(
+                                "Run environment",
+                                "The working directory and the variables a run needs, like ~/src/app AWS_PROFILE PORT=8080 for a default. Empty for none.",
+                                InputPurpose::RunEnvironment { index: selected },
+                                current,
+                            )));
+                            }
+                        }
+                        Action::Label => {
+                            if let Some(selected) = app_state.selected_index() {
+                                app_state.popup = Some(Popup::Label(LabelPopup::new(
+                                    selected,
+                                    &app_state.messages[selected],
+                                )));
+                            }
+                        }
+                        Action::NextScheduled => app_state.select_next_scheduled(),
+                        Action::Search => app_state.input_mode = InputMode::Search,
+                        Action::FilterTag => {
+                            app_state.popup = Some(Popup::TagFilter(TagFilterPopup::new(
+                                &app_state.messages,
+                                app_state.tag_filter.as_deref(),
+                            )));
+                        }
+                        Action::FocusSidebar => match app_state.folders().is_empty() {
+                            true => app_state.notifications.info(
+                                "The sidebar lists namespaces, give titles one like docker/prune"
+                                    .to_string(),
+                            ),
+                            false => app_state.sidebar_focus = true,
+                        },
+                        Action::MoveToCollection | Action::CopyToCollection => {
+                            let indices: Vec<usize> = match app_state.marked.is_empty() {
+                                true => app_state.selected_index().into_iter().collect(),
+                                false => app_state.marked.iter().copied().collect(),
+                            };
+                            let others = collections::others(
+                                &app_state.collections,
+                                messages_file_path().as_deref(),
+                            );
+                            let moving = action == Action::MoveToCollection;
+                            if !indices.is_empty() {
+                                match CollectionPopup::new(indices, others, moving) {
+                                    Some(popup) => app_state.popup = Some(Popup::Collection(popup)),
+                                    None => app_state.notifications.info(
+                                        "List other stores under [collections] in the config \
+                                            to move snippets there"
+                                            .to_string(),
+                                    ),
+                                }
+                            }
+                        }
+                        Action::ClearFilter => {
+                            let selected = app_state.selected_index();
+                            app_state.filter.clear();
+                            app_state.tag_filter = None;
+                            app_state.folder = None;
+                            app_state.refilter();
+                            if let Some(selected) = selected {
+                                app_state.select_index(selected);
+                            }
+                        }
+                        Action::CycleTarget => {
+                            let target = app_state.copy_router.cycle();
+                            app_state.notifications.info(format!(
+                                "Copying to {} (unless a tag routes elsewhere)",
+                                target.name
+                            ));
+                        }
+                        Action::Mark => {
+                            if let Some(selected) = app_state.selected_index() {
+                                if !app_state.marked.remove(&selected) {
+                                    app_state.marked.insert(selected);
+                                }
+                            }
+                        }
+                        Action::Diff => {
+                            let marked: Vec<usize> = app_state.marked.iter().copied().collect();
+                            match marked.as_slice() {
+                                [left, right] => {
+                                    let left = app_state.messages[*left].clone();
+                                    let right = app_state.messages[*right].clone();
+                                    app_state.popup =
+                                        Some(Popup::Diff(Box::new(DiffPopup::new(left, right))));
+                                }
+                                _ => {
+                                    app_state.notifications.info(
+                                        "Mark exactly two snippets with Space to compare them",
+                                    );
+                                }
+                            }
+                        }
+                        Action::Dedupe => {
+                            app_state.popup =
+                                Some(Popup::Dedupe(DedupePopup::new(&app_state.messages)));
+                        }
+                        Action::Import => {
+                            app_state.popup = Some(Popup::Input(InputPopup::new(
+                                "Import snippets from",
+                                "Path to a sniprrr store, backup or export, or a YAML, TOML or CSV file. The snippets are listed for review first.",
+                                InputPurpose::Import,
+                                String::new(),
+                            )));
+                        }
+                        Action::Export => app_state.open_export(),
+                        Action::FindReplace => {
+                            app_state.popup = Some(Popup::Input(InputPopup::new(
+                                "Find and replace",
+                                "s/<REGEX>/<REPLACEMENT>/ in the bodies of all snippets, i at the end to ignore case, $1 for a group. Every match is shown to accept or skip first.",
+                                InputPurpose::FindReplace,
+                                "s/".to_string(),
+                            )));
+                        }
+                        Action::Storage => app_state.open_storage(),
+                        Action::OpenConfigDir => match open::config_dir() {
+                            Some(dir) => match open::reveal(&dir) {
+                                Ok(()) => app_state
+                                    .notifications
+                                    .info(format!("Opened {}", dir.display())),
+                                Err(error) => app_state.report_error(error),
+                            },
+                            None => {
+                                app_state.report_error("There is no config directory".to_string())
+                            }
+                        },
+                        Action::OpenStore => {
+                            if app_state.daemon_client {
+                                app_state.notifications.info(
+                                    "The daemon keeps the store, stop it to edit the file by hand",
+                                );
+                            } else if app_state.tutorial.is_some() {
+                                app_state
+                                    .notifications
+                                    .info("The tutorial's snippets are a sandbox, not the store");
+                            } else {
+                                match messages_file_path() {
+                                    Some(store) => {
+                                        match edit_store(terminal, &store, app_state.print)? {
+                                            Ok(Some(edited)) => {
+                                                app_state.reload_store(edited);
+                                                app_state.notifications.info(
+                                                    "Reloaded the store, u takes the edit back",
+                                                );
+                                            }
+                                            Ok(None) => app_state
+                                                .notifications
+                                                .info("The store is unchanged"),
+                                            Err(error) => app_state.report_error(error),
+                                        }
+                                    }
+                                    None => app_state.report_error(
+                                        "There is no config directory to keep the store in"
+                                            .to_string(),
+                                    ),
+                                }
+                            }
+                        }
+                        Action::CycleSort => app_state.set_sort(app_state.sort.next()),
+                        Action::ToggleMostUsed => match app_state.sort {
+                            SortOrder::MostUsed => {
+                                app_state.set_sort(app_state.sort_before_most_used)
+                            }
+                            sort => {
+                                app_state.sort_before_most_used = sort;
+                                app_state.set_sort(SortOrder::MostUsed);
+                            }
+                        },
+                        Action::Review => app_state.open_review(app_state.selected_index()),
+                        Action::History => {
+                            let states = messages_file_path()
+                                .map(|store_path| history::states(&store_path))
+                                .unwrap_or_default();
+                            app_state.popup = Some(Popup::History(HistoryPopup::new(
+                                states,
+                                &app_state.messages,
+                            )));
+                        }
+                        Action::Down => (0..count).for_each(|_| app_state.next()),
+                        Action::Up => (0..count).for_each(|_| app_state.previous()),
+                        Action::MoveDown => app_state.move_selected(count as isize),
+                        Action::MoveUp => app_state.move_selected(-(count as isize)),
+                        Action::ScrollLeft => {
+                            app_state.scroll_description(-SCROLL_COLUMNS * count as isize)
+                        }
+                        Action::ScrollRight => {
+                            app_state.scroll_description(SCROLL_COLUMNS * count as isize)
+                        }
+                        Action::ScrollHome => app_state.description_offset = 0,
+                        Action::NarrowTitle => app_state.widen_title(-(count as i32)),
+                        Action::WidenTitle => app_state.widen_title(count as i32),
+                        Action::ToggleNamespace => app_state.toggle_namespace(),
+                        Action::ToggleSingleLine => {
+                            app_state.single_line = !app_state.single_line;
+                            app_state.scroll_description(0);
+                        }
+                        Action::ToggleList => app_state.list = !app_state.list,
+                        Action::TogglePreview => app_state.preview = !app_state.preview,
+                        Action::Top => app_state.select_row(0),
+                        Action::Bottom => match given_count {
+                            Some(row) => app_state.select_row(row.saturating_sub(1)),
+                            None => app_state.select_row(usize::MAX),
+                        },
+                        Action::ToggleTemporary => {
+                            if let Some(selected) = app_state.selected_index() {
+                                let snippet = &mut app_state.messages[selected];
+                                snippet.temporary = !snippet.temporary;
+                                app_state.notifications.info(match snippet.temporary {
+                                    true => format!(
+                                        "'{}' is temporary now, it goes away when sniprrr closes",
+                                        snippet.title
+                                    ),
+                                    false => format!("'{}' is kept in the library", snippet.title),
+                                });
+                                app_state.save();
+                            }
+                        }
+                        Action::Undo => app_state.undo(),
+                        Action::Redo => app_state.redo(),
+                        Action::Help => {
+                            app_state.popup = Some(Popup::Help(HelpPopup::new(&app_state.keymap)))
+                        }
+                        Action::Quit => {
+                            if app_state.may_quit(quit_pending) {
+                                return Ok(());
+                            }
+                        }
                     }
-                    KeyCode::Down | KeyCode::Char('j') => app_state.next(),
-                    KeyCode::Up | KeyCode::Char('k') => app_state.previous(),
-                    KeyCode::Char('q') => return Ok(()),
-                    _ => {}
-                },
+                }
                 InputMode::Editing if key.kind == KeyEventKind::Press => match key.code {
-                    KeyCode::Tab => {
-                        app_state.focused_input_index =
-                            (app_state.focused_input_index + 1) % MAX_INPUT_COUNT
+                    KeyCode::Tab => app_state.focus_next_input(),
+                    // Terminals tell Shift+Enter apart only with some protocols, so
+                    // Alt+Enter and Ctrl+J start a new line too
+                    KeyCode::Enter
+                        if app_state.focused_input_index == INPUT_DESCRIPTION_INDEX
+                            && key
+                                .modifiers
+                                .intersects(KeyModifiers::SHIFT | KeyModifiers::ALT) =>
+                    {
+                        app_state.description_input.insert('\n')
+                    }
+                    KeyCode::Char('j')
+                        if app_state.focused_input_index == INPUT_DESCRIPTION_INDEX
+                            && key.modifiers.contains(KeyModifiers::CONTROL) =>
+                    {
+                        app_state.description_input.insert('\n')
+                    }
+                    KeyCode::Up | KeyCode::Down
+                        if app_state.focused_input_index == INPUT_DESCRIPTION_INDEX =>
+                    {
+                        let description = &mut app_state.description_input;
+                        match key.code {
+                            KeyCode::Up => description.up(),
+                            _ => description.down(),
+                        }
+                    }
+                    KeyCode::Left | KeyCode::Right
+                        if key.modifiers.contains(KeyModifiers::CONTROL) =>
+                    {
+                        if let Some(input) = app_state.focused_input() {
+                            match key.code {
+                                KeyCode::Left => input.word_left(),
+                                _ => input.word_right(),
+                            }
+                        }
+                    }
+                    KeyCode::Left
+                    | KeyCode::Right
+                    | KeyCode::Home
+                    | KeyCode::End
+                    | KeyCode::Delete => {
+                        if let Some(input) = app_state.focused_input() {
+                            match key.code {
+                                KeyCode::Left => input.left(),
+                                KeyCode::Right => input.right(),
+                                KeyCode::Home => input.home(),
+                                KeyCode::End => input.end(),
+                                _ => input.delete(),
+                            }
+                        }
                     }
                     KeyCode::Enter => {
                         // If we are not on the last field, enter moves to the next field
                         if app_state.focused_input_index == MAX_INPUT_COUNT - 1 {
                             // Last field index
-                            let snippet = Snippet {
-                                title: app_state.title_input.clone(),
-                                description: app_state.description_input.clone(),
-                            };
-
-                            app_state.messages.push(snippet);
-
-                            app_state.title_input.clear();
-                            app_state.description_input.clear();
-                            app_state.input_mode = InputMode::Normal;
-
-                            let json_string =
-                                serde_json::to_string::<Vec<Snippet>>(&app_state.messages).unwrap();
-
-                            write_messages_to_file(&json_string)?;
+                            match app_state.form_duplicate() {
+                                Some(existing) => {
+                                    app_state.popup =
+                                        Some(Popup::Duplicate(DuplicatePopup::new(existing)))
+                                }
+                                None => app_state.add_form_snippet(),
+                            }
                         } else {
                             // Not the last field
                             // Move to next field
-                            app_state.focused_input_index =
-                                (app_state.focused_input_index + 1) % MAX_INPUT_COUNT
+                            app_state.focus_next_input()
                         }
                     }
-                    KeyCode::Char(c) => {
-                        match app_state.focused_input_index {
-                            INPUT_TITLE_INDEX => app_state.title_input.push(c),
-                            INPUT_DESCRIPTION_INDEX => app_state.description_input.push(c),
-                            _ => {}
+                    KeyCode::Char('v') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        let mode = match app_state.focused_input_index {
+                            INPUT_DESCRIPTION_INDEX => app_state.paste_mode,
+                            _ => PasteMode::Plain,
                         };
+                        match paste::read_clipboard(mode) {
+                            Ok(text) => app_state.insert_pasted(&text),
+                            Err(error) => {
+                                app_state.report_error(format!("Could not paste: {}", error))
+                            }
+                        }
+                    }
+                    KeyCode::Char('t') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        app_state.form_temporary = !app_state.form_temporary;
+                    }
+                    KeyCode::Char('p') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        app_state.paste_mode = app_state.paste_mode.toggle();
+                        app_state.notifications.info(format!(
+                            "Pasting into the description as {}",
+                            app_state.paste_mode.label()
+                        ));
+                    }
+                    KeyCode::Char(c) => {
+                        if let Some(input) = app_state.focused_input() {
+                            input.insert(c);
+                        }
                     }
                     KeyCode::Backspace => {
-                        match app_state.focused_input_index {
-                            INPUT_TITLE_INDEX => {
-                                app_state.title_input.pop();
-                            }
-                            INPUT_DESCRIPTION_INDEX => {
-                                app_state.description_input.pop();
-                            }
-                            _ => {}
-                        };
+                        if let Some(input) = app_state.focused_input() {
+                            input.backspace();
+                        }
+                    }
+                    KeyCode::Esc => {
+                        app_state.input_mode = InputMode::Normal;
+                    }
+                    _ => {}
+                },
+                InputMode::Search if key.kind == KeyEventKind::Press => match key.code {
+                    KeyCode::Enter => {
+                        #[cfg(feature = "semantic")]
+                        app_state.search_semantically();
+                        app_state.input_mode = InputMode::Normal;
                     }
                     KeyCode::Esc => {
+                        app_state.filter.clear();
                         app_state.input_mode = InputMode::Normal;
+                        app_state.clamp_selection();
+                    }
+                    KeyCode::Char(c) => {
+                        app_state.filter.push(c);
+                        app_state.table_state.select(Some(0));
+                        app_state.clamp_selection();
+                    }
+                    KeyCode::Backspace => {
+                        app_state.filter.pop();
+                        app_state.table_state.select(Some(0));
+                        app_state.clamp_selection();
                     }
+                    KeyCode::Down => app_state.next(),
+                    KeyCode::Up => app_state.previous(),
                     _ => {}
                 },
                 _ => {}
@@ -226,19 +3216,215 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app_state: AppState) -> i
     }
 }
 
-fn get_selected_snippet(app: &AppState) -> Option<&Snippet> {
-    let selected_index = app.table_state.selected()?;
-    app.messages.get(selected_index)
+/// Answers a request sent by another sniprrr invocation
+fn handle_request(app_state: &mut AppState, request: Request) -> io::Result<Response> {
+    match request {
+        Request::Ping => Ok(Response::ok("running")),
+        // Only what `sniprrr list` would read from the store itself
+        Request::List => Ok(Response::with_snippets(
+            app_state
+                .messages
+                .iter()
+                .filter(|snippet| snippet.source.is_none())
+                .cloned()
+                .collect(),
+        )),
+        Request::Save { .. } => Ok(Response::error(
+            "the interactive sniprrr owns the store, close it first",
+        )),
+        Request::Focus => {
+            // There is no portable way to raise the terminal window, so ring the bell instead
+            print!("\x07");
+            io::stdout().flush()?;
+            app_state
+                .notifications
+                .info("Another sniprrr was started, this one is still open");
+            Ok(Response::ok(
+                "sniprrr is already running in another terminal",
+            ))
+        }
+        Request::Copy {
+            title,
+            text,
+            parameters,
+        } => {
+            let index = match app_state.messages.iter().position(|s| s.title == title) {
+                Some(index) => index,
+                None => return Ok(Response::error(format!("No snippet titled '{}'", title))),
+            };
+
+            let snippet = &app_state.messages[index];
+            let text = match text {
+                Some(text) => text,
+                None if !template::placeholders(&snippet.description).is_empty()
+                    || template::has_expansions(&snippet.description) =>
+                {
+                    return Ok(Response::error(format!(
+                        "'{}' is a template, copy it from the running sniprrr",
+                        title
+                    )));
+                }
+                None => snippet.description.clone(),
+            };
+
+            app_state.messages[index].remember_parameters(&parameters);
+            // `sniprrr copy` already picked the fields, and a copy started
+            // here may still be waiting in a popup
+            let copy_field = std::mem::replace(&mut app_state.copy_field, CopyField::Body);
+            let copied = copy_snippet(app_state, index, &text);
+            app_state.copy_field = copy_field;
+            if copied {
+                let target = app_state.copy_router.target_for(&app_state.messages[index]);
+                app_state
+                    .notifications
+                    .info(format!("Copied '{}' for another terminal", title));
+                Ok(Response::ok(format!(
+                    "Copied '{}' to {}",
+                    title, target.name
+                )))
+            } else {
+                Ok(Response::error(
+                    app_state
+                        .notifications
+                        .last_error()
+                        .unwrap_or_default()
+                        .to_string(),
+                ))
+            }
+        }
+    }
+}
+
+/// Copies the body of `variant` of the snippet at `index`, the main body for
+/// `None`. Templates go through the fill-in or preview popup first.
+/// Returns whether a copy was made right away.
+fn start_copy(app_state: &mut AppState, index: usize, variant: Option<&str>) -> bool {
+    let mut snippet = app_state.messages[index].clone();
+    snippet.description = variants::body(&snippet, variant).to_string();
+    let fields = template::placeholders(&snippet.description);
+    if snippet.binary || app_state.copy_field == CopyField::Title {
+        // Binary bodies are copied in their encoded form, see `B` for decoded.
+        // A title is copied as it is.
+        return copy_snippet(app_state, index, &snippet.description);
+    } else if snippet.needs_review && !template::commands(&snippet.description).is_empty() {
+        let message = match &snippet.source {
+            Some(source) => format!(
+                "'{}' is read from the source {}, its commands don't run",
+                snippet.title, source
+            ),
+            None => "Imported snippets run commands once accepted, review them with R".to_string(),
+        };
+        app_state.notifications.info(message);
+    } else if !fields.is_empty() {
+        app_state.popup = Some(Popup::Fill(FillPopup::new(index, &snippet, fields)));
+    } else if template::has_expansions(&snippet.description) {
+        app_state.popup = Some(preview_popup(
+            index,
+            &snippet.title,
+            &snippet.description,
+            snippet.trusted_commands.as_deref(),
+            HashMap::new(),
+        ));
+    } else {
+        return copy_snippet(app_state, index, &snippet.description);
+    }
+    false
+}
+
+/// Sends `text`, the body of the snippet at `index`, to the snippet's copy target
+/// with the fields of `copy_field` and records the use of the snippet.
+/// Returns whether the copy succeeded.
+fn copy_snippet(app_state: &mut AppState, index: usize, text: &str) -> bool {
+    copy_snippet_as(app_state, index, text, Flavor::Plain)
+}
+
+/// Like `copy_snippet`, putting the text into the form of `flavor` for this copy
+fn copy_snippet_as(app_state: &mut AppState, index: usize, text: &str, flavor: Flavor) -> bool {
+    let target = app_state
+        .copy_router
+        .target_for(&app_state.messages[index])
+        .clone();
+    send_copy(app_state, index, text, flavor, &target)
+}
+
+/// Like `copy_snippet`, running the text through `transforms` in place of
+/// the target's own for this copy
+fn copy_snippet_transformed(
+    app_state: &mut AppState,
+    index: usize,
+    text: &str,
+    transforms: Vec<Transform>,
+) -> bool {
+    let mut target = app_state
+        .copy_router
+        .target_for(&app_state.messages[index])
+        .clone();
+    target.transforms = transforms;
+    send_copy(app_state, index, text, Flavor::Plain, &target)
+}
+
+fn send_copy(
+    app_state: &mut AppState,
+    index: usize,
+    text: &str,
+    flavor: Flavor,
+    target: &CopyTarget,
+) -> bool {
+    let snippet = &app_state.messages[index];
+    let text = app_state.copy_field.text(&snippet.title, text);
+    let language = snippet.language.as_deref();
+    let sent = match app_state.print {
+        true => Ok(Some(target.render(&text, flavor, language))),
+        false => target.send_as(&text, flavor, language).map(|_| None),
+    };
+    match sent {
+        Ok(Some(printed)) => {
+            app_state.printed = Some(printed);
+            app_state.messages[index].mark_used();
+            app_state.save();
+            true
+        }
+        Ok(_) => {
+            app_state
+                .notifications
+                .info(format!("Copied '{}' to {}", snippet.title, target.name));
+            app_state.messages[index].mark_used();
+
+            app_state.save();
+            app_state.select_frecent();
+
+            true
+        }
+        Err(error) => {
+            let message = format!("Could not copy to {}: {}", target.name, error);
+            app_state.report_error(message);
+            false
+        }
+    }
+}
+
+/// How many changes `u` can undo
+const UNDO_LIMIT: usize = 100;
+
+/// How many columns `h` and `l` scroll the description
+const SCROLL_COLUMNS: isize = 8;
+
+/// The part of a one line field that fits into `area` (inside the borders)
+/// and the cursor's column within it
+fn input_view(input: &TextArea, area: Rect) -> (String, u16) {
+    input.line_view(area.width.saturating_sub(2) as usize)
 }
 
 fn ui<B: Backend>(f: &mut Frame<B>, app: &mut AppState) {
+    let theme = &app.theme;
+
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .margin(2)
         .constraints(
             [
                 Constraint::Length(1),
-                Constraint::Length(6),
+                Constraint::Length(16),
                 Constraint::Min(1),
             ]
             .as_ref(),
@@ -246,27 +3432,116 @@ fn ui<B: Backend>(f: &mut Frame<B>, app: &mut AppState) {
         .split(f.size());
 
     let (msg, style) = match app.input_mode {
+        InputMode::Normal if app.tutorial.is_some() => (
+            vec![Span::raw(
+                app.tutorial
+                    .as_ref()
+                    .map(Tutorial::instructions)
+                    .unwrap_or_default(),
+            )],
+            theme.help_line,
+        ),
+        InputMode::Normal if app.sidebar_focus => (
+            vec![
+                Span::raw("Press "),
+                Span::styled("j", theme.key_hint),
+                Span::raw("/"),
+                Span::styled("k", theme.key_hint),
+                Span::raw(" to pick a namespace, "),
+                Span::styled("Enter", theme.key_hint),
+                Span::raw(" or "),
+                Span::styled("Tab", theme.key_hint),
+                Span::raw(" to go back to the snippets, "),
+                Span::styled("Esc", theme.key_hint),
+                Span::raw(" to list all of them."),
+            ],
+            theme.help_line,
+        ),
         InputMode::Normal => (
             vec![
                 Span::raw("Press "),
-                Span::styled("q", Style::default().add_modifier(Modifier::BOLD)),
+                Span::styled("q", theme.key_hint),
                 Span::raw(" to exit, "),
-                Span::styled("e", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" to start editing."),
+                Span::styled("c", theme.key_hint),
+                Span::raw(" to copy, "),
+                Span::styled("Q", theme.key_hint),
+                Span::raw(" to copy and exit, "),
+                Span::styled("e", theme.key_hint),
+                Span::raw(" to start editing, "),
+                Span::styled("/", theme.key_hint),
+                Span::raw(" to search, "),
+                Span::styled("u", theme.key_hint),
+                Span::raw(" to undo, "),
+                Span::styled("?", theme.key_hint),
+                Span::raw(" for all keys."),
             ],
-            Style::default().add_modifier(Modifier::RAPID_BLINK),
+            theme.help_line,
         ),
+        InputMode::Search => {
+            // With the `semantic` feature a search starting with `~` runs on Enter
+            let semantic = cfg!(feature = "semantic");
+            let (enter, clear) = match semantic && app.filter.starts_with('~') {
+                true => (" to search by meaning, ", " to clear it)"),
+                false if semantic => (
+                    " to keep the filter, ",
+                    " to clear it, start with ~ to search by meaning)",
+                ),
+                false => (" to keep the filter, ", " to clear it)"),
+            };
+            (
+                vec![
+                    Span::styled("/", theme.key_hint),
+                    Span::raw(app.filter.as_str()),
+                    Span::raw("  ("),
+                    Span::styled("Enter", theme.key_hint),
+                    Span::raw(enter),
+                    Span::styled("Esc", theme.key_hint),
+                    Span::raw(clear),
+                ],
+                theme.text,
+            )
+        }
         InputMode::Editing => (
             vec![
                 Span::raw("Press "),
-                Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+                Span::styled("Esc", theme.key_hint),
                 Span::raw(" to stop editing, "),
-                Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" to record the message"),
+                Span::styled("Enter", theme.key_hint),
+                Span::raw(" to record the message, "),
+                Span::styled("Alt+Enter", theme.key_hint),
+                Span::raw(" for a new line in the description, "),
+                Span::styled("Ctrl+V", theme.key_hint),
+                Span::raw(format!(" to paste ({}), ", app.paste_mode.label())),
+                Span::styled("Ctrl+P", theme.key_hint),
+                Span::raw(" to switch paste mode"),
             ],
-            Style::default(),
+            theme.text,
         ),
     };
+    let pending = app.chord.describe();
+    let notification = app.notifications.current(Instant::now()).cloned();
+    let waiting = app.notifications.waiting();
+    let (msg, style) = match &notification {
+        Some(notification) => {
+            let mut spans = vec![Span::raw(notification.message.as_str())];
+            if waiting > 0 {
+                spans.push(Span::styled(
+                    format!(" (+{} more)", waiting),
+                    theme.namespace,
+                ));
+            }
+            let style = match notification.severity {
+                Severity::Info => theme.text,
+                Severity::Error => theme.error,
+            };
+            (spans, style)
+        }
+        None if !app.chord.is_empty() => (
+            vec![Span::styled(pending.as_str(), theme.key_hint)],
+            theme.text,
+        ),
+        None => (msg, style),
+    };
     let mut text = Text::from(Spans::from(msg));
     text.patch_style(style);
     let help_message = Paragraph::new(text);
@@ -275,46 +3550,114 @@ fn ui<B: Backend>(f: &mut Frame<B>, app: &mut AppState) {
     // Split remaining chunk
     let inner_chunks = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+        .constraints(
+            [
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Min(3),
+            ]
+            .as_ref(),
+        )
         .split(chunks[1]);
 
     // Render the title input
-    let title_input = Paragraph::new(app.title_input.as_ref())
+    let (title_text, title_x) = input_view(&app.title_input, inner_chunks[0]);
+    let title_input = Paragraph::new(title_text)
         .style(match (&app.input_mode, app.focused_input_index) {
-            (InputMode::Editing, INPUT_TITLE_INDEX) => Style::default().fg(Color::Yellow),
-            _ => Style::default(),
+            (InputMode::Editing, INPUT_TITLE_INDEX) => theme.focused_input,
+            _ => theme.text,
         })
-        .block(Block::default().borders(Borders::ALL).title("Title"));
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(match app.form_temporary {
+                    true => "Title (temporary, for this session only, Ctrl+T to keep it)",
+                    false => "Title (Ctrl+T for a temporary snippet)",
+                }),
+        );
 
     f.render_widget(title_input, inner_chunks[0]);
 
+    // Render the tags input
+    let (tags_text, tags_x) = input_view(&app.tags_input, inner_chunks[1]);
+    let tags_input = Paragraph::new(tags_text)
+        .style(match (&app.input_mode, app.focused_input_index) {
+            (InputMode::Editing, INPUT_TAGS_INDEX) => theme.focused_input,
+            _ => theme.text,
+        })
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Tags (separated by commas)"),
+        );
+
+    f.render_widget(tags_input, inner_chunks[1]);
+
+    // Render the variant input, listing the variants typed so far
+    let (variant_text, variant_x) = input_view(&app.variant_input, inner_chunks[2]);
+    let other_variants: Vec<&str> = app
+        .form_bodies
+        .keys()
+        .chain(std::iter::once(&app.form_variant))
+        .filter(|name| !name.is_empty())
+        .map(String::as_str)
+        .collect();
+    let variant_title = match other_variants.is_empty() {
+        true => "Variant (empty for the main body, os:macos picks itself on macOS)".to_string(),
+        false => format!(
+            "Variant (empty for the main body, has {})",
+            other_variants.join(", ")
+        ),
+    };
+    let variant_input = Paragraph::new(variant_text)
+        .style(match (&app.input_mode, app.focused_input_index) {
+            (InputMode::Editing, INPUT_VARIANT_INDEX) => theme.focused_input,
+            _ => theme.text,
+        })
+        .block(Block::default().borders(Borders::ALL).title(variant_title));
+
+    f.render_widget(variant_input, inner_chunks[2]);
+
     // Render the description input
-    let description_input = Paragraph::new(app.description_input.as_ref())
+    let (description_rows, description_x, description_y) = app.description_input.view(
+        inner_chunks[3].width.saturating_sub(2) as usize,
+        inner_chunks[3].height.saturating_sub(2) as usize,
+    );
+    let description_text: Vec<Spans> = description_rows.into_iter().map(Spans::from).collect();
+    let description_input = Paragraph::new(description_text)
         .style(match (&app.input_mode, app.focused_input_index) {
-            (InputMode::Editing, INPUT_DESCRIPTION_INDEX) => Style::default().fg(Color::Yellow),
-            _ => Style::default(),
+            (InputMode::Editing, INPUT_DESCRIPTION_INDEX) => theme.focused_input,
+            _ => theme.text,
         })
         .block(Block::default().borders(Borders::ALL).title("Description"));
 
-    f.render_widget(description_input, inner_chunks[1]);
+    f.render_widget(description_input, inner_chunks[3]);
 
     match app.input_mode {
         InputMode::Normal =>
             // Hide the cursor. `Frame` does this by default, so we don't need to do anything here
             {}
 
+        InputMode::Search => {
+            f.set_cursor(chunks[0].x + app.filter.width() as u16 + 1, chunks[0].y);
+        }
+
         InputMode::Editing => {
             match app.focused_input_index {
                 INPUT_TITLE_INDEX => {
-                    f.set_cursor(
-                        chunks[1].x + app.title_input.width() as u16 + 1,
-                        chunks[1].y + 1,
-                    );
+                    f.set_cursor(inner_chunks[0].x + title_x + 1, inner_chunks[0].y + 1);
+                }
+                INPUT_TAGS_INDEX => {
+                    f.set_cursor(inner_chunks[1].x + tags_x + 1, inner_chunks[1].y + 1);
+                }
+                INPUT_VARIANT_INDEX => {
+                    f.set_cursor(inner_chunks[2].x + variant_x + 1, inner_chunks[2].y + 1);
                 }
                 INPUT_DESCRIPTION_INDEX => {
                     f.set_cursor(
-                        inner_chunks[1].x + app.description_input.width() as u16 + 1,
-                        inner_chunks[1].y + 1,
+                        inner_chunks[3].x + description_x + 1,
+                        inner_chunks[3].y + description_y + 1,
                     );
                 }
                 _ => {}
@@ -322,36 +3665,175 @@ fn ui<B: Backend>(f: &mut Frame<B>, app: &mut AppState) {
         }
     }
 
-    let normal_style = Style::default().bg(Color::Rgb(0xff, 0x00, 0xff));
-    let selected_style = Style::default().add_modifier(Modifier::REVERSED);
-
     // Create rows for the data
 
-    let header_cells = vec!["Title", "Description"];
+    let description_header = match app.description_offset {
+        0 => "Description".to_string(),
+        offset => format!("Description (+{})", offset),
+    };
+    // Shows which way the titles are sorted, the other orders go into the table's title
+    let title_header = match app.sort {
+        SortOrder::Title => terminal::glyph("Title ▲", "Title ^"),
+        _ => "Title",
+    };
+    let header_cells = vec!["", title_header, "Tags", description_header.as_str()];
     let header = Row::new(header_cells)
-        .style(normal_style)
+        .style(theme.header)
         .height(1)
         .bottom_margin(1);
 
-    let rows = app.messages.iter().map(|snippet| {
-        let height = snippet.description.chars().filter(|c| *c == '\n').count() + 1;
+    let now = time_utils::now();
+    let visible_rows = app.visible_rows();
+    let collapsed_rows: HashMap<usize, String> = visible_rows
+        .iter()
+        .filter_map(|&index| {
+            let namespace = app.collapsed_namespace(&app.messages[index].title)?;
+            Some((index, namespace.to_string()))
+        })
+        .collect();
+    let table_view = TableView {
+        snippets: &app.messages,
+        marked: &app.marked,
+        filter: &app.filter,
+        tag_filter: app.tag_filter.as_deref(),
+        single_line: app.single_line,
+        description_offset: app.description_offset,
+        collapsed: &collapsed_rows,
+        uses: app.sort == SortOrder::MostUsed,
+        template: app.list.then_some(&app.row_template),
+        theme,
+    };
+    let rows = table_view.rows(&visible_rows, now);
 
-        let title_cell = Cell::from(snippet.title.clone());
-        let description_cell = Cell::from(snippet.description.clone());
+    let widths = match app.list {
+        true => vec![Constraint::Length(2), Constraint::Percentage(100)],
+        false => vec![
+            Constraint::Length(2),
+            Constraint::Percentage(app.view.title_width),
+            Constraint::Length(20),
+            Constraint::Percentage(app.view.description_width()),
+        ],
+    };
+    let mut table = Table::new(rows);
+    // The list is compact, its rows say what they show
+    if !app.list {
+        table = table.header(header);
+    }
+    let title = match (app.filter.is_empty(), &app.tag_filter, app.sort) {
+        (true, None, SortOrder::Store | SortOrder::Title) => "Snippets".to_string(),
+        (true, None, sort) => format!("Snippets, {}", sort.label()),
+        (true, Some(tag), SortOrder::Store | SortOrder::Title) => {
+            format!("Snippets tagged '{}'", tag)
+        }
+        (true, Some(tag), sort) => format!("Snippets tagged '{}', {}", tag, sort.label()),
+        (false, None, _) => format!("Snippets matching '{}'", app.filter),
+        (false, Some(tag), _) => {
+            format!("Snippets tagged '{}' matching '{}'", tag, app.filter)
+        }
+    };
+    let title = match &app.folder {
+        Some(folder) => title.replacen("Snippets", &format!("Snippets in {}/", folder), 1),
+        None => title,
+    };
+    let table = table
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .highlight_style(theme.selected)
+        .highlight_symbol(theme.highlight_symbol)
+        .widths(&widths);
 
-        Row::new(vec![title_cell, description_cell]).height(height as u16)
-    });
+    let main_area = match app.sidebar_shown() {
+        true => {
+            let columns = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Length(SIDEBAR_WIDTH), Constraint::Min(1)].as_ref())
+                .split(chunks[2]);
+            render_sidebar(f, app, columns[0]);
+            columns[1]
+        }
+        false => chunks[2],
+    };
+    let (table_area, preview_area) = match app.preview {
+        true => {
+            let halves = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(60), Constraint::Percentage(40)].as_ref())
+                .split(main_area);
+            (halves[0], Some(halves[1]))
+        }
+        false => (main_area, None),
+    };
+    if let Some(area) = preview_area {
+        let selected = app.selected_index().map(|index| &app.messages[index]);
+        let (title, text) = match selected {
+            Some(snippet) => (
+                match &snippet.language {
+                    Some(language) => format!("{} ({})", snippet.title, language),
+                    None => snippet.title.clone(),
+                },
+                preview_text(snippet, theme),
+            ),
+            None => ("Preview".to_string(), Text::default()),
+        };
+        let preview = Paragraph::new(text)
+            .style(theme.text)
+            .wrap(Wrap { trim: false })
+            .block(Block::default().borders(Borders::ALL).title(title));
+        f.render_widget(preview, area);
+    }
 
-    let table = Table::new(rows)
-        .header(header)
-        .block(Block::default().borders(Borders::ALL).title("Snippets"))
-        .highlight_style(selected_style)
-        // .highlight_symbol("🦀 ")
-        .widths(&[
-            Constraint::Percentage(50),
-            Constraint::Length(30),
-            Constraint::Min(10),
-        ]);
+    f.render_stateful_widget(table, table_area, &mut app.table_state);
+
+    if let Some(popup) = &app.popup {
+        popup.render(f, &app.theme);
+    }
+}
+
+/// Columns of the namespace sidebar, borders included
+const SIDEBAR_WIDTH: u16 = 26;
 
-    f.render_stateful_widget(table, chunks[2], &mut app.table_state);
+/// The namespaces of the titles like folders, each with how many snippets it
+/// holds, below "All". The picked one is highlighted, in the selection's
+/// style while keys move through the sidebar.
+fn render_sidebar<B: Backend>(f: &mut Frame<B>, app: &AppState, area: Rect) {
+    let theme = &app.theme;
+    let picked_style = match app.sidebar_focus {
+        true => theme.selected,
+        false => theme.highlight,
+    };
+    let folders = app.folders();
+    let all = (
+        Spans::from(format!("All ({})", app.messages.len())),
+        app.folder.is_none(),
+    );
+    let entries = folders.iter().map(|(folder, count)| {
+        let depth = folder.matches('/').count();
+        let name = folder.rsplit('/').next().unwrap_or(folder);
+        let line = Spans::from(vec![
+            Span::raw(format!("{}{}/ ", "  ".repeat(depth + 1), name)),
+            Span::styled(format!("({})", count), theme.namespace),
+        ]);
+        (line, app.folder.as_ref() == Some(folder))
+    });
+    let mut picked = 0;
+    let lines: Vec<Spans> = std::iter::once(all)
+        .chain(entries)
+        .enumerate()
+        .map(|(position, (mut line, is_picked))| {
+            if is_picked {
+                picked = position;
+                for span in &mut line.0 {
+                    span.style = span.style.patch(picked_style);
+                }
+            }
+            line
+        })
+        .collect();
+    // Keeps the picked namespace in view
+    let height = area.height.saturating_sub(2) as usize;
+    let scroll = (picked + 1).saturating_sub(height) as u16;
+    let sidebar = Paragraph::new(lines)
+        .style(theme.text)
+        .scroll((scroll, 0))
+        .block(Block::default().borders(Borders::ALL).title("Namespaces"));
+    f.render_widget(sidebar, area);
 }
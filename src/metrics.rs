@@ -0,0 +1,210 @@
+//! Prometheus metrics for `sniprrr daemon`, served on `/metrics` when
+//! `[metrics] listen` is set, so a long-running daemon can be monitored like
+//! any other service. It is plain HTTP without authentication and meant for
+//! localhost or a trusted network; the snippets and their changes are served
+//! with tokens on `[server] listen`.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use serde::Deserialize;
+
+use crate::file_utils::messages_file_path;
+use crate::server::{read_request, write_response, PLAIN_TEXT};
+use crate::time_utils;
+
+/// The `[metrics]` section of the config file
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct MetricsConfig {
+    /// Address to serve `/metrics` on, e.g. `127.0.0.1:9464`. Off when not
+    /// set.
+    pub listen: Option<String>,
+}
+
+/// Counters and gauges updated by the daemon as it serves requests
+pub struct Metrics {
+    started_at: u64,
+    /// Requests over the socket by command
+    requests: Mutex<BTreeMap<&'static str, u64>>,
+    copies: AtomicU64,
+    copy_failures: AtomicU64,
+    saves: AtomicU64,
+    save_failures: AtomicU64,
+    last_save: AtomicU64,
+    snippets: AtomicU64,
+    /// The store file is as the daemon last wrote or read it
+    in_sync: AtomicBool,
+}
+
+impl Metrics {
+    pub fn new(snippets: usize) -> Metrics {
+        Metrics {
+            started_at: time_utils::now(),
+            requests: Mutex::new(BTreeMap::new()),
+            copies: AtomicU64::new(0),
+            copy_failures: AtomicU64::new(0),
+            saves: AtomicU64::new(0),
+            save_failures: AtomicU64::new(0),
+            last_save: AtomicU64::new(0),
+            snippets: AtomicU64::new(snippets as u64),
+            in_sync: AtomicBool::new(true),
+        }
+    }
+
+    pub fn request(&self, command: &'static str) {
+        if let Ok(mut requests) = self.requests.lock() {
+            *requests.entry(command).or_default() += 1;
+        }
+    }
+
+    pub fn copied(&self, ok: bool) {
+        match ok {
+            true => self.copies.fetch_add(1, Ordering::Relaxed),
+            false => self.copy_failures.fetch_add(1, Ordering::Relaxed),
+        };
+    }
+
+    /// Counts a write of the store holding `snippets` snippets
+    pub fn saved(&self, ok: bool, snippets: usize) {
+        if ok {
+            self.saves.fetch_add(1, Ordering::Relaxed);
+            self.last_save.store(time_utils::now(), Ordering::Relaxed);
+            self.snippets.store(snippets as u64, Ordering::Relaxed);
+            self.in_sync.store(true, Ordering::Relaxed);
+        } else {
+            self.save_failures.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// The store file was changed by something other than the daemon
+    pub fn out_of_sync(&self) {
+        self.in_sync.store(false, Ordering::Relaxed);
+    }
+
+    /// The metrics in the Prometheus text format
+    pub fn render(&self) -> String {
+        let mut text = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, samples: Vec<(String, u64)>| {
+            text.push_str(&format!(
+                "# HELP {} {}\n# TYPE {} {}\n",
+                name, help, name, kind
+            ));
+            for (labels, value) in samples {
+                text.push_str(&format!("{}{} {}\n", name, labels, value));
+            }
+        };
+        let value = |counter: &AtomicU64| vec![(String::new(), counter.load(Ordering::Relaxed))];
+
+        let requests = self
+            .requests
+            .lock()
+            .map(|requests| {
+                requests
+                    .iter()
+                    .map(|(command, count)| (format!("{{command=\"{}\"}}", command), *count))
+                    .collect()
+            })
+            .unwrap_or_default();
+        metric(
+            "sniprrr_requests_total",
+            "counter",
+            "Requests from other sniprrr invocations, by command.",
+            requests,
+        );
+        metric(
+            "sniprrr_copies_total",
+            "counter",
+            "Snippets copied on behalf of other invocations.",
+            value(&self.copies),
+        );
+        metric(
+            "sniprrr_copy_failures_total",
+            "counter",
+            "Copies that could not be sent to their copy target.",
+            value(&self.copy_failures),
+        );
+        metric(
+            "sniprrr_saves_total",
+            "counter",
+            "Writes of the store file.",
+            value(&self.saves),
+        );
+        metric(
+            "sniprrr_save_failures_total",
+            "counter",
+            "Writes of the store file that failed.",
+            value(&self.save_failures),
+        );
+        metric(
+            "sniprrr_last_save_timestamp_seconds",
+            "gauge",
+            "Unix time of the last write of the store file, 0 before the first.",
+            value(&self.last_save),
+        );
+        metric(
+            "sniprrr_snippets",
+            "gauge",
+            "Snippets in the store.",
+            value(&self.snippets),
+        );
+        let store_bytes = messages_file_path()
+            .and_then(|path| path.metadata().ok())
+            .map_or(0, |metadata| metadata.len());
+        metric(
+            "sniprrr_store_bytes",
+            "gauge",
+            "Size of the store file.",
+            vec![(String::new(), store_bytes)],
+        );
+        metric(
+            "sniprrr_store_in_sync",
+            "gauge",
+            "1 unless the store file was changed behind the daemon's back since its last write.",
+            vec![(String::new(), self.in_sync.load(Ordering::Relaxed) as u64)],
+        );
+        metric(
+            "sniprrr_start_time_seconds",
+            "gauge",
+            "Unix time the daemon started.",
+            vec![(String::new(), self.started_at)],
+        );
+        text
+    }
+}
+
+/// Serves `metrics` on `address`, a scrape at a time as each is quick and
+/// slow clients are dropped after a timeout
+pub fn serve(address: &str, metrics: Arc<Metrics>) -> io::Result<()> {
+    let listener = TcpListener::bind(address)?;
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            if let Err(error) = respond(stream, &metrics) {
+                eprintln!("Could not answer an HTTP request: {}", error);
+            }
+        }
+    });
+    Ok(())
+}
+
+fn respond(mut stream: TcpStream, metrics: &Metrics) -> io::Result<()> {
+    let request = read_request(&stream)?;
+    let (status, content_type, body) = match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/metrics") => ("200 OK", "text/plain; version=0.0.4", metrics.render()),
+        ("GET", _) => (
+            "404 Not Found",
+            PLAIN_TEXT,
+            "Only /metrics is served, the snippets are on [server] listen\n".to_string(),
+        ),
+        _ => (
+            "405 Method Not Allowed",
+            PLAIN_TEXT,
+            "Only GET is served\n".to_string(),
+        ),
+    };
+    write_response(&mut stream, status, content_type, body, request.gzip)
+}
@@ -0,0 +1,53 @@
+//! `/` in titles as lightweight namespaces, e.g. `docker/prune/volumes`.
+//! The table shows them as a breadcrumb and can collapse a namespace into one
+//! row, a search ending in `/` lists only that namespace, and the sidebar
+//! picked with Tab lists them all like folders.
+
+use std::collections::BTreeMap;
+
+use ratatui::style::Style;
+use ratatui::text::{Span, Spans};
+
+use crate::terminal;
+
+pub use sniprrr_core::namespace::*;
+
+/// Every namespace of `titles`, the ones above them included, with how many
+/// titles it holds, each followed by the ones below it
+pub fn counts<'a>(titles: impl Iterator<Item = &'a str>) -> Vec<(String, usize)> {
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for title in titles {
+        let Some(namespace) = split(title).0 else {
+            continue;
+        };
+        let ends = namespace.match_indices('/').map(|(end, _)| end);
+        // A title starting with `/` has no namespace above its first part
+        for end in ends.chain([namespace.len()]).filter(|&end| end > 0) {
+            *counts.entry(&namespace[..end]).or_default() += 1;
+        }
+    }
+    let mut counts: Vec<(String, usize)> = counts
+        .into_iter()
+        .map(|(namespace, count)| (namespace.to_string(), count))
+        .collect();
+    // By part, so `docker-compose` doesn't come between `docker` and `docker/prune`
+    counts.sort_by(|(a, _), (b, _)| a.split('/').cmp(b.split('/')));
+    counts
+}
+
+/// `docker › prune › volumes`, with the namespace part in `style`
+pub fn breadcrumb(title: &str, style: Style) -> Spans<'static> {
+    let (namespace, name) = split(title);
+    let mut spans: Vec<Span> = namespace
+        .into_iter()
+        .flat_map(|namespace| namespace.split('/'))
+        .flat_map(|part| {
+            [
+                Span::styled(part.to_string(), style),
+                Span::styled(terminal::glyph(" › ", " > "), style),
+            ]
+        })
+        .collect();
+    spans.push(Span::raw(name.to_string()));
+    Spans::from(spans)
+}
@@ -0,0 +1,69 @@
+//! Desktop notifications for things that happen while the daemon runs in the
+//! background. Each kind can be switched off under `[notifications]`.
+
+use notify_rust::Notification;
+use serde::Deserialize;
+
+/// The `[notifications]` section of the config file
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct NotificationConfig {
+    /// A scheduled snippet became due
+    pub reminders: bool,
+    /// The store file was changed behind the daemon's back, its next save
+    /// would overwrite that change
+    pub conflicts: bool,
+    /// A snippet was added through the daemon, e.g. with `sniprrr add`
+    pub saved: bool,
+}
+
+impl Default for NotificationConfig {
+    fn default() -> NotificationConfig {
+        NotificationConfig {
+            reminders: true,
+            conflicts: true,
+            saved: true,
+        }
+    }
+}
+
+/// Something worth a notification
+pub enum Event<'a> {
+    /// The snippet with this title is scheduled for now
+    Due(&'a str),
+    Conflict,
+    /// The snippet with this title was added
+    Saved(&'a str),
+}
+
+impl NotificationConfig {
+    /// Shows `event` unless its kind is switched off
+    pub fn notify(&self, event: Event) -> Result<(), String> {
+        let (enabled, summary, body) = match event {
+            Event::Due(title) => (
+                self.reminders,
+                "Snippet due",
+                format!("'{}' is scheduled for now", title),
+            ),
+            Event::Conflict => (
+                self.conflicts,
+                "Store changed on disk",
+                "Something else edited the store while the daemon keeps it, \
+                 the daemon's next save will overwrite that change"
+                    .to_string(),
+            ),
+            Event::Saved(title) => (self.saved, "Snippet saved", format!("Added '{}'", title)),
+        };
+        if !enabled {
+            return Ok(());
+        }
+
+        Notification::new()
+            .appname("sniprrr")
+            .summary(summary)
+            .body(&body)
+            .show()
+            .map(|_| ())
+            .map_err(|error| format!("Could not show a notification: {}", error))
+    }
+}
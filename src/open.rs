@@ -0,0 +1,115 @@
+//! Opening the config directory in the system's file manager and the store
+//! in `$VISUAL` or `$EDITOR`, with `sniprrr open` or `gc` and `gs` in the
+//! app, so editing by hand and troubleshooting don't start with looking up
+//! where the platform keeps config files.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::config::config_file_path;
+use crate::storage::{self, Backend};
+use crate::{compression, encryption};
+
+/// The directory of `config.toml`, which also holds the store, its backups
+/// and history unless `store` points elsewhere
+pub fn config_dir() -> Option<PathBuf> {
+    Some(config_file_path()?.parent()?.to_path_buf())
+}
+
+#[cfg(target_os = "macos")]
+const FILE_MANAGER: &str = "open";
+#[cfg(windows)]
+const FILE_MANAGER: &str = "explorer";
+#[cfg(not(any(target_os = "macos", windows)))]
+const FILE_MANAGER: &str = "xdg-open";
+
+/// Shows `dir` in the file manager, creating it first so there is
+/// something to show on a fresh install
+pub fn reveal(dir: &Path) -> Result<(), String> {
+    fs::create_dir_all(dir)
+        .map_err(|error| format!("Could not create {}: {}", dir.display(), error))?;
+    let status = Command::new(FILE_MANAGER)
+        .arg(dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map_err(|error| {
+            format!(
+                "Could not run {} to open {}: {}",
+                FILE_MANAGER,
+                dir.display(),
+                error
+            )
+        })?;
+    // explorer exits with 1 even when the window opened
+    match status.success() || cfg!(windows) {
+        true => Ok(()),
+        false => Err(format!(
+            "{} could not open {}, {}",
+            FILE_MANAGER,
+            dir.display(),
+            status
+        )),
+    }
+}
+
+/// The command of `$VISUAL` or `$EDITOR` split at spaces, so `code --wait`
+/// works, `vi` (`notepad` on Windows) when neither is set
+fn editor() -> Vec<String> {
+    let command = ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|command| !command.trim().is_empty());
+    match command {
+        Some(command) => command.split_whitespace().map(str::to_string).collect(),
+        None if cfg!(windows) => vec!["notepad".to_string()],
+        None => vec!["vi".to_string()],
+    }
+}
+
+/// Why the store at `path` can't be edited as text, if it can't: it isn't
+/// there yet, or it is a SQLite database, encrypted or compressed
+pub fn check_editable(path: &Path) -> Result<(), String> {
+    if !path.exists() {
+        return Err(format!(
+            "There is no store at {} yet, add a snippet first",
+            path.display()
+        ));
+    }
+    if storage::detect(path) == Backend::Sqlite {
+        return Err(
+            "The store is a SQLite database, edit it with sqlite3 or set [storage] backend = \"json\""
+                .to_string(),
+        );
+    }
+    let bytes = fs::read(path).map_err(|error| format!("Could not read the store: {}", error))?;
+    if encryption::is_encrypted(&bytes) {
+        return Err("The store is encrypted, turn [encryption] off to edit it by hand".to_string());
+    }
+    if compression::is_compressed(&bytes) {
+        return Err(
+            "The store is compressed, turn [compression] off to edit it by hand".to_string(),
+        );
+    }
+    Ok(())
+}
+
+/// Opens `path` in the editor on the terminal, drawn to `stdout`, and waits
+/// for it to close
+pub fn edit(path: &Path, stdout: Stdio) -> Result<(), String> {
+    let editor = editor();
+    let (program, args) = editor.split_first().ok_or("No editor set in $EDITOR")?;
+    let status = Command::new(program)
+        .args(args)
+        .arg(path)
+        .stdout(stdout)
+        .status()
+        .map_err(|error| format!("Could not run {}, set $EDITOR: {}", program, error))?;
+    match status.success() {
+        true => Ok(()),
+        false => Err(format!("{} ended with {}", program, status)),
+    }
+}
@@ -0,0 +1,172 @@
+use std::process::Command;
+use std::sync::OnceLock;
+
+use arboard::Clipboard;
+use regex::Regex;
+use serde::Deserialize;
+
+/// The `[paste]` section of the config file: clean-up of text pasted into
+/// the form, so commands copied from a terminal can be reused right away
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PasteConfig {
+    /// Drop shell prompts such as `$ `, `PS C:\> `, `➜  dir ` and `user@host:~$ `
+    pub strip_prompts: bool,
+    /// More prompt prefixes to drop from the start of lines, e.g. `>>> `
+    pub prompts: Vec<String>,
+    /// Drop the color and cursor codes of terminal output
+    pub strip_ansi: bool,
+    /// Drop whitespace at the end of lines and blank lines at the end
+    pub trim_whitespace: bool,
+}
+
+impl Default for PasteConfig {
+    fn default() -> PasteConfig {
+        PasteConfig {
+            strip_prompts: true,
+            prompts: Vec::new(),
+            strip_ansi: true,
+            trim_whitespace: true,
+        }
+    }
+}
+
+/// Prompts of common shells at the start of a line. `➜` is oh-my-zsh's,
+/// followed by the directory and git status, or alone when nothing follows it.
+const PROMPT_PATTERN: &str = r"^\s*(?:\$ |PS(?: [^>]*)?> |[\w.-]+@[\w.-]+:[^$#\n]*[$#] |➜\s+\S+(?:\s+git:\([^)]*\))?(?:\s+✗)?\s+|➜\s+)";
+/// CSI sequences such as colors, OSC sequences such as titles and links, and
+/// two-character escapes
+const ANSI_PATTERN: &str = r"\x1b(?:\[[0-9;?]*[ -/]*[@-~]|\][^\x07\x1b]*(?:\x07|\x1b\\)|[@-_])";
+
+impl PasteConfig {
+    /// `text` with the configured clean-up applied
+    pub fn clean_up(&self, text: &str) -> String {
+        static PROMPT: OnceLock<Regex> = OnceLock::new();
+        static ANSI: OnceLock<Regex> = OnceLock::new();
+
+        let mut text = text.to_string();
+        if self.strip_ansi {
+            let ansi = ANSI.get_or_init(|| Regex::new(ANSI_PATTERN).expect("valid pattern"));
+            text = ansi.replace_all(&text, "").into_owned();
+        }
+        if self.strip_prompts {
+            let prompt = PROMPT.get_or_init(|| Regex::new(PROMPT_PATTERN).expect("valid pattern"));
+            text = text
+                .split('\n')
+                .map(|line| {
+                    let line = prompt.replace(line, "");
+                    match self
+                        .prompts
+                        .iter()
+                        .find(|extra| !extra.is_empty() && line.starts_with(extra.as_str()))
+                    {
+                        Some(extra) => line[extra.len()..].to_string(),
+                        None => line.into_owned(),
+                    }
+                })
+                .collect::<Vec<String>>()
+                .join("\n");
+        }
+        if self.trim_whitespace {
+            text = text
+                .split('\n')
+                .map(str::trim_end)
+                .collect::<Vec<&str>>()
+                .join("\n")
+                .trim_end_matches('\n')
+                .to_string();
+        }
+        text
+    }
+}
+
+/// How clipboard contents are inserted while editing, toggled with Ctrl+P
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PasteMode {
+    #[default]
+    Plain,
+    /// Convert the clipboard's HTML flavor to Markdown when there is one
+    Markdown,
+}
+
+impl PasteMode {
+    pub fn toggle(self) -> PasteMode {
+        match self {
+            PasteMode::Plain => PasteMode::Markdown,
+            PasteMode::Markdown => PasteMode::Plain,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PasteMode::Plain => "plain text",
+            PasteMode::Markdown => "HTML as Markdown",
+        }
+    }
+}
+
+/// Reads the clipboard for pasting, converting HTML to Markdown in `PasteMode::Markdown`
+pub fn read_clipboard(mode: PasteMode) -> Result<String, String> {
+    if mode == PasteMode::Markdown {
+        if let Some(html) = clipboard_html() {
+            return Ok(html_to_markdown(&html));
+        }
+    }
+
+    let mut clipboard = Clipboard::new().map_err(|error| error.to_string())?;
+    clipboard.get_text().map_err(|error| error.to_string())
+}
+
+/// `html` as Markdown. The `<head>`, styles and scripts some browsers put
+/// in the flavor are dropped first, as html2md keeps their text.
+fn html_to_markdown(html: &str) -> String {
+    static HIDDEN: OnceLock<Regex> = OnceLock::new();
+    let hidden = HIDDEN.get_or_init(|| {
+        Regex::new(r"(?is)<head\b.*?</head>|<style\b.*?</style>|<script\b.*?</script>")
+            .expect("valid pattern")
+    });
+    html2md::parse_html(&hidden.replace_all(html, ""))
+        .trim()
+        .to_string()
+}
+
+/// The `text/html` flavor of the clipboard. arboard can only read plain
+/// text, so this asks the platform clipboard tools for it.
+fn clipboard_html() -> Option<String> {
+    let output = if cfg!(target_os = "macos") {
+        Command::new("osascript")
+            .args(["-e", "the clipboard as «class HTML»"])
+            .output()
+    } else if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        Command::new("wl-paste")
+            .args(["--no-newline", "--type", "text/html"])
+            .output()
+    } else {
+        Command::new("xclip")
+            .args(["-selection", "clipboard", "-target", "text/html", "-out"])
+            .output()
+    };
+
+    let output = output.ok().filter(|output| output.status.success())?;
+    let text = String::from_utf8_lossy(&output.stdout).to_string();
+
+    // osascript prints the data as «data HTML3C6D...»
+    let html = match text.trim().strip_prefix("«data HTML") {
+        Some(hex) => decode_hex(hex.trim_end_matches('»'))?,
+        None => text,
+    };
+
+    if html.trim().is_empty() {
+        None
+    } else {
+        Some(html)
+    }
+}
+
+fn decode_hex(hex: &str) -> Option<String> {
+    let bytes = (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    Some(String::from_utf8_lossy(&bytes).to_string())
+}
@@ -0,0 +1,3131 @@
+use std::cell::Cell;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::PathBuf;
+
+use crossterm::event::KeyCode;
+use ratatui::{
+    backend::Backend,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+    Frame,
+};
+
+use crate::binary::format_size;
+use crate::collections::Existing;
+use crate::copy_target::Flavor;
+use crate::csv_io::{ColumnMapping, Table};
+use crate::dedupe::{find_near_duplicates, merge_snippets, DuplicatePair, Pick};
+use crate::diff::diff_lines;
+use crate::file_utils::BACKUP_COUNT;
+use crate::history::StoreState;
+use crate::keymap::Keymap;
+use crate::models::{LabelColor, Snippet};
+use crate::replace::{self, Occurrence};
+use crate::run::{self, RunOutput};
+use crate::storage::StorageReport;
+use crate::template::{self, FieldType, Placeholder};
+use crate::terminal;
+use crate::theme::{label_color, Theme};
+use crate::time_utils;
+use crate::transform::{self, Transform};
+use crate::variants;
+
+/// Modal windows drawn on top of the table. While one is open it receives all key presses.
+pub enum Popup {
+    Diff(Box<DiffPopup>),
+    Dedupe(DedupePopup),
+    Fill(FillPopup),
+    Preview(PreviewPopup),
+    Input(InputPopup),
+    Label(LabelPopup),
+    Message(MessagePopup),
+    History(HistoryPopup),
+    Import(ImportPopup),
+    Output(OutputPopup),
+    Flavor(FlavorPopup),
+    Trust(TrustPopup),
+    Storage(StoragePopup),
+    Columns(ColumnsPopup),
+    Variant(VariantPopup),
+    Review(ReviewPopup),
+    TagFilter(TagFilterPopup),
+    Collection(CollectionPopup),
+    SplitPaste(SplitPastePopup),
+    TagSuggest(TagSuggestPopup),
+    Delete(DeletePopup),
+    Duplicate(DuplicatePopup),
+    Run(RunPopup),
+    Help(HelpPopup),
+    Transforms(TransformPopup),
+    Replace(ReplacePopup),
+}
+
+/// What the app should do after a popup handled a key
+pub enum PopupOutcome {
+    Keep,
+    Close,
+    /// Replace this popup with another one
+    Open(Box<Popup>),
+    /// Replace the snippet at `keep` with `merged` and delete the one at `remove`
+    Merge {
+        keep: usize,
+        remove: usize,
+        merged: Snippet,
+    },
+    /// The value entered in an `InputPopup`
+    Submit(InputPurpose, String),
+    /// Copy `text` on behalf of the snippet at `index`, remembering the placeholder values used
+    Copy {
+        index: usize,
+        text: String,
+        parameters: HashMap<String, String>,
+    },
+    /// Add a new snippet
+    Add(Snippet),
+    /// Delete the snippet at this index
+    Delete(usize),
+    /// Add the snippet in the form even though its body is kept already
+    AddDuplicate,
+    /// Leave the form and select the snippet at this index
+    Jump(usize),
+    /// Add the snippets chosen in an `ImportPopup`
+    Import(Vec<Snippet>),
+    /// List the snippets read from `source` in an `ImportPopup`
+    Review {
+        source: String,
+        incoming: Vec<Snippet>,
+    },
+    /// Put a snippet from an earlier state of the store back
+    Restore(Snippet),
+    /// Always allow the commands with this fingerprint for the snippet at `index`, then open `next`
+    Trust {
+        index: usize,
+        fingerprint: String,
+        next: Box<Popup>,
+    },
+    /// Run an action picked in the storage overview
+    Storage(StorageAction),
+    /// Accept the imported snippet at `index` into the library, or delete it
+    Reviewed {
+        index: usize,
+        accepted: bool,
+    },
+    /// Copy the snippet at `index` using the body of `variant`, the main one for `None`
+    Variant {
+        index: usize,
+        variant: Option<String>,
+    },
+    /// Copy the snippet at `index` once in the form of `flavor`
+    CopyAs {
+        index: usize,
+        flavor: Flavor,
+    },
+    /// Copy the snippet at `index` once through `transforms` in place of its target's
+    CopyTransformed {
+        index: usize,
+        transforms: Vec<Transform>,
+    },
+    /// List only the snippets with this tag, or all of them for `None`
+    TagFilter(Option<String>),
+    /// Copy the snippets at `indices` to the collection `name` kept at
+    /// `path`, taking them out of this one when `moving`
+    Transfer {
+        indices: Vec<usize>,
+        name: String,
+        path: PathBuf,
+        moving: bool,
+    },
+    /// Fill the form with text pasted into the title, split into the title
+    /// and description when `description` is set
+    Paste {
+        title: String,
+        description: Option<String>,
+    },
+    /// Set the label of the snippet at `index`
+    Label {
+        index: usize,
+        color: Option<LabelColor>,
+        icon: Option<String>,
+    },
+    /// Replace the bodies of the snippets at the indices of `edits`, the
+    /// main one or that of the named variant, making `replaced` replacements
+    /// in all
+    Replace {
+        edits: Vec<(usize, Option<String>, String)>,
+        replaced: usize,
+    },
+    /// Run the snippet at `index` in `environment`
+    Run {
+        index: usize,
+        environment: run::Environment,
+    },
+    /// Suspend the app and run `command`, the body of the snippet at `index`
+    /// filled in with `parameters`, on the terminal
+    Execute {
+        index: usize,
+        command: String,
+        environment: run::Environment,
+        parameters: HashMap<String, String>,
+    },
+}
+
+impl Popup {
+    pub fn handle_key(&mut self, key: KeyCode) -> PopupOutcome {
+        match self {
+            Popup::Diff(diff) => diff.handle_key(key),
+            Popup::Dedupe(dedupe) => dedupe.handle_key(key),
+            Popup::Fill(fill) => fill.handle_key(key),
+            Popup::Preview(preview) => preview.handle_key(key),
+            Popup::Input(input) => input.handle_key(key),
+            Popup::Label(label) => label.handle_key(key),
+            Popup::Message(message) => message.handle_key(key),
+            Popup::History(history) => history.handle_key(key),
+            Popup::Import(import) => import.handle_key(key),
+            Popup::Output(output) => output.handle_key(key),
+            Popup::Flavor(flavor) => flavor.handle_key(key),
+            Popup::Transforms(transforms) => transforms.handle_key(key),
+            Popup::Trust(trust) => trust.handle_key(key),
+            Popup::Storage(storage) => storage.handle_key(key),
+            Popup::Columns(columns) => columns.handle_key(key),
+            Popup::Variant(variant) => variant.handle_key(key),
+            Popup::Review(review) => review.handle_key(key),
+            Popup::TagFilter(tag_filter) => tag_filter.handle_key(key),
+            Popup::Collection(collection) => collection.handle_key(key),
+            Popup::SplitPaste(split_paste) => split_paste.handle_key(key),
+            Popup::TagSuggest(tag_suggest) => tag_suggest.handle_key(key),
+            Popup::Delete(delete) => delete.handle_key(key),
+            Popup::Duplicate(duplicate) => duplicate.handle_key(key),
+            Popup::Run(run) => run.handle_key(key),
+            Popup::Replace(replace) => replace.handle_key(key),
+            Popup::Help(help) => help.handle_key(key),
+        }
+    }
+
+    pub fn render<B: Backend>(&self, f: &mut Frame<B>, theme: &Theme) {
+        match self {
+            Popup::Diff(diff) => diff.render(f, theme),
+            Popup::Dedupe(dedupe) => dedupe.render(f, theme),
+            Popup::Fill(fill) => fill.render(f, theme),
+            Popup::Preview(preview) => preview.render(f, theme),
+            Popup::Input(input) => input.render(f, theme),
+            Popup::Label(label) => label.render(f, theme),
+            Popup::Message(message) => message.render(f, theme),
+            Popup::History(history) => history.render(f, theme),
+            Popup::Import(import) => import.render(f, theme),
+            Popup::Output(output) => output.render(f, theme),
+            Popup::Flavor(flavor) => flavor.render(f, theme),
+            Popup::Transforms(transforms) => transforms.render(f, theme),
+            Popup::Trust(trust) => trust.render(f, theme),
+            Popup::Storage(storage) => storage.render(f, theme),
+            Popup::Columns(columns) => columns.render(f, theme),
+            Popup::Variant(variant) => variant.render(f, theme),
+            Popup::Review(review) => review.render(f, theme),
+            Popup::TagFilter(tag_filter) => tag_filter.render(f, theme),
+            Popup::Collection(collection) => collection.render(f, theme),
+            Popup::SplitPaste(split_paste) => split_paste.render(f, theme),
+            Popup::TagSuggest(tag_suggest) => tag_suggest.render(f, theme),
+            Popup::Delete(delete) => delete.render(f, theme),
+            Popup::Duplicate(duplicate) => duplicate.render(f, theme),
+            Popup::Run(run) => run.render(f, theme),
+            Popup::Replace(replace) => replace.render(f, theme),
+            Popup::Help(help) => help.render(f, theme),
+        }
+    }
+}
+
+/// Side-by-side diff of two snippet descriptions
+pub struct DiffPopup {
+    left: Snippet,
+    right: Snippet,
+    scroll: u16,
+}
+
+impl DiffPopup {
+    pub fn new(left: Snippet, right: Snippet) -> DiffPopup {
+        DiffPopup {
+            left,
+            right,
+            scroll: 0,
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyCode) -> PopupOutcome {
+        match key {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('=') => return PopupOutcome::Close,
+            KeyCode::Down | KeyCode::Char('j') => self.scroll = self.scroll.saturating_add(1),
+            KeyCode::Up | KeyCode::Char('k') => self.scroll = self.scroll.saturating_sub(1),
+            _ => {}
+        }
+        PopupOutcome::Keep
+    }
+
+    fn render<B: Backend>(&self, f: &mut Frame<B>, theme: &Theme) {
+        let area = centered_rect(90, 80, f.size());
+        f.render_widget(Clear, area);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title("Diff (j/k to scroll, Esc to close)");
+        let inner = block.inner(area);
+        f.render_widget(block, area);
+
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+            .split(inner);
+
+        let removed = theme.text.fg(Color::Red);
+        let added = theme.text.fg(Color::Green);
+
+        let mut left_lines = Vec::new();
+        let mut right_lines = Vec::new();
+        for row in diff_lines(&self.left.description, &self.right.description) {
+            let (left_style, right_style) = if row.is_change() {
+                (removed, added)
+            } else {
+                (theme.text, theme.text)
+            };
+            left_lines.push(diff_line(row.left, left_style));
+            right_lines.push(diff_line(row.right, right_style));
+        }
+
+        let left = Paragraph::new(left_lines)
+            .block(
+                Block::default()
+                    .borders(Borders::RIGHT)
+                    .title(self.left.title.as_str()),
+            )
+            .scroll((self.scroll, 0));
+        let right = Paragraph::new(right_lines)
+            .block(Block::default().title(self.right.title.as_str()))
+            .scroll((self.scroll, 0));
+
+        f.render_widget(left, columns[0]);
+        f.render_widget(right, columns[1]);
+    }
+}
+
+enum MergeStep {
+    ChooseTitle,
+    ChooseBody { title: Pick },
+    Confirm { merged: Box<Snippet> },
+}
+
+/// Lists near-duplicate pairs and walks through merging one of them
+pub struct DedupePopup {
+    snippets: Vec<Snippet>,
+    pairs: Vec<DuplicatePair>,
+    selected: usize,
+    step: Option<MergeStep>,
+}
+
+impl DedupePopup {
+    pub fn new(snippets: &[Snippet]) -> DedupePopup {
+        DedupePopup {
+            snippets: snippets.to_vec(),
+            pairs: find_near_duplicates(snippets),
+            selected: 0,
+            step: None,
+        }
+    }
+
+    fn current_pair(&self) -> Option<(&Snippet, &Snippet)> {
+        let pair = self.pairs.get(self.selected)?;
+        Some((&self.snippets[pair.left], &self.snippets[pair.right]))
+    }
+
+    fn handle_key(&mut self, key: KeyCode) -> PopupOutcome {
+        let (left, right) = match self.current_pair() {
+            Some(pair) => pair,
+            None => return PopupOutcome::Close,
+        };
+
+        match (&self.step, key) {
+            (None, KeyCode::Esc | KeyCode::Char('q')) => return PopupOutcome::Close,
+            (None, KeyCode::Down | KeyCode::Char('j')) => {
+                self.selected = (self.selected + 1).min(self.pairs.len() - 1)
+            }
+            (None, KeyCode::Up | KeyCode::Char('k')) => {
+                self.selected = self.selected.saturating_sub(1)
+            }
+            (None, KeyCode::Enter) => self.step = Some(MergeStep::ChooseTitle),
+            (Some(_), KeyCode::Esc) => self.step = None,
+            (Some(MergeStep::ChooseTitle), KeyCode::Char('1')) => {
+                self.step = Some(MergeStep::ChooseBody { title: Pick::Left })
+            }
+            (Some(MergeStep::ChooseTitle), KeyCode::Char('2')) => {
+                self.step = Some(MergeStep::ChooseBody { title: Pick::Right })
+            }
+            (Some(MergeStep::ChooseBody { title }), KeyCode::Char(c @ ('1' | '2' | 'b'))) => {
+                let body = match c {
+                    '1' => Pick::Left,
+                    '2' => Pick::Right,
+                    _ => Pick::Both,
+                };
+                let merged = merge_snippets(left, right, *title, body);
+                self.step = Some(MergeStep::Confirm {
+                    merged: Box::new(merged),
+                });
+            }
+            (Some(MergeStep::Confirm { merged }), KeyCode::Enter) => {
+                let pair = &self.pairs[self.selected];
+                return PopupOutcome::Merge {
+                    keep: pair.left,
+                    remove: pair.right,
+                    merged: merged.as_ref().clone(),
+                };
+            }
+            _ => {}
+        }
+        PopupOutcome::Keep
+    }
+
+    fn render<B: Backend>(&self, f: &mut Frame<B>, theme: &Theme) {
+        let area = centered_rect(80, 70, f.size());
+        f.render_widget(Clear, area);
+
+        let (left, right) = match self.current_pair() {
+            Some(pair) => pair,
+            None => {
+                let empty = Paragraph::new("No near-duplicate snippets found. Press any key.")
+                    .style(theme.text)
+                    .block(Block::default().borders(Borders::ALL).title("Duplicates"));
+                f.render_widget(empty, area);
+                return;
+            }
+        };
+
+        let key = |k: &'static str| Span::styled(k, theme.key_hint);
+        let mut lines: Vec<Spans> = Vec::new();
+        let title = match &self.step {
+            None => {
+                for (index, pair) in self.pairs.iter().enumerate() {
+                    let style = if index == self.selected {
+                        theme.selected
+                    } else {
+                        theme.text
+                    };
+                    lines.push(Spans::from(Span::styled(
+                        format!(
+                            "{:>3.0}%  {}  <->  {}",
+                            pair.score * 100.0,
+                            self.snippets[pair.left].title,
+                            self.snippets[pair.right].title
+                        ),
+                        style,
+                    )));
+                }
+                "Near-duplicates (Enter to merge, Esc to close)"
+            }
+            Some(MergeStep::ChooseTitle) => {
+                lines.push(Spans::from(vec![key("1 "), Span::raw(left.title.as_str())]));
+                lines.push(Spans::from(vec![
+                    key("2 "),
+                    Span::raw(right.title.as_str()),
+                ]));
+                "Merge: which title should be kept?"
+            }
+            Some(MergeStep::ChooseBody { .. }) => {
+                lines.push(Spans::from(vec![
+                    key("1 "),
+                    Span::raw("keep the first body"),
+                ]));
+                push_text(&mut lines, &left.description, theme.text);
+                lines.push(Spans::from(vec![
+                    key("2 "),
+                    Span::raw("keep the second body"),
+                ]));
+                push_text(&mut lines, &right.description, theme.text);
+                lines.push(Spans::from(vec![
+                    key("b "),
+                    Span::raw("combine both bodies"),
+                ]));
+                "Merge: which body should be kept?"
+            }
+            Some(MergeStep::Confirm { merged }) => {
+                lines.push(Spans::from(Span::styled(
+                    merged.title.as_str(),
+                    theme.key_hint,
+                )));
+                if !merged.tags.is_empty() {
+                    lines.push(Spans::from(Span::styled(
+                        format!("Tags: {}", merged.tags.join(", ")),
+                        theme.text,
+                    )));
+                }
+                push_text(&mut lines, &merged.description, theme.text);
+                lines.push(Spans::from(vec![
+                    key("Enter"),
+                    Span::raw(" to replace both snippets with this one, "),
+                    key("Esc"),
+                    Span::raw(" to cancel"),
+                ]));
+                "Merge: confirm"
+            }
+        };
+
+        let paragraph =
+            Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(title));
+        f.render_widget(paragraph, area);
+    }
+}
+
+/// Form asking for a value for each placeholder before a templated snippet is copied
+pub struct FillPopup {
+    index: usize,
+    title: String,
+    template: String,
+    /// Fingerprint of the commands the snippet may run without asking
+    trusted: Option<String>,
+    fields: Vec<Placeholder>,
+    values: Vec<String>,
+    /// Selected option for each enum field
+    choices: Vec<usize>,
+    /// Previously used values for each field, most recent first
+    history: Vec<Vec<String>>,
+    /// Position in `history` of the value shown in each field
+    history_position: Vec<usize>,
+    focused: usize,
+    error: Option<String>,
+    /// Confirms running the filled in body rather than copying it
+    run: Option<RunPopup>,
+}
+
+impl FillPopup {
+    pub fn new(index: usize, snippet: &Snippet, fields: Vec<Placeholder>) -> FillPopup {
+        let history: Vec<Vec<String>> = fields
+            .iter()
+            .map(|field| {
+                snippet
+                    .parameter_history
+                    .get(&field.name)
+                    .cloned()
+                    .unwrap_or_default()
+            })
+            .collect();
+
+        // Pre-fill every field with the value used last time, or the declared default
+        let values = history
+            .iter()
+            .zip(&fields)
+            .map(|(previous, field)| {
+                previous
+                    .first()
+                    .or_else(|| snippet.field_defaults.get(&field.name))
+                    .cloned()
+                    .unwrap_or_default()
+            })
+            .collect::<Vec<String>>();
+        let choices = fields
+            .iter()
+            .zip(&values)
+            .map(|(field, value)| match &field.field_type {
+                FieldType::Enum(options) => options.iter().position(|o| o == value).unwrap_or(0),
+                _ => 0,
+            })
+            .collect();
+
+        FillPopup {
+            index,
+            title: snippet.title.clone(),
+            template: snippet.description.clone(),
+            trusted: snippet.trusted_commands.clone(),
+            values,
+            choices,
+            history_position: vec![0; fields.len()],
+            history,
+            fields,
+            focused: 0,
+            error: None,
+            run: None,
+        }
+    }
+
+    /// Opens `run` with the body filled in, instead of copying it
+    pub fn then_run(mut self, run: RunPopup) -> FillPopup {
+        self.run = Some(run);
+        self
+    }
+
+    /// Shows the next (`step` = 1) or previous (`step` = -1) remembered value in the focused field
+    fn cycle_history(&mut self, step: isize) {
+        let history = &self.history[self.focused];
+        if history.is_empty() {
+            return;
+        }
+        let len = history.len() as isize;
+        let position = (self.history_position[self.focused] as isize + step).rem_euclid(len);
+        self.history_position[self.focused] = position as usize;
+        self.values[self.focused] = history[position as usize].clone();
+    }
+
+    fn value(&self, index: usize) -> String {
+        match &self.fields[index].field_type {
+            FieldType::Enum(options) => options
+                .get(self.choices[index])
+                .cloned()
+                .unwrap_or_default(),
+            _ => self.values[index].clone(),
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyCode) -> PopupOutcome {
+        let field_count = self.fields.len();
+        let options = match &self.fields[self.focused].field_type {
+            FieldType::Enum(options) => Some(options.len()),
+            _ => None,
+        };
+
+        match (key, options) {
+            (KeyCode::Esc, _) => return PopupOutcome::Close,
+            (KeyCode::Tab, _) => self.focused = (self.focused + 1) % field_count,
+            (KeyCode::BackTab, _) => self.focused = (self.focused + field_count - 1) % field_count,
+            (KeyCode::Down | KeyCode::Char('j'), Some(count)) if count > 0 => {
+                self.choices[self.focused] = (self.choices[self.focused] + 1) % count
+            }
+            (KeyCode::Up | KeyCode::Char('k'), Some(count)) if count > 0 => {
+                self.choices[self.focused] = (self.choices[self.focused] + count - 1) % count
+            }
+            (KeyCode::Down, None) => self.cycle_history(1),
+            (KeyCode::Up, None) => self.cycle_history(-1),
+            (KeyCode::Char(c), None) => self.values[self.focused].push(c),
+            (KeyCode::Backspace, None) => {
+                self.values[self.focused].pop();
+            }
+            (KeyCode::Enter, _) => {
+                let value = self.value(self.focused);
+                if let Err(error) = self.fields[self.focused].field_type.validate(&value) {
+                    self.error = Some(error);
+                    return PopupOutcome::Keep;
+                }
+                self.error = None;
+
+                if self.focused + 1 < field_count {
+                    self.focused += 1;
+                    return PopupOutcome::Keep;
+                }
+
+                // Every field is checked again in case one was skipped with Tab
+                let mut values = HashMap::new();
+                for (index, field) in self.fields.iter().enumerate() {
+                    let value = self.value(index);
+                    if let Err(error) = field.field_type.validate(&value) {
+                        self.focused = index;
+                        self.error = Some(format!("{}: {}", field.name, error));
+                        return PopupOutcome::Keep;
+                    }
+                    values.insert(field.name.clone(), value);
+                }
+
+                if let Some(run) = self.run.take() {
+                    let command = template::render_segments(&self.template, &values)
+                        .into_iter()
+                        .map(|segment| segment.text)
+                        .collect();
+                    return PopupOutcome::Open(Box::new(Popup::Run(run.filled(command, values))));
+                }
+                return PopupOutcome::Open(Box::new(preview_popup(
+                    self.index,
+                    &self.title,
+                    &self.template,
+                    self.trusted.as_deref(),
+                    values,
+                )));
+            }
+            _ => {}
+        }
+        PopupOutcome::Keep
+    }
+
+    fn render<B: Backend>(&self, f: &mut Frame<B>, theme: &Theme) {
+        let area = centered_rect(60, 60, f.size());
+        f.render_widget(Clear, area);
+
+        let mut lines: Vec<Spans> = Vec::new();
+        for (index, field) in self.fields.iter().enumerate() {
+            let focused = index == self.focused;
+            let label_style = if focused {
+                theme.focused_input
+            } else {
+                theme.text
+            };
+            let label = Span::styled(
+                format!("{} ({}): ", field.name, field.field_type.label()),
+                label_style,
+            );
+
+            match &field.field_type {
+                FieldType::Enum(options) => {
+                    lines.push(Spans::from(vec![label, Span::raw(self.value(index))]));
+                    if focused {
+                        for (option_index, option) in options.iter().enumerate() {
+                            let style = if option_index == self.choices[index] {
+                                theme.selected
+                            } else {
+                                theme.text
+                            };
+                            lines.push(Spans::from(Span::styled(format!("    {}", option), style)));
+                        }
+                    }
+                }
+                _ => {
+                    let cursor = if focused { "_" } else { "" };
+                    lines.push(Spans::from(vec![
+                        label,
+                        Span::raw(format!("{}{}", self.values[index], cursor)),
+                    ]));
+                }
+            }
+        }
+
+        lines.push(Spans::from(""));
+        if let Some(error) = &self.error {
+            lines.push(Spans::from(Span::styled(
+                error.as_str(),
+                theme.text.fg(Color::Red),
+            )));
+        }
+        lines.push(Spans::from(vec![
+            Span::styled("Enter", theme.key_hint),
+            Span::raw(match self.run {
+                Some(_) => " next/run, ",
+                None => " next/copy, ",
+            }),
+            Span::styled("Tab", theme.key_hint),
+            Span::raw(" switch field, "),
+            Span::styled("Up/Down", theme.key_hint),
+            Span::raw(" pick a choice or a previous value, "),
+            Span::styled("Esc", theme.key_hint),
+            Span::raw(" cancel"),
+        ]));
+
+        let paragraph = Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(format!("Fill in '{}'", self.title)),
+        );
+        f.render_widget(paragraph, area);
+    }
+}
+
+/// The preview of a template, preceded by asking whether its commands may run
+/// unless exactly these commands were allowed for good
+pub fn preview_popup(
+    index: usize,
+    title: &str,
+    template: &str,
+    trusted: Option<&str>,
+    parameters: HashMap<String, String>,
+) -> Popup {
+    match template::commands_fingerprint(template) {
+        Some(fingerprint) if trusted != Some(fingerprint.as_str()) => Popup::Trust(TrustPopup {
+            index,
+            title: title.to_string(),
+            template: template.to_string(),
+            commands: template::commands(template),
+            fingerprint,
+            parameters,
+        }),
+        _ => Popup::Preview(PreviewPopup::new(index, title, template, parameters)),
+    }
+}
+
+/// Shows the exact commands a template is about to run and asks for permission
+pub struct TrustPopup {
+    index: usize,
+    title: String,
+    template: String,
+    commands: Vec<String>,
+    fingerprint: String,
+    parameters: HashMap<String, String>,
+}
+
+impl TrustPopup {
+    fn preview(&mut self) -> Box<Popup> {
+        Box::new(Popup::Preview(PreviewPopup::new(
+            self.index,
+            &self.title,
+            &self.template,
+            std::mem::take(&mut self.parameters),
+        )))
+    }
+
+    fn handle_key(&mut self, key: KeyCode) -> PopupOutcome {
+        match key {
+            KeyCode::Esc | KeyCode::Char('n') | KeyCode::Char('q') => PopupOutcome::Close,
+            KeyCode::Char('y') => PopupOutcome::Open(self.preview()),
+            KeyCode::Char('a') => PopupOutcome::Trust {
+                index: self.index,
+                fingerprint: self.fingerprint.clone(),
+                next: self.preview(),
+            },
+            _ => PopupOutcome::Keep,
+        }
+    }
+
+    fn render<B: Backend>(&self, f: &mut Frame<B>, theme: &Theme) {
+        let area = centered_rect(80, 50, f.size());
+        f.render_widget(Clear, area);
+
+        let mut lines = vec![
+            Spans::from(Span::styled(
+                format!("'{}' runs these commands when expanded:", self.title),
+                theme.text,
+            )),
+            Spans::default(),
+        ];
+        for command in &self.commands {
+            lines.push(Spans::from(Span::styled(
+                format!("$ {}", command),
+                theme.highlight,
+            )));
+        }
+        lines.push(Spans::default());
+        lines.push(Spans::from(vec![
+            Span::styled("y", theme.key_hint),
+            Span::styled(" run once  ", theme.text),
+            Span::styled("a", theme.key_hint),
+            Span::styled(" always allow until they change  ", theme.text),
+            Span::styled("n", theme.key_hint),
+            Span::styled(" cancel", theme.text),
+        ]));
+
+        let paragraph = Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Run commands?"),
+        );
+        f.render_widget(paragraph, area);
+    }
+}
+
+/// Shows the fully expanded text of a templated snippet and copies it once confirmed
+pub struct PreviewPopup {
+    index: usize,
+    title: String,
+    segments: Vec<template::Segment>,
+    parameters: HashMap<String, String>,
+    scroll: u16,
+}
+
+impl PreviewPopup {
+    pub fn new(
+        index: usize,
+        title: &str,
+        template: &str,
+        parameters: HashMap<String, String>,
+    ) -> PreviewPopup {
+        PreviewPopup {
+            index,
+            title: title.to_string(),
+            segments: template::render_segments(template, &parameters),
+            parameters,
+            scroll: 0,
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyCode) -> PopupOutcome {
+        match key {
+            KeyCode::Esc | KeyCode::Char('q') => return PopupOutcome::Close,
+            KeyCode::Down | KeyCode::Char('j') => self.scroll = self.scroll.saturating_add(1),
+            KeyCode::Up | KeyCode::Char('k') => self.scroll = self.scroll.saturating_sub(1),
+            KeyCode::Enter | KeyCode::Char('c') | KeyCode::Char('y') => {
+                return PopupOutcome::Copy {
+                    index: self.index,
+                    text: self.segments.iter().map(|s| s.text.as_str()).collect(),
+                    parameters: std::mem::take(&mut self.parameters),
+                }
+            }
+            _ => {}
+        }
+        PopupOutcome::Keep
+    }
+
+    fn render<B: Backend>(&self, f: &mut Frame<B>, theme: &Theme) {
+        let area = centered_rect(80, 70, f.size());
+        f.render_widget(Clear, area);
+
+        // Split the segments on newlines so highlighted parts stay on their own lines
+        let mut lines: Vec<Spans> = vec![Spans::default()];
+        for segment in &self.segments {
+            let style = if segment.substituted {
+                theme.highlight
+            } else {
+                theme.text
+            };
+            for (i, part) in segment.text.split('\n').enumerate() {
+                if i > 0 {
+                    lines.push(Spans::default());
+                }
+                if !part.is_empty() {
+                    lines.last_mut().unwrap().0.push(Span::styled(part, style));
+                }
+            }
+        }
+
+        let paragraph = Paragraph::new(lines)
+            .block(Block::default().borders(Borders::ALL).title(format!(
+                "Preview '{}' (Enter to copy, Esc to cancel)",
+                self.title
+            )))
+            .scroll((self.scroll, 0));
+        f.render_widget(paragraph, area);
+    }
+}
+
+/// What the value typed into an `InputPopup` is used for
+#[derive(Clone)]
+pub enum InputPurpose {
+    Schedule {
+        index: usize,
+    },
+    Tags {
+        index: usize,
+    },
+    /// The declaration read by `run::parse_declaration`
+    RunEnvironment {
+        index: usize,
+    },
+    /// Path of a file to import snippets from
+    Import,
+    /// Path to write a copy of the store to
+    Export,
+    /// What to ask the language model for, about the snippet at `index` when given
+    Generate {
+        index: Option<usize>,
+    },
+    /// A substitution read by `replace::Substitution::parse`
+    FindReplace,
+}
+
+/// Single line prompt
+pub struct InputPopup {
+    title: String,
+    hint: String,
+    purpose: InputPurpose,
+    value: String,
+}
+
+impl InputPopup {
+    pub fn new(title: &str, hint: &str, purpose: InputPurpose, value: String) -> InputPopup {
+        InputPopup {
+            title: title.to_string(),
+            hint: hint.to_string(),
+            purpose,
+            value,
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyCode) -> PopupOutcome {
+        match key {
+            KeyCode::Esc => return PopupOutcome::Close,
+            KeyCode::Enter => {
+                return PopupOutcome::Submit(self.purpose.clone(), std::mem::take(&mut self.value))
+            }
+            KeyCode::Char(c) => self.value.push(c),
+            KeyCode::Backspace => {
+                self.value.pop();
+            }
+            _ => {}
+        }
+        PopupOutcome::Keep
+    }
+
+    fn render<B: Backend>(&self, f: &mut Frame<B>, theme: &Theme) {
+        let area = centered_rect(60, 20, f.size());
+        f.render_widget(Clear, area);
+
+        let lines = vec![
+            Spans::from(Span::styled(
+                format!("{}_", self.value),
+                theme.focused_input,
+            )),
+            Spans::from(""),
+            Spans::from(Span::styled(self.hint.as_str(), theme.text)),
+        ];
+        let paragraph = Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(self.title.as_str()),
+        );
+        f.render_widget(paragraph, area);
+    }
+}
+
+/// Browses earlier states of the store and restores single snippets from them
+pub struct HistoryPopup {
+    states: Vec<StoreState>,
+    /// Titles in the current store, to point out what has been deleted since
+    current: HashSet<String>,
+    selected: usize,
+    /// The state being looked into and the selected snippet in it
+    open: Option<(usize, usize)>,
+}
+
+impl HistoryPopup {
+    pub fn new(states: Vec<StoreState>, current: &[Snippet]) -> HistoryPopup {
+        HistoryPopup {
+            states,
+            current: current.iter().map(|s| s.title.clone()).collect(),
+            selected: 0,
+            open: None,
+        }
+    }
+
+    fn deleted_count(&self, state: &StoreState) -> usize {
+        state
+            .snippets
+            .iter()
+            .filter(|s| !self.current.contains(&s.title))
+            .count()
+    }
+
+    fn handle_key(&mut self, key: KeyCode) -> PopupOutcome {
+        match (&mut self.open, key) {
+            (None, KeyCode::Esc | KeyCode::Char('q')) => return PopupOutcome::Close,
+            (None, KeyCode::Down | KeyCode::Char('j')) => {
+                self.selected = (self.selected + 1).min(self.states.len().saturating_sub(1))
+            }
+            (None, KeyCode::Up | KeyCode::Char('k')) => {
+                self.selected = self.selected.saturating_sub(1)
+            }
+            (None, KeyCode::Enter) if !self.states.is_empty() => {
+                self.open = Some((self.selected, 0))
+            }
+            (Some(_), KeyCode::Esc) => self.open = None,
+            (Some((state, snippet)), KeyCode::Down | KeyCode::Char('j')) => {
+                let count = self.states[*state].snippets.len();
+                *snippet = (*snippet + 1).min(count.saturating_sub(1))
+            }
+            (Some((_, snippet)), KeyCode::Up | KeyCode::Char('k')) => {
+                *snippet = snippet.saturating_sub(1)
+            }
+            (Some((state, snippet)), KeyCode::Enter) => {
+                if let Some(restored) = self.states[*state].snippets.get(*snippet) {
+                    return PopupOutcome::Restore(restored.clone());
+                }
+            }
+            _ => {}
+        }
+        PopupOutcome::Keep
+    }
+
+    fn render<B: Backend>(&self, f: &mut Frame<B>, theme: &Theme) {
+        let area = centered_rect(80, 70, f.size());
+        f.render_widget(Clear, area);
+
+        let style = |selected: bool| if selected { theme.selected } else { theme.text };
+        let (lines, title): (Vec<Spans>, String) = match self.open {
+            None => (
+                self.states
+                    .iter()
+                    .enumerate()
+                    .map(|(index, state)| {
+                        Spans::from(Span::styled(
+                            format!(
+                                "until {}  {} snippets, {} deleted since",
+                                time_utils::format_datetime(state.until),
+                                state.snippets.len(),
+                                self.deleted_count(state)
+                            ),
+                            style(index == self.selected),
+                        ))
+                    })
+                    .collect(),
+                "History (Enter to look inside, Esc to close)".to_string(),
+            ),
+            Some((state, selected)) => {
+                let state = &self.states[state];
+                (
+                    state
+                        .snippets
+                        .iter()
+                        .enumerate()
+                        .map(|(index, snippet)| {
+                            let marker = if self.current.contains(&snippet.title) {
+                                "  "
+                            } else {
+                                "+ "
+                            };
+                            Spans::from(Span::styled(
+                                format!("{}{}", marker, snippet.title),
+                                style(index == selected),
+                            ))
+                        })
+                        .collect(),
+                    format!(
+                        "Until {}, + is deleted now (Enter to restore, Esc to go back)",
+                        time_utils::format_datetime(state.until)
+                    ),
+                )
+            }
+        };
+
+        let lines = if lines.is_empty() {
+            vec![Spans::from(
+                "No earlier states yet, they are kept as snippets are saved",
+            )]
+        } else {
+            lines
+        };
+        let scroll = match self.open {
+            Some((_, selected)) => selected,
+            None => self.selected,
+        };
+        let visible = area.height.saturating_sub(2) as usize;
+        let paragraph = Paragraph::new(lines)
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .scroll((scroll.saturating_sub(visible.saturating_sub(1)) as u16, 0));
+        f.render_widget(paragraph, area);
+    }
+}
+
+/// Lists incoming snippets with checkboxes so an import can be reviewed
+/// before anything is written
+pub struct ImportPopup {
+    source: String,
+    incoming: Vec<Snippet>,
+    included: Vec<bool>,
+    /// A snippet with the same title exists and would be replaced
+    collides: Vec<bool>,
+    /// Title of a snippet with the same body under another title, in the
+    /// store or earlier in the import
+    duplicate_of: Vec<Option<String>>,
+    /// Shown after each title, such as how often a command from the shell history ran
+    notes: Vec<String>,
+    selected: usize,
+}
+
+impl ImportPopup {
+    /// Everything is included except snippets that would replace an existing
+    /// one or that are already there under another title
+    pub fn new(source: &str, incoming: Vec<Snippet>, current: &[Snippet]) -> ImportPopup {
+        let titles: HashSet<&str> = current.iter().map(|s| s.title.as_str()).collect();
+        let collides: Vec<bool> = incoming
+            .iter()
+            .map(|s| titles.contains(s.title.as_str()))
+            .collect();
+        let mut bodies: HashMap<&str, &str> = HashMap::new();
+        for snippet in current.iter().chain(&incoming).rev() {
+            bodies.insert(snippet.description.trim(), &snippet.title);
+        }
+        let duplicate_of: Vec<Option<String>> = incoming
+            .iter()
+            .map(|s| {
+                bodies
+                    .get(s.description.trim())
+                    .filter(|_| !s.description.trim().is_empty())
+                    .filter(|title| **title != s.title)
+                    .map(|title| title.to_string())
+            })
+            .collect();
+        ImportPopup {
+            source: source.to_string(),
+            included: collides
+                .iter()
+                .zip(&duplicate_of)
+                .map(|(collides, duplicate_of)| !collides && duplicate_of.is_none())
+                .collect(),
+            collides,
+            duplicate_of,
+            notes: vec![],
+            incoming,
+            selected: 0,
+        }
+    }
+
+    /// Starts with nothing included, for long lists where only a few are kept
+    pub fn none_included(mut self) -> ImportPopup {
+        self.included
+            .iter_mut()
+            .for_each(|included| *included = false);
+        self
+    }
+
+    /// Shows `notes[i]` after the title of the `i`-th snippet
+    pub fn with_notes(mut self, notes: Vec<String>) -> ImportPopup {
+        self.notes = notes;
+        self
+    }
+
+    fn handle_key(&mut self, key: KeyCode) -> PopupOutcome {
+        match key {
+            KeyCode::Esc | KeyCode::Char('q') => return PopupOutcome::Close,
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.selected = (self.selected + 1).min(self.incoming.len().saturating_sub(1))
+            }
+            KeyCode::Up | KeyCode::Char('k') => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Char(' ') => {
+                if let Some(included) = self.included.get_mut(self.selected) {
+                    *included = !*included;
+                }
+            }
+            // Include everything, or nothing when everything already is
+            KeyCode::Char('a') => {
+                let all = self.included.iter().all(|included| *included);
+                self.included
+                    .iter_mut()
+                    .for_each(|included| *included = !all);
+            }
+            KeyCode::Enter => {
+                let chosen = self
+                    .incoming
+                    .iter()
+                    .zip(&self.included)
+                    .filter(|(_, included)| **included)
+                    .map(|(snippet, _)| snippet.clone())
+                    .collect();
+                return PopupOutcome::Import(chosen);
+            }
+            _ => {}
+        }
+        PopupOutcome::Keep
+    }
+
+    fn render<B: Backend>(&self, f: &mut Frame<B>, theme: &Theme) {
+        let area = centered_rect(80, 70, f.size());
+        f.render_widget(Clear, area);
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(1), Constraint::Length(2)].as_ref())
+            .split(area);
+
+        let lines: Vec<Spans> = self
+            .incoming
+            .iter()
+            .enumerate()
+            .map(|(index, snippet)| {
+                let style = if index == self.selected {
+                    theme.selected
+                } else {
+                    theme.text
+                };
+                let checkbox = if self.included[index] { "[x] " } else { "[ ] " };
+                let mut spans = vec![Span::styled(
+                    format!("{}{}", checkbox, snippet.title),
+                    style,
+                )];
+                if let Some(note) = self.notes.get(index) {
+                    spans.push(Span::styled(format!("  {}", note), theme.namespace));
+                }
+                if self.collides[index] {
+                    spans.push(Span::styled("  ! replaces existing", theme.key_hint));
+                }
+                if let Some(title) = &self.duplicate_of[index] {
+                    spans.push(Span::styled(
+                        format!("  = same as '{}'", title),
+                        theme.key_hint,
+                    ));
+                }
+                Spans::from(spans)
+            })
+            .collect();
+        let visible = chunks[0].height.saturating_sub(2) as usize;
+        let list = Paragraph::new(lines)
+            .block(
+                Block::default()
+                    .borders(Borders::TOP | Borders::LEFT | Borders::RIGHT)
+                    .title(format!("Import from {}", self.source)),
+            )
+            .scroll((
+                self.selected.saturating_sub(visible.saturating_sub(1)) as u16,
+                0,
+            ));
+        f.render_widget(list, chunks[0]);
+
+        let included = self.included.iter().filter(|included| **included).count();
+        let replacing = (0..self.incoming.len())
+            .filter(|&index| self.included[index] && self.collides[index])
+            .count();
+        let key = |k: &'static str| Span::styled(k, theme.key_hint);
+        let summary = Paragraph::new(Spans::from(vec![
+            Span::raw(format!(
+                "{} of {} selected, {} replacing existing  ",
+                included,
+                self.incoming.len(),
+                replacing
+            )),
+            key("Space"),
+            Span::raw(" toggle  "),
+            key("a"),
+            Span::raw(" all  "),
+            key("Enter"),
+            Span::raw(" import  "),
+            key("Esc"),
+            Span::raw(" cancel"),
+        ]))
+        .style(theme.text)
+        .block(Block::default().borders(Borders::BOTTOM | Borders::LEFT | Borders::RIGHT));
+        f.render_widget(summary, chunks[1]);
+    }
+}
+
+/// What a run of a snippet printed, with the option to keep it as a snippet
+pub struct OutputPopup {
+    source: String,
+    output: RunOutput,
+    scroll: u16,
+}
+
+impl OutputPopup {
+    pub fn new(source: &str, output: RunOutput) -> OutputPopup {
+        OutputPopup {
+            source: source.to_string(),
+            output,
+            scroll: 0,
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyCode) -> PopupOutcome {
+        match key {
+            KeyCode::Esc | KeyCode::Char('q') => return PopupOutcome::Close,
+            KeyCode::Down | KeyCode::Char('j') => self.scroll = self.scroll.saturating_add(1),
+            KeyCode::Up | KeyCode::Char('k') => self.scroll = self.scroll.saturating_sub(1),
+            KeyCode::Char('s') => {
+                let mut description = self.output.stdout.clone();
+                if !self.output.stderr.is_empty() {
+                    if !description.is_empty() && !description.ends_with('\n') {
+                        description.push('\n');
+                    }
+                    description.push_str(&self.output.stderr);
+                }
+                let title = format!(
+                    "{} output {}",
+                    self.source,
+                    time_utils::format_datetime(time_utils::now())
+                );
+                let mut snippet = Snippet::new(title, description);
+                snippet.tags = vec!["output".to_string()];
+                snippet.output_of = Some(self.source.clone());
+                return PopupOutcome::Add(snippet);
+            }
+            _ => {}
+        }
+        PopupOutcome::Keep
+    }
+
+    fn render<B: Backend>(&self, f: &mut Frame<B>, theme: &Theme) {
+        let area = centered_rect(90, 80, f.size());
+        f.render_widget(Clear, area);
+
+        let mut lines: Vec<Spans> = Vec::new();
+        push_text(&mut lines, &self.output.stdout, theme.text);
+        if !self.output.stderr.is_empty() {
+            push_text(&mut lines, &self.output.stderr, theme.text.fg(Color::Red));
+        }
+        lines.push(Spans::from(""));
+        lines.push(Spans::from(Span::styled(
+            format!(
+                "{} in {:.1}s",
+                self.output.status,
+                self.output.duration.as_secs_f64()
+            ),
+            theme.key_hint,
+        )));
+
+        let paragraph = Paragraph::new(lines)
+            .block(Block::default().borders(Borders::ALL).title(format!(
+                "Output of '{}' (s to save as a snippet, j/k to scroll, Esc to close)",
+                self.source
+            )))
+            .scroll((self.scroll, 0));
+        f.render_widget(paragraph, area);
+    }
+}
+
+/// Keys of the sidebar, which are not in the keymap
+const SIDEBAR_KEYS: [(&str, &str); 4] = [
+    ("j, k, <Down>, <Up>", "Pick the next or previous namespace"),
+    ("g, G", "Pick All or the last namespace"),
+    ("<Enter>, <Tab>, l", "Go back to the snippets"),
+    ("<Esc>", "List all snippets and go back"),
+];
+
+/// Keys while typing a search after `/`
+const SEARCH_KEYS: [(&str, &str); 4] = [
+    ("<Enter>", "Keep the search"),
+    ("<Esc>", "Clear the search"),
+    ("<Down>, <Up>", "Select the next or previous row"),
+    ("<BS>", "Delete the last character"),
+];
+
+/// Keys of the form for a new snippet
+const FORM_KEYS: [(&str, &str); 8] = [
+    ("<Tab>", "Move to the next field"),
+    ("<C-Left>, <C-Right>", "Move the cursor by word"),
+    (
+        "<Enter>",
+        "Move to the next field, add the snippet on the last one",
+    ),
+    ("<A-Enter>, <C-j>", "Start a new line in the description"),
+    ("<C-v>", "Paste from the clipboard"),
+    (
+        "<C-p>",
+        "Switch how text pasted into the description is cleaned up",
+    ),
+    ("<C-t>", "Make the new snippet temporary, or keep it"),
+    ("<Esc>", "Leave the form, the draft stays"),
+];
+
+/// Every key by mode, the Normal mode ones as bound in the keymap, `[keys]`
+/// of the config included
+pub struct HelpPopup {
+    sections: Vec<(&'static str, Vec<(String, String)>)>,
+    scroll: u16,
+    /// The scroll bringing the last line to the bottom, known once drawn
+    max_scroll: Cell<u16>,
+}
+
+impl HelpPopup {
+    pub fn new(keymap: &Keymap) -> HelpPopup {
+        let fixed = |keys: &[(&str, &str)]| -> Vec<(String, String)> {
+            keys.iter()
+                .map(|(keys, description)| (keys.to_string(), description.to_string()))
+                .collect()
+        };
+        let normal = keymap
+            .describe()
+            .into_iter()
+            .map(|(keys, action)| (keys, action.describe().to_string()))
+            .collect();
+        let mut search = fixed(&SEARCH_KEYS);
+        if cfg!(feature = "semantic") {
+            search.push((
+                "~".to_string(),
+                "Search by meaning, starting the search with it".to_string(),
+            ));
+        }
+        HelpPopup {
+            sections: vec![
+                ("Snippets", normal),
+                ("Sidebar (after Tab)", fixed(&SIDEBAR_KEYS)),
+                ("Search (after /)", search),
+                ("Form (after e)", fixed(&FORM_KEYS)),
+            ],
+            scroll: 0,
+            max_scroll: Cell::new(u16::MAX),
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyCode) -> PopupOutcome {
+        let last = self.max_scroll.get();
+        self.scroll = match key {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('?') => return PopupOutcome::Close,
+            KeyCode::Down | KeyCode::Char('j') => self.scroll.saturating_add(1),
+            KeyCode::Up | KeyCode::Char('k') => self.scroll.saturating_sub(1),
+            KeyCode::PageDown | KeyCode::Char(' ') => self.scroll.saturating_add(10),
+            KeyCode::PageUp => self.scroll.saturating_sub(10),
+            KeyCode::Home | KeyCode::Char('g') => 0,
+            KeyCode::End | KeyCode::Char('G') => last,
+            _ => self.scroll,
+        }
+        .min(last);
+        PopupOutcome::Keep
+    }
+
+    fn render<B: Backend>(&self, f: &mut Frame<B>, theme: &Theme) {
+        let area = centered_rect(70, 80, f.size());
+        f.render_widget(Clear, area);
+
+        let key_width = self
+            .sections
+            .iter()
+            .flat_map(|(_, keys)| keys.iter().map(|(keys, _)| keys.chars().count()))
+            .max()
+            .unwrap_or_default();
+        let mut lines: Vec<Spans> = Vec::new();
+        for (name, keys) in &self.sections {
+            lines.push(Spans::from(Span::styled(*name, theme.header)));
+            for (keys, description) in keys {
+                lines.push(Spans::from(vec![
+                    Span::styled(
+                        format!("  {:width$}  ", keys, width = key_width),
+                        theme.key_hint,
+                    ),
+                    Span::styled(description.as_str(), theme.text),
+                ]));
+            }
+            lines.push(Spans::from(""));
+        }
+        lines.pop();
+
+        let height = area.height.saturating_sub(2) as usize;
+        self.max_scroll
+            .set(lines.len().saturating_sub(height) as u16);
+        let paragraph = Paragraph::new(lines)
+            .block(Block::default().borders(Borders::ALL).title(
+                "Keys (j/k to scroll, Esc to close, rebind them under [keys] in the config)",
+            ))
+            .scroll((self.scroll.min(self.max_scroll.get()), 0));
+        f.render_widget(paragraph, area);
+    }
+}
+
+/// Asks before deleting a snippet, with `confirm_delete` set in the config
+pub struct DeletePopup {
+    index: usize,
+    title: String,
+}
+
+impl DeletePopup {
+    pub fn new(index: usize, title: &str) -> DeletePopup {
+        DeletePopup {
+            index,
+            title: title.to_string(),
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyCode) -> PopupOutcome {
+        match key {
+            KeyCode::Char('y') | KeyCode::Enter => PopupOutcome::Delete(self.index),
+            KeyCode::Esc | KeyCode::Char('n') | KeyCode::Char('q') => PopupOutcome::Close,
+            _ => PopupOutcome::Keep,
+        }
+    }
+
+    fn render<B: Backend>(&self, f: &mut Frame<B>, theme: &Theme) {
+        let area = centered_rect(50, 20, f.size());
+        f.render_widget(Clear, area);
+
+        let lines = vec![
+            Spans::from(Span::styled(
+                format!("Delete '{}'?", self.title),
+                theme.text,
+            )),
+            Spans::default(),
+            Spans::from(vec![
+                Span::styled("y", theme.key_hint),
+                Span::styled(" delete  ", theme.text),
+                Span::styled("n", theme.key_hint),
+                Span::styled(" keep it", theme.text),
+            ]),
+        ];
+        let paragraph = Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .block(Block::default().borders(Borders::ALL).title("Delete"));
+        f.render_widget(paragraph, area);
+    }
+}
+
+/// Tells that the body in the form is kept already, before adding it again
+pub struct DuplicatePopup {
+    existing: Existing,
+}
+
+impl DuplicatePopup {
+    pub fn new(existing: Existing) -> DuplicatePopup {
+        DuplicatePopup { existing }
+    }
+
+    fn handle_key(&mut self, key: KeyCode) -> PopupOutcome {
+        match (key, &self.existing) {
+            (KeyCode::Char('a') | KeyCode::Enter, _) => PopupOutcome::AddDuplicate,
+            (KeyCode::Char('g'), Existing::Here { index, .. }) => PopupOutcome::Jump(*index),
+            (KeyCode::Esc | KeyCode::Char('q'), _) => PopupOutcome::Close,
+            _ => PopupOutcome::Keep,
+        }
+    }
+
+    fn render<B: Backend>(&self, f: &mut Frame<B>, theme: &Theme) {
+        let area = centered_rect(50, 20, f.size());
+        f.render_widget(Clear, area);
+
+        let mut keys = vec![
+            Span::styled("a", theme.key_hint),
+            Span::styled(" add anyway  ", theme.text),
+        ];
+        if let Existing::Here { .. } = self.existing {
+            keys.extend([
+                Span::styled("g", theme.key_hint),
+                Span::styled(" go to it  ", theme.text),
+            ]);
+        }
+        keys.extend([
+            Span::styled("Esc", theme.key_hint),
+            Span::styled(" back to the form", theme.text),
+        ]);
+        let lines = vec![
+            Spans::from(Span::styled(
+                format!("{} already has this body.", self.existing.describe()),
+                theme.text,
+            )),
+            Spans::default(),
+            Spans::from(keys),
+        ];
+        let paragraph = Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .block(Block::default().borders(Borders::ALL).title("Kept already"));
+        f.render_widget(paragraph, area);
+    }
+}
+
+/// Confirms running a snippet, showing the command with its working
+/// directory and environment variables and asking for the missing values
+pub struct RunPopup {
+    index: usize,
+    title: String,
+    command: String,
+    dir: Option<PathBuf>,
+    vars: Vec<RunVariable>,
+    focused: usize,
+    error: Option<String>,
+    /// Runs on the terminal with the app suspended, rather than captured
+    in_terminal: bool,
+    /// The placeholder values `command` was filled in with
+    parameters: HashMap<String, String>,
+}
+
+struct RunVariable {
+    name: String,
+    value: String,
+    /// Where `value` came from, e.g. "from the environment"
+    source: &'static str,
+}
+
+impl RunPopup {
+    /// Variables are filled in from sniprrr's environment, or else the
+    /// snippet's defaults, and the first one without a value is focused
+    pub fn new(index: usize, snippet: &Snippet, dir: Option<PathBuf>) -> RunPopup {
+        let vars: Vec<RunVariable> = snippet
+            .env
+            .iter()
+            .map(|(name, default)| {
+                let (value, source) = match std::env::var(name) {
+                    Ok(value) if !value.is_empty() => (value, "from the environment"),
+                    _ if !default.is_empty() => (default.clone(), "default"),
+                    _ => (String::new(), "needed"),
+                };
+                RunVariable {
+                    name: name.clone(),
+                    value,
+                    source,
+                }
+            })
+            .collect();
+        RunPopup {
+            index,
+            title: snippet.title.clone(),
+            command: snippet.description.clone(),
+            dir,
+            focused: vars
+                .iter()
+                .position(|var| var.value.is_empty())
+                .unwrap_or(0),
+            vars,
+            error: None,
+            in_terminal: false,
+            parameters: HashMap::new(),
+        }
+    }
+
+    /// Runs on the terminal once confirmed, see `run::execute`
+    pub fn in_terminal(mut self) -> RunPopup {
+        self.in_terminal = true;
+        self
+    }
+
+    fn filled(mut self, command: String, parameters: HashMap<String, String>) -> RunPopup {
+        self.command = command;
+        self.parameters = parameters;
+        self
+    }
+
+    fn handle_key(&mut self, key: KeyCode) -> PopupOutcome {
+        let count = self.vars.len().max(1);
+        match key {
+            KeyCode::Esc => return PopupOutcome::Close,
+            KeyCode::Tab | KeyCode::Down => self.focused = (self.focused + 1) % count,
+            KeyCode::BackTab | KeyCode::Up => self.focused = (self.focused + count - 1) % count,
+            KeyCode::Char(c) => {
+                if let Some(var) = self.vars.get_mut(self.focused) {
+                    var.value.push(c);
+                    var.source = "entered";
+                }
+            }
+            KeyCode::Backspace => {
+                if let Some(var) = self.vars.get_mut(self.focused) {
+                    var.value.pop();
+                    var.source = "entered";
+                }
+            }
+            KeyCode::Enter => {
+                if let Some(missing) = self.vars.iter().position(|var| var.value.is_empty()) {
+                    self.error = Some(format!("{} needs a value", self.vars[missing].name));
+                    self.focused = missing;
+                    return PopupOutcome::Keep;
+                }
+                let environment = run::Environment {
+                    dir: self.dir.clone(),
+                    vars: self
+                        .vars
+                        .iter()
+                        .map(|var| (var.name.clone(), var.value.clone()))
+                        .collect(),
+                };
+                return match self.in_terminal {
+                    true => PopupOutcome::Execute {
+                        index: self.index,
+                        command: self.command.clone(),
+                        environment,
+                        parameters: std::mem::take(&mut self.parameters),
+                    },
+                    false => PopupOutcome::Run {
+                        index: self.index,
+                        environment,
+                    },
+                };
+            }
+            _ => {}
+        }
+        PopupOutcome::Keep
+    }
+
+    fn render<B: Backend>(&self, f: &mut Frame<B>, theme: &Theme) {
+        let area = centered_rect(70, 60, f.size());
+        f.render_widget(Clear, area);
+
+        let mut lines: Vec<Spans> = self
+            .command
+            .lines()
+            .map(|line| Spans::from(Span::styled(format!("$ {}", line), theme.highlight)))
+            .collect();
+        lines.push(Spans::default());
+        lines.push(Spans::from(Span::styled(
+            match &self.dir {
+                Some(dir) => format!("In {}", dir.display()),
+                None => "In the directory sniprrr was started in".to_string(),
+            },
+            theme.text,
+        )));
+        if !self.vars.is_empty() {
+            lines.push(Spans::default());
+        }
+        for (index, var) in self.vars.iter().enumerate() {
+            let focused = index == self.focused;
+            let style = match focused {
+                true => theme.focused_input,
+                false => theme.text,
+            };
+            let cursor = if focused { "_" } else { "" };
+            lines.push(Spans::from(vec![
+                Span::styled(format!("{}=", var.name), style),
+                Span::raw(format!("{}{}", var.value, cursor)),
+                Span::styled(format!("  ({})", var.source), theme.namespace),
+            ]));
+        }
+
+        lines.push(Spans::default());
+        if let Some(error) = &self.error {
+            lines.push(Spans::from(Span::styled(
+                error.as_str(),
+                theme.text.fg(Color::Red),
+            )));
+        }
+        let mut hints = vec![
+            Span::styled("Enter", theme.key_hint),
+            Span::raw(match self.in_terminal {
+                true => " run in the terminal, ",
+                false => " run, ",
+            }),
+        ];
+        if !self.vars.is_empty() {
+            hints.push(Span::styled("Tab", theme.key_hint));
+            hints.push(Span::raw(" next variable, "));
+        }
+        hints.push(Span::styled("Esc", theme.key_hint));
+        hints.push(Span::raw(" cancel"));
+        lines.push(Spans::from(hints));
+
+        let paragraph = Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(format!("Run '{}'?", self.title)),
+        );
+        f.render_widget(paragraph, area);
+    }
+}
+
+/// Text that stays on screen until it is dismissed
+pub struct MessagePopup {
+    title: String,
+    message: String,
+}
+
+impl MessagePopup {
+    pub fn new(title: &str, message: &str) -> MessagePopup {
+        MessagePopup {
+            title: title.to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyCode) -> PopupOutcome {
+        match key {
+            KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q') => PopupOutcome::Close,
+            _ => PopupOutcome::Keep,
+        }
+    }
+
+    fn render<B: Backend>(&self, f: &mut Frame<B>, theme: &Theme) {
+        let area = centered_rect(60, 30, f.size());
+        f.render_widget(Clear, area);
+
+        let paragraph = Paragraph::new(self.message.as_str())
+            .style(theme.text)
+            .wrap(Wrap { trim: false })
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(format!("{} (Enter to dismiss)", self.title)),
+            );
+        f.render_widget(paragraph, area);
+    }
+}
+
+/// Asks whether several lines pasted into the empty title should become the
+/// title and the description, the first line being the title
+pub struct SplitPastePopup {
+    title: String,
+    description: String,
+}
+
+impl SplitPastePopup {
+    /// `None` when there is only one line, with nothing to split
+    pub fn new(text: &str) -> Option<SplitPastePopup> {
+        let text = text.trim_start_matches(['\n', ' ', '\t']);
+        let (title, rest) = text.split_once('\n')?;
+        let description = rest.trim_start_matches('\n');
+        if description.trim().is_empty() {
+            return None;
+        }
+        Some(SplitPastePopup {
+            title: title.trim().to_string(),
+            description: description.to_string(),
+        })
+    }
+
+    fn handle_key(&mut self, key: KeyCode) -> PopupOutcome {
+        match key {
+            KeyCode::Esc | KeyCode::Char('q') => PopupOutcome::Close,
+            KeyCode::Enter | KeyCode::Char('y') => PopupOutcome::Paste {
+                title: self.title.clone(),
+                description: Some(self.description.clone()),
+            },
+            KeyCode::Char('n') => PopupOutcome::Paste {
+                title: std::iter::once(self.title.as_str())
+                    .chain(self.description.lines())
+                    .collect::<Vec<&str>>()
+                    .join(" "),
+                description: None,
+            },
+            _ => PopupOutcome::Keep,
+        }
+    }
+
+    fn render<B: Backend>(&self, f: &mut Frame<B>, theme: &Theme) {
+        let area = centered_rect(70, 50, f.size());
+        f.render_widget(Clear, area);
+
+        let mut lines = vec![
+            Spans::from(vec![
+                Span::styled("Title: ", theme.text),
+                Span::styled(self.title.as_str(), theme.highlight),
+            ]),
+            Spans::from(Span::styled("Description:", theme.text)),
+        ];
+        let height = area.height.saturating_sub(6) as usize;
+        let description: Vec<&str> = self.description.lines().collect();
+        for line in description.iter().take(height) {
+            lines.push(Spans::from(Span::styled(*line, theme.highlight)));
+        }
+        if description.len() > height {
+            lines.push(Spans::from(Span::styled(
+                format!("... {} more lines", description.len() - height),
+                theme.text,
+            )));
+        }
+        lines.push(Spans::default());
+        lines.push(Spans::from(vec![
+            Span::styled("y", theme.key_hint),
+            Span::styled(" split  ", theme.text),
+            Span::styled("n", theme.key_hint),
+            Span::styled(" paste it all into the title  ", theme.text),
+            Span::styled("Esc", theme.key_hint),
+            Span::styled(" cancel", theme.text),
+        ]));
+
+        let paragraph = Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Use the first line as the title?"),
+        );
+        f.render_widget(paragraph, area);
+    }
+}
+
+/// Shows everything an imported snippet holds, so it can be accepted into
+/// the library or thrown away
+pub struct ReviewPopup {
+    index: usize,
+    snippet: Snippet,
+    /// How many more snippets wait for review after this one
+    remaining: usize,
+    scroll: u16,
+}
+
+impl ReviewPopup {
+    pub fn new(index: usize, snippet: &Snippet, remaining: usize) -> ReviewPopup {
+        ReviewPopup {
+            index,
+            snippet: snippet.clone(),
+            remaining,
+            scroll: 0,
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyCode) -> PopupOutcome {
+        match key {
+            KeyCode::Esc | KeyCode::Char('q') => PopupOutcome::Close,
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.scroll = self.scroll.saturating_add(1);
+                PopupOutcome::Keep
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.scroll = self.scroll.saturating_sub(1);
+                PopupOutcome::Keep
+            }
+            KeyCode::Char('a') => PopupOutcome::Reviewed {
+                index: self.index,
+                accepted: true,
+            },
+            KeyCode::Char('d') => PopupOutcome::Reviewed {
+                index: self.index,
+                accepted: false,
+            },
+            _ => PopupOutcome::Keep,
+        }
+    }
+
+    fn render<B: Backend>(&self, f: &mut Frame<B>, theme: &Theme) {
+        let area = centered_rect(80, 70, f.size());
+        f.render_widget(Clear, area);
+
+        let snippet = &self.snippet;
+        let field = |label: &str, value: String| {
+            Spans::from(vec![
+                Span::styled(format!("{:<10}", label), theme.key_hint),
+                Span::styled(value, theme.text),
+            ])
+        };
+        let mut lines = vec![
+            field("Tags", snippet.tags.join(", ")),
+            field(
+                "Language",
+                snippet.language.clone().unwrap_or_else(|| "-".to_string()),
+            ),
+        ];
+        if !snippet.variants.is_empty() {
+            let names: Vec<&str> = snippet.variants.keys().map(String::as_str).collect();
+            lines.push(field("Variants", names.join(", ")));
+        }
+        let commands: Vec<String> = std::iter::once(&snippet.description)
+            .chain(snippet.variants.values())
+            .flat_map(|body| template::commands(body))
+            .collect();
+        for command in commands {
+            lines.push(Spans::from(Span::styled(
+                format!("Runs when copied: $ {}", command),
+                theme.highlight,
+            )));
+        }
+        lines.push(Spans::default());
+        push_text(&mut lines, &snippet.description, theme.text);
+        for (name, body) in &snippet.variants {
+            lines.push(Spans::default());
+            lines.push(Spans::from(Span::styled(
+                format!("Variant {}:", name),
+                theme.key_hint,
+            )));
+            push_text(&mut lines, body, theme.text);
+        }
+
+        let paragraph = Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .scroll((self.scroll, 0))
+            .block(Block::default().borders(Borders::ALL).title(format!(
+                "Review '{}', {} more waiting (a accept, d delete, Esc later)",
+                snippet.title, self.remaining
+            )));
+        f.render_widget(paragraph, area);
+    }
+}
+
+/// Menu of the bodies of a snippet with variants, to pick the one to copy
+pub struct VariantPopup {
+    index: usize,
+    title: String,
+    /// `None` stands for the main body
+    variants: Vec<Option<String>>,
+    selected: usize,
+}
+
+impl VariantPopup {
+    pub fn new(index: usize, snippet: &Snippet) -> VariantPopup {
+        VariantPopup {
+            index,
+            title: snippet.title.clone(),
+            variants: std::iter::once(None)
+                .chain(snippet.variants.keys().cloned().map(Some))
+                .collect(),
+            selected: 0,
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyCode) -> PopupOutcome {
+        match key {
+            KeyCode::Esc | KeyCode::Char('q') => return PopupOutcome::Close,
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.selected = (self.selected + 1) % self.variants.len()
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.selected = (self.selected + self.variants.len() - 1) % self.variants.len()
+            }
+            KeyCode::Enter => {
+                return PopupOutcome::Variant {
+                    index: self.index,
+                    variant: self.variants[self.selected].clone(),
+                }
+            }
+            _ => {}
+        }
+        PopupOutcome::Keep
+    }
+
+    fn render<B: Backend>(&self, f: &mut Frame<B>, theme: &Theme) {
+        let area = centered_rect(50, 40, f.size());
+        f.render_widget(Clear, area);
+
+        let lines: Vec<Spans> = self
+            .variants
+            .iter()
+            .enumerate()
+            .map(|(index, variant)| {
+                let style = if index == self.selected {
+                    theme.selected
+                } else {
+                    theme.text
+                };
+                Spans::from(Span::styled(
+                    variant.as_deref().unwrap_or("(main)").to_string(),
+                    style,
+                ))
+            })
+            .collect();
+        let paragraph = Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(format!("Copy which '{}'", self.title)),
+        );
+        f.render_widget(paragraph, area);
+    }
+}
+
+/// Menu of every tag in use with how many snippets have it, to list only those
+pub struct TagFilterPopup {
+    /// `None` stands for all snippets
+    tags: Vec<(Option<String>, usize)>,
+    selected: usize,
+}
+
+impl TagFilterPopup {
+    pub fn new(snippets: &[Snippet], current: Option<&str>) -> TagFilterPopup {
+        let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+        for tag in snippets.iter().flat_map(|snippet| &snippet.tags) {
+            *counts.entry(tag).or_default() += 1;
+        }
+        let tags: Vec<(Option<String>, usize)> = std::iter::once((None, snippets.len()))
+            .chain(
+                counts
+                    .into_iter()
+                    .map(|(tag, count)| (Some(tag.to_string()), count)),
+            )
+            .collect();
+        let selected = tags
+            .iter()
+            .position(|(tag, _)| tag.as_deref() == current)
+            .unwrap_or(0);
+        TagFilterPopup { tags, selected }
+    }
+
+    fn handle_key(&mut self, key: KeyCode) -> PopupOutcome {
+        match key {
+            KeyCode::Esc | KeyCode::Char('q') => return PopupOutcome::Close,
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.selected = (self.selected + 1) % self.tags.len()
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.selected = (self.selected + self.tags.len() - 1) % self.tags.len()
+            }
+            KeyCode::Enter => return PopupOutcome::TagFilter(self.tags[self.selected].0.clone()),
+            _ => {}
+        }
+        PopupOutcome::Keep
+    }
+
+    fn render<B: Backend>(&self, f: &mut Frame<B>, theme: &Theme) {
+        let area = centered_rect(50, 60, f.size());
+        f.render_widget(Clear, area);
+
+        // Keep the selection in view in long tag lists
+        let height = area.height.saturating_sub(2) as usize;
+        let skip = (self.selected + 1).saturating_sub(height);
+        let lines: Vec<Spans> = self
+            .tags
+            .iter()
+            .enumerate()
+            .skip(skip)
+            .map(|(index, (tag, count))| {
+                let style = if index == self.selected {
+                    theme.selected
+                } else {
+                    theme.text
+                };
+                Spans::from(Span::styled(
+                    format!("{} ({})", tag.as_deref().unwrap_or("(all snippets)"), count),
+                    style,
+                ))
+            })
+            .collect();
+        let paragraph = Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("List the snippets tagged (Enter pick, Esc cancel)"),
+        );
+        f.render_widget(paragraph, area);
+    }
+}
+
+/// Menu of the other collections, to move or copy snippets to
+pub struct CollectionPopup {
+    indices: Vec<usize>,
+    collections: Vec<(String, PathBuf)>,
+    moving: bool,
+    selected: usize,
+}
+
+impl CollectionPopup {
+    /// `None` when there is no other collection to pick
+    pub fn new(
+        indices: Vec<usize>,
+        collections: Vec<(String, PathBuf)>,
+        moving: bool,
+    ) -> Option<CollectionPopup> {
+        (!collections.is_empty()).then_some(CollectionPopup {
+            indices,
+            collections,
+            moving,
+            selected: 0,
+        })
+    }
+
+    fn handle_key(&mut self, key: KeyCode) -> PopupOutcome {
+        match key {
+            KeyCode::Esc | KeyCode::Char('q') => return PopupOutcome::Close,
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.selected = (self.selected + 1) % self.collections.len()
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.selected =
+                    (self.selected + self.collections.len() - 1) % self.collections.len()
+            }
+            KeyCode::Enter => {
+                let (name, path) = self.collections[self.selected].clone();
+                return PopupOutcome::Transfer {
+                    indices: std::mem::take(&mut self.indices),
+                    name,
+                    path,
+                    moving: self.moving,
+                };
+            }
+            _ => {}
+        }
+        PopupOutcome::Keep
+    }
+
+    fn render<B: Backend>(&self, f: &mut Frame<B>, theme: &Theme) {
+        let area = centered_rect(50, 40, f.size());
+        f.render_widget(Clear, area);
+
+        let lines: Vec<Spans> = self
+            .collections
+            .iter()
+            .enumerate()
+            .map(|(index, (name, path))| {
+                let style = if index == self.selected {
+                    theme.selected
+                } else {
+                    theme.text
+                };
+                Spans::from(vec![
+                    Span::styled(name.clone(), style),
+                    Span::styled(format!("  {}", path.display()), theme.namespace),
+                ])
+            })
+            .collect();
+        let verb = if self.moving { "Move" } else { "Copy" };
+        let what = match self.indices.len() {
+            1 => "the snippet".to_string(),
+            count => format!("{} snippets", count),
+        };
+        let paragraph = Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(format!("{} {} to (Enter pick, Esc cancel)", verb, what)),
+        );
+        f.render_widget(paragraph, area);
+    }
+}
+
+/// Tags suggested for a snippet being saved, each one accepted or rejected
+/// before it is added
+pub struct TagSuggestPopup {
+    snippet: Snippet,
+    /// Each suggestion and whether it is accepted
+    tags: Vec<(String, bool)>,
+    selected: usize,
+}
+
+impl TagSuggestPopup {
+    /// All suggestions start out accepted
+    pub fn new(snippet: Snippet, suggestions: Vec<String>) -> TagSuggestPopup {
+        TagSuggestPopup {
+            snippet,
+            tags: suggestions.into_iter().map(|tag| (tag, true)).collect(),
+            selected: 0,
+        }
+    }
+
+    fn add(&self, accepted: bool) -> PopupOutcome {
+        let mut snippet = self.snippet.clone();
+        snippet.tags.extend(
+            self.tags
+                .iter()
+                .filter(|(_, on)| accepted && *on)
+                .map(|(tag, _)| tag.clone()),
+        );
+        PopupOutcome::Add(snippet)
+    }
+
+    fn handle_key(&mut self, key: KeyCode) -> PopupOutcome {
+        match key {
+            // The snippet is saved either way, only the suggestions are dropped
+            KeyCode::Esc => return self.add(false),
+            KeyCode::Enter => return self.add(true),
+            KeyCode::Right | KeyCode::Char('l') | KeyCode::Tab => {
+                self.selected = (self.selected + 1) % self.tags.len()
+            }
+            KeyCode::Left | KeyCode::Char('h') | KeyCode::BackTab => {
+                self.selected = (self.selected + self.tags.len() - 1) % self.tags.len()
+            }
+            KeyCode::Char(' ') => {
+                if let Some((_, on)) = self.tags.get_mut(self.selected) {
+                    *on = !*on;
+                }
+            }
+            KeyCode::Char('y') => {
+                if let Some((_, on)) = self.tags.get_mut(self.selected) {
+                    *on = true;
+                }
+            }
+            KeyCode::Char('n') => {
+                if let Some((_, on)) = self.tags.get_mut(self.selected) {
+                    *on = false;
+                }
+            }
+            _ => {}
+        }
+        PopupOutcome::Keep
+    }
+
+    fn render<B: Backend>(&self, f: &mut Frame<B>, theme: &Theme) {
+        let area = centered_rect(60, 30, f.size());
+        f.render_widget(Clear, area);
+
+        let mut chips = vec![];
+        for (index, (tag, on)) in self.tags.iter().enumerate() {
+            let style = match on {
+                true => theme.highlight,
+                false => theme
+                    .text
+                    .add_modifier(Modifier::CROSSED_OUT | Modifier::DIM),
+            };
+            let style = match index == self.selected {
+                true => style.patch(theme.selected),
+                false => style,
+            };
+            let mark = if *on { "✓" } else { "✗" };
+            chips.push(Span::styled(format!("[{} {}]", mark, tag), style));
+            chips.push(Span::raw(" "));
+        }
+
+        let lines = vec![
+            Spans::from(Span::styled(
+                format!("Tags for '{}' from its contents:", self.snippet.title),
+                theme.text,
+            )),
+            Spans::default(),
+            Spans::from(chips),
+            Spans::default(),
+            Spans::from(vec![
+                Span::styled("←/→", theme.key_hint),
+                Span::styled(" pick  ", theme.text),
+                Span::styled("Space", theme.key_hint),
+                Span::styled(" accept or reject  ", theme.text),
+                Span::styled("Enter", theme.key_hint),
+                Span::styled(" save with the accepted tags  ", theme.text),
+                Span::styled("Esc", theme.key_hint),
+                Span::styled(" save without them", theme.text),
+            ]),
+        ];
+        let paragraph = Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Suggested tags"),
+        );
+        f.render_widget(paragraph, area);
+    }
+}
+
+/// Menu of the forms a single copy can take
+pub struct FlavorPopup {
+    index: usize,
+    title: String,
+    selected: usize,
+}
+
+impl FlavorPopup {
+    pub fn new(index: usize, title: &str) -> FlavorPopup {
+        FlavorPopup {
+            index,
+            title: title.to_string(),
+            selected: 0,
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyCode) -> PopupOutcome {
+        let flavor = match key {
+            KeyCode::Esc | KeyCode::Char('q') => return PopupOutcome::Close,
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.selected = (self.selected + 1) % Flavor::ALL.len();
+                return PopupOutcome::Keep;
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.selected = (self.selected + Flavor::ALL.len() - 1) % Flavor::ALL.len();
+                return PopupOutcome::Keep;
+            }
+            KeyCode::Enter => Flavor::ALL[self.selected],
+            KeyCode::Char(c) => match Flavor::ALL.into_iter().find(|f| f.key() == c) {
+                Some(flavor) => flavor,
+                None => return PopupOutcome::Keep,
+            },
+            _ => return PopupOutcome::Keep,
+        };
+        PopupOutcome::CopyAs {
+            index: self.index,
+            flavor,
+        }
+    }
+
+    fn render<B: Backend>(&self, f: &mut Frame<B>, theme: &Theme) {
+        let area = centered_rect(50, 30, f.size());
+        f.render_widget(Clear, area);
+
+        let lines: Vec<Spans> = Flavor::ALL
+            .iter()
+            .enumerate()
+            .map(|(index, flavor)| {
+                let style = if index == self.selected {
+                    theme.selected
+                } else {
+                    theme.text
+                };
+                Spans::from(vec![
+                    Span::styled(format!("{} ", flavor.key()), theme.key_hint),
+                    Span::styled(flavor.label(), style),
+                ])
+            })
+            .collect();
+        let paragraph = Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(format!("Copy '{}' as", self.title)),
+        );
+        f.render_widget(paragraph, area);
+    }
+}
+
+/// Every match of a find and replace, each accepted or skipped before the
+/// accepted ones are replaced
+pub struct ReplacePopup {
+    occurrences: Vec<Occurrence>,
+    /// Accepted or skipped, `None` while undecided
+    decisions: Vec<Option<bool>>,
+    /// Title and body of each snippet or variant with a match, by index and
+    /// variant
+    bodies: BTreeMap<(usize, Option<String>), (String, String)>,
+    selected: usize,
+}
+
+impl ReplacePopup {
+    pub fn new(occurrences: Vec<Occurrence>, snippets: &[Snippet]) -> ReplacePopup {
+        let bodies = occurrences
+            .iter()
+            .map(|occurrence| {
+                let snippet = &snippets[occurrence.index];
+                let variant = occurrence.variant.as_deref();
+                let title = match variant {
+                    Some(name) => format!("{} [{}]", snippet.title, name),
+                    None => snippet.title.clone(),
+                };
+                let body = variants::body(snippet, variant).to_string();
+                (
+                    (occurrence.index, occurrence.variant.clone()),
+                    (title, body),
+                )
+            })
+            .collect();
+        ReplacePopup {
+            decisions: vec![None; occurrences.len()],
+            occurrences,
+            bodies,
+            selected: 0,
+        }
+    }
+
+    fn snippet_count(&self) -> usize {
+        let indices: BTreeSet<usize> = self.bodies.keys().map(|(index, _)| *index).collect();
+        indices.len()
+    }
+
+    /// Decides the selected match and moves on to the next undecided one
+    fn decide(&mut self, accept: bool) {
+        self.decisions[self.selected] = Some(accept);
+        let count = self.occurrences.len();
+        self.selected = (1..count)
+            .map(|step| (self.selected + step) % count)
+            .find(|&index| self.decisions[index].is_none())
+            .unwrap_or(self.selected);
+    }
+
+    fn handle_key(&mut self, key: KeyCode) -> PopupOutcome {
+        let last = self.occurrences.len() - 1;
+        match key {
+            KeyCode::Esc => return PopupOutcome::Close,
+            KeyCode::Down | KeyCode::Char('j') => self.selected = (self.selected + 1).min(last),
+            KeyCode::Up | KeyCode::Char('k') => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Char('y') => self.decide(true),
+            KeyCode::Char('n') => self.decide(false),
+            KeyCode::Char('a') => {
+                for decision in self.decisions.iter_mut().filter(|d| d.is_none()) {
+                    *decision = Some(true);
+                }
+            }
+            KeyCode::Enter => {
+                let mut edits = vec![];
+                let mut replaced = 0;
+                for ((index, variant), (_, body)) in &self.bodies {
+                    let accepted: Vec<&Occurrence> = self
+                        .occurrences
+                        .iter()
+                        .zip(&self.decisions)
+                        .filter(|(occurrence, decision)| {
+                            occurrence.index == *index
+                                && occurrence.variant == *variant
+                                && **decision == Some(true)
+                        })
+                        .map(|(occurrence, _)| occurrence)
+                        .collect();
+                    if !accepted.is_empty() {
+                        replaced += accepted.len();
+                        edits.push((*index, variant.clone(), replace::apply(body, &accepted)));
+                    }
+                }
+                return match edits.is_empty() {
+                    true => PopupOutcome::Close,
+                    false => PopupOutcome::Replace { edits, replaced },
+                };
+            }
+            _ => {}
+        }
+        PopupOutcome::Keep
+    }
+
+    fn render<B: Backend>(&self, f: &mut Frame<B>, theme: &Theme) {
+        let area = centered_rect(80, 70, f.size());
+        f.render_widget(Clear, area);
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(1), Constraint::Length(2)].as_ref())
+            .split(area);
+
+        let removed = theme
+            .text
+            .fg(Color::Red)
+            .add_modifier(Modifier::CROSSED_OUT);
+        let added = theme.text.fg(Color::Green);
+        let one_line = |text: &str| text.replace('\n', terminal::glyph("⏎", "\\n"));
+        let lines: Vec<Spans> = self
+            .occurrences
+            .iter()
+            .zip(&self.decisions)
+            .enumerate()
+            .map(|(position, (occurrence, decision))| {
+                let (title, body) = &self.bodies[&(occurrence.index, occurrence.variant.clone())];
+                let (line, before, after) = replace::context(body, occurrence);
+                let style = match position == self.selected {
+                    true => theme.selected,
+                    false => theme.text,
+                };
+                let mark = match decision {
+                    Some(true) => "[y] ",
+                    Some(false) => "[n] ",
+                    None => "[ ] ",
+                };
+                // Enough of the line before the match to recognize it
+                let skipped = before.chars().count().saturating_sub(30);
+                let before: String = before.chars().skip(skipped).collect();
+                let ellipsis = if skipped > 0 { "…" } else { "" };
+                Spans::from(vec![
+                    Span::styled(format!("{}{}:{}  ", mark, title, line), style),
+                    Span::raw(format!("{}{}", ellipsis, before)),
+                    Span::styled(one_line(&body[occurrence.range.clone()]), removed),
+                    Span::styled(one_line(&occurrence.replacement), added),
+                    Span::raw(after.to_string()),
+                ])
+            })
+            .collect();
+        let visible = chunks[0].height.saturating_sub(2) as usize;
+        let list = Paragraph::new(lines)
+            .block(
+                Block::default()
+                    .borders(Borders::TOP | Borders::LEFT | Borders::RIGHT)
+                    .title(format!("Replace in {} snippets", self.snippet_count())),
+            )
+            .scroll((
+                self.selected.saturating_sub(visible.saturating_sub(1)) as u16,
+                0,
+            ));
+        f.render_widget(list, chunks[0]);
+
+        let count = |wanted: Option<bool>| self.decisions.iter().filter(|d| **d == wanted).count();
+        let key = |k: &'static str| Span::styled(k, theme.key_hint);
+        let summary = Paragraph::new(Spans::from(vec![
+            Span::raw(format!(
+                "{} accepted, {} skipped, {} left  ",
+                count(Some(true)),
+                count(Some(false)),
+                count(None)
+            )),
+            key("y"),
+            Span::raw(" accept  "),
+            key("n"),
+            Span::raw(" skip  "),
+            key("a"),
+            Span::raw(" accept the rest  "),
+            key("Enter"),
+            Span::raw(" replace accepted  "),
+            key("Esc"),
+            Span::raw(" cancel"),
+        ]))
+        .block(Block::default().borders(Borders::BOTTOM | Borders::LEFT | Borders::RIGHT));
+        f.render_widget(summary, chunks[1]);
+    }
+}
+
+/// Tries chains of copy transformations on a snippet: its body before and
+/// after them side by side, and the line configuring the chain for a target
+pub struct TransformPopup {
+    index: usize,
+    title: String,
+    body: String,
+    /// The copy target the chain started from
+    target: String,
+    chain: Vec<Transform>,
+    selected: usize,
+    /// Templates and base64 snippets are only previewed, `c` fills them in
+    copyable: bool,
+}
+
+impl TransformPopup {
+    pub fn new(
+        index: usize,
+        snippet: &Snippet,
+        target: &str,
+        chain: &[Transform],
+        copyable: bool,
+    ) -> TransformPopup {
+        TransformPopup {
+            index,
+            title: snippet.title.clone(),
+            body: snippet.description.clone(),
+            target: target.to_string(),
+            chain: chain.to_vec(),
+            selected: 0,
+            copyable,
+        }
+    }
+
+    /// Adds the transformation to the end of the chain, or takes it out
+    fn toggle(&mut self, transform: Transform) {
+        match self.chain.iter().position(|t| *t == transform) {
+            Some(position) => {
+                self.chain.remove(position);
+            }
+            None => self.chain.push(transform),
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyCode) -> PopupOutcome {
+        let count = Transform::ALL.len();
+        match key {
+            KeyCode::Esc | KeyCode::Char('q') => return PopupOutcome::Close,
+            KeyCode::Down | KeyCode::Char('j') => self.selected = (self.selected + 1) % count,
+            KeyCode::Up | KeyCode::Char('k') => self.selected = (self.selected + count - 1) % count,
+            KeyCode::Char(' ') => self.toggle(Transform::ALL[self.selected]),
+            KeyCode::Char(c @ '1'..='9') => {
+                if let Some(transform) = Transform::ALL.get(c as usize - '1' as usize) {
+                    self.selected = c as usize - '1' as usize;
+                    self.toggle(*transform);
+                }
+            }
+            KeyCode::Char('x') => self.chain.clear(),
+            KeyCode::Enter if self.copyable => {
+                return PopupOutcome::CopyTransformed {
+                    index: self.index,
+                    transforms: self.chain.clone(),
+                }
+            }
+            _ => {}
+        }
+        PopupOutcome::Keep
+    }
+
+    fn render<B: Backend>(&self, f: &mut Frame<B>, theme: &Theme) {
+        let area = centered_rect(90, 80, f.size());
+        f.render_widget(Clear, area);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(format!("Transformations of '{}'", self.title));
+        let inner = block.inner(area);
+        f.render_widget(block, area);
+
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(
+                [
+                    Constraint::Length(Transform::ALL.len() as u16 + 1),
+                    Constraint::Length(3),
+                    Constraint::Min(3),
+                ]
+                .as_ref(),
+            )
+            .split(inner);
+
+        let mut lines: Vec<Spans> = Transform::ALL
+            .iter()
+            .enumerate()
+            .map(|(index, transform)| {
+                let style = match index == self.selected {
+                    true => theme.selected,
+                    false => theme.text,
+                };
+                // Where it runs in the chain
+                let position = match self.chain.iter().position(|t| t == transform) {
+                    Some(position) => format!("[{}]", position + 1),
+                    None => "[ ]".to_string(),
+                };
+                Spans::from(vec![
+                    Span::styled(format!("{} ", index + 1), theme.key_hint),
+                    Span::styled(
+                        format!(
+                            "{} {:<12} {}",
+                            position,
+                            transform.name(),
+                            transform.describe()
+                        ),
+                        style,
+                    ),
+                ])
+            })
+            .collect();
+        lines.push(Spans::from(Span::styled(
+            match self.copyable {
+                true => "Space or 1-5 to add or take out, x to clear, Enter to copy with it once",
+                false => "Space or 1-5 to add or take out, x to clear",
+            },
+            theme.namespace,
+        )));
+        f.render_widget(Paragraph::new(lines), rows[0]);
+
+        let config = Paragraph::new(transform::config_line(&self.chain)).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(format!("Under [[copy_targets]] for {}", self.target)),
+        );
+        f.render_widget(config, rows[1]);
+
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+            .split(rows[2]);
+        let after = transform::apply_all(&self.chain, &self.body);
+        for (column, (label, text)) in [("Before", &self.body), ("After", &after)]
+            .into_iter()
+            .enumerate()
+        {
+            let paragraph = Paragraph::new(visible_whitespace(text))
+                .style(theme.text)
+                .block(Block::default().borders(Borders::ALL).title(format!(
+                    "{} ({})",
+                    label,
+                    text_size(text)
+                )));
+            f.render_widget(paragraph, columns[column]);
+        }
+    }
+}
+
+/// `text` with carriage returns and trailing spaces made visible, which is
+/// most of what the transformations change
+fn visible_whitespace(text: &str) -> String {
+    let carriage_return = terminal::glyph("␍", "\\r");
+    let space = terminal::glyph("·", ".");
+    text.split('\n')
+        .map(|line| {
+            let (line, crlf) = match line.strip_suffix('\r') {
+                Some(line) => (line, carriage_return),
+                None => (line, ""),
+            };
+            let kept = line.trim_end_matches(' ');
+            let trailing = line.len() - kept.len();
+            format!(
+                "{}{}{}",
+                kept.replace('\r', carriage_return),
+                space.repeat(trailing),
+                crlf
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// e.g. "3 lines, 52 characters"
+fn text_size(text: &str) -> String {
+    let lines = text.lines().count();
+    let characters = text.chars().count();
+    format!(
+        "{} line{}, {} character{}",
+        lines,
+        if lines == 1 { "" } else { "s" },
+        characters,
+        if characters == 1 { "" } else { "s" }
+    )
+}
+
+/// The column mapping step of a CSV import: which column holds the title,
+/// body and tags
+pub struct ColumnsPopup {
+    source: String,
+    table: Table,
+    mapping: ColumnMapping,
+    /// 0 title, 1 description, 2 tags
+    focused: usize,
+}
+
+impl ColumnsPopup {
+    pub fn new(source: &str, table: Table) -> ColumnsPopup {
+        ColumnsPopup {
+            source: source.to_string(),
+            mapping: table.guess_mapping(),
+            table,
+            focused: 0,
+        }
+    }
+
+    /// Moves the focused field to the next or previous column. Tags can also be left out.
+    fn cycle(&mut self, forward: bool) {
+        let count = self.table.headers.len();
+        let step = |column: usize, count: usize| {
+            if forward {
+                (column + 1) % count
+            } else {
+                (column + count - 1) % count
+            }
+        };
+        match self.focused {
+            0 => self.mapping.title = step(self.mapping.title, count),
+            1 => self.mapping.description = step(self.mapping.description, count),
+            // `count` stands for no column
+            _ => {
+                let column = step(self.mapping.tags.unwrap_or(count), count + 1);
+                self.mapping.tags = (column < count).then_some(column);
+            }
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyCode) -> PopupOutcome {
+        match key {
+            KeyCode::Esc | KeyCode::Char('q') => return PopupOutcome::Close,
+            KeyCode::Down | KeyCode::Char('j') | KeyCode::Tab => {
+                self.focused = (self.focused + 1) % 3
+            }
+            KeyCode::Up | KeyCode::Char('k') => self.focused = (self.focused + 2) % 3,
+            KeyCode::Right | KeyCode::Char('l') => self.cycle(true),
+            KeyCode::Left | KeyCode::Char('h') => self.cycle(false),
+            KeyCode::Enter => {
+                return PopupOutcome::Review {
+                    source: self.source.clone(),
+                    incoming: self.table.to_snippets(self.mapping),
+                }
+            }
+            _ => {}
+        }
+        PopupOutcome::Keep
+    }
+
+    fn render<B: Backend>(&self, f: &mut Frame<B>, theme: &Theme) {
+        let area = centered_rect(70, 60, f.size());
+        f.render_widget(Clear, area);
+
+        let header = |column: Option<usize>| match column {
+            Some(column) => format!("{} (column {})", self.table.headers[column], column + 1),
+            None => "none".to_string(),
+        };
+        let fields = [
+            ("Title", header(Some(self.mapping.title))),
+            ("Description", header(Some(self.mapping.description))),
+            ("Tags", header(self.mapping.tags)),
+        ];
+        let mut lines: Vec<Spans> = fields
+            .into_iter()
+            .enumerate()
+            .map(|(index, (label, column))| {
+                let style = if index == self.focused {
+                    theme.selected
+                } else {
+                    theme.text
+                };
+                Spans::from(vec![
+                    Span::styled(format!("{:<13}", label), theme.key_hint),
+                    Span::styled(format!("< {} >", column), style),
+                ])
+            })
+            .collect();
+
+        let snippets = self.table.to_snippets(self.mapping);
+        lines.push(Spans::default());
+        lines.push(Spans::from(Span::styled(
+            format!("{} snippets, starting with:", snippets.len()),
+            theme.text,
+        )));
+        for snippet in snippets.iter().take(5) {
+            let body = snippet.description.lines().next().unwrap_or_default();
+            lines.push(Spans::from(vec![
+                Span::styled(format!("  {}", snippet.title), theme.highlight),
+                Span::styled(format!("  {}", body), theme.text),
+            ]));
+        }
+
+        let paragraph = Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Columns (h/l to change, Enter to review the snippets, Esc to cancel)"),
+        );
+        f.render_widget(paragraph, area);
+    }
+}
+
+/// Something that can be done from the storage overview
+pub enum StorageAction {
+    /// Rewrite the store from the snippets shown
+    Compact,
+    /// Copy the store into a new backup now
+    BackUp,
+    /// Ask for a path to write a copy of the store to
+    Export,
+}
+
+/// Overview of the store on disk and its backups
+pub struct StoragePopup {
+    report: StorageReport,
+}
+
+impl StoragePopup {
+    pub fn new(report: StorageReport) -> StoragePopup {
+        StoragePopup { report }
+    }
+
+    fn handle_key(&mut self, key: KeyCode) -> PopupOutcome {
+        match key {
+            KeyCode::Esc | KeyCode::Char('q') => PopupOutcome::Close,
+            KeyCode::Char('c') => PopupOutcome::Storage(StorageAction::Compact),
+            KeyCode::Char('b') => PopupOutcome::Storage(StorageAction::BackUp),
+            KeyCode::Char('e') => PopupOutcome::Storage(StorageAction::Export),
+            _ => PopupOutcome::Keep,
+        }
+    }
+
+    fn render<B: Backend>(&self, f: &mut Frame<B>, theme: &Theme) {
+        let area = centered_rect(70, 70, f.size());
+        f.render_widget(Clear, area);
+
+        let report = &self.report;
+        let now = time_utils::now();
+        let age = |timestamp: Option<u64>| match timestamp {
+            Some(timestamp) => format!(
+                "{} ({} ago)",
+                time_utils::format_datetime(timestamp),
+                format_age(now.saturating_sub(timestamp))
+            ),
+            None => "never".to_string(),
+        };
+        let row = |label: &str, value: String| {
+            Spans::from(vec![
+                Span::styled(format!("{:<16}", label), theme.key_hint),
+                Span::styled(value, theme.text),
+            ])
+        };
+
+        let state = match (report.size, report.dirty) {
+            (None, _) => "not written yet",
+            (Some(_), true) => "differs from what is shown, compact to rewrite it",
+            (Some(_), false) => "matches what is shown",
+        };
+        let mut lines = vec![
+            row("File", report.path.display().to_string()),
+            row("Format", report.backend.name().to_string()),
+            row("Snippets", report.snippets.to_string()),
+            row(
+                "Size",
+                match report.size {
+                    None => "-".to_string(),
+                    Some(size) => {
+                        let notes: Vec<&str> = [
+                            (report.compressed, "compressed"),
+                            (report.encrypted, "encrypted"),
+                        ]
+                        .into_iter()
+                        .filter_map(|(applies, note)| applies.then_some(note))
+                        .collect();
+                        match notes.is_empty() {
+                            true => format_size(size as usize),
+                            false => {
+                                format!("{} ({})", format_size(size as usize), notes.join(", "))
+                            }
+                        }
+                    }
+                },
+            ),
+            row("Last modified", age(report.modified)),
+            row("State", state.to_string()),
+            row(
+                "Saved by",
+                if report.daemon {
+                    "the daemon"
+                } else {
+                    "this instance"
+                }
+                .to_string(),
+            ),
+            row(
+                "Backups",
+                format!(
+                    "{} of {}, newest {}",
+                    report.backups.len(),
+                    BACKUP_COUNT,
+                    age(report.backups.first().copied())
+                ),
+            ),
+            row(
+                "Daily snapshots",
+                format!(
+                    "{}, newest {}",
+                    report.snapshots,
+                    age(report.newest_snapshot)
+                ),
+            ),
+            row("History size", format_size(report.history_size as usize)),
+            Spans::default(),
+        ];
+        for (tag, count) in &report.tags {
+            lines.push(row(&format!("#{}", tag), count.to_string()));
+        }
+        lines.push(row("untagged", report.untagged.to_string()));
+
+        let paragraph = Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Storage (c compact, b back up now, e export, Esc to close)"),
+        );
+        f.render_widget(paragraph, area);
+    }
+}
+
+/// Roughly how long `seconds` is, in its largest unit
+fn format_age(seconds: u64) -> String {
+    match seconds {
+        0..=59 => format!("{}s", seconds),
+        60..=3599 => format!("{}m", seconds / 60),
+        3600..=86_399 => format!("{}h", seconds / 3600),
+        _ => format!("{}d", seconds / time_utils::SECONDS_PER_DAY),
+    }
+}
+
+/// Picks the color and icon shown in front of a snippet
+pub struct LabelPopup {
+    index: usize,
+    title: String,
+    color: Option<LabelColor>,
+    icon: Option<String>,
+}
+
+impl LabelPopup {
+    pub fn new(index: usize, snippet: &Snippet) -> LabelPopup {
+        LabelPopup {
+            index,
+            title: snippet.title.clone(),
+            color: snippet.color,
+            icon: snippet.icon.clone(),
+        }
+    }
+
+    /// Moves through no color followed by each `LabelColor`
+    fn cycle_color(&mut self, forward: bool) {
+        let choices = LabelColor::ALL.len() + 1;
+        let current = match self.color {
+            Some(color) => {
+                LabelColor::ALL
+                    .iter()
+                    .position(|c| *c == color)
+                    .unwrap_or(0)
+                    + 1
+            }
+            None => 0,
+        };
+        let next = if forward {
+            (current + 1) % choices
+        } else {
+            (current + choices - 1) % choices
+        };
+        self.color = next.checked_sub(1).map(|i| LabelColor::ALL[i]);
+    }
+
+    fn handle_key(&mut self, key: KeyCode) -> PopupOutcome {
+        match key {
+            KeyCode::Esc => return PopupOutcome::Close,
+            KeyCode::Enter => {
+                return PopupOutcome::Label {
+                    index: self.index,
+                    color: self.color,
+                    icon: self.icon.take(),
+                }
+            }
+            KeyCode::Left | KeyCode::Up => self.cycle_color(false),
+            KeyCode::Right | KeyCode::Down | KeyCode::Tab => self.cycle_color(true),
+            KeyCode::Char(c) => self.icon = Some(c.to_string()),
+            KeyCode::Backspace | KeyCode::Delete => self.icon = None,
+            _ => {}
+        }
+        PopupOutcome::Keep
+    }
+
+    fn render<B: Backend>(&self, f: &mut Frame<B>, theme: &Theme) {
+        let area = centered_rect(60, 30, f.size());
+        f.render_widget(Clear, area);
+
+        let mut swatches = vec![Span::styled("Color: ", theme.text)];
+        let none_style = if self.color.is_none() {
+            theme.selected
+        } else {
+            theme.text
+        };
+        swatches.push(Span::styled(" none ", none_style));
+        for color in LabelColor::ALL {
+            let mut style = Style::default().fg(label_color(color));
+            if self.color == Some(color) {
+                style = style.add_modifier(Modifier::REVERSED);
+            }
+            swatches.push(Span::styled(" ■ ", style));
+        }
+
+        let icon = self.icon.as_deref().unwrap_or("none");
+        let mut preview = theme.text;
+        if let Some(color) = self.color {
+            preview = preview.fg(label_color(color));
+        }
+        let lines = vec![
+            Spans::from(swatches),
+            Spans::from(vec![
+                Span::styled("Icon: ", theme.text),
+                Span::styled(icon, preview),
+            ]),
+            Spans::from(""),
+            Spans::from(Span::styled(
+                "Left/Right picks a color, typing a character sets the icon, Backspace clears it.",
+                theme.text,
+            )),
+        ];
+        let paragraph = Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+            Block::default().borders(Borders::ALL).title(format!(
+                "Label '{}' (Enter to save, Esc to cancel)",
+                self.title
+            )),
+        );
+        f.render_widget(paragraph, area);
+    }
+}
+
+fn push_text<'a>(lines: &mut Vec<Spans<'a>>, text: &'a str, style: Style) {
+    for line in text.lines() {
+        lines.push(Spans::from(Span::styled(format!("    {}", line), style)));
+    }
+}
+
+fn diff_line(line: Option<&str>, style: Style) -> Spans<'_> {
+    match line {
+        Some(line) => Spans::from(Span::styled(line, style)),
+        None => Spans::from(Span::styled("~", style)),
+    }
+}
+
+/// A rectangle taking the given percentage of `area`, centered in it
+pub fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+    let vertical = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Percentage((100 - percent_y) / 2),
+                Constraint::Percentage(percent_y),
+                Constraint::Percentage((100 - percent_y) / 2),
+            ]
+            .as_ref(),
+        )
+        .split(area);
+
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(
+            [
+                Constraint::Percentage((100 - percent_x) / 2),
+                Constraint::Percentage(percent_x),
+                Constraint::Percentage((100 - percent_x) / 2),
+            ]
+            .as_ref(),
+        )
+        .split(vertical[1])[1]
+}
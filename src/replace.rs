@@ -0,0 +1,159 @@
+//! Find and replace across the bodies of every snippet with `%`, named
+//! variants included, written like vim's `:%s/old/new/`. Every match is listed before anything changes and is
+//! accepted or skipped on its own, for when a host name or a flag that
+//! dozens of snippets mention changes.
+
+use std::ops::Range;
+
+use regex::{Regex, RegexBuilder};
+
+use crate::models::Snippet;
+
+/// A parsed `s/<PATTERN>/<REPLACEMENT>/<FLAGS>`
+pub struct Substitution {
+    pattern: Regex,
+    replacement: String,
+}
+
+impl Substitution {
+    /// Parses `s/<PATTERN>/<REPLACEMENT>/`, `:%` in front allowed, with `i`
+    /// after the last `/` to ignore case. Another character after `s` can
+    /// stand in for `/`, which saves escaping paths; the one in use is
+    /// escaped with `\` within the parts. `$1` in the replacement puts back a
+    /// group.
+    pub fn parse(text: &str) -> Result<Substitution, String> {
+        let text = text.trim();
+        let invalid = || format!("'{}' isn't like s/<FIND>/<REPLACE>/", text);
+        let command = text
+            .trim_start_matches(':')
+            .trim_start_matches('%')
+            .strip_prefix('s')
+            .ok_or_else(invalid)?;
+        let mut chars = command.chars();
+        let delimiter = chars
+            .next()
+            .filter(|c| !c.is_alphanumeric() && !c.is_whitespace() && *c != '\\')
+            .ok_or_else(invalid)?;
+        let parts = split_unescaped(chars.as_str(), delimiter);
+        let (pattern, replacement, flags) = match parts.as_slice() {
+            [pattern, replacement] => (pattern, replacement, ""),
+            [pattern, replacement, flags] => (pattern, replacement, flags.as_str()),
+            _ => return Err(invalid()),
+        };
+        if pattern.is_empty() {
+            return Err("Nothing to find, the pattern is empty".to_string());
+        }
+        let mut ignore_case = false;
+        for flag in flags.chars() {
+            match flag {
+                'i' => ignore_case = true,
+                // Every match is listed anyway
+                'g' => {}
+                flag => return Err(format!("Unknown flag '{}', use i to ignore case", flag)),
+            }
+        }
+        let pattern = RegexBuilder::new(pattern)
+            .case_insensitive(ignore_case)
+            .build()
+            .map_err(|error| format!("Invalid regex '{}': {}", pattern, error))?;
+        Ok(Substitution {
+            pattern,
+            replacement: replacement.clone(),
+        })
+    }
+}
+
+/// `text` split at each `delimiter` not preceded by `\`. Other escapes are
+/// kept for the regex.
+fn split_unescaped(text: &str, delimiter: char) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        let part = parts.last_mut().expect("a part");
+        match c {
+            '\\' => match chars.next() {
+                Some(next) if next == delimiter => part.push(next),
+                Some(next) => {
+                    part.push('\\');
+                    part.push(next);
+                }
+                None => part.push('\\'),
+            },
+            c if c == delimiter => parts.push(String::new()),
+            c => part.push(c),
+        }
+    }
+    parts
+}
+
+/// A match in the body of a snippet
+pub struct Occurrence {
+    /// Index of the snippet in the app
+    pub index: usize,
+    /// The variant whose body has the match, `None` for the main body
+    pub variant: Option<String>,
+    /// Where the match is in the body, in bytes
+    pub range: Range<usize>,
+    /// What the match is replaced with, groups filled in
+    pub replacement: String,
+}
+
+/// Every match of `substitution` in the bodies of `snippets`, the main one
+/// first and then the variants, leaving out binary snippets and the
+/// read-only snippets of other sources
+pub fn find(snippets: &[Snippet], substitution: &Substitution) -> Vec<Occurrence> {
+    let mut occurrences = vec![];
+    for (index, snippet) in snippets.iter().enumerate() {
+        if snippet.binary || snippet.source.is_some() {
+            continue;
+        }
+        let bodies = std::iter::once((None, &snippet.description)).chain(
+            snippet
+                .variants
+                .iter()
+                .map(|(name, body)| (Some(name), body)),
+        );
+        for (variant, body) in bodies {
+            for captures in substitution.pattern.captures_iter(body) {
+                let Some(found) = captures.get(0).filter(|found| !found.is_empty()) else {
+                    continue;
+                };
+                let mut replacement = String::new();
+                captures.expand(&substitution.replacement, &mut replacement);
+                occurrences.push(Occurrence {
+                    index,
+                    variant: variant.cloned(),
+                    range: found.range(),
+                    replacement,
+                });
+            }
+        }
+    }
+    occurrences
+}
+
+/// `body` with the `accepted` occurrences in it replaced, which are in the
+/// order they were found
+pub fn apply(body: &str, accepted: &[&Occurrence]) -> String {
+    let mut replaced = String::with_capacity(body.len());
+    let mut end = 0;
+    for occurrence in accepted {
+        replaced.push_str(&body[end..occurrence.range.start]);
+        replaced.push_str(&occurrence.replacement);
+        end = occurrence.range.end;
+    }
+    replaced.push_str(&body[end..]);
+    replaced
+}
+
+/// The number of the line `occurrence` starts on, and the text of that line
+/// before and after the match, the match itself possibly spanning lines
+pub fn context<'a>(body: &'a str, occurrence: &Occurrence) -> (usize, &'a str, &'a str) {
+    let Range { start, end } = occurrence.range;
+    let line_start = body[..start].rfind('\n').map_or(0, |newline| newline + 1);
+    let line_end = body[end..]
+        .find('\n')
+        .map_or(body.len(), |newline| end + newline);
+    let number = body[..start].matches('\n').count() + 1;
+    (number, &body[line_start..start], &body[end..line_end])
+}
@@ -0,0 +1,185 @@
+//! Rows of the compact list view, laid out from `row_template` in the config
+//! such as `{title} [{tags}] — {description:40}`. A field followed by `:N`
+//! takes exactly N columns, cut short with `…` or padded with spaces, which
+//! lines the rows up. `{{` and `}}` stand for braces.
+
+use serde::Deserialize;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::binary;
+use crate::models::Snippet;
+use crate::table::description_view;
+use crate::time_utils;
+
+/// The template used when `V` switches to the list view without one configured
+const DEFAULT_TEMPLATE: &str = "{title} [{tags}] — {description}";
+
+/// What a row template can show of a snippet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Title,
+    Tags,
+    /// On one line, the way single line view shows it
+    Description,
+    Language,
+    /// How often the snippet was copied
+    Uses,
+    Created,
+    LastUsed,
+    /// The source the snippet is layered in from, empty for the personal store
+    Source,
+}
+
+impl Field {
+    const NAMES: [(&'static str, Field); 8] = [
+        ("title", Field::Title),
+        ("tags", Field::Tags),
+        ("description", Field::Description),
+        ("language", Field::Language),
+        ("uses", Field::Uses),
+        ("created", Field::Created),
+        ("used", Field::LastUsed),
+        ("source", Field::Source),
+    ];
+
+    fn value(self, snippet: &Snippet) -> String {
+        let date = |timestamp: Option<u64>| timestamp.map(time_utils::format_date);
+        match self {
+            Field::Title => snippet.title.clone(),
+            Field::Tags => snippet.tags.join(", "),
+            Field::Description if snippet.binary => binary::summary(&snippet.description),
+            Field::Description => description_view(&snippet.description, true),
+            Field::Language => snippet.language.clone().unwrap_or_default(),
+            Field::Uses => snippet.copy_count.to_string(),
+            Field::Created => date(snippet.created_at).unwrap_or_default(),
+            Field::LastUsed => date(snippet.last_used_at).unwrap_or_else(|| "never".to_string()),
+            Field::Source => snippet.source.clone().unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Text(String),
+    Field { field: Field, width: Option<usize> },
+}
+
+/// A parsed `row_template`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct RowTemplate {
+    parts: Vec<Part>,
+}
+
+impl Default for RowTemplate {
+    fn default() -> RowTemplate {
+        RowTemplate::parse(DEFAULT_TEMPLATE).expect("valid default template")
+    }
+}
+
+impl TryFrom<String> for RowTemplate {
+    type Error = String;
+
+    fn try_from(template: String) -> Result<RowTemplate, String> {
+        RowTemplate::parse(&template)
+    }
+}
+
+impl RowTemplate {
+    pub fn parse(template: &str) -> Result<RowTemplate, String> {
+        let mut parts = vec![];
+        let mut text = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let placeholder: String = chars.by_ref().take_while(|c| *c != '}').collect();
+                    if !text.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(parse_field(&placeholder)?);
+                }
+                '}' => return Err("a '}' without its '{', write '}}' for a brace".to_string()),
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+        Ok(RowTemplate { parts })
+    }
+
+    /// The row of `snippet`, on one line
+    pub fn render(&self, snippet: &Snippet) -> String {
+        self.parts
+            .iter()
+            .map(|part| match part {
+                Part::Text(text) => text.clone(),
+                Part::Field { field, width: None } => field.value(snippet),
+                Part::Field {
+                    field,
+                    width: Some(width),
+                } => fit(&field.value(snippet), *width),
+            })
+            .collect()
+    }
+}
+
+/// A `{field}` or `{field:width}` placeholder, without its braces
+fn parse_field(placeholder: &str) -> Result<Part, String> {
+    let (name, width) = match placeholder.split_once(':') {
+        Some((name, width)) => {
+            let width = width
+                .trim()
+                .parse()
+                .map_err(|_| format!("'{}' in {{{}}} is not a width", width, placeholder))?;
+            (name, Some(width))
+        }
+        None => (placeholder, None),
+    };
+    let field = Field::NAMES
+        .iter()
+        .find(|(known, _)| *known == name.trim())
+        .map(|(_, field)| *field)
+        .ok_or_else(|| {
+            let names: Vec<&str> = Field::NAMES.iter().map(|(name, _)| *name).collect();
+            format!(
+                "unknown field {{{}}}, use one of {}",
+                name,
+                names.join(", ")
+            )
+        })?;
+    Ok(Part::Field { field, width })
+}
+
+/// `text` in exactly `width` display columns
+fn fit(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return format!("{}{}", text, " ".repeat(width - text.width()));
+    }
+    let mut fitted = String::new();
+    let mut used = 0;
+    for c in text.chars() {
+        let c_width = c.width().unwrap_or_default();
+        // Leaves a column for the ellipsis
+        if used + c_width + 1 > width {
+            break;
+        }
+        fitted.push(c);
+        used += c_width;
+    }
+    if width > 0 {
+        fitted.push('…');
+        used += 1;
+    }
+    fitted.push_str(&" ".repeat(width - used));
+    fitted
+}
@@ -0,0 +1,261 @@
+//! Runs shell snippets with their output captured, so the results of
+//! diagnostic commands can be read in the TUI and kept as snippets, or in the
+//! terminal itself with the TUI suspended, for commands that ask for input
+
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::import;
+use crate::models::Snippet;
+
+/// Runs are stopped after this long so a stuck command can't freeze the TUI
+pub const RUN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Languages whose snippets are handed to the shell. Snippets without a
+/// language are assumed to be shell commands too.
+const SHELL_LANGUAGES: [&str; 5] = ["sh", "bash", "zsh", "shell", "console"];
+
+pub struct RunOutput {
+    pub stdout: String,
+    pub stderr: String,
+    /// How the command ended, e.g. `exit status: 0`
+    pub status: String,
+    pub duration: Duration,
+}
+
+/// Where a run starts and the variables it gets on top of sniprrr's own environment
+#[derive(Debug, Default, Clone)]
+pub struct Environment {
+    pub dir: Option<PathBuf>,
+    pub vars: Vec<(String, String)>,
+}
+
+/// Whether the description of `snippet` is a command that can be run as it is
+pub fn is_runnable(snippet: &Snippet) -> bool {
+    !snippet.binary
+        && snippet
+            .language
+            .as_deref()
+            .is_none_or(|language| SHELL_LANGUAGES.contains(&language.to_lowercase().as_str()))
+}
+
+#[cfg(unix)]
+fn shell(command: &str) -> Command {
+    use std::os::unix::process::CommandExt;
+
+    let mut shell = Command::new("sh");
+    // In its own process group so a timeout also stops what the command started
+    shell.args(["-c", command]).process_group(0);
+    shell
+}
+
+#[cfg(not(unix))]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.args(["/C", command]);
+    shell
+}
+
+/// The user's shell running `command` on the terminal, in the foreground
+#[cfg(unix)]
+fn attached(command: &str) -> Command {
+    use std::os::unix::process::CommandExt;
+
+    let shell = std::env::var("SHELL")
+        .ok()
+        .filter(|shell| !shell.is_empty())
+        .unwrap_or_else(|| "sh".to_string());
+    let mut attached = Command::new(shell);
+    attached.args(["-c", command]);
+    // Safe: only resets signal dispositions in the child before it starts.
+    // sniprrr ignores Ctrl+C while waiting, the command mustn't.
+    unsafe {
+        attached.pre_exec(|| {
+            libc::signal(libc::SIGINT, libc::SIG_DFL);
+            libc::signal(libc::SIGQUIT, libc::SIG_DFL);
+            Ok(())
+        });
+    }
+    attached
+}
+
+#[cfg(not(unix))]
+fn attached(command: &str) -> Command {
+    shell(command)
+}
+
+/// Calls `wait` with Ctrl+C left to the command in the foreground
+#[cfg(unix)]
+fn ignoring_interrupts<T>(wait: impl FnOnce() -> T) -> T {
+    // Safe: the previous dispositions are put back right after
+    let (interrupt, quit) = unsafe {
+        (
+            libc::signal(libc::SIGINT, libc::SIG_IGN),
+            libc::signal(libc::SIGQUIT, libc::SIG_IGN),
+        )
+    };
+    let result = wait();
+    unsafe {
+        libc::signal(libc::SIGINT, interrupt);
+        libc::signal(libc::SIGQUIT, quit);
+    }
+    result
+}
+
+#[cfg(not(unix))]
+fn ignoring_interrupts<T>(wait: impl FnOnce() -> T) -> T {
+    wait()
+}
+
+#[cfg(unix)]
+fn kill(child: &mut Child) {
+    // Safe: only sends a signal to the group created for this child
+    unsafe {
+        libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+    }
+}
+
+#[cfg(not(unix))]
+fn kill(child: &mut Child) {
+    let _ = child.kill();
+}
+
+/// Whether `snippet` declares variables or a directory, which are confirmed before a run
+pub fn has_environment(snippet: &Snippet) -> bool {
+    !snippet.env.is_empty() || snippet.working_dir.is_some()
+}
+
+/// Whether `name` can be an environment variable for the shell
+pub fn is_variable_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// The run environment of `snippet` as it is edited: the working directory,
+/// then the variables as `NAME` or `NAME=default`, separated by spaces
+pub fn declaration(snippet: &Snippet) -> String {
+    let vars = snippet
+        .env
+        .iter()
+        .map(|(name, default)| match default.is_empty() {
+            true => name.clone(),
+            false => format!("{}={}", name, default),
+        });
+    snippet
+        .working_dir
+        .clone()
+        .into_iter()
+        .chain(vars)
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Reads a `declaration` back: words starting with `/`, `~` or `.` are the
+/// working directory, the others variables
+pub fn parse_declaration(text: &str) -> Result<(Option<String>, BTreeMap<String, String>), String> {
+    let mut dir = None;
+    let mut env = BTreeMap::new();
+    for word in text.split_whitespace() {
+        if word.starts_with(['/', '~', '.']) {
+            if dir.replace(word.to_string()).is_some() {
+                return Err("Only one working directory can be given".to_string());
+            }
+            continue;
+        }
+        let (name, default) = word.split_once('=').unwrap_or((word, ""));
+        if !is_variable_name(name) {
+            return Err(format!("'{}' isn't a variable name", name));
+        }
+        env.insert(name.to_string(), default.to_string());
+    }
+    Ok((dir, env))
+}
+
+/// The directory `snippet` runs in, checked to exist
+pub fn working_dir(snippet: &Snippet) -> Result<Option<PathBuf>, String> {
+    let Some(dir) = &snippet.working_dir else {
+        return Ok(None);
+    };
+    let path = import::expand_home(dir);
+    match path.is_dir() {
+        true => Ok(Some(path)),
+        false => Err(format!("The working directory {} doesn't exist", dir)),
+    }
+}
+
+/// Runs `command` without input in `environment` and collects what it prints
+pub fn run(command: &str, environment: &Environment) -> Result<RunOutput, String> {
+    let started = Instant::now();
+    let mut shell = shell(command);
+    if let Some(dir) = &environment.dir {
+        shell.current_dir(dir);
+    }
+    let mut child = shell
+        .envs(environment.vars.iter().map(|(name, value)| (name, value)))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|error| error.to_string())?;
+
+    // Read both pipes on their own threads so a chatty command can't block on a full pipe
+    let stdout = child.stdout.take().map(read_all);
+    let stderr = child.stderr.take().map(read_all);
+
+    let status = wait_with_timeout(&mut child)?;
+    let collect = |reader: Option<thread::JoinHandle<String>>| {
+        reader
+            .and_then(|reader| reader.join().ok())
+            .unwrap_or_default()
+    };
+    Ok(RunOutput {
+        stdout: collect(stdout),
+        stderr: collect(stderr),
+        status,
+        duration: started.elapsed(),
+    })
+}
+
+/// Runs `command` through `$SHELL -c` on the terminal sniprrr was started
+/// in, writing to `stdout`, without a time limit, and returns how it ended
+pub fn execute(command: &str, environment: &Environment, stdout: Stdio) -> Result<String, String> {
+    let mut attached = attached(command);
+    attached.stdout(stdout);
+    if let Some(dir) = &environment.dir {
+        attached.current_dir(dir);
+    }
+    attached.envs(environment.vars.iter().map(|(name, value)| (name, value)));
+    ignoring_interrupts(|| attached.status())
+        .map(|status| status.to_string())
+        .map_err(|error| error.to_string())
+}
+
+fn read_all(mut pipe: impl Read + Send + 'static) -> thread::JoinHandle<String> {
+    thread::spawn(move || {
+        let mut bytes = Vec::new();
+        let _ = pipe.read_to_end(&mut bytes);
+        String::from_utf8_lossy(&bytes).to_string()
+    })
+}
+
+fn wait_with_timeout(child: &mut Child) -> Result<String, String> {
+    let deadline = Instant::now() + RUN_TIMEOUT;
+    loop {
+        if let Some(status) = child.try_wait().map_err(|error| error.to_string())? {
+            return Ok(status.to_string());
+        }
+        if Instant::now() >= deadline {
+            kill(child);
+            let _ = child.wait();
+            return Ok(format!("stopped after {}s", RUN_TIMEOUT.as_secs()));
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+}
@@ -0,0 +1,42 @@
+//! Filtering the snippet table, with the matching from `sniprrr_core::search`
+//! and the highlighting of what matched
+
+use ratatui::style::Style;
+use ratatui::text::{Span, Spans, Text};
+
+pub use sniprrr_core::search::*;
+
+/// Splits `text` into lines with the chars matched by the words of `query` drawn in `style`
+pub fn highlight(text: &str, query: &str, style: Style) -> Text<'static> {
+    let mut ranges: Vec<(usize, usize)> = query
+        .split_whitespace()
+        .filter_map(|term| fuzzy_match(text, term))
+        .flat_map(|(_, ranges)| ranges)
+        .collect();
+    ranges.sort_unstable();
+
+    let mut lines = vec![];
+    let mut line_start = 0;
+    for line in text.split('\n') {
+        let line_end = line_start + line.len();
+        let mut spans = vec![];
+        let mut last = line_start;
+        for &(start, end) in &ranges {
+            let (start, end) = (start.max(last), end.min(line_end));
+            if start >= end {
+                continue;
+            }
+            if start > last {
+                spans.push(Span::raw(text[last..start].to_string()));
+            }
+            spans.push(Span::styled(text[start..end].to_string(), style));
+            last = end;
+        }
+        if last < line_end {
+            spans.push(Span::raw(text[last..line_end].to_string()));
+        }
+        lines.push(Spans::from(spans));
+        line_start = line_end + 1;
+    }
+    Text::from(lines)
+}
@@ -0,0 +1,185 @@
+//! Semantic search, built with `--features semantic`: a search starting with
+//! `~` lists the snippets closest in meaning to the rest of it, so "restart
+//! the web thing" finds `systemctl restart nginx` without sharing a word.
+//!
+//! Texts are turned into embeddings by an OpenAI-compatible `/embeddings`
+//! endpoint set with `[semantic]`, such as a local Ollama. The embeddings of
+//! the snippets are cached in `embeddings.json` next to the store, so only
+//! new and edited snippets are sent along with the query.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::file_utils::messages_file_path;
+use crate::http;
+use crate::models::Snippet;
+
+/// Searches starting with this are semantic
+pub const PREFIX: char = '~';
+/// Texts sent in one request
+const BATCH: usize = 64;
+/// Characters of a body that go into its embedding, which is about its gist
+const BODY_LENGTH: usize = 2000;
+
+/// The `[semantic]` section of the config file
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SemanticConfig {
+    /// e.g. `http://localhost:11434/v1/embeddings`. Off when not set.
+    pub endpoint: Option<String>,
+    pub model: String,
+    /// Environment variable holding an API key, sent as a bearer token
+    pub api_key_env: Option<String>,
+    /// Cosine similarity below which snippets aren't listed
+    pub min_similarity: f32,
+}
+
+impl Default for SemanticConfig {
+    fn default() -> SemanticConfig {
+        SemanticConfig {
+            endpoint: None,
+            model: "nomic-embed-text".to_string(),
+            api_key_env: None,
+            min_similarity: 0.3,
+        }
+    }
+}
+
+/// Embeddings by the text they were made from, for one model
+#[derive(Default, Serialize, Deserialize)]
+struct Cache {
+    model: String,
+    vectors: HashMap<String, Vec<f32>>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<Embedding>,
+}
+
+#[derive(Deserialize)]
+struct Embedding {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+/// The query of a semantic search, `None` for other searches
+pub fn query(filter: &str) -> Option<&str> {
+    filter
+        .strip_prefix(PREFIX)
+        .map(str::trim)
+        .filter(|query| !query.is_empty())
+}
+
+/// Indices of the snippets similar enough to `query`, the most similar first
+pub fn rank(
+    config: &SemanticConfig,
+    query: &str,
+    snippets: &[Snippet],
+) -> Result<Vec<usize>, String> {
+    let endpoint = config
+        .endpoint
+        .as_deref()
+        .ok_or("Semantic search needs an endpoint set with `[semantic] endpoint`")?;
+
+    let texts: Vec<String> = snippets.iter().map(text).collect();
+    let mut cache = load_cache(&config.model);
+    let mut missing: Vec<&str> = texts
+        .iter()
+        .filter(|text| !cache.vectors.contains_key(*text))
+        .map(String::as_str)
+        .collect();
+    missing.sort_unstable();
+    missing.dedup();
+    for batch in missing.chunks(BATCH) {
+        let vectors = embed(config, endpoint, batch)?;
+        cache
+            .vectors
+            .extend(batch.iter().map(|text| text.to_string()).zip(vectors));
+    }
+    let query = embed(config, endpoint, &[query])?
+        .pop()
+        .ok_or("The endpoint sent no embedding for the query")?;
+
+    let current: HashSet<&String> = texts.iter().collect();
+    cache.vectors.retain(|text, _| current.contains(text));
+    // Only costs the next search its speed when it can't be written
+    save_cache(&cache);
+
+    let mut ranked: Vec<(f32, usize)> = texts
+        .iter()
+        .enumerate()
+        .filter_map(|(index, text)| {
+            let similarity = cosine(&query, cache.vectors.get(text)?);
+            (similarity >= config.min_similarity).then_some((similarity, index))
+        })
+        .collect();
+    ranked.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+    Ok(ranked.into_iter().map(|(_, index)| index).collect())
+}
+
+/// What a snippet's embedding is made from
+fn text(snippet: &Snippet) -> String {
+    let body: String = snippet.description.chars().take(BODY_LENGTH).collect();
+    format!("{}\n{}", snippet.title, body)
+}
+
+fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(a, b)| a * b).sum();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    match norm(a) * norm(b) {
+        0.0 => 0.0,
+        norms => dot / norms,
+    }
+}
+
+/// Asks the endpoint for the embeddings of `texts`, in their order
+fn embed(config: &SemanticConfig, endpoint: &str, texts: &[&str]) -> Result<Vec<Vec<f32>>, String> {
+    let body = serde_json::json!({ "model": config.model, "input": texts });
+    let answer = http::post_json(endpoint, config.api_key_env.as_deref(), &body)
+        .map_err(|error| format!("The embedding endpoint failed: {}", error))?;
+
+    let mut response: EmbeddingResponse = serde_json::from_slice(&answer)
+        .map_err(|error| format!("Unexpected answer from the embedding endpoint: {}", error))?;
+    if response.data.len() != texts.len() {
+        return Err(format!(
+            "The embedding endpoint sent {} embeddings for {} texts",
+            response.data.len(),
+            texts.len()
+        ));
+    }
+    response.data.sort_by_key(|embedding| embedding.index);
+    Ok(response
+        .data
+        .into_iter()
+        .map(|embedding| embedding.embedding)
+        .collect())
+}
+
+fn cache_path() -> Option<PathBuf> {
+    Some(messages_file_path()?.with_file_name("embeddings.json"))
+}
+
+/// The cached embeddings, empty when they were made with another model
+fn load_cache(model: &str) -> Cache {
+    cache_path()
+        .and_then(|path| fs::read(path).ok())
+        .and_then(|bytes| serde_json::from_slice::<Cache>(&bytes).ok())
+        .filter(|cache| cache.model == model)
+        .unwrap_or_else(|| Cache {
+            model: model.to_string(),
+            vectors: HashMap::new(),
+        })
+}
+
+fn save_cache(cache: &Cache) {
+    if let (Some(path), Ok(contents)) = (cache_path(), serde_json::to_vec(cache)) {
+        let _ = fs::write(path, contents);
+    }
+}
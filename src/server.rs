@@ -0,0 +1,483 @@
+//! The HTTP API of `sniprrr daemon` for overlays, chat bots and web pages,
+//! served on `[server] listen`: the snippets on `/snippets` (see `listing`),
+//! gzipped for clients that accept it, and their changes on `/events` (see
+//! `events`). Every request needs one of the `tokens` or `editor_tokens`,
+//! sent as `Authorization: Bearer <TOKEN>`, or as `?token=<TOKEN>` by
+//! clients that can't set headers, like `EventSource`. Editors may also add
+//! snippets with `POST /snippets` and replace or delete them with `PUT` and
+//! `DELETE` on `/snippets/<TITLE>`, which the daemon saves like any other
+//! client's changes. It is plain HTTP, so it listens on loopback unless a
+//! host is given; put a TLS proxy in front to reach it from elsewhere.
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::Deserialize;
+
+use crate::events::{self, Feed};
+use crate::ipc::{self, Incoming, Request};
+use crate::listing::{self, Listing, Query};
+use crate::models::Snippet;
+use crate::time_utils;
+
+/// Clients taking longer than this to send their request are dropped
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+/// Connections answered at once, each has a thread; more get a 503
+const MAX_CONNECTIONS: usize = 64;
+/// Of the connections, how many may be event streams, which stay open, so
+/// they can't take every thread from requests for `/snippets`
+const MAX_STREAMS: usize = 16;
+/// Request bodies larger than this are refused, a snippet is far smaller
+const MAX_BODY: usize = 1024 * 1024;
+/// Where `listen` binds when it is just a port
+const LOOPBACK: &str = "127.0.0.1";
+
+/// The `[server]` section of the config file
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ServerConfig {
+    /// Port to serve on, on loopback, or `<HOST>:<PORT>` to serve elsewhere
+    /// too. Off when not set.
+    pub listen: Option<String>,
+    /// Tokens of clients that only read, one for each so they can be revoked
+    /// on their own, e.g. made with `openssl rand -hex 32`
+    pub tokens: Vec<String>,
+    /// Tokens of clients that may also add, replace and delete snippets
+    pub editor_tokens: Vec<String>,
+}
+
+impl ServerConfig {
+    /// The address to bind, `None` when serving is off
+    pub fn address(&self) -> Option<String> {
+        let listen = self.listen.as_deref()?.trim();
+        Some(match listen.parse::<u16>() {
+            Ok(port) => format!("{}:{}", LOOPBACK, port),
+            Err(_) => listen.to_string(),
+        })
+    }
+
+    /// What the client with `token` may do, `None` for unknown tokens
+    fn role(&self, token: &str) -> Option<Role> {
+        let known = |tokens: &[String]| tokens.iter().any(|known| same(known, token));
+        match known(&self.editor_tokens) {
+            true => Some(Role::Editor),
+            false => known(&self.tokens).then_some(Role::Reader),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Role {
+    Reader,
+    Editor,
+}
+
+/// A number of things allowed at once
+struct Slots {
+    used: AtomicUsize,
+    max: usize,
+}
+
+impl Slots {
+    fn new(max: usize) -> Arc<Self> {
+        Arc::new(Slots {
+            used: AtomicUsize::new(0),
+            max,
+        })
+    }
+
+    /// One of the slots until the returned guard is dropped, `None` when all
+    /// are taken
+    fn take(self: &Arc<Self>) -> Option<Slot> {
+        self.used
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |used| {
+                (used < self.max).then_some(used + 1)
+            })
+            .ok()?;
+        Some(Slot(self.clone()))
+    }
+}
+
+struct Slot(Arc<Slots>);
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        self.0.used.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// What the connections of the listener share
+struct Shared {
+    config: ServerConfig,
+    listing: Arc<Listing>,
+    feed: Arc<Feed>,
+    /// Where the daemon takes requests, so changes are saved by it
+    requests: Sender<Incoming>,
+    streams: Arc<Slots>,
+}
+
+/// Serves the snippets of `listing` and the changes of `feed` on `address` to
+/// clients with one of the tokens of `config`, sending the changes of editors
+/// to the daemon on `requests`
+pub fn serve(
+    address: &str,
+    config: ServerConfig,
+    listing: Arc<Listing>,
+    feed: Arc<Feed>,
+    requests: Sender<Incoming>,
+) -> io::Result<()> {
+    let tokens = config.tokens.iter().chain(&config.editor_tokens);
+    if tokens.clone().any(|token| token.trim().is_empty()) {
+        return Err(io::Error::other("tokens under [server] can't be empty"));
+    }
+    if tokens.count() == 0 {
+        return Err(io::Error::other(
+            "set tokens or editor_tokens under [server] first, every request needs one",
+        ));
+    }
+    let listener = TcpListener::bind(address)?;
+    let shared = Arc::new(Shared {
+        config,
+        listing,
+        feed,
+        requests,
+        streams: Slots::new(MAX_STREAMS),
+    });
+    let connections = Slots::new(MAX_CONNECTIONS);
+    thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let Some(slot) = connections.take() else {
+                let _ = stream.set_write_timeout(Some(READ_TIMEOUT));
+                let _ = busy(&mut stream);
+                continue;
+            };
+            let shared = shared.clone();
+            thread::spawn(move || {
+                let _slot = slot;
+                if let Err(error) = respond(stream, &shared) {
+                    eprintln!("Could not answer an HTTP request: {}", error);
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+fn busy(stream: &mut TcpStream) -> io::Result<()> {
+    write_response(
+        stream,
+        "503 Service Unavailable",
+        PLAIN_TEXT,
+        "Too many connections, try again later\n".to_string(),
+        false,
+    )
+}
+
+/// Status, content type and body of a response
+type Answer = (&'static str, &'static str, String);
+
+fn text(status: &'static str, message: impl Into<String>) -> Answer {
+    (status, PLAIN_TEXT, format!("{}\n", message.into()))
+}
+
+fn respond(mut stream: TcpStream, shared: &Shared) -> io::Result<()> {
+    let request = read_request(&stream)?;
+    let (token, query) = take_token(&request.query);
+    let token = request.bearer.as_deref().or(token);
+    let Some(role) = token.and_then(|token| shared.config.role(token)) else {
+        let (status, content_type, body) = text(
+            "401 Unauthorized",
+            "Send one of the tokens of [server] as a bearer token",
+        );
+        return write_response(&mut stream, status, content_type, body, false);
+    };
+    let (method, path) = (request.method.as_str(), request.path.as_str());
+    if (method, path) == ("GET", "/events") {
+        return match shared.streams.take() {
+            Some(_slot) => events::stream(stream, shared.feed.subscribe()),
+            None => busy(&mut stream),
+        };
+    }
+    let title = path.strip_prefix("/snippets/").map(listing::decode);
+    let (status, content_type, body) = match (method, path, title) {
+        (_, _, _) if request.too_large => text("413 Content Too Large", "The body is too large"),
+        (_, _, Some(Err(error))) => text("400 Bad Request", error),
+        ("GET", "/snippets", _) => match Query::parse(&query) {
+            Ok(query) => ("200 OK", "application/json", shared.listing.page(&query)),
+            Err(error) => text("400 Bad Request", error),
+        },
+        ("GET", _, Some(Ok(title))) => match shared.listing.find(&title) {
+            Some(snippet) => ("200 OK", "application/json", snippet),
+            None => text("404 Not Found", format!("No snippet titled '{}'", title)),
+        },
+        ("POST", "/snippets", _) => change(shared, role, Edit::Add, &request.body),
+        ("PUT", _, Some(Ok(title))) => change(shared, role, Edit::Replace(title), &request.body),
+        ("DELETE", _, Some(Ok(title))) => change(shared, role, Edit::Delete(title), &request.body),
+        (_, "/snippets" | "/events", _) | (_, _, Some(_)) => text(
+            "405 Method Not Allowed",
+            "/snippets takes GET and POST, /snippets/<TITLE> GET, PUT and DELETE, /events GET",
+        ),
+        _ => text(
+            "404 Not Found",
+            "Only /snippets, /snippets/<TITLE> and /events are served",
+        ),
+    };
+    write_response(&mut stream, status, content_type, body, request.gzip)
+}
+
+/// A change to the snippets an editor asks for
+enum Edit {
+    Add,
+    Replace(String),
+    Delete(String),
+}
+
+/// Makes `edit` with the snippet in `body` and has the daemon save it, so it
+/// is merged, written and sent on `/events` as a change of any other client
+fn change(shared: &Shared, role: Role, edit: Edit, body: &[u8]) -> Answer {
+    if role != Role::Editor {
+        return text(
+            "403 Forbidden",
+            "Only the editor_tokens of [server] may change snippets",
+        );
+    }
+    let listed = ipc::ask(&shared.requests, Request::List);
+    let (Some(mut snippets), Some(base)) = (listed.snippets, listed.revision) else {
+        return text("500 Internal Server Error", listed.message);
+    };
+    let taken = |snippets: &[Snippet], title: &str| snippets.iter().any(|s| s.title == title);
+    let find = |snippets: &[Snippet], title: &str| {
+        snippets
+            .iter()
+            .position(|s| s.title == title)
+            .ok_or_else(|| text("404 Not Found", format!("No snippet titled '{}'", title)))
+    };
+    let answer = match edit {
+        Edit::Add => {
+            received(body, None).and_then(|snippet| match taken(&snippets, &snippet.title) {
+                true => Err(text(
+                    "409 Conflict",
+                    format!("There already is a snippet titled '{}'", snippet.title),
+                )),
+                false => {
+                    snippets.push(snippet.clone());
+                    Ok(("201 Created", "application/json", json(&snippet)))
+                }
+            })
+        }
+        Edit::Replace(title) => find(&snippets, &title).and_then(|index| {
+            let snippet = received(body, Some(&snippets[index]))?;
+            if snippet.title != title && taken(&snippets, &snippet.title) {
+                return Err(text(
+                    "409 Conflict",
+                    format!("There already is a snippet titled '{}'", snippet.title),
+                ));
+            }
+            snippets[index] = snippet.clone();
+            Ok(("200 OK", "application/json", json(&snippet)))
+        }),
+        Edit::Delete(title) => find(&snippets, &title).map(|index| {
+            snippets.remove(index);
+            text("200 OK", format!("Deleted '{}'", title))
+        }),
+    };
+    let answer = match answer {
+        Ok(answer) => answer,
+        Err(answer) => return answer,
+    };
+    let saved = ipc::ask(
+        &shared.requests,
+        Request::Save {
+            snippets,
+            base: Some(base),
+        },
+    );
+    match saved.ok {
+        true => answer,
+        false => text("500 Internal Server Error", saved.message),
+    }
+}
+
+fn json(snippet: &Snippet) -> String {
+    serde_json::to_string(snippet).unwrap_or_default()
+}
+
+/// The snippet an editor sent in `body`, to replace `previous` if given.
+/// What copying counted stays with the snippet, and as someone else wrote
+/// it, it is held for review before its commands can run.
+fn received(body: &[u8], previous: Option<&Snippet>) -> Result<Snippet, Answer> {
+    let mut snippet: Snippet = serde_json::from_slice(body)
+        .map_err(|error| text("400 Bad Request", format!("Not a snippet: {}", error)))?;
+    snippet.title = snippet.title.trim().to_string();
+    if snippet.title.is_empty() {
+        return Err(text("400 Bad Request", "The snippet needs a title"));
+    }
+    let now = time_utils::now();
+    match previous {
+        Some(previous) => {
+            snippet.created_at = previous.created_at;
+            snippet.last_used_at = previous.last_used_at;
+            snippet.copy_count = previous.copy_count;
+            snippet.parameter_history = previous.parameter_history.clone();
+        }
+        None => {
+            snippet.created_at = Some(now);
+            snippet.last_used_at = None;
+            snippet.copy_count = 0;
+            snippet.parameter_history.clear();
+        }
+    }
+    snippet.updated_at = Some(now);
+    snippet.needs_review = true;
+    snippet.trusted_commands = None;
+    Ok(snippet)
+}
+
+/// `query` without its `token` parameter, and the token
+fn take_token(query: &str) -> (Option<&str>, String) {
+    let mut token = None;
+    let rest: Vec<&str> = query
+        .split('&')
+        .filter(|pair| match pair.strip_prefix("token=") {
+            Some(value) => {
+                token = Some(value);
+                false
+            }
+            None => true,
+        })
+        .collect();
+    (token, rest.join("&"))
+}
+
+/// Compares tokens in the same time however much of them matches
+fn same(known: &str, given: &str) -> bool {
+    known.len() == given.len()
+        && known
+            .bytes()
+            .zip(given.bytes())
+            .fold(0, |differences, (a, b)| differences | (a ^ b))
+            == 0
+}
+
+pub const PLAIN_TEXT: &str = "text/plain; charset=utf-8";
+
+/// The parts of a request the daemon's listeners look at
+pub struct HttpRequest {
+    pub method: String,
+    pub path: String,
+    /// The part of the target after `?`
+    pub query: String,
+    /// The client takes gzipped bodies
+    pub gzip: bool,
+    /// The token of an `Authorization: Bearer` header
+    pub bearer: Option<String>,
+    pub body: Vec<u8>,
+    /// The body is over `MAX_BODY` and was left unread
+    pub too_large: bool,
+}
+
+/// Reads the request on `stream`
+pub fn read_request(stream: &TcpStream) -> io::Result<HttpRequest> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut gzip = false;
+    let mut bearer = None;
+    let mut length = 0;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            let name = name.trim();
+            if name.eq_ignore_ascii_case("accept-encoding") {
+                gzip = accepts_gzip(value);
+            } else if name.eq_ignore_ascii_case("authorization") {
+                bearer = value
+                    .trim()
+                    .strip_prefix("Bearer ")
+                    .map(|token| token.trim().to_string());
+            } else if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+    let too_large = length > MAX_BODY;
+    let mut body = vec![0; if too_large { 0 } else { length }];
+    reader.read_exact(&mut body)?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let (path, query) = parts
+        .next()
+        .map(|target| target.split_once('?').unwrap_or((target, "")))
+        .unwrap_or_default();
+    Ok(HttpRequest {
+        method,
+        path: path.to_string(),
+        query: query.to_string(),
+        gzip,
+        bearer,
+        body,
+        too_large,
+    })
+}
+
+/// Answers with `body`, gzipped when `gzip`, and closes the connection
+pub fn write_response(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: String,
+    gzip: bool,
+) -> io::Result<()> {
+    let (body, encoding) = match gzip {
+        true => (gzipped(body.as_bytes())?, "Content-Encoding: gzip\r\n"),
+        false => (body.into_bytes(), ""),
+    };
+    let challenge = match status.starts_with("401") {
+        true => "WWW-Authenticate: Bearer\r\n",
+        false => "",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\n{}{}Vary: Accept-Encoding\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        encoding,
+        challenge,
+        body.len()
+    )?;
+    stream.write_all(&body)?;
+    stream.flush()
+}
+
+/// Whether an `Accept-Encoding` header value lists gzip without ruling it
+/// out with `q=0`
+fn accepts_gzip(value: &str) -> bool {
+    value.split(',').any(|coding| {
+        let mut parts = coding.split(';').map(str::trim);
+        let name = parts.next().unwrap_or_default();
+        let refused = parts.any(|parameter| {
+            parameter
+                .strip_prefix("q=")
+                .and_then(|q| q.parse::<f32>().ok())
+                .is_some_and(|q| q == 0.0)
+        });
+        (name.eq_ignore_ascii_case("gzip") || name == "*") && !refused
+    })
+}
+
+fn gzipped(body: &[u8]) -> io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(body)?;
+    encoder.finish()
+}
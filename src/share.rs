@@ -0,0 +1,87 @@
+//! Uploads snippet bodies to a paste service so long snippets can be shared
+//! as a link, configured with `[share]`
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use serde::Deserialize;
+
+/// Paste services that work without further setup, or `custom` for a
+/// self-hosted endpoint
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum ShareService {
+    #[default]
+    #[serde(rename = "paste.rs")]
+    PasteRs,
+    #[serde(rename = "0x0.st")]
+    ZeroXZero,
+    #[serde(rename = "custom")]
+    Custom,
+}
+
+/// The `[share]` section of the config file
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct ShareConfig {
+    pub service: ShareService,
+    /// Endpoint of a `custom` service, overrides the built-in one otherwise
+    pub url: Option<String>,
+    /// Sends the body as this multipart form field instead of as the raw request body
+    pub form_field: Option<String>,
+}
+
+impl ShareConfig {
+    fn endpoint(&self) -> Result<(&str, Option<&str>), String> {
+        let (url, form_field) = match self.service {
+            ShareService::PasteRs => ("https://paste.rs", None),
+            ShareService::ZeroXZero => ("https://0x0.st", Some("file")),
+            ShareService::Custom => match &self.url {
+                Some(url) => (url.as_str(), None),
+                None => return Err("The custom share service needs a url".to_string()),
+            },
+        };
+        Ok((
+            self.url.as_deref().unwrap_or(url),
+            self.form_field.as_deref().or(form_field),
+        ))
+    }
+}
+
+/// Uploads `text` with curl and returns the link to it
+pub fn upload(config: &ShareConfig, text: &str) -> Result<String, String> {
+    let (url, form_field) = config.endpoint()?;
+
+    let mut curl = Command::new("curl");
+    curl.args(["--silent", "--show-error", "--fail"]);
+    match form_field {
+        Some(field) => curl.args(["--form", &format!("{}=@-;filename=snippet.txt", field)]),
+        None => curl.args(["--data-binary", "@-"]),
+    };
+    let mut child = curl
+        .arg(url)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|error| format!("Could not run curl: {}", error))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(text.as_bytes())
+            .map_err(|error| error.to_string())?;
+    }
+
+    let output = child
+        .wait_with_output()
+        .map_err(|error| error.to_string())?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+
+    let link = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if link.starts_with("http://") || link.starts_with("https://") {
+        Ok(link)
+    } else {
+        Err(format!("{} answered without a link: {}", url, link))
+    }
+}
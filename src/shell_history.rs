@@ -0,0 +1,329 @@
+//! Reads bash, zsh and fish history files into candidate snippets for
+//! `sniprrr import --format shell-history`: every command once, the most run
+//! first, so the ones worth keeping can be picked in `ImportPopup`.
+//!
+//! Commands not worth a snippet are left out: single words, `cd` and `ls`,
+//! commands typed with a leading space (which shells keep out of the history
+//! with `HISTCONTROL=ignorespace` or `HIST_IGNORE_SPACE`) and anything that
+//! looks like it holds a password or token.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+use crate::models::Snippet;
+use crate::{import, tag_suggest, time_utils};
+
+/// Candidates offered at most, the most run ones
+const LIMIT: usize = 200;
+/// Longest title before the command is cut off in it
+const TITLE_LENGTH: usize = 60;
+/// Commands that are quicker to type than to look up
+const TRIVIAL: [&str; 12] = [
+    "cd", "ls", "ll", "la", "l", "pwd", "clear", "exit", "history", "fg", "bg", "jobs",
+];
+const SECRET_PATTERN: &str =
+    r"(?i)(?:password|passwd|secret|token|api[_-]?key)\s*[=:]|\b(?:AKIA|ghp_|sk-)[A-Za-z0-9]";
+const ZSH_PATTERN: &str = r"^: \d+:\d+;";
+
+/// A command from the history with how often it was run
+pub struct Candidate {
+    pub snippet: Snippet,
+    pub count: usize,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl Format {
+    /// The language given to commands from a history in this format
+    fn language(self) -> &'static str {
+        match self {
+            Format::Bash => "bash",
+            Format::Zsh => "zsh",
+            Format::Fish => "fish",
+        }
+    }
+}
+
+/// A command and when it was run, when the history records that
+struct Entry {
+    command: String,
+    at: Option<u64>,
+}
+
+/// What is known about a command across the history files
+struct Seen {
+    count: usize,
+    last_at: Option<u64>,
+    /// Position of the last run among all entries, to rank by recency
+    /// without timestamps
+    last_position: usize,
+    format: Format,
+}
+
+/// The history files of the shells found in the home directory, `$HISTFILE` first
+pub fn default_paths() -> Vec<PathBuf> {
+    let home = dirs::home_dir().unwrap_or_default();
+    let fish_data = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| home.join(".local/share"));
+    let mut paths: Vec<PathBuf> = std::env::var_os("HISTFILE")
+        .map(|path| import::expand_home(&path.to_string_lossy()))
+        .into_iter()
+        .collect();
+    for path in [
+        home.join(".bash_history"),
+        home.join(".zsh_history"),
+        home.join(".histfile"),
+        fish_data.join("fish/fish_history"),
+    ] {
+        if !paths.contains(&path) {
+            paths.push(path);
+        }
+    }
+    paths.retain(|path| path.is_file());
+    paths
+}
+
+/// The commands of the history files at `paths`, deduplicated and ranked by
+/// how often they were run, then by how recently
+pub fn read(paths: &[PathBuf]) -> Result<Vec<Candidate>, String> {
+    let mut seen: HashMap<String, Seen> = HashMap::new();
+    let mut position = 0;
+    for path in paths {
+        let bytes = fs::read(path).map_err(|error| format!("{}: {}", path.display(), error))?;
+        let (format, entries) = parse(&bytes);
+        for entry in entries {
+            position += 1;
+            if !worth_keeping(&entry.command) {
+                continue;
+            }
+            let seen = seen
+                .entry(entry.command.trim().to_string())
+                .or_insert(Seen {
+                    count: 0,
+                    last_at: None,
+                    last_position: 0,
+                    format,
+                });
+            seen.count += 1;
+            seen.last_at = seen.last_at.max(entry.at);
+            seen.last_position = position;
+        }
+    }
+
+    let mut ranked: Vec<(String, Seen)> = seen.into_iter().collect();
+    ranked.sort_by(|(_, a), (_, b)| {
+        b.count
+            .cmp(&a.count)
+            .then(b.last_position.cmp(&a.last_position))
+    });
+    ranked.truncate(LIMIT);
+
+    let now = time_utils::now();
+    let mut titles: Vec<String> = vec![];
+    Ok(ranked
+        .into_iter()
+        .map(|(command, seen)| {
+            let title = unique_title(&command, &titles);
+            titles.push(title.clone());
+            let mut snippet = Snippet {
+                title,
+                description: command,
+                language: Some(seen.format.language().to_string()),
+                created_at: Some(seen.last_at.unwrap_or(now)),
+                ..Default::default()
+            };
+            // The language is already the shell's, a tag for it too would be noise
+            snippet.tags = tag_suggest::suggest(&snippet)
+                .into_iter()
+                .filter(|tag| Some(tag) != snippet.language.as_ref())
+                .chain(std::iter::once("history".to_string()))
+                .collect();
+            Candidate {
+                snippet,
+                count: seen.count,
+            }
+        })
+        .collect())
+}
+
+/// The format of a history file by its contents, and its entries
+fn parse(bytes: &[u8]) -> (Format, Vec<Entry>) {
+    static ZSH: OnceLock<Regex> = OnceLock::new();
+    let zsh = ZSH.get_or_init(|| Regex::new(ZSH_PATTERN).expect("valid pattern"));
+
+    let text = String::from_utf8_lossy(&unmetafy(bytes)).into_owned();
+    if text.lines().any(|line| line.starts_with("- cmd: ")) {
+        (Format::Fish, parse_fish(&text))
+    } else if text.lines().any(|line| zsh.is_match(line)) {
+        (Format::Zsh, parse_zsh(&text))
+    } else {
+        (Format::Bash, parse_bash(&text))
+    }
+}
+
+/// bash: a command per line, after a `#<timestamp>` line when
+/// `HISTTIMEFORMAT` is set
+fn parse_bash(text: &str) -> Vec<Entry> {
+    let mut entries = vec![];
+    let mut at = None;
+    for line in text.lines() {
+        match line.strip_prefix('#').map(str::parse::<u64>) {
+            Some(Ok(timestamp)) => at = Some(timestamp),
+            _ => entries.push(Entry {
+                command: line.to_string(),
+                at: at.take(),
+            }),
+        }
+    }
+    entries
+}
+
+/// zsh: `: <start>:<elapsed>;<command>` with extended history, or a bare
+/// command per line. Lines of a multi-line command end in a backslash.
+fn parse_zsh(text: &str) -> Vec<Entry> {
+    let mut entries: Vec<Entry> = vec![];
+    let mut continued = false;
+    for line in text.lines() {
+        let (body, more) = match line.strip_suffix('\\') {
+            Some(body) => (body, true),
+            None => (line, false),
+        };
+        match entries.last_mut() {
+            Some(entry) if continued => {
+                entry.command.push('\n');
+                entry.command.push_str(body);
+            }
+            _ => {
+                let (at, command) = split_zsh(body);
+                entries.push(Entry {
+                    command: command.to_string(),
+                    at,
+                });
+            }
+        }
+        continued = more;
+    }
+    entries
+}
+
+/// The start time and command of an extended history line
+fn split_zsh(line: &str) -> (Option<u64>, &str) {
+    match line
+        .strip_prefix(": ")
+        .and_then(|rest| rest.split_once(';'))
+    {
+        Some((meta, command)) => (
+            meta.split(':').next().and_then(|start| start.parse().ok()),
+            command,
+        ),
+        None => (None, line),
+    }
+}
+
+/// fish: YAML-like records of `- cmd: <command>` and `  when: <timestamp>`,
+/// with newlines and backslashes in commands escaped
+fn parse_fish(text: &str) -> Vec<Entry> {
+    let mut entries: Vec<Entry> = vec![];
+    for line in text.lines() {
+        if let Some(command) = line.strip_prefix("- cmd: ") {
+            entries.push(Entry {
+                command: unescape_fish(command),
+                at: None,
+            });
+        } else if let (Some(entry), Some(when)) =
+            (entries.last_mut(), line.trim().strip_prefix("when: "))
+        {
+            entry.at = when.trim().parse().ok();
+        }
+    }
+    entries
+}
+
+fn unescape_fish(command: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('n')) => {
+                chars.next();
+                unescaped.push('\n');
+            }
+            ('\\', Some('\\')) => {
+                chars.next();
+                unescaped.push('\\');
+            }
+            (c, _) => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+/// zsh writes some bytes of non-ASCII text as 0x83 followed by the byte xor 32
+fn unmetafy(bytes: &[u8]) -> Vec<u8> {
+    let mut plain = Vec::with_capacity(bytes.len());
+    let mut iter = bytes.iter();
+    while let Some(&byte) = iter.next() {
+        match byte {
+            0x83 => plain.extend(iter.next().map(|next| next ^ 32)),
+            byte => plain.push(byte),
+        }
+    }
+    plain
+}
+
+fn worth_keeping(command: &str) -> bool {
+    static SECRET: OnceLock<Regex> = OnceLock::new();
+
+    if command.starts_with(' ') {
+        return false;
+    }
+    let mut words = command.split_whitespace();
+    let (Some(program), Some(_)) = (words.next(), words.next()) else {
+        return false;
+    };
+    !TRIVIAL.contains(&program)
+        && !SECRET
+            .get_or_init(|| Regex::new(SECRET_PATTERN).expect("valid pattern"))
+            .is_match(command)
+}
+
+/// The first line of `command`, cut off at `TITLE_LENGTH` characters and
+/// numbered when another candidate got the same title
+fn unique_title(command: &str, taken: &[String]) -> String {
+    let line = command.lines().next().unwrap_or_default();
+    let base = match line.char_indices().nth(TITLE_LENGTH) {
+        Some((end, _)) => format!("{}…", line[..end].trim_end()),
+        None if command.contains('\n') => format!("{} …", line),
+        None => line.to_string(),
+    };
+    let mut title = base.clone();
+    let mut number = 2;
+    while taken.contains(&title) {
+        title = format!("{} ({})", base, number);
+        number += 1;
+    }
+    title
+}
+
+/// How `paths` are named in the import popup
+pub fn describe(paths: &[PathBuf]) -> String {
+    let home = dirs::home_dir().unwrap_or_default();
+    let names: Vec<String> = paths
+        .iter()
+        .map(|path| match path.strip_prefix(&home) {
+            Ok(relative) => Path::new("~").join(relative).display().to_string(),
+            Err(_) => path.display().to_string(),
+        })
+        .collect();
+    format!("shell history ({})", names.join(", "))
+}
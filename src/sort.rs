@@ -0,0 +1,59 @@
+//! Orders the table can list the snippets in when nothing is searched for.
+//! Search results are ranked by how well they match, ties in this order.
+
+use std::cmp::Reverse;
+
+use serde::Deserialize;
+
+use crate::models::Snippet;
+
+/// `sort` in the config file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SortOrder {
+    /// As they are kept in the store, the oldest first
+    #[default]
+    Store,
+    /// By title, ignoring case
+    Title,
+    /// The most recently copied first
+    Recent,
+    /// The most often copied first
+    MostUsed,
+    /// The most recently added first
+    Newest,
+}
+
+impl SortOrder {
+    /// The order `s` switches to from this one
+    pub fn next(self) -> SortOrder {
+        match self {
+            SortOrder::Store => SortOrder::Title,
+            SortOrder::Title => SortOrder::Newest,
+            SortOrder::Newest => SortOrder::Recent,
+            SortOrder::Recent => SortOrder::MostUsed,
+            SortOrder::MostUsed => SortOrder::Store,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SortOrder::Store => "in store order",
+            SortOrder::Title => "by title",
+            SortOrder::Recent => "recently used first",
+            SortOrder::MostUsed => "most used first",
+            SortOrder::Newest => "newest first",
+        }
+    }
+
+    /// Sorts `indices` into `snippets` in this order
+    pub fn sort(self, snippets: &[Snippet], indices: &mut [usize]) {
+        match self {
+            SortOrder::Store => indices.sort_unstable(),
+            SortOrder::Title => indices.sort_by_cached_key(|&i| snippets[i].title.to_lowercase()),
+            SortOrder::Recent => indices.sort_by_key(|&i| Reverse(snippets[i].last_used_at)),
+            SortOrder::MostUsed => indices.sort_by_key(|&i| Reverse(snippets[i].copy_count)),
+            SortOrder::Newest => indices.sort_by_key(|&i| Reverse(snippets[i].created_at)),
+        }
+    }
+}
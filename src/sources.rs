@@ -0,0 +1,101 @@
+//! Stores layered with the personal one in the app, listed under `[[sources]]`
+//! in the config from the lowest precedence to the highest: say a pack of
+//! shared snippets, a team's collection, the personal store and a project's
+//! own file. When titles collide the source listed later wins. Snippets of
+//! the other sources are read-only, shown with the source's name and never
+//! saved. Like imported ones they are flagged for review, and as they can't
+//! be accepted their commands never run.
+
+use std::collections::{HashMap, HashSet};
+
+use serde::Deserialize;
+use sniprrr_core::SnippetStore;
+
+use crate::import::expand_home;
+use crate::models::Snippet;
+
+/// One entry of `[[sources]]`
+#[derive(Debug, Clone, Deserialize)]
+pub struct Source {
+    /// Shown next to the titles of its snippets
+    pub name: String,
+    /// The store file, `~/` for the home directory; a relative path starts
+    /// from the directory sniprrr is run in. Left out for the personal store,
+    /// which comes last when not listed.
+    #[serde(default)]
+    pub path: Option<String>,
+}
+
+/// The snippets of all sources, as listed in the app
+#[derive(Debug, Default)]
+pub struct Layers {
+    /// The personal snippets in store order, then those of the other sources
+    pub snippets: Vec<Snippet>,
+    /// Personal snippets hidden by a source of higher precedence, with their
+    /// position in the store, to be saved along with the rest
+    pub shadowed: Vec<(usize, Snippet)>,
+    /// Why sources could not be read; a file that doesn't exist is skipped quietly
+    pub errors: Vec<String>,
+}
+
+/// Layers the snippets of `sources` with the `personal` ones
+pub fn layer(sources: &[Source], personal: Vec<Snippet>) -> Layers {
+    let personal_rank = sources
+        .iter()
+        .position(|source| source.path.is_none())
+        .unwrap_or(sources.len());
+    let mut layers = Layers::default();
+    let mut loaded: Vec<(usize, &str, Vec<Snippet>)> = vec![];
+    for (rank, source) in sources.iter().enumerate() {
+        let Some(path) = &source.path else {
+            continue;
+        };
+        // Most projects don't have a file of their own
+        let path = expand_home(path);
+        if !path.exists() {
+            continue;
+        }
+        match SnippetStore::load_from(&path) {
+            Ok(store) => loaded.push((rank, &source.name, store.snippets().to_vec())),
+            Err(error) => layers.errors.push(format!(
+                "Could not read the source {} ({}): {}",
+                source.name,
+                path.display(),
+                error
+            )),
+        }
+    }
+
+    // The rank of the source each title is listed from
+    let mut winners: HashMap<String, usize> = HashMap::new();
+    let titles = personal.iter().map(|s| (personal_rank, &s.title)).chain(
+        loaded
+            .iter()
+            .flat_map(|(rank, _, snippets)| snippets.iter().map(move |s| (*rank, &s.title))),
+    );
+    for (rank, title) in titles {
+        let winner = winners.entry(title.clone()).or_insert(rank);
+        *winner = rank.max(*winner);
+    }
+
+    for (position, snippet) in personal.into_iter().enumerate() {
+        match winners[&snippet.title] == personal_rank {
+            true => layers.snippets.push(snippet),
+            false => layers.shadowed.push((position, snippet)),
+        }
+    }
+    for (rank, name, snippets) in loaded {
+        // A title repeated within a source is listed once
+        let mut listed = HashSet::new();
+        for mut snippet in snippets {
+            if winners[&snippet.title] == rank && listed.insert(snippet.title.clone()) {
+                snippet.source = Some(name.to_string());
+                // Whoever can write the file could slip in commands, see `import`
+                snippet.needs_review = true;
+                snippet.trusted_commands = None;
+                layers.snippets.push(snippet);
+            }
+        }
+    }
+    layers
+}
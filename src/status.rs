@@ -0,0 +1,107 @@
+//! The status line shown in place of the help line: what the last action did
+//! and what went wrong. Messages queue up and go away on their own; infos
+//! also with the next key press, errors only once they had time to be read.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// How long an info stays when no key is pressed
+const INFO_TIME: Duration = Duration::from_secs(5);
+/// How long an error stays
+const ERROR_TIME: Duration = Duration::from_secs(10);
+/// Key presses before this leave an error in place
+const ERROR_READ_TIME: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Info,
+    Error,
+}
+
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub message: String,
+    pub severity: Severity,
+    /// When it reached the front of the queue and was first drawn
+    shown_since: Option<Instant>,
+}
+
+impl Notification {
+    fn shown_for(&self, now: Instant) -> Duration {
+        self.shown_since
+            .map(|since| now.duration_since(since))
+            .unwrap_or_default()
+    }
+}
+
+/// The messages waiting for the status line, the one shown first
+#[derive(Debug, Default)]
+pub struct Notifications {
+    queue: VecDeque<Notification>,
+}
+
+impl Notifications {
+    pub fn info(&mut self, message: impl Into<String>) {
+        self.push(message.into(), Severity::Info);
+    }
+
+    pub fn error(&mut self, message: impl Into<String>) {
+        self.push(message.into(), Severity::Error);
+    }
+
+    fn push(&mut self, message: String, severity: Severity) {
+        self.queue.push_back(Notification {
+            message,
+            severity,
+            shown_since: None,
+        });
+    }
+
+    /// The message to draw, starting its time if it wasn't drawn before.
+    /// Drops the ones whose time is up.
+    pub fn current(&mut self, now: Instant) -> Option<&Notification> {
+        while let Some(front) = self.queue.front_mut() {
+            let time = match front.severity {
+                Severity::Info => INFO_TIME,
+                Severity::Error => ERROR_TIME,
+            };
+            match front.shown_since {
+                None => front.shown_since = Some(now),
+                Some(_) if front.shown_for(now) >= time => {
+                    self.queue.pop_front();
+                    continue;
+                }
+                Some(_) => {}
+            }
+            break;
+        }
+        self.queue.front()
+    }
+
+    /// How many messages wait behind the one shown
+    pub fn waiting(&self) -> usize {
+        self.queue.len().saturating_sub(1)
+    }
+
+    /// Clears the messages a key press makes old news, before the key is handled
+    pub fn key_pressed(&mut self) {
+        let now = Instant::now();
+        self.queue
+            .retain(|notification| match notification.severity {
+                Severity::Info => false,
+                Severity::Error => {
+                    notification.shown_since.is_none()
+                        || notification.shown_for(now) < ERROR_READ_TIME
+                }
+            });
+    }
+
+    /// The newest error, for answering another sniprrr whose request failed
+    pub fn last_error(&self) -> Option<&str> {
+        self.queue
+            .iter()
+            .rev()
+            .find(|notification| notification.severity == Severity::Error)
+            .map(|notification| notification.message.as_str())
+    }
+}
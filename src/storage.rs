@@ -0,0 +1,122 @@
+//! The storage overview: size and freshness of the store, whether it matches
+//! what is shown, and how recent its backups and daily snapshots are. sniprrr
+//! keeps all snippets in one collection, the store file, written by one of the
+//! backends from `sniprrr_core::storage`.
+
+pub use sniprrr_core::storage::*;
+
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::file_utils::{backup_file_path, store_contents, BACKUP_COUNT};
+use crate::history;
+use crate::models::Snippet;
+use crate::{compression, encryption};
+
+/// What the storage overview shows
+pub struct StorageReport {
+    pub path: PathBuf,
+    /// The format the store is kept in
+    pub backend: Backend,
+    pub snippets: usize,
+    /// Snippets per tag, most used first
+    pub tags: Vec<(String, usize)>,
+    pub untagged: usize,
+    /// `None` while the store hasn't been written yet
+    pub size: Option<u64>,
+    /// The file is zstd-compressed, see `[compression]`
+    pub compressed: bool,
+    /// The file is encrypted, see `[encryption]`
+    pub encrypted: bool,
+    pub modified: Option<u64>,
+    /// The file doesn't hold exactly the snippets shown, e.g. because it was
+    /// edited by hand or is still in an older layout
+    pub dirty: bool,
+    /// Saves go through the daemon instead of straight to the file
+    pub daemon: bool,
+    /// Modification time of each existing backup, newest first
+    pub backups: Vec<u64>,
+    pub snapshots: usize,
+    pub newest_snapshot: Option<u64>,
+    /// Bytes taken by the backups and snapshots together
+    pub history_size: u64,
+}
+
+fn modified(path: &Path) -> Option<u64> {
+    Some(
+        fs::metadata(path)
+            .ok()?
+            .modified()
+            .ok()?
+            .duration_since(UNIX_EPOCH)
+            .ok()?
+            .as_secs(),
+    )
+}
+
+fn size(path: &Path) -> u64 {
+    fs::metadata(path).map_or(0, |metadata| metadata.len())
+}
+
+pub fn report(store_path: &Path, snippets: &[Snippet], daemon: bool) -> StorageReport {
+    let mut tags: BTreeMap<&str, usize> = BTreeMap::new();
+    for tag in snippets.iter().flat_map(|snippet| &snippet.tags) {
+        *tags.entry(tag).or_default() += 1;
+    }
+    let mut tags: Vec<(String, usize)> = tags
+        .into_iter()
+        .map(|(tag, count)| (tag.to_string(), count))
+        .collect();
+    tags.sort_by_key(|(_, count)| Reverse(*count));
+
+    let backend = detect(store_path);
+    let bytes = fs::read(store_path).ok();
+    let encrypted = bytes.as_deref().is_some_and(encryption::is_encrypted);
+    let bytes = bytes.and_then(|bytes| encryption::decrypt(&bytes).ok());
+    let dirty = match (backend, bytes.as_deref()) {
+        (Backend::Sqlite, Some(_)) => match Sqlite.load(store_path) {
+            Ok(stored) => store_contents(&stored).ok() != store_contents(snippets).ok(),
+            Err(_) => true,
+        },
+        (Backend::Json, Some(bytes)) => match compression::decode(bytes) {
+            Ok(contents) => !store_contents(snippets).is_ok_and(|expected| contents == expected),
+            Err(_) => true,
+        },
+        (_, None) => !snippets.is_empty(),
+    };
+
+    let backup_paths: Vec<PathBuf> = (1..=BACKUP_COUNT)
+        .map(|number| backup_file_path(store_path, number))
+        .filter(|path| path.exists())
+        .collect();
+    let snapshots = history::snapshots(store_path);
+    let history_size = backup_paths
+        .iter()
+        .chain(snapshots.iter().map(|(_, path)| path))
+        .map(|path| size(path))
+        .sum();
+
+    StorageReport {
+        path: store_path.to_path_buf(),
+        backend,
+        snippets: snippets.len(),
+        tags,
+        untagged: snippets.iter().filter(|s| s.tags.is_empty()).count(),
+        size: store_path.exists().then(|| size(store_path)),
+        compressed: bytes.is_some_and(|bytes| compression::is_compressed(&bytes)),
+        encrypted,
+        modified: modified(store_path),
+        dirty,
+        daemon,
+        backups: backup_paths
+            .iter()
+            .filter_map(|path| modified(path))
+            .collect(),
+        snapshots: snapshots.len(),
+        newest_snapshot: snapshots.last().map(|(timestamp, _)| *timestamp),
+        history_size,
+    }
+}
@@ -0,0 +1,423 @@
+//! Sharing the store across machines through a git repository, configured
+//! with `[sync]`. A clone of the remote is kept next to the config; when the
+//! UI opens, the store is merged with what the other machines pushed, snippet
+//! by snippet against the version both last had, and when it closes the store
+//! is committed and pushed. `sniprrr sync` does both at once. The remote is
+//! reached with the user's own credentials: the keys of the ssh agent, or
+//! what the credential helper of their git config has for the URL.
+
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use git2::build::{CheckoutBuilder, RepoBuilder};
+use git2::{
+    Commit, Cred, CredentialType, FetchOptions, IndexAddOption, Oid, PushOptions, RemoteCallbacks,
+    Repository, Signature,
+};
+use serde::Deserialize;
+
+use crate::file_utils::{load_store, save_store};
+use crate::import::expand_home;
+use crate::models::Snippet;
+
+/// The `[sync]` section of the config file
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct SyncConfig {
+    /// URL of the git repository to sync with, syncing is off while unset
+    pub remote: Option<String>,
+    /// The branch the store is kept on, `main` when left out
+    pub branch: Option<String>,
+    /// Where the clone is kept, `sync` in the config directory when left out
+    pub path: Option<String>,
+}
+
+impl SyncConfig {
+    pub fn enabled(&self) -> bool {
+        self.remote.is_some()
+    }
+
+    fn branch(&self) -> &str {
+        self.branch.as_deref().unwrap_or("main")
+    }
+
+    fn clone_path(&self) -> Option<PathBuf> {
+        match &self.path {
+            Some(path) => Some(expand_home(path)),
+            None => Some(dirs::config_dir()?.join("sniprrr").join("sync")),
+        }
+    }
+}
+
+/// Tries of the credentials at hand before giving up on the remote, which
+/// libgit2 would otherwise ask for again and again
+const CREDENTIAL_TRIES: usize = 3;
+
+/// Callbacks answering the remote's questions for credentials with those of
+/// the ssh agent or the git credential helper
+fn callbacks<'a>(config: &'a git2::Config, tries: &'a Cell<usize>) -> RemoteCallbacks<'a> {
+    let mut callbacks = RemoteCallbacks::new();
+    callbacks.credentials(move |url, username, allowed| {
+        tries.set(tries.get() + 1);
+        if tries.get() > CREDENTIAL_TRIES {
+            return Err(git2::Error::from_str(&format!(
+                "{} turned down the credentials of the ssh agent and the git credential helper",
+                url
+            )));
+        }
+        if allowed.contains(CredentialType::SSH_KEY) {
+            Cred::ssh_key_from_agent(username.unwrap_or("git"))
+        } else if allowed.contains(CredentialType::USER_PASS_PLAINTEXT) {
+            Cred::credential_helper(config, url, username)
+        } else {
+            Cred::default()
+        }
+    });
+    callbacks
+}
+
+fn fetch_options<'a>(config: &'a git2::Config, tries: &'a Cell<usize>) -> FetchOptions<'a> {
+    let mut options = FetchOptions::new();
+    options.remote_callbacks(callbacks(config, tries));
+    options
+}
+
+/// Fetches every branch of `origin`
+fn fetch(repo: &Repository) -> Result<(), String> {
+    let config = repo.config().map_err(|error| error.to_string())?;
+    let tries = Cell::new(0);
+    repo.find_remote("origin")
+        .and_then(|mut origin| {
+            origin.fetch::<&str>(&[], Some(&mut fetch_options(&config, &tries)), None)
+        })
+        .map_err(|error| format!("Could not fetch: {}", error.message()))
+}
+
+/// The commit `revision` names in the clone, if any
+fn commit_at<'r>(repo: &'r Repository, revision: &str) -> Option<Commit<'r>> {
+    repo.revparse_single(revision).ok()?.peel_to_commit().ok()
+}
+
+/// The clone, made from `remote` when it isn't there yet
+fn open_clone(config: &SyncConfig) -> Result<Repository, String> {
+    let remote = config.remote.as_deref().ok_or("[sync] has no remote")?;
+    let dir = config
+        .clone_path()
+        .ok_or("No config directory to keep the clone in")?;
+    if dir.join(".git").exists() {
+        return Repository::open(&dir).map_err(|error| error.message().to_string());
+    }
+    if let Some(parent) = dir.parent() {
+        fs::create_dir_all(parent).map_err(|error| error.to_string())?;
+    }
+    let git_config = git2::Config::open_default().map_err(|error| error.to_string())?;
+    let tries = Cell::new(0);
+    let mut builder = RepoBuilder::new();
+    builder.fetch_options(fetch_options(&git_config, &tries));
+    builder
+        .clone(remote, &dir)
+        .map_err(|error| format!("Could not clone {}: {}", remote, error.message()))
+}
+
+/// Where the clone keeps the commit the store was last synced with
+fn synced_path(repo: &Repository) -> PathBuf {
+    repo.path().join("sniprrr-synced")
+}
+
+/// The commit the store was last synced with. A fresh clone has none, as
+/// the store hasn't seen any of the remote's snippets yet.
+fn synced(repo: &Repository) -> Option<Oid> {
+    let commit = fs::read_to_string(synced_path(repo)).ok()?;
+    let oid = Oid::from_str(commit.trim()).ok()?;
+    repo.find_commit(oid).is_ok().then_some(oid)
+}
+
+/// The snippets of the store file as it was at `commit`, none when the file
+/// wasn't there
+fn snippets_at(
+    repo: &Repository,
+    commit: &Commit,
+    file_name: &str,
+) -> Result<Vec<Snippet>, String> {
+    let tree = commit.tree().map_err(|error| error.to_string())?;
+    let Ok(entry) = tree.get_path(Path::new(file_name)) else {
+        return Ok(vec![]);
+    };
+    let blob = repo
+        .find_blob(entry.id())
+        .map_err(|error| format!("{}:{}: {}", commit.id(), file_name, error.message()))?;
+    // Read through a file so every backend, compression and encryption apply
+    let scratch = repo.path().join(format!("sniprrr-{}", file_name));
+    fs::write(&scratch, blob.content()).map_err(|error| error.to_string())?;
+    let snippets =
+        load_store(&scratch).map_err(|error| format!("{}:{}: {}", commit.id(), file_name, error));
+    let _ = fs::remove_file(&scratch);
+    snippets
+}
+
+/// The usage counters left out, which both sides change just by copying
+fn content(snippet: &Snippet) -> serde_json::Value {
+    let mut snippet = snippet.clone();
+    snippet.copy_count = 0;
+    snippet.last_used_at = None;
+    serde_json::to_value(snippet).unwrap_or_default()
+}
+
+/// What a merge did besides taking over unchanged snippets
+#[derive(Debug, Default)]
+pub struct Merged {
+    pub snippets: Vec<Snippet>,
+    /// Snippets added or changed on the other side
+    pub received: usize,
+    /// Snippets deleted on the other side
+    pub removed: usize,
+    /// Titles changed on both sides, whose other version was added next to
+    /// the local one
+    pub conflicts: Vec<String>,
+}
+
+/// Identifies a snippet across the sides of a merge: its title, and how many
+/// snippets before it have the same one
+type Key = (String, usize);
+
+/// The keys of `snippets` in order, so the n-th of several snippets with the
+/// same title is matched with the n-th one on the other sides
+fn keys(snippets: &[Snippet]) -> Vec<Key> {
+    let mut seen: HashMap<&str, usize> = HashMap::new();
+    snippets
+        .iter()
+        .map(|snippet| {
+            let count = seen.entry(&snippet.title).or_insert(0);
+            *count += 1;
+            (snippet.title.clone(), *count - 1)
+        })
+        .collect()
+}
+
+/// Merges the `local` and `remote` snippets, both descended from `base`.
+/// Changes on one side win over the unchanged other side; a snippet changed
+/// on both is kept twice, the remote one titled "<title> (<label>)". Copy
+/// counts add up.
+pub fn merge(base: &[Snippet], local: Vec<Snippet>, remote: &[Snippet], label: &str) -> Merged {
+    let base: HashMap<Key, &Snippet> = keys(base).into_iter().zip(base).collect();
+    let remote_keys = keys(remote);
+    let mut remote_left: HashMap<Key, Snippet> = remote_keys
+        .iter()
+        .cloned()
+        .zip(remote.iter().cloned())
+        .collect();
+    let mut merged = Merged::default();
+    let same = |a: Option<&Snippet>, b: Option<&Snippet>| match (a, b) {
+        (Some(a), Some(b)) => content(a) == content(b),
+        (a, b) => a.is_none() && b.is_none(),
+    };
+
+    for (key, mut snippet) in keys(&local).into_iter().zip(local) {
+        let theirs = remote_left.remove(&key);
+        let base = base.get(&key).copied();
+        match theirs {
+            // Deleted over there, and not changed here since
+            None if base.is_some() && same(Some(&snippet), base) => merged.removed += 1,
+            None => merged.snippets.push(snippet),
+            Some(theirs) if same(Some(&snippet), Some(&theirs)) || same(Some(&theirs), base) => {
+                add_usage(&mut snippet, &theirs, base);
+                merged.snippets.push(snippet);
+            }
+            Some(mut theirs) if same(Some(&snippet), base) => {
+                add_usage(&mut theirs, &snippet, base);
+                merged.received += 1;
+                merged.snippets.push(theirs);
+            }
+            Some(mut theirs) => {
+                merged.conflicts.push(snippet.title.clone());
+                theirs.title = format!("{} ({})", theirs.title, label);
+                merged.snippets.push(snippet);
+                merged.snippets.push(theirs);
+            }
+        }
+    }
+    for key in remote_keys {
+        // Taken care of with the local one
+        let Some(theirs) = remote_left.remove(&key) else {
+            continue;
+        };
+        let base = base.get(&key).copied();
+        // Deleted here, and not changed over there since
+        if base.is_some() && same(Some(&theirs), base) {
+            continue;
+        }
+        merged.received += 1;
+        merged.snippets.push(theirs);
+    }
+    merged
+}
+
+/// Adds the copies made on the `other` side since `base` to `snippet`
+fn add_usage(snippet: &mut Snippet, other: &Snippet, base: Option<&Snippet>) {
+    let before = base.map_or(0, |base| base.copy_count);
+    snippet.copy_count += other.copy_count.saturating_sub(before);
+    snippet.last_used_at = snippet.last_used_at.max(other.last_used_at);
+}
+
+/// Fetches what the other machines pushed and merges it into the store at
+/// `store`, then commits the result. Returns what changed, for the status line.
+pub fn pull(config: &SyncConfig, store: &Path) -> Result<String, String> {
+    let repo = open_clone(config)?;
+    let file_name = file_name(store)?;
+    let remote_branch = format!("origin/{}", config.branch());
+    fetch(&repo)?;
+
+    let mut summary = "Nothing new from the sync remote".to_string();
+    if let Some(theirs) = commit_at(&repo, &remote_branch) {
+        let base = synced(&repo)
+            .and_then(|synced| repo.merge_base(synced, theirs.id()).ok())
+            .and_then(|base| repo.find_commit(base).ok());
+        let base = match base {
+            Some(base) => snippets_at(&repo, &base, &file_name)?,
+            None => vec![],
+        };
+        let remote = snippets_at(&repo, &theirs, &file_name)?;
+        let local = match store.exists() {
+            true => load_store(store).map_err(|error| error.to_string())?,
+            false => vec![],
+        };
+        let unchanged = serde_json::to_value(&local).ok();
+        let merged = merge(&base, local, &remote, "remote");
+        if serde_json::to_value(&merged.snippets).ok() != unchanged {
+            save_store(store, &merged.snippets).map_err(|error| error.to_string())?;
+        }
+        summary = describe(&merged);
+        // The store holds everything now, so the clone can follow the remote
+        follow(&repo, config.branch(), &theirs).map_err(|error| error.message().to_string())?;
+    }
+    commit(&repo, store, &file_name, config.branch())?;
+    Ok(summary)
+}
+
+/// Points `branch` at `commit` and checks it out, dropping what was there
+fn follow(repo: &Repository, branch: &str, commit: &Commit) -> Result<(), git2::Error> {
+    let name = format!("refs/heads/{}", branch);
+    repo.reference(&name, commit.id(), true, "sniprrr sync")?;
+    repo.set_head(&name)?;
+    repo.checkout_head(Some(CheckoutBuilder::new().force()))
+}
+
+/// Commits the store at `store` to the clone when it changed and pushes it.
+/// Returns whether there was anything to push.
+pub fn push(config: &SyncConfig, store: &Path) -> Result<bool, String> {
+    let repo = open_clone(config)?;
+    let file_name = file_name(store)?;
+    commit(&repo, store, &file_name, config.branch())?;
+    let Some(head) = commit_at(&repo, "HEAD") else {
+        return Ok(false);
+    };
+    let remote_branch = format!("origin/{}", config.branch());
+    if commit_at(&repo, &remote_branch).is_some_and(|theirs| theirs.id() == head.id()) {
+        return Ok(false);
+    }
+
+    let git_config = repo.config().map_err(|error| error.to_string())?;
+    let tries = Cell::new(0);
+    // A push the remote turns down still succeeds, with the reason given here
+    let rejected = Cell::new(None);
+    let mut callbacks = callbacks(&git_config, &tries);
+    callbacks.push_update_reference(|_, status| {
+        rejected.set(status.map(str::to_string));
+        Ok(())
+    });
+    let mut options = PushOptions::new();
+    options.remote_callbacks(callbacks);
+    let branch = format!("refs/heads/{}", config.branch());
+    let pushed = repo
+        .find_remote("origin")
+        .and_then(|mut origin| origin.push(&[format!("{}:{}", branch, branch)], Some(&mut options)))
+        .map_err(|error| error.message().to_string());
+    if let Err(error) = pushed.and_then(|_| rejected.take().map_or(Ok(()), Err)) {
+        return Err(format!(
+            "Could not push: {}. Another machine may have pushed first, the next start merges its changes",
+            error
+        ));
+    }
+    fetch(&repo)?;
+    Ok(true)
+}
+
+fn file_name(store: &Path) -> Result<String, String> {
+    store
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .ok_or_else(|| format!("{} is not a file", store.display()))
+}
+
+/// Copies the store into the clone and commits it if it differs, noting the
+/// commit as synced
+fn commit(repo: &Repository, store: &Path, file_name: &str, branch: &str) -> Result<(), String> {
+    if !store.exists() {
+        return Ok(());
+    }
+    let dir = repo
+        .workdir()
+        .ok_or("The sync clone has no working directory")?;
+    fs::copy(store, dir.join(file_name)).map_err(|error| error.to_string())?;
+    let head = commit_in(repo, file_name, branch).map_err(|error| error.message().to_string())?;
+    // The store matches the clone now
+    fs::write(synced_path(repo), head.to_string()).map_err(|error| error.to_string())
+}
+
+/// Commits `file_name` as it is in the working directory on `branch` when
+/// it changed, returning the commit now at the head
+fn commit_in(repo: &Repository, file_name: &str, branch: &str) -> Result<Oid, git2::Error> {
+    let parent = commit_at(repo, "HEAD");
+    if parent.is_none() {
+        repo.set_head(&format!("refs/heads/{}", branch))?;
+    }
+    let mut index = repo.index()?;
+    index.add_all([file_name], IndexAddOption::DEFAULT, None)?;
+    index.write()?;
+    let tree = repo.find_tree(index.write_tree()?)?;
+    if let Some(parent) = &parent {
+        if parent.tree_id() == tree.id() {
+            return Ok(parent.id());
+        }
+    }
+    // Commits need an author, which a fresh machine may not have set up
+    let author = repo
+        .signature()
+        .or_else(|_| Signature::now("sniprrr", "sniprrr@localhost"))?;
+    let parents: Vec<&Commit> = parent.iter().collect();
+    repo.commit(
+        Some("HEAD"),
+        &author,
+        &author,
+        "Update snippets",
+        &tree,
+        &parents,
+    )
+}
+
+fn describe(merged: &Merged) -> String {
+    let plural = |count: usize| if count == 1 { "" } else { "s" };
+    let mut parts = vec![];
+    if merged.received > 0 {
+        parts.push(format!(
+            "{} snippet{} received",
+            merged.received,
+            plural(merged.received)
+        ));
+    }
+    if merged.removed > 0 {
+        parts.push(format!("{} deleted", merged.removed));
+    }
+    if !merged.conflicts.is_empty() {
+        parts.push(format!(
+            "changed on both sides, kept twice: {}",
+            merged.conflicts.join(", ")
+        ));
+    }
+    match parts.is_empty() {
+        true => "Nothing new from the sync remote".to_string(),
+        false => format!("Synced: {}", parts.join(", ")),
+    }
+}
@@ -0,0 +1,142 @@
+//! Syntax highlighting of snippet bodies for the description column and the
+//! preview pane, using syntect's built-in syntaxes and color schemes.
+//!
+//! A snippet is highlighted by its language, or by a `#!` first line when
+//! that isn't known. Bodies of unknown languages stay plain text. Highlighted
+//! lines are cached since the table is redrawn on every key press.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+use ratatui::style::{Color, Style};
+use ratatui::text::{Span, Spans};
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{Theme, ThemeSet};
+use syntect::parsing::{SyntaxReference, SyntaxSet};
+use syntect::util::LinesWithEndings;
+
+use crate::terminal;
+
+/// The color scheme used unless `[theme] syntax` names another
+pub const DEFAULT_SCHEME: &str = "base16-ocean.dark";
+/// Bodies highlighted before the cache is emptied
+const CACHE_LIMIT: usize = 1000;
+
+/// Languages snippets are given that syntect knows by another name
+const ALIASES: [(&str, &str); 8] = [
+    ("shell", "sh"),
+    ("zsh", "sh"),
+    ("console", "sh"),
+    ("golang", "go"),
+    ("javascript", "js"),
+    ("typescript", "js"),
+    ("postgres", "sql"),
+    ("yml", "yaml"),
+];
+
+/// A run of text in one color. `None` is the scheme's plain text color, which
+/// is left to the table style so the text stays readable on light terminals.
+pub type Token = (Option<Color>, String);
+/// Highlighted lines of a body, `None` when its language isn't known
+type Highlighted = Option<Vec<Vec<Token>>>;
+
+/// A color scheme from syntect's built-in set
+#[derive(Clone, Copy)]
+pub struct Scheme {
+    name: &'static str,
+    theme: &'static Theme,
+}
+
+fn syntaxes() -> &'static SyntaxSet {
+    static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+    SYNTAXES.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+fn themes() -> &'static ThemeSet {
+    static THEMES: OnceLock<ThemeSet> = OnceLock::new();
+    THEMES.get_or_init(ThemeSet::load_defaults)
+}
+
+/// The built-in scheme called `name`, such as `base16-ocean.dark` or `InspiredGitHub`
+pub fn scheme(name: &str) -> Option<Scheme> {
+    let (name, theme) = themes().themes.get_key_value(name)?;
+    Some(Scheme { name, theme })
+}
+
+/// Names of the built-in schemes, for error messages
+pub fn scheme_names() -> Vec<&'static str> {
+    themes().themes.keys().map(String::as_str).collect()
+}
+
+/// The syntax for a snippet in `language` with this `body`, falling back on
+/// its `#!` line when the language isn't one syntect knows
+fn syntax(language: Option<&str>, body: &str) -> Option<&'static SyntaxReference> {
+    let syntaxes = syntaxes();
+    let by_language = language.and_then(|language| {
+        let language = language.to_lowercase();
+        let token = ALIASES
+            .iter()
+            .find(|(alias, _)| *alias == language)
+            .map_or(language.as_str(), |(_, token)| token);
+        syntaxes.find_syntax_by_token(token)
+    });
+    by_language
+        .or_else(|| syntaxes.find_syntax_by_first_line(body.lines().next()?))
+        .filter(|syntax| syntax.name != "Plain Text")
+}
+
+/// The lines of `body` split into colored tokens, or `None` when its language
+/// isn't known
+pub fn highlight(scheme: Scheme, language: Option<&str>, body: &str) -> Highlighted {
+    type Key = (&'static str, Option<String>, String);
+    static CACHE: OnceLock<Mutex<HashMap<Key, Highlighted>>> = OnceLock::new();
+
+    let key = (scheme.name, language.map(str::to_string), body.to_string());
+    let mut cache = CACHE.get_or_init(Default::default).lock().ok()?;
+    if let Some(lines) = cache.get(&key) {
+        return lines.clone();
+    }
+    let lines = syntax(language, body).and_then(|syntax| tokens(scheme, syntax, body));
+    if cache.len() >= CACHE_LIMIT {
+        cache.clear();
+    }
+    cache.insert(key, lines.clone());
+    lines
+}
+
+fn tokens(scheme: Scheme, syntax: &SyntaxReference, body: &str) -> Highlighted {
+    let plain = scheme.theme.settings.foreground;
+    let mut highlighter = HighlightLines::new(syntax, scheme.theme);
+    let mut lines = vec![];
+    for line in LinesWithEndings::from(body) {
+        let regions = highlighter.highlight_line(line, syntaxes()).ok()?;
+        let tokens = regions
+            .into_iter()
+            .map(|(style, text)| {
+                let color = Some(style.foreground)
+                    .filter(|color| Some(*color) != plain)
+                    .map(|color| terminal::color(Color::Rgb(color.r, color.g, color.b)));
+                (color, text.trim_end_matches(['\n', '\r']).to_string())
+            })
+            .filter(|(_, text)| !text.is_empty())
+            .collect();
+        lines.push(tokens);
+    }
+    if body.is_empty() || body.ends_with('\n') {
+        lines.push(vec![]);
+    }
+    Some(lines)
+}
+
+/// `tokens` as a line of text drawn on top of `base`
+pub fn spans(tokens: Vec<Token>, base: Style) -> Spans<'static> {
+    Spans::from(
+        tokens
+            .into_iter()
+            .map(|(color, text)| match color {
+                Some(color) => Span::styled(text, base.fg(color)),
+                None => Span::styled(text, base),
+            })
+            .collect::<Vec<Span>>(),
+    )
+}
@@ -0,0 +1,305 @@
+//! Rows of the snippet table, built from borrowed app state so they can be
+//! benchmarked on their own
+
+use std::collections::{BTreeSet, HashMap};
+
+use ratatui::style::Style;
+use ratatui::text::{Span, Spans, Text};
+use ratatui::widgets::{Cell, Row};
+use unicode_width::UnicodeWidthChar;
+
+use crate::binary;
+use crate::models::Snippet;
+use crate::namespace;
+use crate::row_template::RowTemplate;
+use crate::search;
+use crate::syntax::{self, Token};
+use crate::terminal;
+use crate::theme::{label_color, Theme};
+
+/// What the table rows are built from
+pub struct TableView<'a> {
+    pub snippets: &'a [Snippet],
+    /// Rows marked with Space for multi-row actions
+    pub marked: &'a BTreeSet<usize>,
+    pub filter: &'a str,
+    /// The tag the listed snippets were narrowed down to, highlighted in the tags column
+    pub tag_filter: Option<&'a str>,
+    pub single_line: bool,
+    /// Columns of the description scrolled out of view on the left
+    pub description_offset: usize,
+    /// The collapsed namespace each listed row stands for, by snippet index
+    pub collapsed: &'a HashMap<usize, String>,
+    /// Shows after each title how often the snippet was copied
+    pub uses: bool,
+    /// Lays each row out from this template in a single column after the label
+    pub template: Option<&'a RowTemplate>,
+    pub theme: &'a Theme,
+}
+
+impl<'a> TableView<'a> {
+    /// One row for each of the snippets at `visible`
+    pub fn rows(&self, visible: &[usize], now: u64) -> Vec<Row<'a>> {
+        visible
+            .iter()
+            .map(|&index| {
+                let snippet = &self.snippets[index];
+                let collapsed = self.collapsed.get(&index).map(String::as_str);
+                let height = if snippet.binary || self.single_line || collapsed.is_some() {
+                    1
+                } else {
+                    snippet.description.chars().filter(|c| *c == '\n').count() + 1
+                };
+
+                let scheduled = if snippet.is_scheduled_now(now) {
+                    terminal::glyph("⏰ ", "@ ")
+                } else {
+                    ""
+                };
+                let review = if snippet.needs_review {
+                    terminal::glyph("⚑ ", "! ")
+                } else {
+                    ""
+                };
+                let temporary = if snippet.temporary {
+                    terminal::glyph("◌ ", "~ ")
+                } else {
+                    ""
+                };
+                let marker = if self.marked.contains(&index) {
+                    terminal::glyph("● ", "* ")
+                } else {
+                    ""
+                };
+                let mut title = match collapsed {
+                    Some(namespace) => {
+                        let count = self
+                            .snippets
+                            .iter()
+                            .filter(|s| namespace::contains(namespace, &s.title))
+                            .count();
+                        Text::from(Spans::from(vec![
+                            Span::styled(
+                                format!("{}{}/", terminal::glyph("▸ ", "> "), namespace),
+                                self.theme.namespace,
+                            ),
+                            Span::raw(format!(" ({} snippets)", count)),
+                        ]))
+                    }
+                    None if self.template.is_some() => search::highlight(
+                        &self.template.map(|t| t.render(snippet)).unwrap_or_default(),
+                        self.filter,
+                        self.theme.highlight,
+                    ),
+                    None if self.filter.is_empty() => {
+                        Text::from(namespace::breadcrumb(&snippet.title, self.theme.namespace))
+                    }
+                    None => search::highlight(&snippet.title, self.filter, self.theme.highlight),
+                };
+                if let Some(line) = title.lines.first_mut() {
+                    line.0.insert(
+                        0,
+                        Span::raw(format!("{}{}{}{}", marker, review, temporary, scheduled)),
+                    );
+                    if self.uses && collapsed.is_none() {
+                        line.0.push(Span::styled(
+                            format!("  {}{}", snippet.copy_count, terminal::glyph("×", "x")),
+                            self.theme.namespace,
+                        ));
+                    }
+                    if let (Some(source), None) = (&snippet.source, collapsed) {
+                        line.0.push(Span::styled(
+                            format!("  [{}]", source),
+                            self.theme.namespace,
+                        ));
+                    }
+                }
+                let title_cell = if self.marked.contains(&index) {
+                    Cell::from(title).style(self.theme.marked)
+                } else {
+                    Cell::from(title)
+                };
+
+                let label = match (&snippet.icon, snippet.color) {
+                    (Some(icon), _) => icon.as_str(),
+                    (None, Some(_)) => terminal::glyph("■", "#"),
+                    (None, None) => "",
+                };
+                let label_cell = match snippet.color {
+                    Some(color) => Cell::from(label).style(self.theme.text.fg(label_color(color))),
+                    None => Cell::from(label),
+                };
+
+                if self.template.is_some() {
+                    return Row::new(vec![label_cell, title_cell])
+                        .style(self.theme.text)
+                        .bottom_margin(self.theme.row_spacing);
+                }
+
+                let description_cell = if collapsed.is_some() {
+                    Cell::from("")
+                } else if snippet.binary {
+                    Cell::from(binary::summary(&snippet.description))
+                } else if let Some(lines) = self.highlighted(snippet) {
+                    Cell::from(highlighted_view(
+                        lines,
+                        self.single_line,
+                        self.description_offset,
+                        self.theme.text,
+                    ))
+                } else {
+                    let shown = shift_lines(
+                        &description_view(&snippet.description, self.single_line),
+                        self.description_offset,
+                    );
+                    Cell::from(search::highlight(&shown, self.filter, self.theme.highlight))
+                };
+
+                let tags: Vec<Span> = snippet
+                    .tags
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(position, tag)| {
+                        let style = match self.tag_filter == Some(tag.as_str()) {
+                            true => self.theme.highlight,
+                            false => self.theme.text,
+                        };
+                        let separator = (position > 0).then(|| Span::raw(", "));
+                        separator
+                            .into_iter()
+                            .chain(std::iter::once(Span::styled(tag.clone(), style)))
+                    })
+                    .collect();
+                let tags_cell = match collapsed {
+                    Some(_) => Cell::from(""),
+                    None => Cell::from(Spans::from(tags)),
+                };
+
+                Row::new(vec![label_cell, title_cell, tags_cell, description_cell])
+                    .style(self.theme.text)
+                    .height(height as u16)
+                    .bottom_margin(self.theme.row_spacing)
+            })
+            .collect()
+    }
+
+    /// The description of `snippet` in syntax colors, unless it is searched
+    /// for, where the matches are highlighted instead
+    fn highlighted(&self, snippet: &Snippet) -> Option<Vec<Vec<Token>>> {
+        if !self.filter.is_empty() {
+            return None;
+        }
+        let mut lines = syntax::highlight(
+            self.theme.syntax?,
+            snippet.language.as_deref(),
+            &snippet.description,
+        )?;
+        // Like `description_view`, which doesn't mark the final newline
+        if self.single_line && snippet.description.ends_with('\n') {
+            lines.pop();
+        }
+        Some(lines)
+    }
+}
+
+/// Highlighted lines as shown in the table, scrolled `columns` to the left and
+/// joined onto one line in single line view
+pub fn highlighted_view(
+    lines: Vec<Vec<Token>>,
+    single_line: bool,
+    columns: usize,
+    base: Style,
+) -> Text<'static> {
+    let lines = lines.into_iter().map(|line| shift_tokens(line, columns));
+    if single_line {
+        let mut joined: Vec<Token> = vec![];
+        for (position, line) in lines.enumerate() {
+            if position > 0 {
+                joined.push((None, terminal::glyph(" ⏎ ", " | ").to_string()));
+            }
+            joined.extend(line);
+        }
+        Text::from(syntax::spans(joined, base))
+    } else {
+        Text::from(
+            lines
+                .map(|line| syntax::spans(line, base))
+                .collect::<Vec<Spans>>(),
+        )
+    }
+}
+
+/// Drops the first `columns` display columns of a highlighted line, the way
+/// `shift_lines` does for plain text
+fn shift_tokens(line: Vec<Token>, columns: usize) -> Vec<Token> {
+    let mut skipped = 0;
+    line.into_iter()
+        .filter_map(|(color, text)| {
+            if skipped >= columns {
+                return Some((color, text));
+            }
+            let start = text
+                .char_indices()
+                .find(|(_, c)| {
+                    if skipped >= columns {
+                        return true;
+                    }
+                    skipped += c.width().unwrap_or_default();
+                    false
+                })
+                .map_or(text.len(), |(start, _)| start);
+            (start < text.len()).then(|| (color, text[start..].to_string()))
+        })
+        .collect()
+}
+
+/// The whole body of `snippet` for the preview pane, in syntax colors when
+/// its language is known
+pub fn preview_text(snippet: &Snippet, theme: &Theme) -> Text<'static> {
+    if snippet.binary {
+        return Text::raw(binary::summary(&snippet.description));
+    }
+    let highlighted = theme.syntax.and_then(|scheme| {
+        syntax::highlight(scheme, snippet.language.as_deref(), &snippet.description)
+    });
+    match highlighted {
+        Some(lines) => highlighted_view(lines, false, 0, theme.text),
+        None => Text::raw(snippet.description.clone()),
+    }
+}
+
+/// The description as shown in the table, joined onto one line in single line view
+pub fn description_view(description: &str, single_line: bool) -> String {
+    if single_line {
+        description
+            .lines()
+            .collect::<Vec<&str>>()
+            .join(terminal::glyph(" ⏎ ", " | "))
+    } else {
+        description.to_string()
+    }
+}
+
+/// Drops the first `columns` display columns of every line
+pub fn shift_lines(text: &str, columns: usize) -> String {
+    if columns == 0 {
+        return text.to_string();
+    }
+    text.split('\n')
+        .map(|line| {
+            let mut skipped = 0;
+            let start = line
+                .char_indices()
+                .find(|(_, c)| {
+                    if skipped >= columns {
+                        return true;
+                    }
+                    skipped += c.width().unwrap_or_default();
+                    false
+                })
+                .map_or(line.len(), |(start, _)| start);
+            &line[start..]
+        })
+        .collect::<Vec<&str>>()
+        .join("\n")
+}
@@ -0,0 +1,111 @@
+//! Tags suggested for a new snippet from what its body contains: the tools
+//! its commands call, SQL, links and the language it is written in
+
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+use crate::models::Snippet;
+
+/// Commands at the start of a line and the tag they suggest
+const COMMANDS: [(&str, &str); 16] = [
+    ("docker", "docker"),
+    ("docker-compose", "docker"),
+    ("podman", "docker"),
+    ("kubectl", "kubernetes"),
+    ("helm", "kubernetes"),
+    ("k9s", "kubernetes"),
+    ("git", "git"),
+    ("gh", "git"),
+    ("ssh", "ssh"),
+    ("scp", "ssh"),
+    ("curl", "http"),
+    ("wget", "http"),
+    ("aws", "aws"),
+    ("terraform", "terraform"),
+    ("psql", "sql"),
+    ("mysql", "sql"),
+];
+
+/// Interpreters named by a `#!` line and the language tag they suggest
+const SHEBANGS: [(&str, &str); 6] = [
+    ("bash", "bash"),
+    ("sh", "shell"),
+    ("zsh", "zsh"),
+    ("python", "python"),
+    ("node", "js"),
+    ("ruby", "ruby"),
+];
+
+/// Statements that make a body SQL, starting a line and in upper case the
+/// way queries are usually written, so prose saying "select" doesn't count
+const SQL_PATTERN: &str =
+    r"(?m)^\s*(?:SELECT|INSERT INTO|UPDATE|DELETE FROM|CREATE (?:TABLE|INDEX|VIEW)|ALTER TABLE)\b";
+const URL_PATTERN: &str = r"\bhttps?://[^\s]+";
+
+/// Tags `snippet` doesn't have yet that its contents suggest, in a stable order
+pub fn suggest(snippet: &Snippet) -> Vec<String> {
+    static SQL: OnceLock<Regex> = OnceLock::new();
+    static URL: OnceLock<Regex> = OnceLock::new();
+
+    let body = &snippet.description;
+    let mut found: Vec<&str> = vec![];
+    if let Some(language) = &snippet.language {
+        found.push(language);
+    }
+    if let Some(interpreter) = shebang(body) {
+        found.extend(
+            SHEBANGS
+                .iter()
+                .filter(|(name, _)| interpreter.starts_with(name))
+                .map(|(_, tag)| *tag)
+                .take(1),
+        );
+    }
+    for line in body.lines() {
+        let command = line
+            .split_whitespace()
+            .find(|word| !matches!(*word, "$" | "sudo"));
+        if let Some(command) = command {
+            found.extend(
+                COMMANDS
+                    .iter()
+                    .filter(|(name, _)| *name == command)
+                    .map(|(_, tag)| *tag),
+            );
+        }
+    }
+    if SQL
+        .get_or_init(|| Regex::new(SQL_PATTERN).expect("valid pattern"))
+        .is_match(body)
+    {
+        found.push("sql");
+    }
+    if URL
+        .get_or_init(|| Regex::new(URL_PATTERN).expect("valid pattern"))
+        .is_match(body)
+    {
+        found.push("link");
+    }
+
+    let mut tags: Vec<String> = vec![];
+    for tag in found {
+        let tag = tag.to_lowercase();
+        let known = |existing: &String| existing.eq_ignore_ascii_case(&tag);
+        if !snippet.tags.iter().any(known) && !tags.iter().any(known) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+/// The interpreter of a `#!/usr/bin/env python3` or `#!/bin/bash` first line
+fn shebang(body: &str) -> Option<&str> {
+    let line = body.lines().next()?.strip_prefix("#!")?;
+    let mut words = line.split_whitespace();
+    let program = words.next()?.rsplit('/').next()?;
+    match program {
+        "env" => words.find(|word| !word.starts_with('-')),
+        program => Some(program),
+    }
+}
@@ -0,0 +1,321 @@
+//! `{{name}}` placeholders inside snippet descriptions.
+//!
+//! A placeholder can declare a type after its name: `{{port:int}}`,
+//! `{{ratio:float}}` or `{{env:enum:dev,staging,prod}}`. Untyped placeholders
+//! accept any text.
+//!
+//! Two kinds of tags are expanded automatically instead of being asked for:
+//! `{{env:NAME}}` inserts an environment variable and `{{cmd:command}}` inserts
+//! the output of a shell command. Commands only run once they have been
+//! allowed, see `commands_fingerprint`.
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::process::Command;
+
+use crate::models::Snippet;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldType {
+    Text,
+    Int,
+    Float,
+    Enum(Vec<String>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Placeholder {
+    pub name: String,
+    pub field_type: FieldType,
+}
+
+impl FieldType {
+    fn parse(spec: &str) -> Option<FieldType> {
+        let (kind, args) = match spec.split_once(':') {
+            Some((kind, args)) => (kind.trim(), Some(args)),
+            None => (spec.trim(), None),
+        };
+
+        match (kind, args) {
+            ("text", None) => Some(FieldType::Text),
+            ("int", None) => Some(FieldType::Int),
+            ("float", None) => Some(FieldType::Float),
+            ("enum", Some(options)) => Some(FieldType::Enum(
+                options
+                    .split(',')
+                    .map(|option| option.trim().to_string())
+                    .filter(|option| !option.is_empty())
+                    .collect(),
+            )),
+            _ => None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            FieldType::Text => "text",
+            FieldType::Int => "int",
+            FieldType::Float => "float",
+            FieldType::Enum(_) => "choice",
+        }
+    }
+
+    /// Checks that `value` is acceptable for this type
+    pub fn validate(&self, value: &str) -> Result<(), String> {
+        match self {
+            FieldType::Text => Ok(()),
+            FieldType::Int => value
+                .trim()
+                .parse::<i64>()
+                .map(|_| ())
+                .map_err(|_| format!("'{}' is not a whole number", value)),
+            FieldType::Float => value
+                .trim()
+                .parse::<f64>()
+                .map(|_| ())
+                .map_err(|_| format!("'{}' is not a number", value)),
+            FieldType::Enum(options) => {
+                if options.iter().any(|option| option == value) {
+                    Ok(())
+                } else {
+                    Err(format!(
+                        "'{}' must be one of: {}",
+                        value,
+                        options.join(", ")
+                    ))
+                }
+            }
+        }
+    }
+}
+
+/// Iterates over the `{{...}}` tags in `text` as (byte range, inner content)
+fn tags(text: &str) -> impl Iterator<Item = (std::ops::Range<usize>, &str)> {
+    let mut offset = 0;
+    std::iter::from_fn(move || {
+        let start = offset + text[offset..].find("{{")?;
+        let end = start + 2 + text[start + 2..].find("}}")?;
+        offset = end + 2;
+        Some((start..end + 2, &text[start + 2..end]))
+    })
+}
+
+fn parse_placeholder(inner: &str) -> Option<Placeholder> {
+    let inner = inner.trim();
+    let (name, spec) = match inner.split_once(':') {
+        Some((name, spec)) => (name.trim(), Some(spec)),
+        None => (inner, None),
+    };
+
+    if name.is_empty() {
+        return None;
+    }
+
+    let field_type = match spec {
+        Some(spec) => FieldType::parse(spec)?,
+        None => FieldType::Text,
+    };
+
+    Some(Placeholder {
+        name: name.to_string(),
+        field_type,
+    })
+}
+
+/// A tag that is filled in automatically
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expansion {
+    Env(String),
+    Cmd(String),
+}
+
+fn parse_expansion(inner: &str) -> Option<Expansion> {
+    let (kind, argument) = inner.trim().split_once(':')?;
+    let argument = argument.trim();
+    if argument.is_empty() {
+        return None;
+    }
+    match kind.trim() {
+        "env" => Some(Expansion::Env(argument.to_string())),
+        "cmd" => Some(Expansion::Cmd(argument.to_string())),
+        _ => None,
+    }
+}
+
+impl Expansion {
+    /// Resolves the expansion to its text. Failures are rendered inline so they show up in the preview.
+    pub fn evaluate(&self) -> String {
+        match self {
+            Expansion::Env(name) => std::env::var(name).unwrap_or_default(),
+            Expansion::Cmd(command) => {
+                let output = if cfg!(windows) {
+                    Command::new("cmd").args(["/C", command]).output()
+                } else {
+                    Command::new("sh").args(["-c", command]).output()
+                };
+
+                match output {
+                    Ok(output) => String::from_utf8_lossy(&output.stdout)
+                        .trim_end_matches(['\n', '\r'])
+                        .to_string(),
+                    Err(error) => format!("<{}: {}>", command, error),
+                }
+            }
+        }
+    }
+}
+
+/// Whether `text` contains any env/cmd expansions
+pub fn has_expansions(text: &str) -> bool {
+    tags(text)
+        .any(|(_, inner)| parse_placeholder(inner).is_none() && parse_expansion(inner).is_some())
+}
+
+/// The commands of the `{{cmd:...}}` expansions in `text`, in order
+pub fn commands(text: &str) -> Vec<String> {
+    tags(text)
+        .filter(|(_, inner)| parse_placeholder(inner).is_none())
+        .filter_map(|(_, inner)| match parse_expansion(inner)? {
+            Expansion::Cmd(command) => Some(command),
+            Expansion::Env(_) => None,
+        })
+        .collect()
+}
+
+/// Identifies the exact commands run by `text` (FNV-1a over all of them), so
+/// allowing them for good stops applying as soon as one is edited. `None`
+/// when nothing would run.
+pub fn commands_fingerprint(text: &str) -> Option<String> {
+    let commands = commands(text);
+    if commands.is_empty() {
+        return None;
+    }
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in commands.join("\0").bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    Some(format!("{:016x}", hash))
+}
+
+/// Part of a rendered template. `substituted` is set for text that replaced a tag.
+pub struct Segment {
+    pub text: String,
+    pub substituted: bool,
+}
+
+/// Renders `text` with placeholder values and expansions, keeping track of
+/// which parts were substituted so they can be highlighted
+pub fn render_segments(text: &str, values: &HashMap<String, String>) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    let mut last = 0;
+    for (range, inner) in tags(text) {
+        let value = match parse_placeholder(inner) {
+            Some(placeholder) => values.get(&placeholder.name).cloned(),
+            None => parse_expansion(inner).map(|expansion| expansion.evaluate()),
+        };
+
+        if let Some(value) = value {
+            segments.push(Segment {
+                text: text[last..range.start].to_string(),
+                substituted: false,
+            });
+            segments.push(Segment {
+                text: value,
+                substituted: true,
+            });
+            last = range.end;
+        }
+    }
+    segments.push(Segment {
+        text: text[last..].to_string(),
+        substituted: false,
+    });
+    segments.retain(|segment| !segment.text.is_empty());
+    segments
+}
+
+/// Asks for the value of each placeholder of `snippet` on `input`, with the
+/// prompts written to `output`, for copying templates from the command line.
+/// An empty answer keeps the value used last time, or the declared default.
+pub fn ask(
+    snippet: &Snippet,
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+) -> io::Result<HashMap<String, String>> {
+    let mut values = HashMap::new();
+    for field in placeholders(&snippet.description) {
+        let default = snippet
+            .parameter_history
+            .get(&field.name)
+            .and_then(|history| history.first())
+            .or_else(|| snippet.field_defaults.get(&field.name))
+            .cloned();
+        let choices = match &field.field_type {
+            FieldType::Enum(options) => format!(", one of {}", options.join(", ")),
+            _ => String::new(),
+        };
+        loop {
+            match &default {
+                Some(default) => write!(
+                    output,
+                    "{} ({}{}) [{}]: ",
+                    field.name,
+                    field.field_type.label(),
+                    choices,
+                    default
+                )?,
+                None => write!(
+                    output,
+                    "{} ({}{}): ",
+                    field.name,
+                    field.field_type.label(),
+                    choices
+                )?,
+            }
+            output.flush()?;
+
+            let mut line = String::new();
+            if input.read_line(&mut line)? == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    format!("No value given for {}", field.name),
+                ));
+            }
+            let line = line.trim_end_matches(['\n', '\r']);
+            let value = match (line.is_empty(), &default) {
+                (true, Some(default)) => default.clone(),
+                _ => line.to_string(),
+            };
+            match field.field_type.validate(&value) {
+                Ok(()) => {
+                    values.insert(field.name.clone(), value);
+                    break;
+                }
+                Err(error) => writeln!(output, "{}", error)?,
+            }
+        }
+    }
+    Ok(values)
+}
+
+/// All distinct placeholders in `text`, in order of first appearance
+pub fn placeholders(text: &str) -> Vec<Placeholder> {
+    let mut found: Vec<Placeholder> = Vec::new();
+    for (_, inner) in tags(text) {
+        if let Some(placeholder) = parse_placeholder(inner) {
+            if !found.iter().any(|p| p.name == placeholder.name) {
+                found.push(placeholder);
+            }
+        }
+    }
+    found
+}
+
+/// Tags that are neither a valid placeholder nor an expansion, e.g. `{{port:integer}}`
+pub fn invalid_tags(text: &str) -> Vec<String> {
+    tags(text)
+        .filter(|(_, inner)| parse_placeholder(inner).is_none() && parse_expansion(inner).is_none())
+        .map(|(range, _)| text[range].to_string())
+        .collect()
+}
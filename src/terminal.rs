@@ -0,0 +1,343 @@
+//! What the terminal sniprrr runs in can do, guessed from the environment at
+//! start-up and overridden under `[terminal]` in the config: how many colors
+//! it shows, whether it draws Unicode symbols, takes mouse input and sets the
+//! clipboard through OSC 52. The theme, the symbols in the table and copies
+//! to the clipboard adapt to it; `sniprrr doctor --terminal` shows the guesses.
+
+use std::io::{self, IsTerminal, Write};
+use std::sync::OnceLock;
+
+use ratatui::style::Color;
+use serde::Deserialize;
+
+use crate::base64;
+
+/// Colors the terminal can show
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum ColorDepth {
+    #[serde(rename = "truecolor")]
+    TrueColor,
+    #[serde(rename = "256")]
+    Indexed,
+    #[serde(rename = "16")]
+    Basic,
+}
+
+impl ColorDepth {
+    fn label(self) -> &'static str {
+        match self {
+            ColorDepth::TrueColor => "true color",
+            ColorDepth::Indexed => "256 colors",
+            ColorDepth::Basic => "16 colors",
+        }
+    }
+}
+
+/// Settings under `[terminal]`, each one guessed when left out
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct TerminalConfig {
+    /// `truecolor`, `256` or `16`
+    pub colors: Option<ColorDepth>,
+    /// Symbols such as `›` and `⏎`, ASCII stand-ins otherwise
+    pub unicode: Option<bool>,
+    pub mouse: Option<bool>,
+    /// Copies to the clipboard go through the terminal when there is no
+    /// clipboard to reach directly, like over SSH
+    pub osc52: Option<bool>,
+}
+
+/// A capability and what it was decided from
+#[derive(Debug, Clone)]
+pub struct Detected<T> {
+    pub value: T,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Capabilities {
+    pub colors: Detected<ColorDepth>,
+    pub unicode: Detected<bool>,
+    pub mouse: Detected<bool>,
+    pub osc52: Detected<bool>,
+    /// A clipboard sniprrr can set itself, which needs a display on Linux
+    pub system_clipboard: Detected<bool>,
+}
+
+static CAPABILITIES: OnceLock<Capabilities> = OnceLock::new();
+
+/// Keeps the capabilities for the rest of the process. Set once the config is
+/// read; before that everything is taken to be supported.
+pub fn configure(capabilities: Capabilities) {
+    let _ = CAPABILITIES.set(capabilities);
+}
+
+fn capabilities() -> Option<&'static Capabilities> {
+    CAPABILITIES.get()
+}
+
+/// Terminals without xterm mouse reporting or with few colors and glyphs
+const BASIC_TERMS: [&str; 6] = ["linux", "dumb", "vt100", "vt220", "ansi", "cons25"];
+
+/// `TERM` values, by part, of terminals showing true color and setting the
+/// clipboard through OSC 52
+const MODERN_TERMS: [&str; 6] = [
+    "kitty",
+    "alacritty",
+    "foot",
+    "wezterm",
+    "ghostty",
+    "contour",
+];
+
+/// `TERM_PROGRAM` values of the same
+const MODERN_PROGRAMS: [&str; 4] = ["iTerm.app", "WezTerm", "vscode", "ghostty"];
+
+fn var(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+fn detected<T>(value: T, reason: impl Into<String>) -> Detected<T> {
+    Detected {
+        value,
+        reason: reason.into(),
+    }
+}
+
+fn configured<T>(value: Option<T>, guess: impl FnOnce() -> Detected<T>) -> Detected<T> {
+    match value {
+        Some(value) => detected(value, "set in [terminal] of the config"),
+        None => guess(),
+    }
+}
+
+/// The capabilities of the terminal sniprrr runs in
+pub fn detect(config: &TerminalConfig) -> Capabilities {
+    let term = var("TERM").unwrap_or_default();
+    let program = var("TERM_PROGRAM");
+    let basic = BASIC_TERMS.contains(&term.as_str());
+    let modern = MODERN_TERMS.iter().any(|name| term.contains(name))
+        || program
+            .as_ref()
+            .is_some_and(|program| MODERN_PROGRAMS.contains(&program.as_str()));
+    let windows_terminal = var("WT_SESSION").is_some();
+
+    let colors = configured(config.colors, || {
+        match var("COLORTERM") {
+            Some(colorterm) if colorterm == "truecolor" || colorterm == "24bit" => {
+                return detected(ColorDepth::TrueColor, format!("COLORTERM={}", colorterm))
+            }
+            _ => {}
+        }
+        if modern || windows_terminal {
+            detected(ColorDepth::TrueColor, terminal_name(&term, &program))
+        } else if term.contains("256") {
+            detected(ColorDepth::Indexed, format!("TERM={}", term))
+        } else if basic || term.is_empty() {
+            detected(ColorDepth::Basic, terminal_name(&term, &program))
+        } else {
+            detected(ColorDepth::Indexed, format!("TERM={}, most show 256", term))
+        }
+    });
+
+    let unicode = configured(config.unicode, || {
+        if term == "linux" {
+            return detected(false, "the Linux console font lacks most symbols");
+        }
+        let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+            .iter()
+            .find_map(|name| Some((name, var(name)?)));
+        match locale {
+            Some((name, locale)) => {
+                let lower = locale.to_lowercase();
+                let utf8 = lower.contains("utf-8") || lower.contains("utf8");
+                detected(utf8, format!("{}={}", name, locale))
+            }
+            None => detected(true, "no locale set, taken to be UTF-8"),
+        }
+    });
+
+    let mouse = configured(config.mouse, || match basic || term.is_empty() {
+        true => detected(false, terminal_name(&term, &program)),
+        false => detected(true, terminal_name(&term, &program)),
+    });
+
+    let osc52 = configured(config.osc52, || {
+        if var("TMUX").is_some() {
+            detected(false, "tmux passes it on only with set-clipboard on")
+        } else if modern || windows_terminal {
+            detected(true, terminal_name(&term, &program))
+        } else {
+            detected(
+                false,
+                format!(
+                    "{} is not known to support it",
+                    terminal_name(&term, &program)
+                ),
+            )
+        }
+    });
+
+    let system_clipboard = if cfg!(any(target_os = "macos", target_os = "windows")) {
+        detected(true, std::env::consts::OS)
+    } else if let Some(display) = var("WAYLAND_DISPLAY") {
+        detected(true, format!("WAYLAND_DISPLAY={}", display))
+    } else if let Some(display) = var("DISPLAY") {
+        detected(true, format!("DISPLAY={}", display))
+    } else {
+        detected(false, "neither DISPLAY nor WAYLAND_DISPLAY is set")
+    };
+
+    Capabilities {
+        colors,
+        unicode,
+        mouse,
+        osc52,
+        system_clipboard,
+    }
+}
+
+fn terminal_name(term: &str, program: &Option<String>) -> String {
+    match (program, term) {
+        (Some(program), _) => format!("TERM_PROGRAM={}", program),
+        (None, "") => "TERM is not set".to_string(),
+        (None, term) => format!("TERM={}", term),
+    }
+}
+
+/// `color` as close as the terminal shows it
+pub fn color(color: Color) -> Color {
+    let depth = capabilities().map_or(ColorDepth::TrueColor, |c| c.colors.value);
+    match (color, depth) {
+        (Color::Rgb(r, g, b), ColorDepth::Indexed) => Color::Indexed(indexed(r, g, b)),
+        (Color::Rgb(r, g, b), ColorDepth::Basic) => basic(r, g, b),
+        (color, _) => color,
+    }
+}
+
+/// The nearest color of the 6×6×6 cube or the gray ramp of 256 color terminals
+fn indexed(r: u8, g: u8, b: u8) -> u8 {
+    let level = |value: u8| ((value as u16 * 5 + 127) / 255) as u8;
+    let cube = 16 + 36 * level(r) + 6 * level(g) + level(b);
+    if r == g && g == b {
+        // The ramp runs from 8 to 238 in steps of 10
+        return match r {
+            0..=3 => 16,
+            248..=255 => 231,
+            gray => 232 + ((gray as u16 - 3) / 10).min(23) as u8,
+        };
+    }
+    cube
+}
+
+/// The nearest of the 16 ANSI colors, by their usual xterm values
+fn basic(r: u8, g: u8, b: u8) -> Color {
+    const PALETTE: [(Color, (u8, u8, u8)); 16] = [
+        (Color::Black, (0, 0, 0)),
+        (Color::Red, (205, 0, 0)),
+        (Color::Green, (0, 205, 0)),
+        (Color::Yellow, (205, 205, 0)),
+        (Color::Blue, (0, 0, 238)),
+        (Color::Magenta, (205, 0, 205)),
+        (Color::Cyan, (0, 205, 205)),
+        (Color::Gray, (229, 229, 229)),
+        (Color::DarkGray, (127, 127, 127)),
+        (Color::LightRed, (255, 0, 0)),
+        (Color::LightGreen, (0, 255, 0)),
+        (Color::LightYellow, (255, 255, 0)),
+        (Color::LightBlue, (92, 92, 255)),
+        (Color::LightMagenta, (255, 0, 255)),
+        (Color::LightCyan, (0, 255, 255)),
+        (Color::White, (255, 255, 255)),
+    ];
+    let distance = |(pr, pg, pb): (u8, u8, u8)| {
+        let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+        d(r, pr) + d(g, pg) + d(b, pb)
+    };
+    PALETTE
+        .iter()
+        .min_by_key(|(_, rgb)| distance(*rgb))
+        .map_or(Color::Reset, |(color, _)| *color)
+}
+
+/// `unicode`, or `ascii` in terminals that don't draw it
+pub fn glyph(unicode: &'static str, ascii: &'static str) -> &'static str {
+    match capabilities().is_none_or(|c| c.unicode.value) {
+        true => unicode,
+        false => ascii,
+    }
+}
+
+pub fn mouse() -> bool {
+    capabilities().is_none_or(|c| c.mouse.value)
+}
+
+/// Whether copies to the clipboard go through the terminal: there is no
+/// clipboard to set directly and the terminal takes OSC 52
+pub fn copies_through_terminal() -> bool {
+    capabilities().is_some_and(|c| !c.system_clipboard.value && c.osc52.value)
+}
+
+/// Sets the clipboard of the terminal to `text` with OSC 52
+pub fn copy_osc52(text: &str) -> Result<(), String> {
+    let mut stdout = io::stdout();
+    if !stdout.is_terminal() {
+        return Err("no terminal to copy through, the output is redirected".to_string());
+    }
+    write!(stdout, "\x1b]52;c;{}\x07", base64::encode(text.as_bytes()))
+        .and_then(|_| stdout.flush())
+        .map_err(|error| error.to_string())
+}
+
+/// What `sniprrr doctor --terminal` prints
+pub fn report(capabilities: &Capabilities) -> String {
+    let yes_no = |value: bool| if value { "yes" } else { "no" };
+    let c = capabilities;
+    let mut lines = vec![
+        format!("Colors: {} ({})", c.colors.value.label(), c.colors.reason),
+        format!(
+            "Unicode symbols: {} ({})",
+            yes_no(c.unicode.value),
+            c.unicode.reason
+        ),
+        format!("Mouse: {} ({})", yes_no(c.mouse.value), c.mouse.reason),
+        format!(
+            "System clipboard: {} ({})",
+            yes_no(c.system_clipboard.value),
+            c.system_clipboard.reason
+        ),
+        format!(
+            "OSC 52 clipboard: {} ({})",
+            yes_no(c.osc52.value),
+            c.osc52.reason
+        ),
+        String::new(),
+    ];
+    if c.colors.value != ColorDepth::TrueColor {
+        lines.push(format!(
+            "- Theme and syntax colors are mapped to the nearest of {}",
+            c.colors.value.label()
+        ));
+    }
+    if !c.unicode.value {
+        lines.push("- The table shows ASCII stand-ins for its symbols".to_string());
+    }
+    if !c.mouse.value {
+        lines.push("- Mouse input is left to the terminal".to_string());
+    }
+    match (c.system_clipboard.value, c.osc52.value) {
+        (true, _) => {}
+        (false, true) => {
+            lines.push("- Copies to the clipboard go through the terminal".to_string())
+        }
+        (false, false) => lines.push(
+            "- Copies to the clipboard fail, set up a tmux or command copy target instead"
+                .to_string(),
+        ),
+    }
+    if lines.last().is_some_and(String::is_empty) {
+        lines.push("Nothing to adapt to.".to_string());
+    }
+    lines.push(String::new());
+    lines.join("\n")
+}
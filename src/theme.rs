@@ -0,0 +1,120 @@
+use ratatui::style::{Color, Modifier, Style};
+use serde::Deserialize;
+
+use crate::models::LabelColor;
+use crate::syntax::{self, Scheme};
+use crate::terminal;
+
+/// How much room rows and indicators take up in the table
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Density {
+    #[default]
+    Normal,
+    /// Low-vision mode: double row spacing, a bigger selection marker and
+    /// only high-contrast bold styles
+    Large,
+}
+
+/// The `[theme]` section of the config file
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct ThemeConfig {
+    pub density: Density,
+    /// Color scheme for highlighting snippet bodies, or `none` for plain text
+    pub syntax: String,
+}
+
+impl Default for ThemeConfig {
+    fn default() -> ThemeConfig {
+        ThemeConfig {
+            density: Density::default(),
+            syntax: syntax::DEFAULT_SCHEME.to_string(),
+        }
+    }
+}
+
+/// Resolved styles used by `ui()`
+pub struct Theme {
+    pub text: Style,
+    pub header: Style,
+    pub selected: Style,
+    /// Rows marked for multi-row actions
+    pub marked: Style,
+    /// Substituted or matched text inside a cell or preview
+    pub highlight: Style,
+    /// The namespace part of titles such as `docker/prune/volumes`
+    pub namespace: Style,
+    pub focused_input: Style,
+    pub key_hint: Style,
+    pub help_line: Style,
+    /// Failures on the status line
+    pub error: Style,
+    pub highlight_symbol: &'static str,
+    /// Blank lines inserted below each table row
+    pub row_spacing: u16,
+    /// Colors for snippet bodies in a known language, none for plain text
+    pub syntax: Option<Scheme>,
+}
+
+impl Theme {
+    pub fn from_config(config: &ThemeConfig) -> Theme {
+        match config.density {
+            Density::Normal => Theme {
+                text: Style::default(),
+                header: Style::default().bg(terminal::color(Color::Rgb(0xff, 0x00, 0xff))),
+                selected: Style::default().add_modifier(Modifier::REVERSED),
+                marked: Style::default().fg(Color::Cyan),
+                highlight: Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+                namespace: Style::default().fg(Color::DarkGray),
+                focused_input: Style::default().fg(Color::Yellow),
+                key_hint: Style::default().add_modifier(Modifier::BOLD),
+                help_line: Style::default().add_modifier(Modifier::RAPID_BLINK),
+                error: Style::default().fg(Color::Red),
+                highlight_symbol: "",
+                row_spacing: 0,
+                syntax: syntax::scheme(&config.syntax),
+            },
+            Density::Large => {
+                let bold = Style::default().add_modifier(Modifier::BOLD);
+                Theme {
+                    text: bold.fg(Color::White),
+                    header: bold.fg(Color::Black).bg(Color::White),
+                    selected: bold.fg(Color::Black).bg(Color::Yellow),
+                    marked: bold.fg(Color::Cyan),
+                    highlight: bold.fg(Color::Black).bg(Color::White),
+                    namespace: bold.fg(Color::Gray),
+                    focused_input: bold.fg(Color::Yellow),
+                    key_hint: bold.fg(Color::Yellow),
+                    help_line: bold.fg(Color::White),
+                    error: bold.fg(Color::White).bg(Color::Red),
+                    highlight_symbol: terminal::glyph("▶▶ ", ">> "),
+                    row_spacing: 1,
+                    // Scheme colors aren't all high-contrast
+                    syntax: None,
+                }
+            }
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        Theme::from_config(&ThemeConfig::default())
+    }
+}
+
+/// Terminal color used for a snippet label
+pub fn label_color(color: LabelColor) -> Color {
+    match color {
+        LabelColor::Red => Color::Red,
+        LabelColor::Green => Color::Green,
+        LabelColor::Yellow => Color::Yellow,
+        LabelColor::Blue => Color::Blue,
+        LabelColor::Magenta => Color::Magenta,
+        LabelColor::Cyan => Color::Cyan,
+        LabelColor::Gray => Color::Gray,
+    }
+}
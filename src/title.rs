@@ -0,0 +1,31 @@
+//! The terminal window title, so several terminal tabs running sniprrr can be
+//! told apart. The previous title is kept on the terminal's title stack
+//! (xterm `CSI 22/23 t`) and comes back on exit; terminals without a stack
+//! ignore those sequences.
+
+use std::io::{self, Write};
+
+use crossterm::{execute, terminal::SetTitle};
+
+/// Saves the title the terminal had before sniprrr started
+pub fn save() -> io::Result<()> {
+    let mut stdout = io::stdout();
+    write!(stdout, "\x1b[22;0t")?;
+    stdout.flush()
+}
+
+/// Puts back the title saved by `save`
+pub fn restore() -> io::Result<()> {
+    let mut stdout = io::stdout();
+    write!(stdout, "\x1b[23;0t")?;
+    stdout.flush()
+}
+
+/// Sets the title to `title` unless it already is, as remembered in `current`
+pub fn update(current: &mut String, title: String) -> io::Result<()> {
+    if *current != title {
+        execute!(io::stdout(), SetTitle(&title))?;
+        *current = title;
+    }
+    Ok(())
+}
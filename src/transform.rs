@@ -0,0 +1,94 @@
+use serde::Deserialize;
+
+/// A text transformation applied before a snippet is sent to a copy target
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Transform {
+    /// Remove leading and trailing whitespace
+    Trim,
+    /// Remove trailing whitespace from every line
+    TrimLines,
+    /// Remove the indentation shared by all lines
+    Dedent,
+    /// Join all lines into one, separated by single spaces
+    SingleLine,
+    /// Use Windows line endings
+    Crlf,
+}
+
+impl Transform {
+    pub const ALL: [Transform; 5] = [
+        Transform::Trim,
+        Transform::TrimLines,
+        Transform::Dedent,
+        Transform::SingleLine,
+        Transform::Crlf,
+    ];
+
+    /// The name used in the config
+    pub fn name(self) -> &'static str {
+        match self {
+            Transform::Trim => "trim",
+            Transform::TrimLines => "trim-lines",
+            Transform::Dedent => "dedent",
+            Transform::SingleLine => "single-line",
+            Transform::Crlf => "crlf",
+        }
+    }
+
+    pub fn describe(self) -> &'static str {
+        match self {
+            Transform::Trim => "Remove leading and trailing whitespace",
+            Transform::TrimLines => "Remove trailing whitespace from every line",
+            Transform::Dedent => "Remove the indentation shared by all lines",
+            Transform::SingleLine => "Join all lines into one",
+            Transform::Crlf => "Use Windows line endings",
+        }
+    }
+
+    pub fn apply(&self, text: &str) -> String {
+        match self {
+            Transform::Trim => text.trim().to_string(),
+            Transform::TrimLines => text
+                .lines()
+                .map(str::trim_end)
+                .collect::<Vec<&str>>()
+                .join("\n"),
+            Transform::Dedent => {
+                let indent = text
+                    .lines()
+                    .filter(|line| !line.trim().is_empty())
+                    .map(|line| line.len() - line.trim_start().len())
+                    .min()
+                    .unwrap_or(0);
+                text.lines()
+                    .map(|line| line.get(indent..).unwrap_or_else(|| line.trim_start()))
+                    .collect::<Vec<&str>>()
+                    .join("\n")
+            }
+            Transform::SingleLine => text
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .collect::<Vec<&str>>()
+                .join(" "),
+            Transform::Crlf => text.lines().collect::<Vec<&str>>().join("\r\n"),
+        }
+    }
+}
+
+/// Runs `text` through every transformation in order
+pub fn apply_all(transforms: &[Transform], text: &str) -> String {
+    transforms
+        .iter()
+        .fold(text.to_string(), |text, transform| transform.apply(&text))
+}
+
+/// The `transforms` line of a `[[copy_targets]]` entry running `transforms`
+pub fn config_line(transforms: &[Transform]) -> String {
+    let names: Vec<String> = transforms
+        .iter()
+        .map(|transform| format!("\"{}\"", transform.name()))
+        .collect();
+    format!("transforms = [{}]", names.join(", "))
+}
@@ -0,0 +1,130 @@
+//! `sniprrr tutorial`: the UI on a sandbox collection in a temporary
+//! directory, with the help line walking through adding, tagging, searching,
+//! copying and deleting a snippet. The real store is never opened.
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::file_utils::{set_store_path, write_messages_to_file};
+use crate::models::Snippet;
+use crate::storage;
+
+/// The directory holding the sandbox store, its backups and history, removed
+/// again when dropped
+pub struct Sandbox {
+    dir: PathBuf,
+}
+
+impl Sandbox {
+    /// Makes the sandbox the store of this process, with a few snippets to look at
+    pub fn create() -> io::Result<Sandbox> {
+        let dir = std::env::temp_dir().join(format!("sniprrr-tutorial-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        set_store_path(dir.join(storage::configured().file_name()));
+
+        let mut greeting = Snippet::new(
+            "Greeting".to_string(),
+            "Hello! Thanks for getting in touch.".to_string(),
+        );
+        greeting.tags = vec!["chat".to_string()];
+        let mut disk = Snippet::new("Disk usage".to_string(), "du -sh * | sort -h".to_string());
+        disk.tags = vec!["shell".to_string()];
+        write_messages_to_file(&[greeting, disk])?;
+        Ok(Sandbox { dir })
+    }
+}
+
+impl Drop for Sandbox {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// The steps of the tutorial, in order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Step {
+    Add,
+    Tag,
+    Search,
+    Copy,
+    Delete,
+    Done,
+}
+
+/// How far the user got, checked against the snippets after every key
+pub struct Tutorial {
+    step: Step,
+    /// Titles of the sandbox's own snippets, to tell the added one apart
+    initial: Vec<String>,
+    /// The snippet added in the first step
+    added: Option<String>,
+}
+
+impl Tutorial {
+    pub fn new(snippets: &[Snippet]) -> Tutorial {
+        Tutorial {
+            step: Step::Add,
+            initial: snippets.iter().map(|s| s.title.clone()).collect(),
+            added: None,
+        }
+    }
+
+    /// What to do next, shown in place of the help line
+    pub fn instructions(&self) -> String {
+        let added = self.added.as_deref().unwrap_or_default();
+        match self.step {
+            Step::Add => "Tutorial 1/5: press e, type a title, Tab to the description and \
+                write something, then Enter on the last field to add the snippet"
+                .to_string(),
+            Step::Tag => format!(
+                "Tutorial 2/5: select '{}' with j/k and press # to give it a tag",
+                added
+            ),
+            Step::Search => format!(
+                "Tutorial 3/5: press /, type part of '{}' and Enter to keep the search",
+                added
+            ),
+            Step::Copy => format!("Tutorial 4/5: select '{}' and press c to copy it", added),
+            Step::Delete => format!(
+                "Tutorial 5/5: press Esc to list everything again, then dd to delete '{}'",
+                added
+            ),
+            Step::Done => "Tutorial done! Press q to quit, the sandbox goes away and your \
+                own snippets were never touched"
+                .to_string(),
+        }
+    }
+
+    /// Moves on to the next step once the current one shows in `snippets`.
+    /// `search` is the search kept in the table, empty while one is typed.
+    pub fn check(&mut self, snippets: &[Snippet], search: &str) {
+        let added = self
+            .added
+            .as_ref()
+            .and_then(|title| snippets.iter().find(|s| &s.title == title));
+        self.step = match self.step {
+            // Deleted early, so it takes adding another one
+            Step::Tag | Step::Search | Step::Copy if added.is_none() => {
+                self.added = None;
+                Step::Add
+            }
+            Step::Add => match snippets.iter().find(|s| !self.initial.contains(&s.title)) {
+                Some(snippet) => {
+                    self.added = Some(snippet.title.clone());
+                    // Tags given in the form already cover the next step
+                    match snippet.tags.is_empty() {
+                        true => Step::Tag,
+                        false => Step::Search,
+                    }
+                }
+                None => Step::Add,
+            },
+            Step::Tag if added.is_some_and(|s| !s.tags.is_empty()) => Step::Search,
+            Step::Search if !search.is_empty() => Step::Copy,
+            Step::Copy if added.is_some_and(|s| s.copy_count > 0) => Step::Delete,
+            Step::Delete if added.is_none() => Step::Done,
+            step => step,
+        };
+    }
+}
@@ -0,0 +1,85 @@
+//! Named variants of a snippet's body, e.g. `bash` and `powershell` versions
+//! of the same task. Variants named by conditions are picked without asking
+//! when their conditions hold:
+//!
+//! - `os:<OS>` (`linux`, `macos`, `windows`, ...) or just the OS name
+//! - `family:<FAMILY>` (`unix`, `windows`) or just `unix`
+//! - `host:<HOSTNAME>`
+//! - `env:<VAR>` when the variable is set, `env:<VAR>=<VALUE>` when it has that value
+//!
+//! Several conditions separated by commas, e.g. `os:linux,env:WAYLAND_DISPLAY`,
+//! all have to hold. The variant with the most conditions wins.
+
+use crate::models::Snippet;
+
+/// The conditions in a variant name, `None` when it is a plain name like `bash`
+fn conditions(name: &str) -> Option<Vec<&str>> {
+    let conditions: Vec<&str> = name.split(',').map(str::trim).collect();
+    conditions
+        .iter()
+        .all(|condition| is_condition(condition))
+        .then_some(conditions)
+}
+
+fn is_condition(condition: &str) -> bool {
+    match condition.split_once(':') {
+        Some((kind, value)) => {
+            matches!(kind, "os" | "family" | "host" | "env") && !value.is_empty()
+        }
+        None => matches!(
+            condition,
+            "linux" | "macos" | "windows" | "freebsd" | "openbsd" | "netbsd" | "unix"
+        ),
+    }
+}
+
+fn holds(condition: &str) -> bool {
+    match condition.split_once(':') {
+        Some(("os", os)) => os == std::env::consts::OS,
+        Some(("family", family)) => family == std::env::consts::FAMILY,
+        Some(("host", host)) => hostname().is_some_and(|name| name.eq_ignore_ascii_case(host)),
+        Some(("env", variable)) => match variable.split_once('=') {
+            Some((variable, value)) => std::env::var(variable).is_ok_and(|v| v == value),
+            None => std::env::var_os(variable).is_some(),
+        },
+        _ => condition == std::env::consts::OS || condition == std::env::consts::FAMILY,
+    }
+}
+
+#[cfg(unix)]
+fn hostname() -> Option<String> {
+    let mut buffer = [0u8; 256];
+    // Safe: gethostname writes at most `buffer.len()` bytes into the buffer
+    let result = unsafe { libc::gethostname(buffer.as_mut_ptr().cast(), buffer.len()) };
+    if result != 0 {
+        return None;
+    }
+    let end = buffer.iter().position(|&b| b == 0).unwrap_or(buffer.len());
+    Some(String::from_utf8_lossy(&buffer[..end]).into_owned())
+}
+
+#[cfg(not(unix))]
+fn hostname() -> Option<String> {
+    std::env::var("COMPUTERNAME").ok()
+}
+
+/// The variant whose conditions hold on this system, the most specific one
+/// when several do
+pub fn for_this_system(snippet: &Snippet) -> Option<&str> {
+    snippet
+        .variants
+        .keys()
+        .filter_map(|name| Some((name, conditions(name)?)))
+        .filter(|(_, conditions)| conditions.iter().all(|condition| holds(condition)))
+        // `max_by_key` keeps the last of equals, so reverse to prefer the first name
+        .rev()
+        .max_by_key(|(_, conditions)| conditions.len())
+        .map(|(name, _)| name.as_str())
+}
+
+/// The body of `variant`, or the main body for `None`
+pub fn body<'a>(snippet: &'a Snippet, variant: Option<&str>) -> &'a str {
+    variant
+        .and_then(|name| snippet.variants.get(name))
+        .unwrap_or(&snippet.description)
+}
@@ -0,0 +1,80 @@
+//! How the table is laid out for each collection, kept in `view.json` in the
+//! config directory by store path. A collection of chat messages wants a
+//! wide description, one of commands wide titles.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// Percent of the table width the title and description columns share
+pub const SHARED_WIDTH: u16 = 80;
+/// Percent `<` and `>` move the split by
+const STEP: u16 = 5;
+/// Neither column gets narrower than this percent
+const MIN_WIDTH: u16 = 10;
+
+/// The layout of one collection
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct View {
+    /// Percent of the table width for titles, the description gets the rest
+    /// of `SHARED_WIDTH`
+    pub title_width: u16,
+}
+
+impl Default for View {
+    fn default() -> View {
+        View { title_width: 35 }
+    }
+}
+
+impl View {
+    pub fn description_width(self) -> u16 {
+        SHARED_WIDTH - self.title_width
+    }
+
+    /// The title column `steps` steps wider, narrower for negative `steps`
+    pub fn widen_title(self, steps: i32) -> View {
+        let width = self.title_width as i32 + steps * STEP as i32;
+        View {
+            title_width: width.clamp(MIN_WIDTH as i32, (SHARED_WIDTH - MIN_WIDTH) as i32) as u16,
+        }
+    }
+}
+
+/// Views by store path
+type Views = BTreeMap<String, View>;
+
+fn views_file_path() -> Option<PathBuf> {
+    Some(dirs::config_dir()?.join("sniprrr").join("view.json"))
+}
+
+fn read_views(path: &Path) -> Views {
+    fs::read(path)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .unwrap_or_default()
+}
+
+/// The view of the store at `store`, the default for one never adjusted
+pub fn load(store: &Path) -> View {
+    views_file_path()
+        .map(|path| read_views(&path))
+        .and_then(|views| views.get(&store.to_string_lossy().into_owned()).copied())
+        .unwrap_or_default()
+}
+
+/// Keeps `view` for the store at `store`
+pub fn save(store: &Path, view: View) -> io::Result<()> {
+    let Some(path) = views_file_path() else {
+        return Ok(());
+    };
+    let mut views = read_views(&path);
+    views.insert(store.to_string_lossy().into_owned(), view);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_vec_pretty(&views)?)
+}
@@ -0,0 +1,33 @@
+//! Guesses the language of the project the CLI is run from
+
+use std::path::Path;
+
+/// Files that mark the root of a project, and the language they stand for
+const MARKERS: [(&str, &str); 9] = [
+    ("Cargo.toml", "rust"),
+    ("go.mod", "go"),
+    ("pyproject.toml", "python"),
+    ("setup.py", "python"),
+    ("requirements.txt", "python"),
+    ("Gemfile", "ruby"),
+    ("pom.xml", "java"),
+    ("build.gradle", "java"),
+    ("composer.json", "php"),
+];
+
+/// The language of the nearest project containing `dir`
+pub fn detect_language(dir: &Path) -> Option<String> {
+    dir.ancestors().find_map(language_of)
+}
+
+fn language_of(dir: &Path) -> Option<String> {
+    // Checked first since JavaScript projects often carry other tooling files too
+    if dir.join("package.json").is_file() {
+        let typescript = dir.join("tsconfig.json").is_file();
+        return Some(if typescript { "ts" } else { "js" }.to_string());
+    }
+    MARKERS
+        .iter()
+        .find(|(marker, _)| dir.join(marker).is_file())
+        .map(|(_, language)| language.to_string())
+}
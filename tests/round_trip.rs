@@ -0,0 +1,316 @@
+//! Snippets written out in each format sniprrr exports have to come back the
+//! same when imported again. The store formats keep everything; CSV only
+//! carries the title, body and tags, with the limits described at each test.
+//!
+//! VS Code and Espanso snippet files aren't formats sniprrr reads or writes,
+//! and Markdown only comes out of copies and `sniprrr digest`, which are
+//! never read back, so none of them has a round trip to test. The shell
+//! exports go one way too: `aliases` is checked for defining each command
+//! once, and reading shell history for taking any file without panicking.
+
+use std::path::PathBuf;
+
+use proptest::collection::{btree_map, vec};
+use proptest::option;
+use proptest::prelude::*;
+
+use sniprrr::aliases::{self, Shell};
+use sniprrr::csv_io;
+use sniprrr::file_utils::{parse_store, store_contents, write_store};
+use sniprrr::formats::{self, Format};
+use sniprrr::import;
+use sniprrr::models::{LabelColor, Snippet};
+use sniprrr::shell_history;
+use sniprrr::storage::{Sqlite, Storage};
+
+/// Any text, including newlines, quotes and characters outside ASCII
+fn text() -> impl Strategy<Value = String> {
+    any::<String>()
+}
+
+/// A tag as `parse_tags` reads it back: no commas, no surrounding whitespace
+fn tag() -> impl Strategy<Value = String> {
+    "[^,\\s]([^,]{0,10}[^,\\s])?"
+}
+
+fn color() -> impl Strategy<Value = LabelColor> {
+    proptest::sample::select(LabelColor::ALL.to_vec())
+}
+
+fn snippet() -> impl Strategy<Value = Snippet> {
+    (
+        (
+            text(),
+            text(),
+            vec(text(), 0..4),
+            option::of(text()),
+            option::of(color()),
+            option::of(text()),
+            option::of(any::<u64>()),
+            option::of(any::<u64>()),
+        ),
+        (
+            option::of(any::<u64>()),
+            any::<u32>(),
+            option::of(text()),
+            option::of(text()),
+            btree_map(text(), text(), 0..3),
+            any::<bool>(),
+            btree_map(text(), text(), 0..3),
+            any::<bool>(),
+        ),
+        (
+            option::of("[0-9a-f]{16}"),
+            btree_map(text(), vec(text(), 0..3), 0..3),
+            btree_map(text(), text(), 0..3),
+            option::of(text()),
+        ),
+    )
+        .prop_map(
+            |(
+                (title, description, tags, language, color, icon, created_at, updated_at),
+                (
+                    last_used_at,
+                    copy_count,
+                    schedule,
+                    output_of,
+                    variants,
+                    binary,
+                    field_defaults,
+                    needs_review,
+                ),
+                (trusted_commands, parameter_history, env, working_dir),
+            )| Snippet {
+                title,
+                description,
+                tags,
+                language,
+                color,
+                icon,
+                created_at,
+                updated_at,
+                last_used_at,
+                copy_count,
+                schedule,
+                output_of,
+                variants,
+                binary,
+                field_defaults,
+                needs_review,
+                trusted_commands,
+                parameter_history,
+                env,
+                working_dir,
+                temporary: false,
+                source: None,
+            },
+        )
+}
+
+/// Snippets as CSV carries them: a title that isn't blank or padded, and
+/// distinct tags as read by `parse_tags`
+fn csv_snippet() -> impl Strategy<Value = Snippet> {
+    (
+        "[^\\s]([^\\n]{0,30}[^\\s])?",
+        text(),
+        proptest::collection::btree_set(tag(), 0..4),
+    )
+        .prop_map(|(title, description, tags)| {
+            let mut snippet = Snippet::new(title, description);
+            snippet.tags = tags.into_iter().collect();
+            snippet
+        })
+}
+
+/// Snippets with timestamps TOML can hold
+fn toml_snippet() -> impl Strategy<Value = Snippet> {
+    snippet().prop_map(|mut snippet| {
+        let limit = |timestamp: Option<u64>| timestamp.map(|t| t.min(i64::MAX as u64));
+        snippet.created_at = limit(snippet.created_at);
+        snippet.updated_at = limit(snippet.updated_at);
+        snippet.last_used_at = limit(snippet.last_used_at);
+        snippet
+    })
+}
+
+/// A shell command snippet the alias export takes: tagged `shell`, with a
+/// body that isn't a template
+fn command_snippet() -> impl Strategy<Value = Snippet> {
+    (text(), "[a-z][a-z0-9 ./|-]{0,30}").prop_map(|(title, description)| {
+        let mut snippet = Snippet::new(title, description);
+        snippet.tags = vec!["shell".to_string()];
+        snippet
+    })
+}
+
+/// A line as bash, zsh or fish write them, or anything else
+fn history_line() -> impl Strategy<Value = String> {
+    prop_oneof![
+        text(),
+        "[a-z]{1,8}( [a-z0-9=-]{1,8}){0,3}",
+        (any::<u32>(), text()).prop_map(|(at, command)| format!(": {}:0;{}", at, command)),
+        (text(), any::<u32>())
+            .prop_map(|(command, at)| format!("- cmd: {}\n  when: {}", command, at)),
+    ]
+}
+
+/// The names of the functions `script` defines for `shell`
+fn function_names(script: &str, shell: Shell) -> Vec<&str> {
+    script
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| match shell {
+            Shell::Posix => line.strip_suffix("() {"),
+            Shell::PowerShell => line.strip_prefix("function ")?.strip_suffix(" {"),
+        })
+        .collect()
+}
+
+/// Snippets compare by their serialized form, which covers every field
+fn as_json(snippets: &[Snippet]) -> serde_json::Value {
+    serde_json::to_value(snippets).expect("snippets serialize")
+}
+
+/// A file in a directory of its own that is removed again when dropped
+struct TempFile(PathBuf);
+
+impl TempFile {
+    fn new(name: &str) -> TempFile {
+        let dir = tempdir();
+        std::fs::create_dir_all(&dir).expect("the temporary directory is writable");
+        TempFile(dir.join(name))
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        if let Some(dir) = self.0.parent() {
+            let _ = std::fs::remove_dir_all(dir);
+        }
+    }
+}
+
+fn tempdir() -> PathBuf {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    std::env::temp_dir().join(format!(
+        "sniprrr-round-trip-{}-{}",
+        std::process::id(),
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    ))
+}
+
+proptest! {
+    #[test]
+    fn store_keeps_every_field(snippets in vec(snippet(), 0..8)) {
+        let contents = store_contents(&snippets).expect("snippets serialize");
+        let loaded = parse_store(&contents).expect("the written store parses");
+        prop_assert_eq!(as_json(&loaded), as_json(&snippets));
+    }
+
+    /// Exports and backups are store files, read back by the importer
+    #[test]
+    fn store_file_export_imports_unchanged(snippets in vec(snippet(), 0..8)) {
+        let file = TempFile::new("export.json");
+        write_store(&file.0, &snippets).expect("the export is written");
+        let imported = import::read_store_file(&file.0.to_string_lossy())
+            .expect("the export imports");
+        prop_assert_eq!(as_json(&imported), as_json(&snippets));
+    }
+
+    /// YAML and TOML exports carry every field, like the store file
+    #[test]
+    fn yaml_keeps_every_field(snippets in vec(snippet(), 0..8)) {
+        let file = TempFile::new("export.yaml");
+        formats::write(&file.0, &snippets, Format::Yaml).expect("the export is written");
+        let imported = formats::read(&file.0, Format::Yaml).expect("the export imports");
+        prop_assert_eq!(as_json(&imported), as_json(&snippets));
+    }
+
+    /// TOML integers are signed, so timestamps stop at `i64::MAX`
+    #[test]
+    fn toml_keeps_every_field(snippets in vec(toml_snippet(), 0..8)) {
+        let file = TempFile::new("export.toml");
+        formats::write(&file.0, &snippets, Format::Toml).expect("the export is written");
+        let imported = formats::read(&file.0, Format::Toml).expect("the export imports");
+        prop_assert_eq!(as_json(&imported), as_json(&snippets));
+    }
+
+    /// Saving over an existing database only rewrites the rows that changed,
+    /// so the second save has to leave exactly the new snippets behind
+    #[test]
+    fn sqlite_store_keeps_every_field(
+        before in vec(snippet(), 0..8),
+        snippets in vec(snippet(), 0..8),
+    ) {
+        let file = TempFile::new("messages.db");
+        Sqlite.save(&file.0, &before).expect("the database is written");
+        Sqlite.save(&file.0, &snippets).expect("the database is updated");
+        let loaded = Sqlite.load(&file.0).expect("the database loads");
+        prop_assert_eq!(as_json(&loaded), as_json(&snippets));
+    }
+
+    /// Only titles, bodies and tags go through CSV. Everything else starts
+    /// fresh on import, the way a new snippet does.
+    #[test]
+    fn csv_keeps_title_body_and_tags(snippets in vec(csv_snippet(), 0..8)) {
+        let file = TempFile::new("export.csv");
+        csv_io::write(&file.0, &snippets).expect("the CSV is written");
+        let table = csv_io::read_table(&file.0).expect("the CSV reads");
+        let imported = table.to_snippets(table.guess_mapping());
+
+        prop_assert_eq!(imported.len(), snippets.len());
+        for (imported, snippet) in imported.iter().zip(&snippets) {
+            prop_assert_eq!(&imported.title, &snippet.title);
+            prop_assert_eq!(&imported.description, &snippet.description);
+            prop_assert_eq!(&imported.tags, &snippet.tags);
+        }
+    }
+
+    /// Every command snippet becomes a function of its own, named so the
+    /// shell accepts it, however its title is written
+    #[test]
+    fn aliases_define_each_command_once(
+        snippets in vec(command_snippet(), 0..8),
+        shell in prop_oneof![Just(Shell::Posix), Just(Shell::PowerShell)],
+    ) {
+        let (script, count) = aliases::render(&snippets, shell);
+        let names = function_names(&script, shell);
+        prop_assert_eq!(count, snippets.len());
+        prop_assert_eq!(names.len(), snippets.len());
+        for (index, name) in names.iter().enumerate() {
+            prop_assert!(!names[..index].contains(name), "{} is defined twice", name);
+            prop_assert!(
+                !name.starts_with(|c: char| c.is_ascii_digit())
+                    && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_'),
+                "{} is not a function name", name
+            );
+        }
+        for snippet in &snippets {
+            prop_assert!(script.contains(snippet.description.trim_end()));
+        }
+    }
+
+    /// History files hold whatever the shell or an editor left in them, so
+    /// any bytes are read without panicking, each command under a title of
+    /// its own
+    #[test]
+    fn shell_history_reads_any_file(
+        lines in vec(history_line(), 0..20),
+        noise in vec(any::<u8>(), 0..256),
+    ) {
+        let file = TempFile::new("history");
+        let mut bytes = lines.join("\n").into_bytes();
+        bytes.extend(noise);
+        std::fs::write(&file.0, &bytes).expect("the history is written");
+        let candidates = shell_history::read(std::slice::from_ref(&file.0))
+            .expect("the history reads");
+
+        let titles: Vec<&str> = candidates.iter().map(|c| c.snippet.title.as_str()).collect();
+        for (index, candidate) in candidates.iter().enumerate() {
+            prop_assert!(!titles[..index].contains(&titles[index]));
+            prop_assert!(!candidate.snippet.description.trim().is_empty());
+            prop_assert!(candidate.count > 0);
+        }
+    }
+}